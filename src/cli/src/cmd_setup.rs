@@ -6,20 +6,32 @@ pub const CREATE_REMOTE: &str = "create-remote";
 pub const REMOTE: &str = "remote";
 pub const STATUS: &str = "status";
 pub const LOG: &str = "log";
+pub const BLAME: &str = "blame";
+pub const GREP: &str = "grep";
 pub const DF: &str = "df";
 pub const SCHEMAS: &str = "schemas";
 pub const ADD: &str = "add";
 pub const RM: &str = "rm";
+pub const MV: &str = "mv";
+pub const CHECK_IGNORE: &str = "check-ignore";
 pub const COMMIT: &str = "commit";
 pub const RESTORE: &str = "restore";
 pub const BRANCH: &str = "branch";
 pub const CHECKOUT: &str = "checkout";
 pub const MERGE: &str = "merge";
+pub const CHERRY_PICK: &str = "cherry-pick";
+pub const REVERT: &str = "revert";
+pub const STASH: &str = "stash";
+pub const REBASE: &str = "rebase";
+pub const REFLOG: &str = "reflog";
+pub const NOTES: &str = "notes";
 pub const CLONE: &str = "clone";
 pub const PUSH: &str = "push";
 pub const PULL: &str = "pull";
 pub const DIFF: &str = "diff";
 pub const MIGRATE: &str = "migrate";
+pub const GC: &str = "gc";
+pub const FSCK: &str = "fsck";
 pub const KVDB_INSPECT: &str = "kvdb-inspect";
 pub const READ_LINES: &str = "read-lines";
 
@@ -61,6 +73,27 @@ pub fn config() -> Command<'static> {
                 .help("Sets the default host used to check version numbers. If empty, the CLI will not do a version check.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("ignore")
+                .long("ignore")
+                .value_name("PATTERN")
+                .help("Add a gitignore style pattern that is ignored in every repository, like a global .oxenignore.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("generate-signing-key")
+                .long("generate-signing-key")
+                .help("Generate an Ed25519 key in ~/.oxen/signing_key to sign your commits with, and print its public key.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("trust-key")
+                .long("trust-key")
+                .number_of_values(2)
+                .value_names(&["EMAIL", "PUBLIC_KEY"])
+                .help("Trust a public key to sign the commits of the email in the current repository, checked by `oxen log --verify`.")
+                .takes_value(true),
+        )
 }
 
 pub fn create_remote() -> Command<'static> {
@@ -118,10 +151,79 @@ pub fn status() -> Command<'static> {
                 .help("If present, does not truncate the output of status at all.")
                 .takes_value(false),
         )
+        .arg(
+            Arg::new("path")
+                .help("Only check the files within this path, faster in large repositories.")
+                .required(false),
+        )
 }
 
 pub fn log() -> Command<'static> {
-    Command::new(LOG).about("See log of commits")
+    Command::new(LOG)
+        .about("See log of commits")
+        .arg(arg!([PATH] "Only show the commits that changed this file"))
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .help("Continue the history of the file across renames")
+                .requires("PATH")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .help("Check the signature of each commit")
+                .takes_value(false),
+        )
+}
+
+pub fn blame() -> Command<'static> {
+    Command::new(BLAME)
+        .about("Shows the commit that introduced each row of a tabular file")
+        .arg(arg!(<PATH> "The csv, tsv, ndjson, jsonl or parquet file to blame"))
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("key")
+                .long("key")
+                .short('k')
+                .help("Only show this column of each row instead of the whole row")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("revision")
+                .long("revision")
+                .short('r')
+                .help("The commit id or branch to blame the file at, defaults to HEAD")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .help("Output file to store the blamed rows")
+                .takes_value(true),
+        )
+}
+
+pub fn grep() -> Command<'static> {
+    Command::new(GREP)
+        .about("Search the lines of text files and the cells of tabular files in a commit for a regex")
+        .arg(arg!(<PATTERN> "The regex to search for"))
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("glob")
+                .long("glob")
+                .short('g')
+                .help("Only search the files that match this gitignore style pattern, ex: \"annotations/**/*.csv\"")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("revision")
+                .long("revision")
+                .short('r')
+                .help("The commit id or branch to search, defaults to HEAD")
+                .takes_value(true),
+        )
 }
 
 pub fn df() -> Command<'static> {
@@ -267,6 +369,17 @@ pub fn schemas() -> Command<'static> {
                 .arg(Arg::new("HASH").help("Hash of the schema you want to name."))
                 .arg(Arg::new("NAME").help("Name of the schema.")),
         )
+        .subcommand(
+            Command::new("create_index")
+                .about("Index the values of a field so filtering on it does not scan every row")
+                .arg(arg!(<SCHEMA> "Name or the hash of the schema."))
+                .arg(arg!(<FIELD> "Name of the field to index.")),
+        )
+        .subcommand(
+            Command::new("indices")
+                .about("List the indexed fields of a schema")
+                .arg(arg!(<SCHEMA> "Name or the hash of the schema.")),
+        )
         .subcommand(df())
 }
 
@@ -276,12 +389,33 @@ pub fn add() -> Command<'static> {
         .arg(Arg::new("files").required(true).min_values(1))
 }
 
+pub fn check_ignore() -> Command<'static> {
+    Command::new(CHECK_IGNORE)
+        .about("Prints the paths that are ignored by an .oxenignore or the user config")
+        .arg(Arg::new("paths").required(true).min_values(1))
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Print the pattern that matched each path, including negated patterns")
+                .takes_value(false),
+        )
+}
+
 pub fn rm() -> Command<'static> {
     Command::new(RM)
         .about("Removes the specified files from the index")
         .arg(Arg::new("files").required(true).min_values(1))
 }
 
+pub fn mv() -> Command<'static> {
+    Command::new(MV)
+        .about("Moves or renames a file or directory, keeping its history")
+        .arg(arg!(<SOURCE> "The committed file or directory to move"))
+        .arg(arg!(<DESTINATION> "Where to move it to"))
+        .arg_required_else_help(true)
+}
+
 pub fn restore() -> Command<'static> {
     Command::new(RESTORE)
         .about("Unstage or discard uncommitted local changes.")
@@ -369,6 +503,118 @@ pub fn merge() -> Command<'static> {
         .arg(arg!(<BRANCH> "The name of the branch you want to merge in."))
 }
 
+pub fn cherry_pick() -> Command<'static> {
+    Command::new(CHERRY_PICK)
+        .about("Applies the changes a commit made on top of HEAD and commits them.")
+        .arg_required_else_help(true)
+        .arg(arg!(<COMMIT> "The commit id or branch name to apply."))
+}
+
+pub fn revert() -> Command<'static> {
+    Command::new(REVERT)
+        .about("Undoes the changes a commit made on top of HEAD and commits them.")
+        .arg_required_else_help(true)
+        .arg(arg!(<COMMIT> "The commit id or branch name to undo."))
+}
+
+pub fn rebase() -> Command<'static> {
+    Command::new(REBASE)
+        .about("Replays the commits of the current branch that are not upstream on top of it.")
+        .arg(arg!([UPSTREAM] "The branch or commit id to replay the commits onto."))
+        .arg(
+            Arg::new("continue")
+                .long("continue")
+                .help("Commit the resolved conflicts and replay the rest of the commits.")
+                .conflicts_with_all(&["UPSTREAM", "abort"])
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("abort")
+                .long("abort")
+                .help("Put the branch back to where it was before the rebase.")
+                .conflicts_with("UPSTREAM")
+                .takes_value(false),
+        )
+}
+
+pub fn reflog() -> Command<'static> {
+    Command::new(REFLOG)
+        .about("Lists the moves of HEAD that rewrote history, newest first.")
+        .subcommand(
+            Command::new("expire")
+                .about("Remove old entries, so gc can collect the commits only they kept reachable")
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .help("Remove the entries older than this many days, defaults to 90")
+                        .takes_value(true),
+                ),
+        )
+}
+
+pub fn stash() -> Command<'static> {
+    Command::new(STASH)
+        .about("Shelve the staged and modified files, and put the working files back to HEAD")
+        .subcommand(
+            Command::new("push")
+                .about("Save the staged and modified files to a new stash")
+                .arg(
+                    Arg::new("message")
+                        .long("message")
+                        .short('m')
+                        .help("Describe what is in the stash")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(Command::new("list").about("List the stashes, newest first"))
+        .subcommand(
+            Command::new("apply")
+                .about("Apply a stash to the working files and keep it")
+                .arg(Arg::new("STASH").help("The stash to apply, defaults to stash@{0}")),
+        )
+        .subcommand(
+            Command::new("pop")
+                .about("Apply a stash and remove it if there were no conflicts")
+                .arg(Arg::new("STASH").help("The stash to apply, defaults to stash@{0}")),
+        )
+        .subcommand(
+            Command::new("drop")
+                .about("Remove a stash without applying it")
+                .arg(Arg::new("STASH").help("The stash to remove, defaults to stash@{0}")),
+        )
+}
+
+pub fn notes() -> Command<'static> {
+    Command::new(NOTES)
+        .about(
+            "Add, show or remove notes on commits, which can change without changing the commit id",
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Add a note to a commit, replacing the note it had")
+                .arg(
+                    Arg::new("message")
+                        .long("message")
+                        .short('m')
+                        .help("The note")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(Arg::new("COMMIT").help("The commit id or branch, defaults to HEAD")),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Show the note on a commit")
+                .arg(Arg::new("COMMIT").help("The commit id or branch, defaults to HEAD")),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove the note from a commit")
+                .arg(Arg::new("COMMIT").help("The commit id or branch, defaults to HEAD")),
+        )
+        .subcommand(Command::new("list").about("List the notes on all the commits"))
+}
+
 pub fn clone() -> Command<'static> {
     Command::new(CLONE)
         .about("Clone a repository by its URL")
@@ -400,6 +646,13 @@ pub fn push() -> Command<'static> {
                 .help("Remove the remote branch")
                 .takes_value(false),
         )
+        .arg(
+            Arg::new("merge-upstream")
+                .long("merge-upstream")
+                .value_name("TARGET_BRANCH")
+                .help("After pushing to a fork, open a merge of the branch into TARGET_BRANCH of the repository it was forked from")
+                .takes_value(true),
+        )
         .arg(arg!(<BRANCH> "Branch name to pull"))
 }
 
@@ -430,6 +683,37 @@ pub fn migrate() -> Command<'static> {
         )
 }
 
+pub fn gc() -> Command<'static> {
+    Command::new(GC)
+        .about("Remove version files and commit history that are no longer reachable from any branch")
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .short('n')
+                .help("Report what would be removed and how much space it would free, without deleting anything")
+                .takes_value(false),
+        )
+}
+
+pub fn fsck() -> Command<'static> {
+    Command::new(FSCK)
+        .about("Verify the integrity of the commits, history, and version files in the repository")
+        .arg(
+            Arg::new("commit")
+                .long("commit")
+                .short('c')
+                .help("Only check this commit id")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("repair-from")
+                .long("repair-from")
+                .short('r')
+                .help("Re-download any missing or corrupt objects from this remote")
+                .takes_value(true),
+        )
+}
+
 pub fn read_lines() -> Command<'static> {
     Command::new("read-lines")
         .about("Read a set of lines from a file without loading it all into memory")
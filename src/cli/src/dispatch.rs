@@ -1,17 +1,22 @@
 use liboxen::api;
 use liboxen::command;
-use liboxen::config::UserConfig;
+use liboxen::config::{signing_key, SigningKey, TrustedKey, TrustedKeysConfig, UserConfig};
 use liboxen::df::df_opts::DFOpts;
+use liboxen::df::tabular;
 use liboxen::error;
 use liboxen::error::OxenError;
+use liboxen::index::reflog;
 use liboxen::model::schema;
-use liboxen::model::LocalRepository;
-use liboxen::opts::RestoreOpts;
+use liboxen::model::{Commit, LocalRepository, Note, RebaseReport, SignatureStatus};
+use liboxen::opts::{GCOpts, RestoreOpts};
 use liboxen::util;
 
+use bytesize::ByteSize;
 use colored::Colorize;
+use std::collections::HashMap;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use time::format_description;
 
 pub async fn init(path: &str) -> Result<(), OxenError> {
@@ -131,6 +136,47 @@ pub fn set_default_host(host: &str) -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn add_global_ignore(pattern: &str) -> Result<(), OxenError> {
+    let mut config = UserConfig::get_or_create()?;
+    if !config.ignore.iter().any(|p| p == pattern) {
+        config.ignore.push(String::from(pattern));
+    }
+    config.save_default()?;
+    Ok(())
+}
+
+pub fn generate_signing_key() -> Result<(), OxenError> {
+    let key = match SigningKey::get()? {
+        Some(key) => {
+            println!("Using the existing signing key");
+            key
+        }
+        None => {
+            let key = SigningKey::generate();
+            let path = key.save_default()?;
+            println!("Saved signing key to {path:?}, new commits will be signed");
+            key
+        }
+    };
+    println!("Public key: {}", key.public_key());
+    Ok(())
+}
+
+pub fn trust_key(email: &str, public_key: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let mut config = TrustedKeysConfig::get(&repository)?;
+    config.trust(TrustedKey {
+        email: email.to_string(),
+        public_key: public_key.to_string(),
+        branches: vec![],
+    });
+    config.save(&repository)?;
+    println!("Trusting key {public_key} for the commits of {email}");
+    Ok(())
+}
+
 pub fn add(paths: Vec<PathBuf>) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
@@ -142,6 +188,26 @@ pub fn add(paths: Vec<PathBuf>) -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn check_ignore(paths: Vec<PathBuf>, verbose: bool) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    for path in paths {
+        match command::check_ignore(&repository, &path)? {
+            Some(m) if verbose => {
+                let source = match &m.source {
+                    Some(source) => source.to_string_lossy().to_string(),
+                    None => String::from("user_config.toml"),
+                };
+                println!("{}:{}\t{}", source, m.pattern, path.display());
+            }
+            Some(m) if !m.negated => println!("{}", path.display()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 pub fn rm(paths: Vec<PathBuf>) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
@@ -153,6 +219,13 @@ pub fn rm(paths: Vec<PathBuf>) -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn mv(src: &str, dst: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    command::mv(&repository, repo_dir.join(src), repo_dir.join(dst))
+}
+
 pub fn restore(opts: RestoreOpts) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
@@ -162,11 +235,31 @@ pub fn restore(opts: RestoreOpts) -> Result<(), OxenError> {
     Ok(())
 }
 
-pub async fn push(remote: &str, branch: &str) -> Result<(), OxenError> {
+pub async fn push(
+    remote: &str,
+    branch: &str,
+    merge_upstream: Option<&str>,
+) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
 
-    command::push_remote_branch(&repository, remote, branch).await?;
+    let remote_repo = command::push_remote_branch(&repository, remote, branch).await?;
+    if let Some(target_branch) = merge_upstream {
+        let merge_request =
+            api::remote::merge_requests::create(&remote_repo, branch, target_branch).await?;
+        println!(
+            "Opened merge request {} from {}/{}:{} into {}",
+            merge_request.id,
+            merge_request.source_namespace,
+            merge_request.source_name,
+            merge_request.source_branch,
+            merge_request.target_branch
+        );
+        println!(
+            "Once it is accepted the commits will be on branch '{}' of the upstream repository",
+            merge_request.branch
+        );
+    }
     Ok(())
 }
 
@@ -196,34 +289,281 @@ pub fn merge(branch: &str) -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn cherry_pick(commit: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    match command::cherry_pick(&repository, commit)? {
+        Some(commit) => println!("[{}] {}", commit.id, commit.message),
+        None => eprintln!("Could not apply {commit}; fix conflicts and then commit the result."),
+    }
+    Ok(())
+}
+
+pub fn revert(commit: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    match command::revert(&repository, commit)? {
+        Some(commit) => println!("[{}] {}", commit.id, commit.message),
+        None => eprintln!("Could not apply {commit}; fix conflicts and then commit the result."),
+    }
+    Ok(())
+}
+
+pub fn rebase(upstream: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let report = command::rebase(&repository, upstream)?;
+    print_rebase_report(&report);
+    Ok(())
+}
+
+pub fn rebase_continue() -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let report = command::rebase_continue(&repository)?;
+    print_rebase_report(&report);
+    Ok(())
+}
+
+fn print_rebase_report(report: &RebaseReport) {
+    match report {
+        RebaseReport::UpToDate { branch, head } => {
+            println!("Current branch {branch} is up to date.");
+            println!("HEAD -> {}", head.id);
+        }
+        RebaseReport::Rebased {
+            branch,
+            upstream,
+            head,
+            skipped,
+        } => {
+            print_skipped_commits(skipped);
+            println!("Successfully rebased {branch} onto {upstream}");
+            println!("HEAD -> {}", head.id);
+        }
+        RebaseReport::Stopped { commit, skipped } => {
+            print_skipped_commits(skipped);
+            println!("Could not apply {} {}", commit.id, commit.message);
+            println!(
+                "Resolve the conflicts and `oxen add` them, then run `oxen rebase --continue`."
+            );
+            println!("To go back to where you were, run `oxen rebase --abort`.");
+        }
+    }
+}
+
+fn print_skipped_commits(skipped: &[Commit]) {
+    for commit in skipped.iter() {
+        println!("Skipped {}, its changes are already upstream", commit.id);
+    }
+}
+
+pub fn rebase_abort() -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let commit = command::rebase_abort(&repository)?;
+    println!("HEAD -> {}", commit.id);
+    Ok(())
+}
+
+pub fn reflog() -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    for entry in command::reflog(&repository)? {
+        println!(
+            "{} -> {} {}",
+            entry.old_commit_id, entry.new_commit_id, entry.message
+        );
+    }
+    Ok(())
+}
+
+pub fn reflog_expire(older_than_days: Option<u64>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let older_than = match older_than_days {
+        Some(days) => Duration::from_secs(days * 60 * 60 * 24),
+        None => Duration::from_secs(reflog::DEFAULT_REFLOG_EXPIRY_SECS),
+    };
+    let num_expired = command::reflog_expire(&repository, older_than)?;
+    println!("Removed {num_expired} reflog entries");
+    Ok(())
+}
+
+pub fn stash_push(message: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    match command::stash_push(&repository, message)? {
+        Some(commit) => println!(
+            "Saved working directory and staged files: {}",
+            commit.message
+        ),
+        None => println!("No local changes to save"),
+    }
+    Ok(())
+}
+
+pub fn stash_list() -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    for (i, commit) in command::stash_list(&repository)?.iter().enumerate() {
+        println!("stash@{{{}}}: {}", i, commit.message);
+    }
+    Ok(())
+}
+
+pub fn notes_add(commit: Option<&str>, message: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let note = command::notes_add(&repository, commit, message)?;
+    println!("Added note to commit {}", note.commit_id);
+    Ok(())
+}
+
+pub fn notes_show(commit: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    match command::notes_show(&repository, commit)? {
+        Some(note) => println!("{}", note.message),
+        None => println!("No note on commit"),
+    }
+    Ok(())
+}
+
+pub fn notes_remove(commit: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    match command::notes_remove(&repository, commit)? {
+        Some(note) => println!("Removed note from commit {}", note.commit_id),
+        None => println!("No note on commit"),
+    }
+    Ok(())
+}
+
+pub fn notes_list() -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let mut notes = command::notes_list(&repository)?;
+    notes.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    for note in notes.iter() {
+        println!("{} {}", note.commit_id.yellow(), note.message);
+    }
+    Ok(())
+}
+
+/// Accepts `stash@{1}` or `1`, defaults to the latest stash
+fn parse_stash_index(stash: Option<&str>) -> Result<usize, OxenError> {
+    match stash {
+        Some(stash) => {
+            let index = stash
+                .strip_prefix("stash@{")
+                .and_then(|s| s.strip_suffix('}'))
+                .unwrap_or(stash);
+            index
+                .parse::<usize>()
+                .map_err(|_| OxenError::basic_str(format!("Invalid stash {stash:?}")))
+        }
+        None => Ok(0),
+    }
+}
+
+pub fn stash_apply(stash: Option<&str>, pop: bool) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let index = parse_stash_index(stash)?;
+    let conflicts = if pop {
+        command::stash_pop(&repository, index)?
+    } else {
+        command::stash_apply(&repository, index)?
+    };
+
+    if conflicts.is_empty() {
+        if pop {
+            println!("Applied and dropped stash@{{{index}}}");
+        } else {
+            println!("Applied stash@{{{index}}}");
+        }
+    } else {
+        eprintln!("Conflicts applying stash@{{{index}}}, the stash was kept:");
+        for conflict in conflicts.iter() {
+            eprintln!(
+                "  {}: {}",
+                conflict.description(),
+                conflict.path().to_string_lossy()
+            );
+        }
+        eprintln!(
+            "Take the stashed version with `oxen checkout --theirs <path>` and `oxen add` it."
+        );
+    }
+    Ok(())
+}
+
+pub fn stash_drop(stash: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let index = parse_stash_index(stash)?;
+    let commit = command::stash_drop(&repository, index)?;
+    println!("Dropped stash@{{{}}} ({})", index, commit.id);
+    Ok(())
+}
+
 pub fn commit(args: Vec<&std::ffi::OsStr>) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repo = LocalRepository::from_dir(&repo_dir)?;
 
-    let err_str = "Must supply a commit message with -m. Ex:\n\noxen commit -m \"Adding data\"";
-    if args.len() != 2 {
-        let err = err_str.to_string();
-        return Err(OxenError::Basic(err));
+    let err_str = "Must supply a commit message with -m. Ex:\n\noxen commit -m \"Adding data\" --meta key=value";
+    let mut message: Option<&str> = None;
+    let mut metadata: HashMap<String, String> = HashMap::new();
+    let mut args = args.iter().map(|arg| arg.to_str().unwrap_or_default());
+    while let Some(flag) = args.next() {
+        match (flag, args.next()) {
+            ("-m", Some(value)) => message = Some(value),
+            ("--meta", Some(value)) => match value.split_once('=') {
+                Some((key, value)) if !key.is_empty() => {
+                    metadata.insert(String::from(key), String::from(value));
+                }
+                _ => {
+                    let err = format!("Metadata must be of the form key=value, got: {value}");
+                    return Err(OxenError::basic_str(err));
+                }
+            },
+            _ => {
+                eprintln!("{err_str}");
+                return Err(OxenError::basic_str(err_str));
+            }
+        }
     }
 
-    let err_str = "Must supply a commit message with -m. Ex:\n\noxen commit -m \"Adding data\"";
-    let flag = args[0];
-    let value = args[1];
-    match flag.to_str().unwrap() {
-        "-m" => {
-            let message = value.to_str().unwrap_or_default();
+    match message {
+        Some(message) => {
             println!("Committing with message: {message}");
-            command::commit(&repo, message)?;
+            command::commit_with_metadata(&repo, message, &metadata)?;
             Ok(())
         }
-        _ => {
+        None => {
             eprintln!("{err_str}");
             Err(OxenError::basic_str(err_str))
         }
     }
 }
 
-pub fn log_commits() -> Result<(), OxenError> {
+pub fn log_commits(path: Option<&str>, follow: bool, verify: bool) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
 
@@ -232,24 +572,101 @@ pub fn log_commits() -> Result<(), OxenError> {
         "[weekday], [day] [month repr:long] [year] [hour]:[minute]:[second] [offset_hour sign:mandatory]",
     ).unwrap();
 
-    for commit in command::log(&repository)? {
+    let commits = match path {
+        Some(path) => {
+            let path = util::fs::path_relative_to_dir(&repo_dir.join(path), &repository.path)?;
+            command::log_path(&repository, path, follow)?
+        }
+        None => command::log(&repository)?,
+    };
+    let notes: HashMap<String, Note> = command::notes_list(&repository)?
+        .into_iter()
+        .map(|note| (note.commit_id.to_owned(), note))
+        .collect();
+
+    // Keys can be trusted for only some branches
+    let branch_name = command::current_branch(&repository)?.map(|branch| branch.name);
+
+    for commit in commits {
         let commit_id_str = format!("commit {}", commit.id).yellow();
         println!("{commit_id_str}\n");
+        if verify {
+            match signing_key::verify(&repository, &commit, branch_name.as_deref())? {
+                SignatureStatus::Good { public_key } => {
+                    println!(
+                        "{}",
+                        format!("Good signature from key {public_key}").green()
+                    )
+                }
+                SignatureStatus::Bad { public_key } => {
+                    println!("{}", format!("BAD signature from key {public_key}").red())
+                }
+                SignatureStatus::Untrusted { public_key } => {
+                    println!(
+                        "{}",
+                        format!("Signature from untrusted key {public_key}").red()
+                    )
+                }
+                SignatureStatus::AuthorMismatch {
+                    public_key,
+                    key_emails,
+                } => {
+                    println!(
+                        "{}",
+                        format!(
+                            "Signature from key {public_key} of {}, not the author {}",
+                            key_emails.join(", "),
+                            commit.email
+                        )
+                        .red()
+                    )
+                }
+                SignatureStatus::Unsigned => println!("{}", "No signature".red()),
+            }
+        }
         println!("Author: {}", commit.author);
         println!("Date:   {}\n", commit.timestamp.format(&format).unwrap());
         println!("    {}\n", commit.message);
+
+        let mut metadata: Vec<(&String, &String)> = commit.metadata.iter().collect();
+        metadata.sort();
+        for (key, value) in metadata.iter() {
+            println!("    {key}: {value}");
+        }
+        if !metadata.is_empty() {
+            println!();
+        }
+
+        if let Some(note) = notes.get(&commit.id) {
+            println!("Notes:\n    {}\n", note.message);
+        }
     }
 
     Ok(())
 }
 
-pub fn status(skip: usize, limit: usize, print_all: bool) -> Result<(), OxenError> {
+pub fn status(
+    skip: usize,
+    limit: usize,
+    print_all: bool,
+    path: Option<&str>,
+) -> Result<(), OxenError> {
     // Should we let user call this from any directory and look up for parent?
     let current_dir = env::current_dir().unwrap();
     let repo_dir = util::fs::get_repo_root(&current_dir).expect(error::NO_REPO_FOUND);
 
     let repository = LocalRepository::from_dir(&repo_dir)?;
-    let repo_status = command::status_from_dir(&repository, &current_dir)?;
+    let path_filter = match path {
+        Some(path) => {
+            let full_path = current_dir.join(path);
+            Some(util::fs::path_relative_to_dir(
+                &full_path,
+                &repository.path,
+            )?)
+        }
+        None => None,
+    };
+    let repo_status = command::status_from_dir(&repository, &current_dir, path_filter.as_deref())?;
 
     if let Some(current_branch) = command::current_branch(&repository)? {
         println!(
@@ -269,6 +686,53 @@ pub fn status(skip: usize, limit: usize, print_all: bool) -> Result<(), OxenErro
     Ok(())
 }
 
+pub fn blame(
+    path: &str,
+    key: Option<&str>,
+    revision: Option<&str>,
+    output: Option<PathBuf>,
+) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let path = util::fs::path_relative_to_dir(&repo_dir.join(path), &repository.path)?;
+    let mut df = command::blame(&repository, path, key, revision)?;
+    println!("{df}");
+
+    if let Some(output) = output {
+        println!("Writing {output:?}");
+        tabular::write_df(&mut df, output)?;
+    }
+    Ok(())
+}
+
+pub fn grep(pattern: &str, glob: Option<&str>, revision: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let commit = match revision {
+        Some(revision) => api::local::commits::get_by_id_or_branch(&repository, revision)?
+            .ok_or_else(|| OxenError::local_commit_or_branch_not_found(revision))?,
+        None => command::head_commit(&repository)?,
+    };
+    for m in command::search(&repository, &commit, pattern, glob, None)? {
+        let path = m.path.to_string_lossy().purple();
+        match (m.line, m.column, m.row) {
+            (Some(line), _, _) => println!("{path}:{}: {}", line.to_string().green(), m.text),
+            (_, Some(column), Some(row)) => {
+                println!(
+                    "{path}:{}:{}: {}",
+                    row.to_string().green(),
+                    column.cyan(),
+                    m.text
+                )
+            }
+            _ => println!("{path}: {}", m.text),
+        }
+    }
+    Ok(())
+}
+
 pub fn df<P: AsRef<Path>>(input: P, opts: DFOpts) -> Result<(), OxenError> {
     command::df(input, opts)?;
     Ok(())
@@ -318,6 +782,16 @@ pub fn schema_name(schema_ref: &str, val: &str) -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn schema_create_index(schema_ref: &str, field: &str) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let num_files = command::schema_create_index(&repository, schema_ref, field)?;
+    println!("Indexed field '{field}' in {num_files} file(s) with schema {schema_ref}");
+
+    Ok(())
+}
+
 pub fn schema_list_indices(schema_ref: &str) -> Result<(), OxenError> {
     let repo_dir = env::current_dir().unwrap();
     let repository = LocalRepository::from_dir(&repo_dir)?;
@@ -464,6 +938,88 @@ pub fn show_current_branch() -> Result<(), OxenError> {
     Ok(())
 }
 
+pub fn gc(dry_run: bool) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let opts = GCOpts {
+        dry_run,
+        ..GCOpts::default()
+    };
+    let report = command::gc(&repository, &opts)?;
+    if report.is_empty() {
+        println!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    let verb = if report.dry_run {
+        "Would remove"
+    } else {
+        "Removed"
+    };
+    println!(
+        "{} {} unreachable versions and {} unreachable commits, freeing {}",
+        verb,
+        report.unreachable_versions.len(),
+        report.unreachable_commits.len(),
+        ByteSize::b(report.num_bytes)
+    );
+    Ok(())
+}
+
+pub async fn fsck(commit_id: Option<&str>, repair_from: Option<&str>) -> Result<(), OxenError> {
+    let repo_dir = env::current_dir().unwrap();
+    let repository = LocalRepository::from_dir(&repo_dir)?;
+
+    let report = command::fsck(&repository, commit_id)?;
+    println!(
+        "Checked {} commits and {} entries",
+        report.num_commits_checked, report.num_entries_checked
+    );
+    for entry in report.missing_versions.iter() {
+        println!("{} {:?} ({})", "missing".red(), entry.path, entry.commit_id);
+    }
+    for entry in report.corrupt_versions.iter() {
+        println!("{} {:?} ({})", "corrupt".red(), entry.path, entry.commit_id);
+    }
+    for missing in report.missing_parents.iter() {
+        println!(
+            "{} commit {} is missing parent {}",
+            "missing".red(),
+            missing.commit_id,
+            missing.parent_id
+        );
+    }
+    for db in report.corrupt_dbs.iter() {
+        println!("{} db {:?}: {}", "corrupt".red(), db.path, db.error);
+    }
+
+    if report.is_ok() {
+        println!("{}", "Repository is ok.".green());
+        return Ok(());
+    }
+
+    match repair_from {
+        Some(remote) => {
+            let num_repaired = command::fsck_repair(&repository, &report, remote).await?;
+            println!("Repaired {num_repaired} objects from {remote}");
+            // Make sure the repair worked
+            let report = command::fsck(&repository, commit_id)?;
+            if report.is_ok() {
+                println!("{}", "Repository is ok.".green());
+                Ok(())
+            } else {
+                Err(OxenError::basic_str(
+                    "Repository still has problems after repair, run `oxen fsck` to see them.",
+                ))
+            }
+        }
+        None => Err(OxenError::basic_str(
+            "Repository has problems, run `oxen fsck --repair-from <remote>` to repair them.",
+        )),
+    }
+}
+
 pub fn inspect(path: &Path) -> Result<(), OxenError> {
     command::inspect(path)
 }
@@ -25,20 +25,32 @@ async fn main() {
         .subcommand(cmd_setup::remote())
         .subcommand(cmd_setup::status())
         .subcommand(cmd_setup::log())
+        .subcommand(cmd_setup::blame())
+        .subcommand(cmd_setup::grep())
         .subcommand(cmd_setup::df())
         .subcommand(cmd_setup::schemas())
         .subcommand(cmd_setup::add())
+        .subcommand(cmd_setup::check_ignore())
         .subcommand(cmd_setup::rm())
+        .subcommand(cmd_setup::mv())
         .subcommand(cmd_setup::restore())
         .subcommand(cmd_setup::branch())
         .subcommand(cmd_setup::merge())
+        .subcommand(cmd_setup::cherry_pick())
+        .subcommand(cmd_setup::revert())
         .subcommand(cmd_setup::merge())
+        .subcommand(cmd_setup::rebase())
+        .subcommand(cmd_setup::reflog())
+        .subcommand(cmd_setup::stash())
+        .subcommand(cmd_setup::notes())
         .subcommand(cmd_setup::clone())
         .subcommand(cmd_setup::inspect_kv_db())
         .subcommand(cmd_setup::push())
         .subcommand(cmd_setup::pull())
         .subcommand(cmd_setup::diff())
         .subcommand(cmd_setup::migrate())
+        .subcommand(cmd_setup::gc())
+        .subcommand(cmd_setup::fsck())
         .subcommand(cmd_setup::read_lines());
 
     let matches = command.get_matches();
@@ -51,21 +63,33 @@ async fn main() {
         }
         Some((cmd_setup::REMOTE, sub_matches)) => parse_and_run::remote(sub_matches),
         Some((cmd_setup::STATUS, sub_matches)) => parse_and_run::status(sub_matches),
-        Some((cmd_setup::LOG, _sub_matches)) => parse_and_run::log(),
+        Some((cmd_setup::LOG, sub_matches)) => parse_and_run::log(sub_matches),
+        Some((cmd_setup::BLAME, sub_matches)) => parse_and_run::blame(sub_matches),
+        Some((cmd_setup::GREP, sub_matches)) => parse_and_run::grep(sub_matches),
         Some((cmd_setup::DF, sub_matches)) => parse_and_run::df(sub_matches),
         Some((cmd_setup::SCHEMAS, sub_matches)) => parse_and_run::schemas(sub_matches),
         Some((cmd_setup::ADD, sub_matches)) => parse_and_run::add(sub_matches),
+        Some((cmd_setup::CHECK_IGNORE, sub_matches)) => parse_and_run::check_ignore(sub_matches),
         Some((cmd_setup::RM, sub_matches)) => parse_and_run::rm(sub_matches),
+        Some((cmd_setup::MV, sub_matches)) => parse_and_run::mv(sub_matches),
         Some((cmd_setup::RESTORE, sub_matches)) => parse_and_run::restore(sub_matches),
         Some((cmd_setup::BRANCH, sub_matches)) => parse_and_run::branch(sub_matches).await,
         Some((cmd_setup::CHECKOUT, sub_matches)) => parse_and_run::checkout(sub_matches),
         Some((cmd_setup::MERGE, sub_matches)) => parse_and_run::merge(sub_matches),
+        Some((cmd_setup::CHERRY_PICK, sub_matches)) => parse_and_run::cherry_pick(sub_matches),
+        Some((cmd_setup::REVERT, sub_matches)) => parse_and_run::revert(sub_matches),
+        Some((cmd_setup::REBASE, sub_matches)) => parse_and_run::rebase(sub_matches),
+        Some((cmd_setup::REFLOG, sub_matches)) => parse_and_run::reflog(sub_matches),
+        Some((cmd_setup::STASH, sub_matches)) => parse_and_run::stash(sub_matches),
+        Some((cmd_setup::NOTES, sub_matches)) => parse_and_run::notes(sub_matches),
         Some((cmd_setup::PUSH, sub_matches)) => parse_and_run::push(sub_matches).await,
         Some((cmd_setup::PULL, sub_matches)) => parse_and_run::pull(sub_matches).await,
         Some((cmd_setup::DIFF, sub_matches)) => parse_and_run::diff(sub_matches),
         Some((cmd_setup::CLONE, sub_matches)) => parse_and_run::clone(sub_matches).await,
         Some((cmd_setup::COMMIT, sub_matches)) => parse_and_run::commit(sub_matches),
         Some((cmd_setup::MIGRATE, sub_matches)) => parse_and_run::migrate(sub_matches),
+        Some((cmd_setup::GC, sub_matches)) => parse_and_run::gc(sub_matches),
+        Some((cmd_setup::FSCK, sub_matches)) => parse_and_run::fsck(sub_matches).await,
         Some((cmd_setup::KVDB_INSPECT, sub_matches)) => parse_and_run::kvdb_inspect(sub_matches),
         Some((cmd_setup::READ_LINES, sub_matches)) => parse_and_run::read_lines(sub_matches),
         // TODO: Get these in the help command instead of just falling back
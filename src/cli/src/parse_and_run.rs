@@ -58,6 +58,37 @@ pub fn config(sub_matches: &ArgMatches) {
             }
         }
     }
+
+    if let Some(pattern) = sub_matches.value_of("ignore") {
+        match dispatch::add_global_ignore(pattern) {
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("{err}")
+            }
+        }
+    }
+
+    if sub_matches.is_present("generate-signing-key") {
+        match dispatch::generate_signing_key() {
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("{err}")
+            }
+        }
+    }
+
+    if let Some(trust_key) = sub_matches.values_of("trust-key") {
+        if let [email, public_key] = trust_key.collect::<Vec<_>>()[..] {
+            match dispatch::trust_key(email, public_key) {
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("{err}")
+                }
+            }
+        } else {
+            eprintln!("invalid arguments for --trust-key");
+        }
+    }
 }
 
 pub async fn create_remote(sub_matches: &ArgMatches) {
@@ -120,8 +151,9 @@ pub fn status(sub_matches: &ArgMatches) {
         .parse::<usize>()
         .expect("Limit must be a valid integer.");
     let print_all = sub_matches.is_present("print_all");
+    let path = sub_matches.value_of("path");
 
-    match dispatch::status(skip, limit, print_all) {
+    match dispatch::status(skip, limit, print_all, path) {
         Ok(_) => {}
         Err(err) => {
             eprintln!("{err}");
@@ -129,8 +161,11 @@ pub fn status(sub_matches: &ArgMatches) {
     }
 }
 
-pub fn log() {
-    match dispatch::log_commits() {
+pub fn log(sub_matches: &ArgMatches) {
+    let path = sub_matches.value_of("PATH");
+    let follow = sub_matches.is_present("follow");
+    let verify = sub_matches.is_present("verify");
+    match dispatch::log_commits(path, follow, verify) {
         Ok(_) => {}
         Err(err) => {
             eprintln!("{err}")
@@ -173,6 +208,34 @@ fn parse_df_sub_matches(sub_matches: &ArgMatches) -> liboxen::df::DFOpts {
         unique: sub_matches.value_of("unique").map(String::from),
         should_randomize: sub_matches.is_present("randomize"),
         should_reverse: sub_matches.is_present("reverse"),
+        indexed_rows: None,
+    }
+}
+
+pub fn blame(sub_matches: &ArgMatches) {
+    let path = sub_matches.value_of("PATH").expect("required");
+    let key = sub_matches.value_of("key");
+    let revision = sub_matches.value_of("revision");
+    let output = sub_matches.value_of("output").map(PathBuf::from);
+
+    match dispatch::blame(path, key, revision, output) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
+pub fn grep(sub_matches: &ArgMatches) {
+    let pattern = sub_matches.value_of("PATTERN").expect("required");
+    let glob = sub_matches.value_of("glob");
+    let revision = sub_matches.value_of("revision");
+
+    match dispatch::grep(pattern, glob, revision) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
     }
 }
 
@@ -228,6 +291,25 @@ pub fn schemas(sub_matches: &ArgMatches) {
                     }
                 }
             }
+            ("create_index", sub_matches) => {
+                let schema = sub_matches.value_of("SCHEMA").expect("required");
+                let field = sub_matches.value_of("FIELD").expect("required");
+                match dispatch::schema_create_index(schema, field) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        eprintln!("{err}")
+                    }
+                }
+            }
+            ("indices", sub_matches) => {
+                let schema = sub_matches.value_of("SCHEMA").expect("required");
+                match dispatch::schema_list_indices(schema) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        eprintln!("{err}")
+                    }
+                }
+            }
             (cmd, _) => {
                 eprintln!("Unknown subcommand {cmd}")
             }
@@ -257,6 +339,22 @@ pub fn add(sub_matches: &ArgMatches) {
     }
 }
 
+pub fn check_ignore(sub_matches: &ArgMatches) {
+    let paths: Vec<PathBuf> = sub_matches
+        .values_of("paths")
+        .expect("Must supply paths")
+        .map(PathBuf::from)
+        .collect();
+    let verbose = sub_matches.is_present("verbose");
+
+    match dispatch::check_ignore(paths, verbose) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
 pub fn rm(sub_matches: &ArgMatches) {
     let paths: Vec<PathBuf> = sub_matches
         .values_of("files")
@@ -272,6 +370,18 @@ pub fn rm(sub_matches: &ArgMatches) {
     }
 }
 
+pub fn mv(sub_matches: &ArgMatches) {
+    let src = sub_matches.value_of("SOURCE").expect("required");
+    let dst = sub_matches.value_of("DESTINATION").expect("required");
+
+    match dispatch::mv(src, dst) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
 pub fn restore(sub_matches: &ArgMatches) {
     let path = sub_matches.value_of("PATH").expect("required");
 
@@ -364,6 +474,110 @@ pub fn merge(sub_matches: &ArgMatches) {
     }
 }
 
+pub fn cherry_pick(sub_matches: &ArgMatches) {
+    let commit = sub_matches.value_of("COMMIT").expect("required");
+    match dispatch::cherry_pick(commit) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
+pub fn revert(sub_matches: &ArgMatches) {
+    let commit = sub_matches.value_of("COMMIT").expect("required");
+    match dispatch::revert(commit) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
+pub fn rebase(sub_matches: &ArgMatches) {
+    let result = if sub_matches.is_present("continue") {
+        dispatch::rebase_continue()
+    } else if sub_matches.is_present("abort") {
+        dispatch::rebase_abort()
+    } else {
+        match sub_matches.value_of("UPSTREAM") {
+            Some(upstream) => dispatch::rebase(upstream),
+            None => {
+                eprintln!("Must supply the branch to rebase onto, or --continue or --abort");
+                return;
+            }
+        }
+    };
+    match result {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
+pub fn reflog(sub_matches: &ArgMatches) {
+    let result = match sub_matches.subcommand() {
+        Some(("expire", sub_matches)) => match sub_matches.value_of("older-than") {
+            Some(days) => match days.parse::<u64>() {
+                Ok(days) => dispatch::reflog_expire(Some(days)),
+                Err(_) => {
+                    eprintln!("--older-than must be a number of days");
+                    return;
+                }
+            },
+            None => dispatch::reflog_expire(None),
+        },
+        Some((cmd, _)) => {
+            eprintln!("Unknown subcommand {cmd}");
+            return;
+        }
+        None => dispatch::reflog(),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}")
+    }
+}
+
+pub fn stash(sub_matches: &ArgMatches) {
+    let result = match sub_matches.subcommand() {
+        Some(("push", sub_matches)) => dispatch::stash_push(sub_matches.value_of("message")),
+        Some(("list", _)) => dispatch::stash_list(),
+        Some(("apply", sub_matches)) => dispatch::stash_apply(sub_matches.value_of("STASH"), false),
+        Some(("pop", sub_matches)) => dispatch::stash_apply(sub_matches.value_of("STASH"), true),
+        Some(("drop", sub_matches)) => dispatch::stash_drop(sub_matches.value_of("STASH")),
+        Some((cmd, _)) => {
+            eprintln!("Unknown subcommand {cmd}");
+            return;
+        }
+        // Like git, `oxen stash` on its own pushes
+        None => dispatch::stash_push(None),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}")
+    }
+}
+
+pub fn notes(sub_matches: &ArgMatches) {
+    let result = match sub_matches.subcommand() {
+        Some(("add", sub_matches)) => dispatch::notes_add(
+            sub_matches.value_of("COMMIT"),
+            sub_matches.value_of("message").unwrap(),
+        ),
+        Some(("show", sub_matches)) => dispatch::notes_show(sub_matches.value_of("COMMIT")),
+        Some(("remove", sub_matches)) => dispatch::notes_remove(sub_matches.value_of("COMMIT")),
+        Some(("list", _)) => dispatch::notes_list(),
+        Some((cmd, _)) => {
+            eprintln!("Unknown subcommand {cmd}");
+            return;
+        }
+        None => dispatch::notes_list(),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}")
+    }
+}
+
 pub async fn push(sub_matches: &ArgMatches) {
     let remote = sub_matches
         .value_of("REMOTE")
@@ -375,7 +589,8 @@ pub async fn push(sub_matches: &ArgMatches) {
     if sub_matches.is_present("delete") {
         println!("Delete remote branch {remote}/{branch}");
     } else {
-        match dispatch::push(remote, branch).await {
+        let merge_upstream = sub_matches.value_of("merge-upstream");
+        match dispatch::push(remote, branch, merge_upstream).await {
             Ok(_) => {}
             Err(err) => {
                 eprintln!("{err}")
@@ -471,6 +686,27 @@ pub fn migrate(sub_matches: &ArgMatches) {
     }
 }
 
+pub fn gc(sub_matches: &ArgMatches) {
+    let dry_run = sub_matches.is_present("dry-run");
+    match dispatch::gc(dry_run) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
+pub async fn fsck(sub_matches: &ArgMatches) {
+    let commit_id = sub_matches.value_of("commit");
+    let repair_from = sub_matches.value_of("repair-from");
+    match dispatch::fsck(commit_id, repair_from).await {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{err}")
+        }
+    }
+}
+
 pub fn kvdb_inspect(sub_matches: &ArgMatches) {
     let path_str = sub_matches.value_of("PATH").expect("required");
     let path = Path::new(path_str);
@@ -1,6 +1,7 @@
 pub mod branches;
 pub mod commits;
 pub mod entries;
+pub mod forks;
 pub mod namespaces;
 pub mod repositories;
 pub mod schemas;
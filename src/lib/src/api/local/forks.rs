@@ -0,0 +1,523 @@
+//! Forks of repositories on the server, and the merge requests opened from them back into the
+//! repository they were forked from. Version files are hard linked between the two repositories
+//! instead of copied when both keep them on the local disk, so a fork only takes up the space of
+//! its own new data.
+
+use crate::api;
+use crate::constants::{HISTORY_DIR, VERSIONS_DIR};
+use crate::db;
+use crate::error::OxenError;
+use crate::index::{CommitReader, CommitWriter};
+use crate::model::{Commit, ForkOrigin, LocalRepository, MergeRequest, MergeRequestStatus};
+use crate::storage::StorageConfig;
+use crate::util;
+
+use rocksdb::{IteratorMode, WriteBatch, DB};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+pub const FORK_ORIGIN_FILE: &str = "fork_origin.json";
+pub const MERGE_REQUESTS_FILE: &str = "merge_requests.json";
+
+/// Files in the hidden dir that belong to the upstream repository, and are not copied into a fork
+const UPSTREAM_ONLY_FILES: [&str; 4] = [
+    "tmp",
+    "branch_protection.json",
+    FORK_ORIGIN_FILE,
+    MERGE_REQUESTS_FILE,
+];
+
+/// Every RocksDB database dir has this file
+const ROCKSDB_CURRENT_FILE: &str = "CURRENT";
+
+/// Forks `upstream_namespace/upstream_name` into `namespace/name` within the sync dir. The fork
+/// keeps its versions in `storage`, or in its own .oxen/versions dir if it is None.
+pub fn fork(
+    sync_dir: &Path,
+    upstream_namespace: &str,
+    upstream_name: &str,
+    namespace: &str,
+    name: &str,
+    storage: Option<StorageConfig>,
+) -> Result<LocalRepository, OxenError> {
+    let upstream = api::local::repositories::get_by_namespace_and_name(
+        sync_dir,
+        upstream_namespace,
+        upstream_name,
+    )?
+    .ok_or_else(OxenError::local_repo_not_found)?;
+
+    let fork_dir = sync_dir.join(namespace).join(name);
+    if fork_dir.exists() {
+        let err = format!("Repository already exists {fork_dir:?}");
+        return Err(OxenError::basic_str(err));
+    }
+
+    let mut repo = upstream.clone();
+    repo.path = fork_dir;
+    repo.storage = storage;
+    let link_versions = upstream.stores_versions_locally() && repo.stores_versions_locally();
+
+    log::debug!("Forking {:?} -> {:?}", upstream.path, repo.path);
+    copy_for_fork(&upstream.path, &repo.path, link_versions)?;
+    repo.save_default()?;
+
+    // The fork never shares a remote version store with the upstream, so that garbage
+    // collecting one can not delete the versions of the other
+    if !link_versions {
+        let commits = CommitReader::new(&upstream)?.list_all()?;
+        let num_copied = copy_versions(&upstream, &repo, &commits)?;
+        log::debug!("Copied {} versions into fork", num_copied);
+    }
+
+    let origin = ForkOrigin {
+        namespace: String::from(upstream_namespace),
+        name: String::from(upstream_name),
+    };
+    let origin_path = util::fs::oxen_hidden_dir(&repo.path).join(FORK_ORIGIN_FILE);
+    util::fs::write_to_path(&origin_path, &serde_json::to_string(&origin)?)?;
+
+    Ok(repo)
+}
+
+fn copy_for_fork(src: &Path, dst: &Path, link_versions: bool) -> Result<(), OxenError> {
+    let src_hidden_dir = util::fs::oxen_hidden_dir(src);
+    let dst_hidden_dir = util::fs::oxen_hidden_dir(dst);
+
+    // Working files on the server, if there are any
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        if entry.path() == src_hidden_dir {
+            continue;
+        }
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            util::fs::copy_dir_all(entry.path(), dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), dst_path)?;
+        }
+    }
+
+    std::fs::create_dir_all(&dst_hidden_dir)?;
+    for entry in std::fs::read_dir(&src_hidden_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let dst_path = dst_hidden_dir.join(&file_name);
+        if file_name == VERSIONS_DIR {
+            if link_versions {
+                let num_linked = util::fs::link_dir_all(entry.path(), &dst_path)?;
+                log::debug!("Linked {} version files into fork", num_linked);
+            }
+        } else if UPSTREAM_ONLY_FILES.iter().any(|f| file_name == *f) {
+            continue;
+        } else if entry.file_type()?.is_dir() {
+            copy_db_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a dir from the .oxen dir, reading each database in it through a read only handle so
+/// databases that are open for writing while the server handles other requests are copied in a
+/// consistent state, without taking their lock
+fn copy_db_dir_all(src: &Path, dst: &Path) -> Result<(), OxenError> {
+    if src.join(ROCKSDB_CURRENT_FILE).exists() {
+        return copy_db(src, dst);
+    }
+
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_db_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_db(src: &Path, dst: &Path) -> Result<(), OxenError> {
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let opts = db::opts::default();
+    let src_db = DB::open_for_read_only(&opts, src, false)?;
+    let dst_db = DB::open(&opts, dst)?;
+    let mut batch = WriteBatch::default();
+    for (key, value) in src_db.iterator(IteratorMode::Start) {
+        batch.put(key, value);
+    }
+    dst_db.write(batch)?;
+    Ok(())
+}
+
+/// Copies the versions of the files in the commits from the version store of `src` into the one
+/// of `dst`, for repositories that do not both keep them on the local disk. Returns the number copied.
+fn copy_versions(
+    src: &LocalRepository,
+    dst: &LocalRepository,
+    commits: &[Commit],
+) -> Result<usize, OxenError> {
+    let src_store = src.version_store()?;
+    let dst_store = dst.version_store()?;
+    dst_store.init()?;
+
+    let mut seen: HashSet<(String, PathBuf)> = HashSet::new();
+    for commit in commits {
+        for entry in api::local::entries::list_all(src, commit)? {
+            let filename = entry.filename();
+            if !seen.insert((entry.hash.clone(), filename.clone())) {
+                continue;
+            }
+            if dst_store.version_exists(&entry.hash, &filename)? {
+                continue;
+            }
+            let version = src_store.local_version(&entry.hash, &filename)?;
+            dst_store.store_version_from_path(&entry.hash, &filename, version.path())?;
+        }
+    }
+    Ok(seen.len())
+}
+
+/// The repository this one was forked from, if it is a fork
+pub fn get_origin(repo: &LocalRepository) -> Result<Option<ForkOrigin>, OxenError> {
+    let path = util::fs::oxen_hidden_dir(&repo.path).join(FORK_ORIGIN_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = util::fs::read_from_path(&path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+/// Opens a merge from `branch` of the fork `source_namespace/source_name` into `target_branch` of
+/// the `upstream` it was forked from. Nothing is copied into the upstream until a user who can
+/// write to it accepts the merge request with `import_merge_request` and `accept_merge_request`.
+pub fn open_merge_request(
+    sync_dir: &Path,
+    upstream: &LocalRepository,
+    source_namespace: &str,
+    source_name: &str,
+    branch: &str,
+    target_branch: &str,
+) -> Result<MergeRequest, OxenError> {
+    let fork = api::local::repositories::get_by_namespace_and_name(
+        sync_dir,
+        source_namespace,
+        source_name,
+    )?
+    .ok_or_else(|| {
+        OxenError::basic_str(format!(
+            "Repository {source_namespace}/{source_name} not found"
+        ))
+    })?;
+    let origin = get_origin(&fork)?;
+    let is_fork_of_upstream = origin
+        .map(|origin| sync_dir.join(origin.namespace).join(origin.name) == upstream.path)
+        .unwrap_or(false);
+    if !is_fork_of_upstream {
+        let err =
+            format!("Repository {source_namespace}/{source_name} is not a fork of this repository");
+        return Err(OxenError::basic_str(err));
+    }
+
+    let source_branch = api::local::branches::get_by_name(&fork, branch)?.ok_or_else(|| {
+        OxenError::basic_str(format!(
+            "Branch '{branch}' not found in {source_namespace}/{source_name}"
+        ))
+    })?;
+    if api::local::branches::get_by_name(upstream, target_branch)?.is_none() {
+        let err = format!("Branch '{target_branch}' not found");
+        return Err(OxenError::basic_str(err));
+    }
+    let upstream_branch = format!("{source_namespace}-{branch}");
+    check_branch_is_free(upstream, &upstream_branch)?;
+
+    let merge_request = MergeRequest {
+        id: format!("{}", uuid::Uuid::new_v4()),
+        source_namespace: String::from(source_namespace),
+        source_name: String::from(source_name),
+        source_branch: String::from(branch),
+        commit_id: source_branch.commit_id,
+        branch: upstream_branch,
+        target_branch: String::from(target_branch),
+        status: MergeRequestStatus::Open,
+        created_at: OffsetDateTime::now_utc(),
+    };
+    let mut merge_requests = list_merge_requests(upstream)?;
+    merge_requests.push(merge_request.clone());
+    save_merge_requests(upstream, &merge_requests)?;
+
+    Ok(merge_request)
+}
+
+pub fn get_merge_request(
+    repo: &LocalRepository,
+    id: &str,
+) -> Result<Option<MergeRequest>, OxenError> {
+    Ok(list_merge_requests(repo)?
+        .into_iter()
+        .find(|merge_request| merge_request.id == id))
+}
+
+/// Copies the commits and version files of an open merge request that the upstream does not
+/// have yet. They are not on any branch until the merge request is accepted, so if the branch
+/// protection rules turn it down they are only left for garbage collection.
+pub fn import_merge_request(
+    sync_dir: &Path,
+    upstream: &LocalRepository,
+    merge_request: &MergeRequest,
+) -> Result<(), OxenError> {
+    check_is_open(merge_request)?;
+    let fork = api::local::repositories::get_by_namespace_and_name(
+        sync_dir,
+        &merge_request.source_namespace,
+        &merge_request.source_name,
+    )?
+    .ok_or_else(|| {
+        OxenError::basic_str(format!(
+            "Repository {}/{} of the merge request no longer exists",
+            merge_request.source_namespace, merge_request.source_name
+        ))
+    })?;
+
+    // Copy the commits the upstream does not have yet, oldest first
+    let fork_hidden_dir = util::fs::oxen_hidden_dir(&fork.path);
+    let upstream_hidden_dir = util::fs::oxen_hidden_dir(&upstream.path);
+    let history = CommitReader::new(&fork)?.history_from_commit_id(&merge_request.commit_id)?;
+    let upstream_reader = CommitReader::new(upstream)?;
+    let missing: Vec<Commit> = history
+        .into_iter()
+        .rev()
+        .filter(|commit| !upstream_reader.commit_id_exists(&commit.id))
+        .collect();
+
+    if upstream.stores_versions_locally() && fork.stores_versions_locally() {
+        let fork_versions_dir = fork_hidden_dir.join(VERSIONS_DIR);
+        if fork_versions_dir.exists() {
+            util::fs::link_dir_all(&fork_versions_dir, upstream_hidden_dir.join(VERSIONS_DIR))?;
+        }
+    } else {
+        copy_versions(&fork, upstream, &missing)?;
+    }
+
+    let commit_writer = CommitWriter::new(upstream)?;
+    for commit in missing.iter() {
+        let commit_history_dir = fork_hidden_dir.join(HISTORY_DIR).join(&commit.id);
+        let upstream_history_dir = upstream_hidden_dir.join(HISTORY_DIR).join(&commit.id);
+        if commit_history_dir.exists() {
+            // Left over from an import that did not finish, the commit is not in the db yet
+            if upstream_history_dir.exists() {
+                std::fs::remove_dir_all(&upstream_history_dir)?;
+            }
+            copy_db_dir_all(&commit_history_dir, &upstream_history_dir)?;
+        }
+        commit_writer.add_commit_to_db(commit)?;
+    }
+    log::debug!("Copied {} commits from fork into upstream", missing.len());
+    Ok(())
+}
+
+/// Points the merge request's branch in the upstream at its commit, once it has been imported
+pub fn accept_merge_request(
+    upstream: &LocalRepository,
+    id: &str,
+) -> Result<MergeRequest, OxenError> {
+    let mut merge_requests = list_merge_requests(upstream)?;
+    let merge_request = merge_requests
+        .iter_mut()
+        .find(|merge_request| merge_request.id == id)
+        .ok_or_else(|| OxenError::basic_str(format!("Merge request {id} not found")))?;
+    check_is_open(merge_request)?;
+    check_branch_is_free(upstream, &merge_request.branch)?;
+    if api::local::commits::get_by_id(upstream, &merge_request.commit_id)?.is_none() {
+        let err = format!("Merge request {id} has not been imported");
+        return Err(OxenError::basic_str(err));
+    }
+
+    api::local::branches::update(upstream, &merge_request.branch, &merge_request.commit_id)?;
+    merge_request.status = MergeRequestStatus::Accepted;
+    let merge_request = merge_request.clone();
+    save_merge_requests(upstream, &merge_requests)?;
+    Ok(merge_request)
+}
+
+fn check_is_open(merge_request: &MergeRequest) -> Result<(), OxenError> {
+    if merge_request.status != MergeRequestStatus::Open {
+        let err = format!("Merge request {} is not open", merge_request.id);
+        return Err(OxenError::basic_str(err));
+    }
+    Ok(())
+}
+
+fn check_branch_is_free(repo: &LocalRepository, branch: &str) -> Result<(), OxenError> {
+    if api::local::branches::get_by_name(repo, branch)?.is_some() {
+        let err = format!("Branch '{branch}' already exists");
+        return Err(OxenError::basic_str(err));
+    }
+    Ok(())
+}
+
+/// Merge requests that have been opened into the repository from its forks
+pub fn list_merge_requests(repo: &LocalRepository) -> Result<Vec<MergeRequest>, OxenError> {
+    let path = util::fs::oxen_hidden_dir(&repo.path).join(MERGE_REQUESTS_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents = util::fs::read_from_path(&path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn save_merge_requests(
+    repo: &LocalRepository,
+    merge_requests: &[MergeRequest],
+) -> Result<(), OxenError> {
+    let path = util::fs::oxen_hidden_dir(&repo.path).join(MERGE_REQUESTS_FILE);
+    util::fs::write_to_path(&path, &serde_json::to_string(merge_requests)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api;
+    use crate::command;
+    use crate::constants::VERSIONS_DIR;
+    use crate::error::OxenError;
+    use crate::model::MergeRequestStatus;
+    use crate::test;
+    use crate::util;
+
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_local_forks_fork_links_versions() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|sync_dir| {
+            let upstream_dir = sync_dir.join("ox").join("data");
+            let upstream = command::init(&upstream_dir)?;
+            let file = upstream_dir.join("hello.txt");
+            util::fs::write_to_path(&file, "hello")?;
+            command::add(&upstream, &file)?;
+            command::commit(&upstream, "Adding hello")?.unwrap();
+
+            let fork = api::local::forks::fork(sync_dir, "ox", "data", "bessie", "data", None)?;
+            assert_eq!(fork.path, sync_dir.join("bessie").join("data"));
+
+            let origin = api::local::forks::get_origin(&fork)?.unwrap();
+            assert_eq!(origin.namespace, "ox");
+            assert_eq!(origin.name, "data");
+            assert!(api::local::forks::get_origin(&upstream)?.is_none());
+
+            // The version files are the same files on disk
+            let commit = command::head_commit(&fork)?;
+            let entries = api::local::entries::list_all(&fork, &commit)?;
+            assert_eq!(entries.len(), 1);
+            let fork_version = util::fs::version_path(&fork, &entries[0]);
+            let upstream_version = util::fs::version_path(&upstream, &entries[0]);
+            assert!(
+                fork_version.starts_with(util::fs::oxen_hidden_dir(&fork.path).join(VERSIONS_DIR))
+            );
+            assert_eq!(
+                std::fs::metadata(fork_version)?.ino(),
+                std::fs::metadata(upstream_version)?.ino()
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_local_forks_merge_request_is_only_a_record_until_accepted() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|sync_dir| {
+            let upstream_dir = sync_dir.join("ox").join("data");
+            let upstream = command::init(&upstream_dir)?;
+            let file = upstream_dir.join("hello.txt");
+            util::fs::write_to_path(&file, "hello")?;
+            command::add(&upstream, &file)?;
+            command::commit(&upstream, "Adding hello")?.unwrap();
+
+            let fork = api::local::forks::fork(sync_dir, "ox", "data", "bessie", "data", None)?;
+            let file = fork.path.join("world.txt");
+            util::fs::write_to_path(&file, "world")?;
+            command::add(&fork, &file)?;
+            let commit = command::commit(&fork, "Adding world")?.unwrap();
+
+            let merge_request = api::local::forks::open_merge_request(
+                sync_dir, &upstream, "bessie", "data", "main", "main",
+            )?;
+            assert_eq!(merge_request.commit_id, commit.id);
+            assert_eq!(merge_request.branch, "bessie-main");
+            assert_eq!(merge_request.status, MergeRequestStatus::Open);
+
+            // Nothing is written to the upstream but the record
+            assert!(api::local::branches::get_by_name(&upstream, "bessie-main")?.is_none());
+            assert!(api::local::commits::get_by_id(&upstream, &commit.id)?.is_none());
+            assert!(api::local::forks::accept_merge_request(&upstream, &merge_request.id).is_err());
+
+            api::local::forks::import_merge_request(sync_dir, &upstream, &merge_request)?;
+            let accepted = api::local::forks::accept_merge_request(&upstream, &merge_request.id)?;
+            assert_eq!(accepted.status, MergeRequestStatus::Accepted);
+
+            let branch = api::local::branches::get_by_name(&upstream, "bessie-main")?.unwrap();
+            assert_eq!(branch.commit_id, commit.id);
+            let entries = api::local::entries::list_all(&upstream, &commit)?;
+            assert_eq!(entries.len(), 2);
+            for entry in entries {
+                assert!(util::fs::version_path(&upstream, &entry).exists());
+            }
+
+            let merge_requests = api::local::forks::list_merge_requests(&upstream)?;
+            assert_eq!(merge_requests.len(), 1);
+            assert_eq!(merge_requests[0].status, MergeRequestStatus::Accepted);
+
+            // Can only be accepted once
+            assert!(api::local::forks::accept_merge_request(&upstream, &merge_request.id).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_local_forks_merge_request_branch_already_exists() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|sync_dir| {
+            let upstream_dir = sync_dir.join("ox").join("data");
+            let upstream = command::init(&upstream_dir)?;
+            let file = upstream_dir.join("hello.txt");
+            util::fs::write_to_path(&file, "hello")?;
+            command::add(&upstream, &file)?;
+            command::commit(&upstream, "Adding hello")?.unwrap();
+
+            api::local::forks::fork(sync_dir, "ox", "data", "bessie", "data", None)?;
+            let merge_request = api::local::forks::open_merge_request(
+                sync_dir, &upstream, "bessie", "data", "main", "main",
+            )?;
+
+            // Someone created the branch in the meantime
+            api::local::branches::create(&upstream, "bessie-main")?;
+            assert!(api::local::forks::accept_merge_request(&upstream, &merge_request.id).is_err());
+            let result = api::local::forks::open_merge_request(
+                sync_dir, &upstream, "bessie", "data", "main", "main",
+            );
+            assert!(result.is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_local_forks_open_merge_request_not_a_fork() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|sync_dir| {
+            let upstream = command::init(&sync_dir.join("ox").join("data"))?;
+            command::init(&sync_dir.join("bessie").join("data"))?;
+            let result = api::local::forks::open_merge_request(
+                sync_dir, &upstream, "bessie", "data", "main", "main",
+            );
+            assert!(result.is_err());
+            Ok(())
+        })
+    }
+}
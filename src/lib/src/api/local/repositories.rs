@@ -6,6 +6,7 @@ use crate::index::{CommitDirReader, CommitWriter, RefWriter};
 use crate::model::DataTypeStat;
 use crate::model::RepoStats;
 use crate::model::{CommitStats, LocalRepository, RepositoryNew};
+use crate::storage::StorageConfig;
 use crate::util;
 
 use jwalk::WalkDir;
@@ -153,6 +154,15 @@ pub fn list_repos_in_namespace(namespace_path: &Path) -> Vec<LocalRepository> {
 pub fn create_empty(
     sync_dir: &Path,
     new_repo: &RepositoryNew,
+) -> Result<LocalRepository, OxenError> {
+    create_empty_with_storage(sync_dir, new_repo, None)
+}
+
+/// Creates the repository with its version files in `storage`, or in .oxen/versions if there is none
+pub fn create_empty_with_storage(
+    sync_dir: &Path,
+    new_repo: &RepositoryNew,
+    storage: Option<StorageConfig>,
 ) -> Result<LocalRepository, OxenError> {
     let repo_dir = sync_dir
         .join(&new_repo.namespace)
@@ -173,7 +183,11 @@ pub fn create_empty(
 
     // Create config file
     let config_path = util::fs::config_filepath(&repo_dir);
-    let local_repo = LocalRepository::new(&repo_dir)?;
+    let mut local_repo = LocalRepository::new(&repo_dir)?;
+    if let Some(storage) = storage {
+        local_repo.storage = Some(storage);
+        local_repo.version_store()?.init()?;
+    }
     local_repo.save(&config_path)?;
 
     // Create HEAD file and point it to DEFAULT_BRANCH_NAME
@@ -213,6 +227,7 @@ mod tests {
     use crate::error::OxenError;
     use crate::model::{Commit, LocalRepository, RepositoryNew};
     use crate::test;
+    use std::collections::HashMap;
     use std::path::Path;
     use time::OffsetDateTime;
 
@@ -233,6 +248,8 @@ mod tests {
                     author: String::from("Ox"),
                     email: String::from("ox@oxen.ai"),
                     timestamp,
+                    metadata: HashMap::new(),
+                    signature: None,
                 }),
             };
             let _repo = api::local::repositories::create_empty(sync_dir, &repo_new)?;
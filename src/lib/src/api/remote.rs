@@ -3,5 +3,7 @@ pub mod client;
 pub mod commits;
 pub mod dir;
 pub mod entries;
+pub mod merge_requests;
+pub mod notes;
 pub mod repositories;
 pub mod version;
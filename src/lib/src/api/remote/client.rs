@@ -8,6 +8,17 @@ use reqwest::{header, Client, ClientBuilder, IntoUrl};
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const USER_AGENT: &str = "Oxen";
 
+lazy_static! {
+    /// Every request made by this process is tagged with the same id, so everything
+    /// a single command like `oxen push` did can be found in the server logs
+    static ref REQUEST_ID: String = uuid::Uuid::new_v4().to_string();
+}
+
+/// Id sent in the `X-Oxen-Request-Id` header of every request from this process
+pub fn request_id() -> &'static str {
+    REQUEST_ID.as_str()
+}
+
 fn get_host_from_url<U: IntoUrl>(url: U) -> Result<String, OxenError> {
     let parsed_url = url.into_url()?;
     let mut host_str = parsed_url.host_str().unwrap_or_default().to_string();
@@ -61,7 +72,15 @@ pub fn builder_for_host<S: AsRef<str>>(host: S) -> Result<ClientBuilder, OxenErr
 }
 
 fn builder() -> ClientBuilder {
-    Client::builder().user_agent(format!("{USER_AGENT}/{VERSION}"))
+    let mut headers = header::HeaderMap::new();
+    // A uuid is always a valid header value
+    headers.insert(
+        http::REQUEST_ID_HEADER,
+        header::HeaderValue::from_static(request_id()),
+    );
+    Client::builder()
+        .user_agent(format!("{USER_AGENT}/{VERSION}"))
+        .default_headers(headers)
 }
 
 /// Performs an extra parse to validate that the response is success
@@ -91,22 +110,25 @@ fn parse_status_and_message(
         http::STATUS_SUCCESS => {
             if !status.is_success() {
                 return Err(OxenError::basic_str(format!(
-                    "Err status [{}] from url {} [{}]",
+                    "Err status [{}] from url {} [{}] [request id: {}]",
                     status,
                     url,
-                    response.desc_or_msg()
+                    response.desc_or_msg(),
+                    request_id()
                 )));
             }
 
             Ok(body)
         }
         http::STATUS_WARNING => Err(OxenError::basic_str(format!(
-            "Remote Warning: {}",
-            response.desc_or_msg()
+            "Remote Warning: {} [request id: {}]",
+            response.desc_or_msg(),
+            request_id()
         ))),
         http::STATUS_ERROR => Err(OxenError::basic_str(format!(
-            "Remote Err: {}",
-            response.desc_or_msg()
+            "Remote Err: {} [request id: {}]",
+            response.desc_or_msg(),
+            request_id()
         ))),
         status => Err(OxenError::basic_str(format!("Unknown status [{status}]"))),
     }
@@ -7,7 +7,10 @@ use crate::util;
 use crate::util::hasher::hash_buffer;
 use crate::{api, constants};
 // use crate::util::ReadProgress;
-use crate::view::{CommitParentsResponse, CommitResponse, IsValidStatusMessage, StatusMessage};
+use crate::view::{
+    CommitParentsResponse, CommitResponse, IsValidStatusMessage, LimitExceededResponse,
+    StatusMessage,
+};
 
 use std::path::Path;
 use std::str;
@@ -142,15 +145,20 @@ pub async fn get_remote_parent(
 
 pub async fn post_push_complete(
     remote_repo: &RemoteRepository,
+    branch_name: &str,
     commit_id: &str,
 ) -> Result<(), OxenError> {
-    let uri = format!("/commits/{commit_id}/complete");
+    // Send the branch so that the server can reject the push if the branch is protected
+    let uri = format!(
+        "/commits/{commit_id}/complete?branch={}",
+        urlencoding::encode(branch_name)
+    );
     let url = api::endpoint::url_from_repo(remote_repo, &uri)?;
     log::debug!("post_push_complete: {}", url);
 
     let client = client::new_for_url(&url)?;
     if let Ok(res) = client.post(&url).send().await {
-        let body = client::parse_json_body(&url, res).await?;
+        let body = parse_push_response(&url, res).await?;
         let response: Result<StatusMessage, serde_json::Error> = serde_json::from_str(&body);
         match response {
             Ok(_) => Ok(()),
@@ -163,6 +171,26 @@ pub async fn post_push_complete(
     }
 }
 
+/// Turns the 413 and 429 the server sends when a push goes over its quota, upload size or
+/// rate limits into an `OxenError::LimitExceeded`
+async fn parse_push_response(url: &str, res: reqwest::Response) -> Result<String, OxenError> {
+    let status = res.status();
+    if status == reqwest::StatusCode::PAYLOAD_TOO_LARGE
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        let body = res.text().await?;
+        let response: Result<LimitExceededResponse, serde_json::Error> =
+            serde_json::from_str(&body);
+        return match response {
+            Ok(response) => Err(OxenError::limit_exceeded(response)),
+            Err(_) => Err(OxenError::basic_str(format!(
+                "Err status [{status}] from url {url}\n{body}"
+            ))),
+        };
+    }
+    client::parse_json_body(url, res).await
+}
+
 pub async fn post_commit_to_server(
     local_repo: &LocalRepository,
     remote_repo: &RemoteRepository,
@@ -224,7 +252,7 @@ async fn create_commit_obj_on_server(
         .send()
         .await
     {
-        let body = client::parse_json_body(&url, res).await?;
+        let body = parse_push_response(&url, res).await?;
         log::debug!("create_commit_obj_on_server got response {}", body);
         let response: Result<CommitResponse, serde_json::Error> = serde_json::from_str(&body);
         match response {
@@ -278,6 +306,10 @@ pub async fn upload_single_tarball_to_server_with_retry(
             Ok(_) => {
                 return Ok(());
             }
+            Err(err @ OxenError::LimitExceeded(_)) => {
+                total_tries += 1;
+                wait_for_rate_limit(err).await?;
+            }
             Err(err) => {
                 total_tries += 1;
                 // Exponentially back off
@@ -287,7 +319,7 @@ pub async fn upload_single_tarball_to_server_with_retry(
                     sleep_time,
                     err
                 );
-                std::thread::sleep(std::time::Duration::from_secs(sleep_time));
+                tokio::time::sleep(std::time::Duration::from_secs(sleep_time)).await;
             }
         }
     }
@@ -295,6 +327,19 @@ pub async fn upload_single_tarball_to_server_with_retry(
     Err(OxenError::basic_str("Upload retry failed."))
 }
 
+/// Sleeps until the server accepts requests again if the push was rate limited, there
+/// is no point retrying when it went over the quota or upload size so that is returned
+async fn wait_for_rate_limit(err: OxenError) -> Result<(), OxenError> {
+    match err.retry_after_secs() {
+        Some(secs) => {
+            log::debug!("Rate limited, retrying in {}s", secs);
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            Ok(())
+        }
+        None => Err(err),
+    }
+}
+
 async fn upload_single_tarball_to_server(
     remote_repo: &RemoteRepository,
     commit: &Commit,
@@ -311,7 +356,7 @@ async fn upload_single_tarball_to_server(
     let size = buffer.len() as u64;
     match client.post(&url).body(buffer.to_owned()).send().await {
         Ok(res) => {
-            let body = client::parse_json_body(&url, res).await?;
+            let body = parse_push_response(&url, res).await?;
 
             log::debug!("upload_single_tarball_to_server got response {}", body);
             let response: Result<CommitResponse, serde_json::Error> = serde_json::from_str(&body);
@@ -380,6 +425,8 @@ async fn upload_data_to_server_in_chunks(
             Ok(_) => {
                 log::debug!("Success uploading chunk!")
             }
+            // The rest of the chunks would be rejected too
+            Err(err @ OxenError::LimitExceeded(_)) => return Err(err),
             Err(err) => {
                 log::error!("Err uploading chunk: {}", err)
             }
@@ -414,6 +461,10 @@ pub async fn upload_data_chunk_to_server_with_retry(
             Ok(_) => {
                 return Ok(());
             }
+            Err(err @ OxenError::LimitExceeded(_)) => {
+                total_tries += 1;
+                wait_for_rate_limit(err).await?;
+            }
             Err(err) => {
                 total_tries += 1;
                 // Exponentially back off
@@ -423,7 +474,7 @@ pub async fn upload_data_chunk_to_server_with_retry(
                     sleep_time,
                     err
                 );
-                std::thread::sleep(std::time::Duration::from_secs(sleep_time));
+                tokio::time::sleep(std::time::Duration::from_secs(sleep_time)).await;
             }
         }
     }
@@ -471,7 +522,7 @@ async fn upload_data_chunk_to_server(
 
     match client.post(&url).body(chunk.to_owned()).send().await {
         Ok(res) => {
-            let body = client::parse_json_body(&url, res).await?;
+            let body = parse_push_response(&url, res).await?;
 
             log::debug!("upload_data_chunk_to_server got response {}", body);
             let response: Result<CommitResponse, serde_json::Error> = serde_json::from_str(&body);
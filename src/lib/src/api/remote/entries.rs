@@ -2,6 +2,7 @@ use crate::api::remote::client;
 use crate::constants::AVG_CHUNK_SIZE;
 use crate::error::OxenError;
 use crate::model::{CommitEntry, LocalRepository, RemoteEntry, RemoteRepository};
+use crate::storage;
 use crate::util;
 use crate::{api, constants};
 // use crate::util::ReadProgress;
@@ -27,14 +28,15 @@ pub async fn create(
     remote_repo: &RemoteRepository,
     entry: &CommitEntry,
 ) -> Result<RemoteEntry, OxenError> {
-    let fullpath = util::fs::version_path(local_repo, entry);
-    log::debug!("Creating remote entry: {:?} -> {:?}", entry.path, fullpath);
-
-    if !fullpath.exists() {
-        return Err(OxenError::file_does_not_exist(fullpath));
-    }
+    // Kept until the upload is done, so a downloaded version is not cleaned up underneath it
+    let version = util::fs::local_version(local_repo, entry)?;
+    log::debug!(
+        "Creating remote entry: {:?} -> {:?}",
+        entry.path,
+        version.path()
+    );
 
-    let file = tokio::fs::File::open(&fullpath).await?;
+    let file = tokio::fs::File::open(version.path()).await?;
     let stream = FramedRead::new(file, BytesCodec::new());
     let body = reqwest::Body::wrap_stream(stream);
 
@@ -174,22 +176,14 @@ pub async fn download_large_entry(
         }
     }
 
-    // Copy to version path
-    let version_path = util::fs::version_path(local_repo, entry);
-    log::debug!("Copying to version path {:?}", version_path);
-    if let Some(parent) = version_path.parent() {
-        if !parent.exists() {
-            log::debug!("Creating parent {:?}", parent);
-            let err = format!("Could not create version dir path {parent:?}");
-            std::fs::create_dir_all(parent).expect(&err);
-        }
-    }
-    match std::fs::copy(&full_path, &version_path) {
-        Ok(_) => {}
-        Err(err) => {
-            let err = format!("Could not copy file {full_path:?} to {version_path:?}: {err}");
-            return Err(OxenError::basic_str(err));
-        }
+    // Copy to version store
+    log::debug!("Copying to version store {:?}", entry.hash);
+    let version_store = local_repo.version_store()?;
+    if let Err(err) =
+        version_store.store_version_from_path(&entry.hash, &entry.filename(), &full_path)
+    {
+        let err = format!("Could not store version of file {full_path:?}: {err}");
+        return Err(OxenError::basic_str(err));
     }
 
     Ok(())
@@ -325,6 +319,8 @@ pub async fn try_download_data_from_version_paths(
         let decoder = GzipDecoder::new(futures::io::BufReader::new(reader));
         let archive = Archive::new(decoder);
 
+        let version_store = local_repo.version_store()?;
+        let hidden_dir = util::fs::oxen_hidden_dir(&local_repo.path);
         let mut size: u64 = 0;
         // For debug if you want to see each file we are unpacking...
         let mut entries = archive.entries()?;
@@ -342,6 +338,14 @@ pub async fn try_download_data_from_version_paths(
             // log::debug!("Unpacking into path {:?}", path);
             file.unpack_in(&local_repo.path).await?;
 
+            // Make sure the version makes it to the store if it does not live in .oxen/versions
+            let version_path = util::fs::path_relative_to_dir(&fullpath, &hidden_dir)?;
+            if let Some((hash, filename)) =
+                storage::version_store::hash_and_filename_from_version_path(&version_path)
+            {
+                version_store.store_version_from_path(&hash, &filename, &fullpath)?;
+            }
+
             let metadata = std::fs::metadata(&fullpath)?;
             size += metadata.len();
             log::debug!("Unpacking {} bytes {:?}", metadata.len(), path);
@@ -388,17 +392,9 @@ pub async fn download_entry(
         let mut content = Cursor::new(response.bytes().await?);
         std::io::copy(&mut content, &mut dest)?;
 
-        // Copy to versions dir
-        let version_path = util::fs::version_path(repository, entry);
-
-        if let Some(parent) = version_path.parent() {
-            if !parent.exists() {
-                log::debug!("Create version parent dir {:?}", parent);
-                std::fs::create_dir_all(parent)?;
-            }
-        }
-
-        std::fs::copy(fpath, version_path)?;
+        // Copy to version store
+        let version_store = repository.version_store()?;
+        version_store.store_version_from_path(&entry.hash, &entry.filename(), &fpath)?;
     } else {
         let err = format!("Could not download entry status: {status}");
         return Err(OxenError::basic_str(err));
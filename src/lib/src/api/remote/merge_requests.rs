@@ -0,0 +1,96 @@
+use crate::api;
+use crate::api::remote::client;
+use crate::error::OxenError;
+use crate::model::{MergeRequest, Remote, RemoteRepository};
+use crate::view::{
+    ListMergeRequestsResponse, MergeRequestNew, MergeRequestResponse, RepositoryResponse,
+};
+use url::Url;
+
+/// Opens a merge of `branch` of the fork `repository` into `target_branch` of the repository it was forked from
+pub async fn create(
+    repository: &RemoteRepository,
+    branch: &str,
+    target_branch: &str,
+) -> Result<MergeRequest, OxenError> {
+    let upstream = get_upstream(repository).await?;
+    let url = api::endpoint::url_from_repo(&upstream, "/merge_requests")?;
+    log::debug!("api::remote::merge_requests::create {}", url);
+
+    let params = serde_json::to_string(&MergeRequestNew {
+        source_namespace: repository.namespace.clone(),
+        source_name: repository.name.clone(),
+        branch: String::from(branch),
+        target_branch: String::from(target_branch),
+    })?;
+
+    let client = client::new_for_url(&url)?;
+    if let Ok(res) = client.post(&url).body(params).send().await {
+        let body = client::parse_json_body(&url, res).await?;
+        let response: Result<MergeRequestResponse, serde_json::Error> = serde_json::from_str(&body);
+        match response {
+            Ok(response) => Ok(response.merge_request),
+            Err(err) => {
+                let err = format!(
+                    "Could not open merge request from [{}]: {}\n{}",
+                    repository.name, err, body
+                );
+                Err(OxenError::basic_str(err))
+            }
+        }
+    } else {
+        let msg = format!("Could not open merge request from branch {branch}");
+        log::error!("api::remote::merge_requests::create() {}", msg);
+        Err(OxenError::basic_str(&msg))
+    }
+}
+
+/// The repository the fork was made from, on the same server
+async fn get_upstream(repository: &RemoteRepository) -> Result<RemoteRepository, OxenError> {
+    let url = api::endpoint::url_from_repo(repository, "")?;
+    let client = client::new_for_url(&url)?;
+    let res = client.get(&url).send().await?;
+    let body = client::parse_json_body(&url, res).await?;
+    let response: RepositoryResponse = serde_json::from_str(&body).map_err(|err| {
+        OxenError::basic_str(format!("Could not get repository [{url}]: {err}\n{body}"))
+    })?;
+    let origin = response.repository.fork_of.ok_or_else(|| {
+        OxenError::basic_str(format!(
+            "Repository {}/{} is not a fork",
+            repository.namespace, repository.name
+        ))
+    })?;
+
+    let mut remote_url = Url::parse(&repository.remote.url)?;
+    remote_url.set_path(&format!("{}/{}", origin.namespace, origin.name));
+    Ok(RemoteRepository {
+        namespace: origin.namespace,
+        name: origin.name,
+        remote: Remote {
+            url: remote_url.to_string(),
+            name: repository.remote.name.clone(),
+        },
+    })
+}
+
+pub async fn list(repository: &RemoteRepository) -> Result<Vec<MergeRequest>, OxenError> {
+    let url = api::endpoint::url_from_repo(repository, "/merge_requests")?;
+
+    let client = client::new_for_url(&url)?;
+    if let Ok(res) = client.get(&url).send().await {
+        let body = client::parse_json_body(&url, res).await?;
+        let response: Result<ListMergeRequestsResponse, serde_json::Error> =
+            serde_json::from_str(&body);
+        match response {
+            Ok(response) => Ok(response.merge_requests),
+            Err(err) => {
+                let err = format!("Could not list merge requests: {err}\n{body}");
+                Err(OxenError::basic_str(err))
+            }
+        }
+    } else {
+        let msg = "Could not list merge requests";
+        log::error!("api::remote::merge_requests::list() {}", msg);
+        Err(OxenError::basic_str(msg))
+    }
+}
@@ -0,0 +1,60 @@
+use crate::api;
+use crate::api::remote::client;
+use crate::error::OxenError;
+use crate::model::{Note, RemoteRepository};
+use crate::view::{ListNotesResponse, NotesUpdate};
+
+/// All the notes on the remote, including the removed ones
+pub async fn list(repository: &RemoteRepository) -> Result<Vec<Note>, OxenError> {
+    let url = api::endpoint::url_from_repo(repository, "/notes")?;
+
+    let client = client::new_for_url(&url)?;
+    if let Ok(res) = client.get(&url).send().await {
+        let body = client::parse_json_body(&url, res).await?;
+        let response: Result<ListNotesResponse, serde_json::Error> = serde_json::from_str(&body);
+        match response {
+            Ok(j_res) => Ok(j_res.notes),
+            Err(err) => {
+                log::debug!(
+                    "remote::notes::list() Could not deserialize response [{}] {}",
+                    err,
+                    body
+                );
+                Err(OxenError::basic_str("Could not list remote notes"))
+            }
+        }
+    } else {
+        let err = "Failed to list notes";
+        log::error!("remote::notes::list() err: {}", err);
+        Err(OxenError::basic_str(err))
+    }
+}
+
+/// Pushes the notes to the remote, which keeps the newest note for each commit and responds
+/// with all of its notes
+pub async fn post(repository: &RemoteRepository, notes: Vec<Note>) -> Result<Vec<Note>, OxenError> {
+    let url = api::endpoint::url_from_repo(repository, "/notes")?;
+    log::debug!("remote::notes::post {} notes to {}", notes.len(), url);
+
+    let params = serde_json::to_string(&NotesUpdate { notes })?;
+
+    let client = client::new_for_url(&url)?;
+    if let Ok(res) = client.post(&url).body(params).send().await {
+        let body = client::parse_json_body(&url, res).await?;
+        let response: Result<ListNotesResponse, serde_json::Error> = serde_json::from_str(&body);
+        match response {
+            Ok(j_res) => Ok(j_res.notes),
+            Err(err) => {
+                let err = format!(
+                    "Could not push notes [{}]: {}\n{}",
+                    repository.name, err, body
+                );
+                Err(OxenError::basic_str(err))
+            }
+        }
+    } else {
+        let err = "Failed to push notes";
+        log::error!("remote::notes::post() err: {}", err);
+        Err(OxenError::basic_str(err))
+    }
+}
@@ -5,6 +5,7 @@ use crate::error::OxenError;
 use crate::model::{LocalRepository, Remote, RemoteRepository};
 use crate::view::{RepositoryResolveResponse, RepositoryResponse, StatusMessage};
 use serde_json::json;
+use url::Url;
 
 pub async fn get_by_remote_repo(
     repo: &RemoteRepository,
@@ -78,6 +79,46 @@ pub async fn create<S: AsRef<str>>(
     }
 }
 
+/// Forks the repository into `namespace/name` on the same server
+pub async fn fork(
+    repository: &RemoteRepository,
+    namespace: &str,
+    name: &str,
+) -> Result<RemoteRepository, OxenError> {
+    let url = api::endpoint::url_from_repo(repository, "/fork")?;
+    let params = json!({ "namespace": namespace, "name": name });
+    log::debug!("Fork remote: {} -> {}/{}", url, namespace, name);
+
+    let client = client::new_for_url(&url)?;
+    if let Ok(res) = client.post(&url).json(&params).send().await {
+        let body = client::parse_json_body(&url, res).await?;
+        let response: Result<RepositoryResponse, serde_json::Error> = serde_json::from_str(&body);
+        match response {
+            Ok(response) => {
+                let mut remote_url = Url::parse(&repository.remote.url)?;
+                remote_url.set_path(&format!("{namespace}/{name}"));
+                Ok(RemoteRepository::from_view(
+                    &response.repository,
+                    &Remote {
+                        url: remote_url.to_string(),
+                        name: repository.remote.name.clone(),
+                    },
+                ))
+            }
+            Err(err) => {
+                let err = format!(
+                    "Could not fork repository [{}]: {}\n{}",
+                    repository.name, err, body
+                );
+                Err(OxenError::basic_str(err))
+            }
+        }
+    } else {
+        let err = format!("Fork repository could not connect to {url}");
+        Err(OxenError::basic_str(err))
+    }
+}
+
 pub async fn delete(repository: &RemoteRepository) -> Result<StatusMessage, OxenError> {
     let url = api::endpoint::url_from_repo(repository, "")?;
     log::debug!("Deleting repository: {}", url);
@@ -8,24 +8,33 @@ use crate::compute;
 use crate::constants;
 use crate::df::{df_opts::DFOpts, tabular};
 use crate::error::OxenError;
+use crate::index::oxenignore::IgnoreMatch;
 use crate::index::SchemaIndexReader;
-use crate::index::{self, differ};
+use crate::index::{
+    self, blamer, differ, notes, rebaser, reflog, schema_indexer, searcher, stasher,
+};
 use crate::index::{
     CommitDirReader, CommitReader, CommitWriter, EntryIndexer, MergeConflictReader, Merger,
-    RefReader, RefWriter, Stager,
+    OxenIgnore, RefReader, RefWriter, Stager, StatCache,
 };
 use crate::model::schema;
 use crate::model::Schema;
-use crate::model::{Branch, Commit, LocalRepository, RemoteBranch, RemoteRepository, StagedData};
+use crate::model::{
+    Branch, Commit, CommitEntry, FsckReport, GCReport, LocalRepository, MergeConflict, Note,
+    RebaseReport, ReflogEntry, RemoteBranch, RemoteRepository, StagedData,
+};
 
-use crate::opts::RestoreOpts;
+use crate::opts::{GCOpts, RestoreOpts};
 use crate::util;
 use crate::util::resource;
 
 use bytevec::ByteDecodable;
+use polars::prelude::DataFrame;
 use rocksdb::{IteratorMode, LogLevel, Options, DB};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str;
+use std::time::Duration;
 
 /// # Initialize an Empty Oxen Repository
 ///
@@ -143,14 +152,19 @@ pub fn status(repository: &LocalRepository) -> Result<StagedData, OxenError> {
     Ok(status)
 }
 
-/// Similar to status but takes the starting directory to look from
-pub fn status_from_dir(repository: &LocalRepository, dir: &Path) -> Result<StagedData, OxenError> {
+/// Similar to status but takes the starting directory to look from,
+/// and optionally only checks the paths within `path_filter`
+pub fn status_from_dir(
+    repository: &LocalRepository,
+    dir: &Path,
+    path_filter: Option<&Path>,
+) -> Result<StagedData, OxenError> {
     log::debug!("status before new_from_head");
     let reader = CommitDirReader::new_from_head(repository)?;
     log::debug!("status before Stager::new");
     let stager = Stager::new(repository)?;
     log::debug!("status before stager.status");
-    let status = stager.status_from_dir(&reader, dir)?;
+    let status = stager.status_from_dir(&reader, dir, path_filter)?;
     Ok(status)
 }
 
@@ -189,6 +203,18 @@ pub fn add<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Result<(), OxenEr
     Ok(())
 }
 
+/// The .oxenignore or user config pattern that decides whether the path is ignored, None if no pattern matches.
+/// A negated pattern means the path is not ignored.
+pub fn check_ignore<P: AsRef<Path>>(
+    repo: &LocalRepository,
+    path: P,
+) -> Result<Option<IgnoreMatch>, OxenError> {
+    let full_path = repo.path.join(path.as_ref());
+    let relative = util::fs::path_relative_to_dir(&full_path, &repo.path)?;
+    let ignore = OxenIgnore::new(repo);
+    Ok(ignore.matched(&relative, full_path.is_dir()))
+}
+
 /// Removes the path from disk then adds it to the removed index
 pub fn rm<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Result<(), OxenError> {
     let path = path.as_ref();
@@ -205,6 +231,62 @@ pub fn rm<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Result<(), OxenErr
     add(repo, path)
 }
 
+/// Moves a committed file or directory on disk and stages the rename. The hashes of the committed
+/// entries are reused unless the files changed since they were hashed, so unchanged files are not
+/// hashed or copied into the version store again.
+pub fn mv<P: AsRef<Path>, Q: AsRef<Path>>(
+    repo: &LocalRepository,
+    src: P,
+    dst: Q,
+) -> Result<(), OxenError> {
+    let full_src = repo.path.join(src.as_ref());
+    let mut full_dst = repo.path.join(dst.as_ref());
+    if full_dst.is_dir() {
+        if let Some(file_name) = full_src.file_name() {
+            full_dst = full_dst.join(file_name);
+        }
+    }
+    if full_dst.exists() {
+        let err = format!("Destination already exists: {:?}", dst.as_ref());
+        return Err(OxenError::basic_str(err));
+    }
+    if !full_src.exists() {
+        return Err(OxenError::file_does_not_exist(&full_src));
+    }
+
+    let relative_src = util::fs::path_relative_to_dir(&full_src, &repo.path)?;
+    let relative_dst = util::fs::path_relative_to_dir(&full_dst, &repo.path)?;
+    let commit = head_commit(repo)?;
+    let reader = CommitDirReader::new(repo, &commit)?;
+    let entries = match reader.get_entry(&relative_src)? {
+        Some(entry) => vec![(entry, relative_dst.to_owned())],
+        None => reader
+            .list_files_from_dir(&relative_src)
+            .into_iter()
+            .map(|entry| {
+                let to = relative_dst.join(entry.path.strip_prefix(&relative_src).unwrap());
+                (entry, to)
+            })
+            .collect(),
+    };
+    if entries.is_empty() {
+        let err = format!("Path is not committed: {relative_src:?}");
+        return Err(OxenError::basic_str(err));
+    }
+
+    if let Some(parent) = full_dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(&full_src, &full_dst)?;
+
+    let stager = Stager::new_with_merge(repo)?;
+    let stat_cache = StatCache::new(repo)?;
+    for (entry, to) in entries.iter() {
+        stager.add_moved_file(entry, to, &reader, &stat_cache)?;
+    }
+    Ok(())
+}
+
 /// Interact with DataFrames from CLI
 pub fn df<P: AsRef<Path>>(input: P, opts: DFOpts) -> Result<(), OxenError> {
     let mut df = tabular::show_path(input, opts.clone())?;
@@ -285,6 +367,26 @@ pub fn schema_list_indices(
     }
 }
 
+/// Index the values of a schema field at HEAD, so that `field == value` filters on the files with
+/// the schema look up the matching rows instead of scanning. Returns the number of files indexed.
+pub fn schema_create_index(
+    repo: &LocalRepository,
+    schema_ref: &str,
+    field_name: &str,
+) -> Result<usize, OxenError> {
+    let head_commit = head_commit(repo)?;
+    if let Some(schema) = schema_get(repo, Some(&head_commit.id), schema_ref)? {
+        let field = schema
+            .fields
+            .iter()
+            .find(|f| f.name == field_name)
+            .ok_or_else(|| OxenError::schema_does_not_have_field(field_name))?;
+        schema_indexer::create_field_index(repo, &head_commit, &schema, field)
+    } else {
+        Err(OxenError::schema_does_not_exist(schema_ref))
+    }
+}
+
 /// # Restore a removed file that was committed
 ///
 /// ```
@@ -356,6 +458,16 @@ pub fn restore(repo: &LocalRepository, opts: RestoreOpts) -> Result<(), OxenErro
 /// # }
 /// ```
 pub fn commit(repo: &LocalRepository, message: &str) -> Result<Option<Commit>, OxenError> {
+    commit_with_metadata(repo, message, &HashMap::new())
+}
+
+/// # Commit the staged files with key value metadata
+/// The metadata is stored on the commit and is part of its id
+pub fn commit_with_metadata(
+    repo: &LocalRepository,
+    message: &str,
+    metadata: &HashMap<String, String>,
+) -> Result<Option<Commit>, OxenError> {
     let status = status(repo)?;
     if !status.has_added_entries() {
         println!(
@@ -363,13 +475,13 @@ pub fn commit(repo: &LocalRepository, message: &str) -> Result<Option<Commit>, O
         );
         return Ok(None);
     }
-    let commit = p_commit(repo, &status, message)?;
+    let commit = p_commit(repo, &status, message, metadata)?;
     Ok(Some(commit))
 }
 
 fn commit_with_no_files(repo: &LocalRepository, message: &str) -> Result<Commit, OxenError> {
     let status = StagedData::empty();
-    let commit = p_commit(repo, &status, message)?;
+    let commit = p_commit(repo, &status, message, &HashMap::new())?;
     Ok(commit)
 }
 
@@ -377,10 +489,11 @@ fn p_commit(
     repo: &LocalRepository,
     status: &StagedData,
     message: &str,
+    metadata: &HashMap<String, String>,
 ) -> Result<Commit, OxenError> {
     let stager = Stager::new(repo)?;
     let commit_writer = CommitWriter::new(repo)?;
-    let commit = commit_writer.commit(status, message)?;
+    let commit = commit_writer.commit_with_metadata(status, message, metadata)?;
     stager.unstage()?;
     Ok(commit)
 }
@@ -415,6 +528,95 @@ pub fn log(repo: &LocalRepository) -> Result<Vec<Commit>, OxenError> {
     Ok(commits)
 }
 
+/// # Get the commits that changed the file at `path`, newest first
+/// If `follow` is true the history continues from the old path when the file was renamed
+pub fn log_path<P: AsRef<Path>>(
+    repo: &LocalRepository,
+    path: P,
+    follow: bool,
+) -> Result<Vec<Commit>, OxenError> {
+    let mut path = path.as_ref().to_path_buf();
+    let mut commits: Vec<Commit> = vec![];
+    for commit in log(repo)? {
+        let entry = api::local::entries::get_entry_for_commit(repo, &commit, &path)?;
+        let parent = match commit.parent_ids.first() {
+            Some(parent_id) => api::local::commits::get_by_id(repo, parent_id)?,
+            None => None,
+        };
+        let parent_entry = match &parent {
+            Some(parent) => api::local::entries::get_entry_for_commit(repo, parent, &path)?,
+            None => None,
+        };
+
+        let changed = match (&entry, &parent_entry) {
+            (Some(entry), Some(parent_entry)) => entry.hash != parent_entry.hash,
+            (None, None) => false,
+            _ => true,
+        };
+        if !changed {
+            continue;
+        }
+
+        // Added in this commit, it may have been moved from a path with the same content
+        if let (true, Some(entry), None, Some(parent)) = (follow, &entry, &parent_entry, &parent) {
+            if let Some(from) = moved_from_path(repo, &commit, parent, entry)? {
+                log::debug!("log_path following {:?} -> {:?}", from, path);
+                path = from;
+            }
+        }
+        commits.push(commit);
+    }
+    Ok(commits)
+}
+
+/// The path in the parent commit that has the same content as the entry, and is gone in the commit
+fn moved_from_path(
+    repo: &LocalRepository,
+    commit: &Commit,
+    parent: &Commit,
+    entry: &CommitEntry,
+) -> Result<Option<PathBuf>, OxenError> {
+    let reader = CommitDirReader::new(repo, commit)?;
+    let parent_reader = CommitDirReader::new(repo, parent)?;
+    for parent_entry in parent_reader.list_entries()? {
+        if parent_entry.hash == entry.hash && !reader.has_file(&parent_entry.path) {
+            return Ok(Some(parent_entry.path));
+        }
+    }
+    Ok(None)
+}
+
+/// # Search the files of a commit for a regex
+/// Text files are searched line by line and tabular files cell by cell, straight from the version
+/// files. `path_glob` is a gitignore style pattern to only search some of the files, and the
+/// search stops after `max_matches` if it is set.
+pub fn search(
+    repo: &LocalRepository,
+    commit: &Commit,
+    pattern: &str,
+    path_glob: Option<&str>,
+    max_matches: Option<usize>,
+) -> Result<Vec<SearchMatch>, OxenError> {
+    searcher::search(repo, commit, pattern, path_glob, max_matches)
+}
+
+/// # Blame the rows of a tabular file
+/// Returns the rows, or the values of the `key` column, with the commit that introduced each one.
+/// `revision` is a commit id or branch name, and defaults to HEAD.
+pub fn blame<P: AsRef<Path>>(
+    repo: &LocalRepository,
+    path: P,
+    key: Option<&str>,
+    revision: Option<&str>,
+) -> Result<DataFrame, OxenError> {
+    let commit = match revision {
+        Some(revision) => api::local::commits::get_by_id_or_branch(repo, revision)?
+            .ok_or_else(|| OxenError::local_commit_or_branch_not_found(revision))?,
+        None => head_commit(repo)?,
+    };
+    blamer::blame(repo, &commit, path.as_ref(), key)
+}
+
 /// # Get the history for a specific branch or commit
 pub fn log_commit_or_branch_history(
     repo: &LocalRepository,
@@ -538,15 +740,22 @@ pub fn checkout_theirs<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Resul
     );
 
     // find the path that matches in the conflict, throw error if !found
-    if let Some(conflict) = conflicts
-        .iter()
-        .find(|c| c.merge_entry.path == path.as_ref())
-    {
-        // Lookup the file for the merge commit entry and copy it over
-        restore(
-            repo,
-            RestoreOpts::from_path_ref(path, conflict.merge_entry.commit_id.clone()),
-        )
+    if let Some(conflict) = conflicts.iter().find(|c| c.path() == path.as_ref()) {
+        match conflict.merge_entry() {
+            // Lookup the file for the merge commit entry and copy it over
+            Some(merge_entry) => restore(
+                repo,
+                RestoreOpts::from_path_ref(path, merge_entry.commit_id.clone()),
+            ),
+            // They removed the file
+            None => {
+                let working_path = repo.path.join(path.as_ref());
+                if working_path.exists() {
+                    std::fs::remove_file(working_path)?;
+                }
+                Ok(())
+            }
+        }
     } else {
         Err(OxenError::could_not_find_merge_conflict(path))
     }
@@ -563,15 +772,20 @@ pub fn checkout_combine<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Resu
         conflicts.len()
     );
     // find the path that matches in the conflict, throw error if !found
-    if let Some(conflict) = conflicts
-        .iter()
-        .find(|c| c.merge_entry.path == path.as_ref())
-    {
-        if util::fs::is_tabular(&conflict.head_entry.path) {
-            let df_head_path = util::fs::version_path(repo, &conflict.head_entry);
-            let df_head = tabular::read_df(df_head_path, DFOpts::empty())?;
-            let df_merge_path = util::fs::version_path(repo, &conflict.merge_entry);
-            let df_merge = tabular::read_df(df_merge_path, DFOpts::empty())?;
+    if let Some(conflict) = conflicts.iter().find(|c| c.path() == path.as_ref()) {
+        let (head_entry, merge_entry) = match (conflict.head_entry(), conflict.merge_entry()) {
+            (Some(head_entry), Some(merge_entry)) => (head_entry, merge_entry),
+            _ => {
+                return Err(OxenError::basic_str(
+                    "Cannot use --combine on a file that was removed.",
+                ))
+            }
+        };
+        if util::fs::is_tabular(&head_entry.path) {
+            let df_head_version = util::fs::local_version(repo, head_entry)?;
+            let df_head = tabular::read_df(df_head_version.path(), DFOpts::empty())?;
+            let df_merge_version = util::fs::local_version(repo, merge_entry)?;
+            let df_merge = tabular::read_df(df_merge_version.path(), DFOpts::empty())?;
 
             log::debug!("GOT DF HEAD {}", df_head);
             log::debug!("GOT DF MERGE {}", df_merge);
@@ -582,7 +796,7 @@ pub fn checkout_combine<P: AsRef<Path>>(repo: &LocalRepository, path: P) -> Resu
                     match result.unique(None, polars::frame::UniqueKeepStrategy::First) {
                         Ok(mut uniq) => {
                             log::debug!("GOT DF COMBINED UNIQUE {}", uniq);
-                            let output_path = repo.path.join(&conflict.head_entry.path);
+                            let output_path = repo.path.join(&head_entry.path);
                             tabular::write_df(&mut uniq, &output_path)
                         }
                         _ => Err(OxenError::basic_str("Could not uniq data")),
@@ -708,6 +922,224 @@ pub fn merge<S: AsRef<str>>(
     }
 }
 
+/// # Apply the changes a commit made to its parent on top of HEAD, and commit them
+/// Returns None if there were conflicts, they show up in `oxen status` to be resolved and committed
+pub fn cherry_pick<S: AsRef<str>>(
+    repo: &LocalRepository,
+    commit_or_branch: S,
+) -> Result<Option<Commit>, OxenError> {
+    let (commit, parent) = commit_and_parent(repo, commit_or_branch.as_ref())?;
+    let message = format!(
+        "{}\n\n(cherry picked from commit {})",
+        commit.message, commit.id
+    );
+    commit_delta(repo, &parent, &commit, &message, Some(&commit))
+}
+
+/// # Undo the changes a commit made to its parent on top of HEAD, and commit them
+/// Returns None if there were conflicts, they show up in `oxen status` to be resolved and committed
+pub fn revert<S: AsRef<str>>(
+    repo: &LocalRepository,
+    commit_or_branch: S,
+) -> Result<Option<Commit>, OxenError> {
+    let (commit, parent) = commit_and_parent(repo, commit_or_branch.as_ref())?;
+    let message = format!(
+        "Revert \"{}\"\n\nThis reverts commit {}.",
+        commit.message, commit.id
+    );
+    commit_delta(repo, &commit, &parent, &message, None)
+}
+
+fn commit_and_parent(
+    repo: &LocalRepository,
+    commit_or_branch: &str,
+) -> Result<(Commit, Commit), OxenError> {
+    let commit = api::local::commits::get_by_id_or_branch(repo, commit_or_branch)?
+        .ok_or_else(|| OxenError::local_commit_or_branch_not_found(commit_or_branch))?;
+    let parent_id = match commit.parent_ids.first() {
+        Some(parent_id) => parent_id,
+        None => {
+            let err = format!("Commit {} has no parent to compare against", commit.id);
+            return Err(OxenError::basic_str(err));
+        }
+    };
+    let parent = api::local::commits::get_by_id(repo, parent_id)?
+        .ok_or_else(|| OxenError::commit_db_corrupted(parent_id))?;
+    Ok((commit, parent))
+}
+
+/// Applies the delta from `base` to `target` onto HEAD and commits it with HEAD as the only parent,
+/// as the author of `original` if it is given
+fn commit_delta(
+    repo: &LocalRepository,
+    base: &Commit,
+    target: &Commit,
+    message: &str,
+    original: Option<&Commit>,
+) -> Result<Option<Commit>, OxenError> {
+    if MergeConflictReader::new(repo)?.has_conflicts()? {
+        return Err(OxenError::basic_str(
+            "There are unresolved conflicts, resolve them and commit first.",
+        ));
+    }
+    if status(repo)?.has_added_entries() {
+        return Err(OxenError::basic_str(
+            "There are staged changes, commit or stash them first.",
+        ));
+    }
+
+    let (paths, conflicts) = {
+        let merger = Merger::new(repo)?;
+        merger.apply_delta(base, target)?
+    };
+    if conflicts.is_empty() && paths.is_empty() {
+        return Err(OxenError::basic_str(
+            "The changes are already in HEAD, nothing to commit.",
+        ));
+    }
+
+    // Stage what applied cleanly, so only the conflicts are left to resolve and add
+    let head = head_commit(repo)?;
+    let reader = CommitDirReader::new(repo, &head)?;
+    let stager = Stager::new(repo)?;
+    for path in paths.iter() {
+        stager.add(&repo.path.join(path), &reader)?;
+    }
+    if !conflicts.is_empty() {
+        log::debug!(
+            "commit_delta {} stopped on {} conflicts",
+            target.id,
+            conflicts.len()
+        );
+        return Ok(None);
+    }
+    let status = stager.status(&reader)?;
+    let commit_writer = CommitWriter::new(repo)?;
+    let commit = match original {
+        Some(original) => {
+            commit_writer.commit_as_author_of(&status, vec![head.id], message, original)?
+        }
+        None => commit_writer.commit_with_parent_ids(&status, vec![head.id], message)?,
+    };
+    stager.unstage()?;
+    Ok(Some(commit))
+}
+
+/// # Stash the staged and modified files
+/// Saves them to a commit that is not on any branch and puts the working files back to HEAD.
+/// Returns None if there was nothing to stash.
+pub fn stash_push(
+    repo: &LocalRepository,
+    message: Option<&str>,
+) -> Result<Option<Commit>, OxenError> {
+    stasher::push(repo, message)
+}
+
+/// # List the stashes, newest first
+pub fn stash_list(repo: &LocalRepository) -> Result<Vec<Commit>, OxenError> {
+    stasher::list(repo)
+}
+
+/// # Apply a stash to the working files
+/// Files that were also changed on HEAD are returned as conflicts, and show up in `status`
+pub fn stash_apply(repo: &LocalRepository, index: usize) -> Result<Vec<MergeConflict>, OxenError> {
+    stasher::apply(repo, index)
+}
+
+/// # Apply a stash and remove it if there were no conflicts
+pub fn stash_pop(repo: &LocalRepository, index: usize) -> Result<Vec<MergeConflict>, OxenError> {
+    stasher::pop(repo, index)
+}
+
+/// # Remove a stash without applying it
+pub fn stash_drop(repo: &LocalRepository, index: usize) -> Result<Commit, OxenError> {
+    stasher::drop(repo, index)
+}
+
+/// # Replay the commits of the current branch that are not in `upstream` on top of it
+/// The replayed commits get new ids and keep their author, the original ones stay reachable from
+/// the `reflog`. If a commit stops on conflicts they show up in `status` to be resolved and added
+/// before `rebase_continue`, or the rebase can be undone with `rebase_abort`.
+pub fn rebase<S: AsRef<str>>(
+    repo: &LocalRepository,
+    upstream: S,
+) -> Result<RebaseReport, OxenError> {
+    rebaser::rebase(repo, upstream.as_ref())
+}
+
+/// # Commit the resolved conflicts of a stopped rebase and replay the rest of the commits
+pub fn rebase_continue(repo: &LocalRepository) -> Result<RebaseReport, OxenError> {
+    rebaser::continue_rebase(repo)
+}
+
+/// # Put the branch back to the commit it was on before the rebase started
+pub fn rebase_abort(repo: &LocalRepository) -> Result<Commit, OxenError> {
+    rebaser::abort(repo)
+}
+
+/// # List the moves of HEAD that rewrote history, newest first
+pub fn reflog(repo: &LocalRepository) -> Result<Vec<ReflogEntry>, OxenError> {
+    reflog::list(repo)
+}
+
+/// # Remove the reflog entries older than `older_than`
+/// The commits only they kept reachable can be garbage collected afterwards. Returns how many were removed.
+pub fn reflog_expire(repo: &LocalRepository, older_than: Duration) -> Result<usize, OxenError> {
+    reflog::expire(repo, older_than)
+}
+
+/// # Add a note to a commit or the tip of a branch, replacing the note it had
+/// Defaults to the HEAD commit.
+/// Notes are not part of the commit id, so they can change after the commit and sync on push and pull
+pub fn notes_add(
+    repo: &LocalRepository,
+    commit_id_or_branch: Option<&str>,
+    message: &str,
+) -> Result<Note, OxenError> {
+    let commit = notes_commit(repo, commit_id_or_branch)?;
+    notes::set(repo, &commit.id, message)
+}
+
+/// # Get the note on a commit
+pub fn notes_show(
+    repo: &LocalRepository,
+    commit_id_or_branch: Option<&str>,
+) -> Result<Option<Note>, OxenError> {
+    let commit = notes_commit(repo, commit_id_or_branch)?;
+    notes::get(repo, &commit.id)
+}
+
+/// # Remove the note from a commit, returns the note that was removed
+pub fn notes_remove(
+    repo: &LocalRepository,
+    commit_id_or_branch: Option<&str>,
+) -> Result<Option<Note>, OxenError> {
+    let commit = notes_commit(repo, commit_id_or_branch)?;
+    notes::remove(repo, &commit.id)
+}
+
+/// # List the notes on all the commits
+pub fn notes_list(repo: &LocalRepository) -> Result<Vec<Note>, OxenError> {
+    notes::list(repo)
+}
+
+/// The commit for the id or branch name, defaults to HEAD
+fn notes_commit(
+    repo: &LocalRepository,
+    commit_id_or_branch: Option<&str>,
+) -> Result<Commit, OxenError> {
+    let commit_id_or_branch = match commit_id_or_branch {
+        Some(commit_id_or_branch) => commit_id_or_branch,
+        None => return head_commit(repo),
+    };
+    match api::local::commits::get_by_id_or_branch(repo, commit_id_or_branch)? {
+        Some(commit) => Ok(commit),
+        None => Err(OxenError::local_commit_or_branch_not_found(
+            commit_id_or_branch,
+        )),
+    }
+}
+
 /// # List local branches
 pub fn list_branches(repo: &LocalRepository) -> Result<Vec<Branch>, OxenError> {
     let ref_reader = RefReader::new(repo)?;
@@ -912,6 +1344,51 @@ pub fn migrate_repo(repo: &LocalRepository) -> Result<(), OxenError> {
     Ok(())
 }
 
+/// # Garbage collect unreachable versions and history
+/// Walks every ref and removes the version files and commit history nothing points to anymore,
+/// pass `GCOpts::dry_run()` to only report what would be removed
+pub fn gc(repo: &LocalRepository, opts: &GCOpts) -> Result<GCReport, OxenError> {
+    index::garbage_collector::collect(repo, opts)
+}
+
+/// Run garbage collection on all repositories within a directory
+pub fn gc_all_repos(
+    path: &Path,
+    opts: &GCOpts,
+) -> Result<Vec<(LocalRepository, GCReport)>, OxenError> {
+    let mut reports: Vec<(LocalRepository, GCReport)> = vec![];
+    let namespaces = api::local::repositories::list_namespaces(path)?;
+    for namespace in namespaces {
+        let namespace_path = path.join(namespace);
+        let repos = api::local::repositories::list_repos_in_namespace(&namespace_path);
+        for repo in repos {
+            match gc(&repo, opts) {
+                Ok(report) => reports.push((repo, report)),
+                Err(err) => {
+                    log::error!("Could not gc repo {:?}\nErr: {}", repo.path, err)
+                }
+            }
+        }
+    }
+    Ok(reports)
+}
+
+/// # Check the integrity of the repository
+/// Verifies the version file for every entry hashes to the entry's hash, that commit parents exist,
+/// and that the dbs open cleanly. Pass a commit id to only check that commit.
+pub fn fsck(repo: &LocalRepository, commit_id: Option<&str>) -> Result<FsckReport, OxenError> {
+    index::fsck::check(repo, commit_id)
+}
+
+/// # Repair the problems found by `fsck` by downloading the objects from a remote
+pub async fn fsck_repair(
+    repo: &LocalRepository,
+    report: &FsckReport,
+    remote: &str,
+) -> Result<usize, OxenError> {
+    index::fsck::repair(repo, report, remote).await
+}
+
 /// Inspect a key value database for debugging
 pub fn inspect(path: &Path) -> Result<(), OxenError> {
     let mut opts = Options::default();
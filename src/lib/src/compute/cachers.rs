@@ -1,2 +1,3 @@
 pub mod content_validator;
 pub mod convert_to_arrow;
+pub mod pre_receive;
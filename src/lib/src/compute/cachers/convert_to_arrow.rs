@@ -9,12 +9,12 @@ pub fn convert_to_arrow(repo: &LocalRepository, commit: &Commit) -> Result<(), O
     let commit_entry_reader = CommitDirReader::new(repo, commit)?;
 
     for entry in commit_entry_reader.list_entries()? {
-        let version_path = util::fs::version_path(repo, &entry);
         let arrow_path = util::fs::df_version_path(repo, &entry);
-        let is_already_arrow = util::fs::has_ext(&version_path, "arrow");
-        if util::fs::is_tabular(&version_path) && !arrow_path.exists() && !is_already_arrow {
+        let is_already_arrow = util::fs::has_ext(&entry.path, "arrow");
+        if util::fs::is_tabular(&entry.path) && !arrow_path.exists() && !is_already_arrow {
             log::debug!("convert_to_arrow converting {:?}", entry.path);
-            let mut df = tabular::read_df(version_path, DFOpts::empty())?;
+            let version = util::fs::local_version(repo, &entry)?;
+            let mut df = tabular::read_df(version.path(), DFOpts::empty())?;
             tabular::write_df(&mut df, &arrow_path)?;
             log::debug!("convert_to_arrow wrote {:?}", arrow_path);
         }
@@ -0,0 +1,496 @@
+//! pre_receive runs the checks configured in `.oxen/pre_receive.toml` on the entries a commit adds or changes,
+//! so that the server can refuse to move a branch to a commit that does not pass them
+
+use crate::compute::commit_cacher;
+use crate::compute::commit_cacher::{CacherStatusType, PRE_RECEIVE_CACHER};
+use crate::config::pre_receive_config::HookCommand;
+use crate::config::PreReceiveConfig;
+use crate::df::{tabular, DFOpts};
+use crate::error::OxenError;
+use crate::index::{CommitDirReader, CommitReader};
+use crate::model::{Commit, CommitEntry, LocalRepository, Schema};
+use crate::storage::LocalVersion;
+use crate::util;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Cacher entry point, fails with every check that did not pass so they are reported in the commit status
+pub fn run(repo: &LocalRepository, commit: &Commit) -> Result<(), OxenError> {
+    log::debug!("Running pre_receive checks on commit {}", commit.id);
+    let failures = check(repo, commit)?;
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(OxenError::basic_str(format!(
+            "pre-receive checks failed: {}",
+            failures.join("; ")
+        )))
+    }
+}
+
+/// Returns why the commit did not pass the pre-receive checks, or None if it did.
+/// Uses the result from processing the commit if there is one, otherwise runs the checks.
+pub fn validate(repo: &LocalRepository, commit: &Commit) -> Result<Option<String>, OxenError> {
+    if PreReceiveConfig::get(repo)?.is_none() {
+        return Ok(None);
+    }
+
+    match commit_cacher::get_cacher_status(repo, commit, PRE_RECEIVE_CACHER)? {
+        Some(status) => match status.status {
+            CacherStatusType::Success => Ok(None),
+            CacherStatusType::Failed => Ok(Some(status.status_message)),
+            CacherStatusType::Pending => Ok(Some(format!(
+                "commit {} is still running pre-receive checks",
+                commit.id
+            ))),
+        },
+        None => {
+            // Commits that did not push any entries are never processed
+            let failures = check(repo, commit)?;
+            if failures.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(format!(
+                    "pre-receive checks failed: {}",
+                    failures.join("; ")
+                )))
+            }
+        }
+    }
+}
+
+/// Runs every configured check and returns a message for each one that failed
+pub fn check(repo: &LocalRepository, commit: &Commit) -> Result<Vec<String>, OxenError> {
+    let config = match PreReceiveConfig::get(repo)? {
+        Some(config) => config,
+        None => return Ok(vec![]),
+    };
+
+    let entries = changed_entries(repo, commit)?;
+    log::debug!(
+        "pre_receive checking {} changed entries in commit {}",
+        entries.len(),
+        commit.id
+    );
+
+    let mut failures: Vec<String> = vec![];
+    if let Some(max_mb) = config.max_file_size_mb {
+        for entry in entries.iter() {
+            if entry.num_bytes > max_mb * BYTES_PER_MB {
+                failures.push(format!(
+                    "{:?} is larger than the max file size of {} MB",
+                    entry.path, max_mb
+                ));
+            }
+        }
+    }
+
+    for entry in entries.iter() {
+        let path = entry.path.to_string_lossy();
+        let schema_rules: Vec<_> = config
+            .schemas
+            .iter()
+            .filter(|rule| util::str::wildcard_match(&rule.path, &path))
+            .collect();
+        let non_null_rules: Vec<_> = config
+            .non_null
+            .iter()
+            .filter(|rule| util::str::wildcard_match(&rule.path, &path))
+            .collect();
+        if schema_rules.is_empty() && non_null_rules.is_empty() {
+            continue;
+        }
+
+        if !util::fs::is_tabular(&entry.path) {
+            failures.push(format!("{:?} is not a tabular file", entry.path));
+            continue;
+        }
+        let version = util::fs::local_version(repo, entry)?;
+        let df = tabular::read_df(version.path(), DFOpts::empty())?;
+        let schema = Schema::from_polars(&df.schema());
+
+        for rule in schema_rules {
+            let expected = Schema::from_fields(rule.fields.clone());
+            if expected.hash != schema.hash {
+                failures.push(format!(
+                    "{:?} has schema {} which does not match the declared schema {} for {}",
+                    entry.path,
+                    schema_to_string(&schema),
+                    schema_to_string(&expected),
+                    rule.path
+                ));
+            }
+        }
+
+        for rule in non_null_rules {
+            for column in rule.columns.iter() {
+                match df.column(column) {
+                    Ok(series) => {
+                        let null_count = series.null_count();
+                        if null_count > 0 {
+                            failures.push(format!(
+                                "{:?} has {} null values in required column '{}'",
+                                entry.path, null_count, column
+                            ));
+                        }
+                    }
+                    Err(_) => {
+                        failures.push(format!(
+                            "{:?} is missing required column '{}'",
+                            entry.path, column
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for hook in config.hooks.iter() {
+        if let Some(failure) = run_hook(repo, commit, hook, &entries)? {
+            failures.push(failure);
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Entries that are new or changed compared to the first parent of the commit
+fn changed_entries(repo: &LocalRepository, commit: &Commit) -> Result<Vec<CommitEntry>, OxenError> {
+    let entries = CommitDirReader::new(repo, commit)?.list_entries()?;
+
+    let commit_reader = CommitReader::new(repo)?;
+    let parent = match commit.parent_ids.first() {
+        Some(parent_id) => commit_reader.get_commit_by_id(parent_id)?,
+        None => None,
+    };
+    let parent = match parent {
+        Some(parent) => parent,
+        None => return Ok(entries),
+    };
+
+    let parent_hashes: HashMap<PathBuf, String> = CommitDirReader::new(repo, &parent)?
+        .list_entries()?
+        .into_iter()
+        .map(|entry| (entry.path, entry.hash))
+        .collect();
+    Ok(entries
+        .into_iter()
+        .filter(|entry| parent_hashes.get(&entry.path) != Some(&entry.hash))
+        .collect())
+}
+
+/// Runs the hook with the repository path and commit id as arguments, and each changed
+/// entry on stdin as `<path>\t<version path>`. Returns the failure if it exits with a non zero status
+/// or does not finish within its timeout.
+fn run_hook(
+    repo: &LocalRepository,
+    commit: &Commit,
+    hook: &HookCommand,
+    entries: &[CommitEntry],
+) -> Result<Option<String>, OxenError> {
+    log::debug!("pre_receive running hook {} {:?}", hook.name, hook.command);
+
+    // Versions that are not stored on disk are downloaded, and removed again when these are dropped,
+    // so they have to live until the hook exits
+    let mut versions: Vec<LocalVersion> = vec![];
+    let mut input = String::new();
+    for entry in entries.iter() {
+        let version = util::fs::local_version(repo, entry)?;
+        input.push_str(&format!(
+            "{}\t{}\n",
+            entry.path.display(),
+            version.path().display()
+        ));
+        versions.push(version);
+    }
+
+    let child = Command::new(&hook.command)
+        .args(&hook.args)
+        .arg(&repo.path)
+        .arg(&commit.id)
+        .env("OXEN_REPO_PATH", &repo.path)
+        .env("OXEN_COMMIT_ID", &commit.id)
+        .current_dir(&repo.path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            return Ok(Some(format!(
+                "hook {} could not run {:?}: {}",
+                hook.name, hook.command, err
+            )))
+        }
+    };
+
+    // Write stdin and drain stdout and stderr on their own threads, so a hook that prints
+    // before it reads all of its input cannot block on a full pipe
+    let stdin = child.stdin.take().map(|mut stdin| {
+        thread::spawn(move || {
+            // The hook does not have to read its input
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let stdout = child.stdout.take().map(read_to_string_in_thread);
+    let stderr = child.stderr.take().map(read_to_string_in_thread);
+
+    let timeout = hook.timeout();
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            log::warn!("pre_receive hook {} timed out, killing it", hook.name);
+            // It may have exited since we checked
+            let _ = child.kill();
+            child.wait()?;
+            // Do not wait on the pipes, anything the hook started in the background could still hold them
+            return Ok(Some(format!(
+                "hook {} did not finish within {}s",
+                hook.name,
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(HOOK_POLL_INTERVAL);
+    };
+
+    if let Some(stdin) = stdin {
+        let _ = stdin.join();
+    }
+    let stdout = stdout
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    let stderr = stderr
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    drop(versions);
+
+    if status.success() {
+        return Ok(None);
+    }
+
+    let message = if stderr.trim().is_empty() {
+        stdout.trim().to_string()
+    } else {
+        stderr.trim().to_string()
+    };
+    Ok(Some(format!(
+        "hook {} failed with {}: {}",
+        hook.name, status, message
+    )))
+}
+
+fn read_to_string_in_thread<R: Read + Send + 'static>(mut reader: R) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        let _ = reader.read_to_end(&mut buffer);
+        String::from_utf8_lossy(&buffer).to_string()
+    })
+}
+
+fn schema_to_string(schema: &Schema) -> String {
+    let fields: Vec<String> = schema
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name, field.dtype))
+        .collect();
+    format!("[{}]", fields.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::compute::cachers::pre_receive;
+    use crate::config::pre_receive_config::{HookCommand, NonNullRule, SchemaRule};
+    use crate::config::PreReceiveConfig;
+    use crate::error::OxenError;
+    use crate::model::schema::Field;
+    use crate::test;
+    use crate::util;
+
+    fn field(name: &str, dtype: &str) -> Field {
+        Field {
+            name: String::from(name),
+            dtype: String::from(dtype),
+        }
+    }
+
+    #[test]
+    fn test_pre_receive_no_config_passes() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = repo.path.join("data.csv");
+            util::fs::write_to_path(&path, "file,label\na.jpg,cat\n")?;
+            command::add(&repo, &path)?;
+            let commit = command::commit(&repo, "Adding data")?.unwrap();
+
+            assert!(pre_receive::check(&repo, &commit)?.is_empty());
+            assert!(pre_receive::validate(&repo, &commit)?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pre_receive_schema_and_non_null() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let dir = repo.path.join("annotations");
+            std::fs::create_dir_all(&dir)?;
+            util::fs::write_to_path(
+                &dir.join("train.csv"),
+                "file,label,score\na.jpg,cat,1\nb.jpg,dog,\n",
+            )?;
+            command::add(&repo, &dir)?;
+            let commit = command::commit(&repo, "Adding annotations")?.unwrap();
+
+            let config = PreReceiveConfig {
+                schemas: vec![SchemaRule {
+                    path: String::from("annotations/*.csv"),
+                    fields: vec![
+                        field("file", "str"),
+                        field("label", "str"),
+                        field("score", "i64"),
+                    ],
+                }],
+                non_null: vec![NonNullRule {
+                    path: String::from("annotations/*"),
+                    columns: vec![String::from("file")],
+                }],
+                ..PreReceiveConfig::default()
+            };
+            config.save(&repo)?;
+            assert!(pre_receive::check(&repo, &commit)?.is_empty());
+
+            let config = PreReceiveConfig {
+                schemas: vec![SchemaRule {
+                    path: String::from("annotations/*.csv"),
+                    fields: vec![field("file", "str"), field("width", "i64")],
+                }],
+                non_null: vec![NonNullRule {
+                    path: String::from("annotations/*"),
+                    columns: vec![String::from("score"), String::from("missing")],
+                }],
+                ..PreReceiveConfig::default()
+            };
+            config.save(&repo)?;
+            let failures = pre_receive::check(&repo, &commit)?;
+            assert_eq!(failures.len(), 3);
+            assert!(pre_receive::validate(&repo, &commit)?.is_some());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pre_receive_max_file_size_only_checks_changed_files() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let first = repo.path.join("first.txt");
+            util::fs::write_to_path(&first, "first")?;
+            command::add(&repo, &first)?;
+            command::commit(&repo, "Adding first")?;
+
+            let second = repo.path.join("second.txt");
+            util::fs::write_to_path(&second, "second")?;
+            command::add(&repo, &second)?;
+            let commit = command::commit(&repo, "Adding second")?.unwrap();
+
+            let config = PreReceiveConfig {
+                max_file_size_mb: Some(0),
+                ..PreReceiveConfig::default()
+            };
+            config.save(&repo)?;
+            let failures = pre_receive::check(&repo, &commit)?;
+            assert_eq!(failures.len(), 1);
+            assert!(failures[0].contains("second.txt"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pre_receive_hooks() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = repo.path.join("hello.txt");
+            util::fs::write_to_path(&path, "hello")?;
+            command::add(&repo, &path)?;
+            let commit = command::commit(&repo, "Adding hello")?.unwrap();
+
+            let config = PreReceiveConfig {
+                hooks: vec![
+                    HookCommand {
+                        name: String::from("pass"),
+                        command: String::from("sh"),
+                        args: vec![String::from("-c"), String::from("exit 0")],
+                        timeout_secs: None,
+                    },
+                    HookCommand {
+                        name: String::from("fail"),
+                        command: String::from("sh"),
+                        args: vec![
+                            String::from("-c"),
+                            String::from("echo 'no hello allowed' >&2; exit 1"),
+                        ],
+                        timeout_secs: None,
+                    },
+                ],
+                ..PreReceiveConfig::default()
+            };
+            config.save(&repo)?;
+
+            let failures = pre_receive::check(&repo, &commit)?;
+            assert_eq!(failures.len(), 1);
+            assert!(failures[0].contains("no hello allowed"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pre_receive_hook_reads_versions_and_times_out() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = repo.path.join("hello.txt");
+            util::fs::write_to_path(&path, "hello")?;
+            command::add(&repo, &path)?;
+            let commit = command::commit(&repo, "Adding hello")?.unwrap();
+
+            let config = PreReceiveConfig {
+                hooks: vec![
+                    HookCommand {
+                        name: String::from("read"),
+                        command: String::from("sh"),
+                        args: vec![
+                            String::from("-c"),
+                            String::from("cut -f 2 | while read version; do grep -q hello \"$version\" || exit 1; done"),
+                        ],
+                        timeout_secs: None,
+                    },
+                    HookCommand {
+                        name: String::from("slow"),
+                        command: String::from("sh"),
+                        args: vec![String::from("-c"), String::from("sleep 30")],
+                        timeout_secs: Some(1),
+                    },
+                ],
+                ..PreReceiveConfig::default()
+            };
+            config.save(&repo)?;
+
+            let failures = pre_receive::check(&repo, &commit)?;
+            assert_eq!(failures.len(), 1);
+            assert!(failures[0].contains("slow did not finish"));
+
+            Ok(())
+        })
+    }
+}
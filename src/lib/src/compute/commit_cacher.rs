@@ -9,7 +9,7 @@ use crate::error::OxenError;
 use crate::model::{Commit, LocalRepository};
 use crate::util;
 
-use super::cachers::content_validator;
+use super::cachers::{content_validator, pre_receive};
 use lazy_static::lazy_static;
 use rocksdb::{DBWithThreadMode, MultiThreaded};
 use serde::{Deserialize, Serialize};
@@ -51,6 +51,9 @@ impl CacherStatus {
     }
 }
 
+/// Name the pre-receive checks are stored under in the status db
+pub const PRE_RECEIVE_CACHER: &str = "PRE_RECEIVE_CHECKS";
+
 type CommitCacher = fn(&LocalRepository, &Commit) -> Result<(), OxenError>;
 
 lazy_static! {
@@ -58,6 +61,7 @@ lazy_static! {
     static ref CACHERS: HashMap<String, CommitCacher> = {
         let mut cachers = HashMap::new();
         cachers.insert(String::from("COMMIT_CONTENT_IS_VALID"), content_validator::compute as CommitCacher);
+        cachers.insert(String::from(PRE_RECEIVE_CACHER), pre_receive::run as CommitCacher);
         // cachers.insert(String::from("ARROW_CONVERSION"), convert_to_arrow::convert_to_arrow as CommitCacher);
         cachers
     };
@@ -131,6 +135,27 @@ pub fn get_all_statuses(
     }
 }
 
+/// Return the status of a single cacher, None if it has not been run on the commit
+pub fn get_cacher_status(
+    repo: &LocalRepository,
+    commit: &Commit,
+    name: &str,
+) -> Result<Option<CacherStatus>, OxenError> {
+    let db_path = cached_status_db_path(repo, commit);
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    let opts = db::opts::default();
+    match DBWithThreadMode::<MultiThreaded>::open(&opts, db_path) {
+        Ok(db) => str_json_db::get(&db, name),
+        Err(_) => {
+            // Same as get_all_statuses, another thread is still writing to the db
+            log::debug!("Could not open db....still processing");
+            Ok(Some(CacherStatus::pending()))
+        }
+    }
+}
+
 /// Run all the cachers and update their status's as you go
 pub fn run_all(repo: &LocalRepository, commit: &Commit) -> Result<(), OxenError> {
     // Create kvdb of NAME -> STATUS
@@ -1,5 +1,15 @@
 pub mod endpoint;
+pub mod pre_receive_config;
+pub mod signing_key;
+pub mod trusted_keys_config;
 pub mod user_config;
 
+pub use crate::config::pre_receive_config::PreReceiveConfig;
+pub use crate::config::pre_receive_config::PRE_RECEIVE_CONFIG_FILENAME;
+pub use crate::config::signing_key::SigningKey;
+pub use crate::config::signing_key::SIGNING_KEY_FILENAME;
+pub use crate::config::trusted_keys_config::TrustedKey;
+pub use crate::config::trusted_keys_config::TrustedKeysConfig;
+pub use crate::config::trusted_keys_config::TRUSTED_KEYS_CONFIG_FILENAME;
 pub use crate::config::user_config::UserConfig;
 pub use crate::config::user_config::USER_CONFIG_FILENAME;
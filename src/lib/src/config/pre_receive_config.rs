@@ -0,0 +1,97 @@
+use crate::error::OxenError;
+use crate::model::schema::Field;
+use crate::model::LocalRepository;
+use crate::util;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const PRE_RECEIVE_CONFIG_FILENAME: &str = "pre_receive.toml";
+
+/// How long a hook can run before it is killed and the push is rejected
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 300;
+
+/// Tabular files matching `path` must have exactly these fields
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SchemaRule {
+    pub path: String,
+    pub fields: Vec<Field>,
+}
+
+/// Tabular files matching `path` cannot have null values in these columns
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NonNullRule {
+    pub path: String,
+    pub columns: Vec<String>,
+}
+
+/// Executable run with the repository path and commit id as arguments,
+/// the push is rejected if it exits with a non zero status
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HookCommand {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Seconds the hook can run for, defaults to DEFAULT_HOOK_TIMEOUT_SECS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl HookCommand {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS))
+    }
+}
+
+/// # PreReceiveConfig
+/// Checks a server runs on pushed commits before a branch is allowed to point at them,
+/// read from `.oxen/pre_receive.toml` in the repository on the server. Paths are matched
+/// with `*` wildcards, for example `annotations/*.csv`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PreReceiveConfig {
+    pub max_file_size_mb: Option<u64>,
+    #[serde(default)]
+    pub schemas: Vec<SchemaRule>,
+    #[serde(default)]
+    pub non_null: Vec<NonNullRule>,
+    #[serde(default)]
+    pub hooks: Vec<HookCommand>,
+}
+
+impl PreReceiveConfig {
+    pub fn path(repo: &LocalRepository) -> PathBuf {
+        util::fs::oxen_hidden_dir(&repo.path).join(PRE_RECEIVE_CONFIG_FILENAME)
+    }
+
+    /// Returns None if the repository does not have any pre-receive checks configured
+    pub fn get(repo: &LocalRepository) -> Result<Option<PreReceiveConfig>, OxenError> {
+        let path = PreReceiveConfig::path(repo);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = util::fs::read_from_path(&path)?;
+        match toml::from_str(&contents) {
+            Ok(config) => Ok(Some(config)),
+            Err(err) => Err(OxenError::basic_str(format!(
+                "Could not parse {PRE_RECEIVE_CONFIG_FILENAME}: {err}"
+            ))),
+        }
+    }
+
+    pub fn save(&self, repo: &LocalRepository) -> Result<(), OxenError> {
+        match toml::to_string(self) {
+            Ok(contents) => util::fs::write_to_path(&PreReceiveConfig::path(repo), &contents),
+            Err(err) => Err(OxenError::basic_str(format!(
+                "Could not serialize {PRE_RECEIVE_CONFIG_FILENAME}: {err}"
+            ))),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_file_size_mb.is_none()
+            && self.schemas.is_empty()
+            && self.non_null.is_empty()
+            && self.hooks.is_empty()
+    }
+}
@@ -0,0 +1,276 @@
+//! The Ed25519 key that signs new commits, kept next to the user config in
+//! `~/.oxen/signing_key`. Commits are only signed once a key has been generated.
+//!
+//! The signature covers a SHA-256 digest of the commit fields and the path and hash of every
+//! entry in the commit, which `verify` recomputes from the repository it reads the commit from.
+
+use crate::config::TrustedKeysConfig;
+use crate::error::OxenError;
+use crate::index::CommitDirReader;
+use crate::model::{Commit, CommitEntry, CommitSignature, LocalRepository, SignatureStatus};
+use crate::test;
+use crate::util;
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SIGNING_KEY_FILENAME: &str = "signing_key";
+
+pub struct SigningKey {
+    keypair: Keypair,
+}
+
+impl SigningKey {
+    pub fn generate() -> SigningKey {
+        let bytes: [u8; 32] = rand::random();
+        // Any 32 bytes are a valid secret key
+        let secret = SecretKey::from_bytes(&bytes).unwrap();
+        let public = PublicKey::from(&secret);
+        SigningKey {
+            keypair: Keypair { secret, public },
+        }
+    }
+
+    /// Reads the hex encoded secret key from the file
+    pub fn new(path: &Path) -> Result<SigningKey, OxenError> {
+        let contents = util::fs::read_from_path(path)?;
+        let bytes = hex::decode(contents.trim())
+            .map_err(|_| OxenError::basic_str(format!("Invalid signing key in {path:?}")))?;
+        let secret = SecretKey::from_bytes(&bytes)
+            .map_err(|_| OxenError::basic_str(format!("Invalid signing key in {path:?}")))?;
+        let public = PublicKey::from(&secret);
+        Ok(SigningKey {
+            keypair: Keypair { secret, public },
+        })
+    }
+
+    pub fn default_path() -> Result<PathBuf, OxenError> {
+        if std::env::var("TEST").is_ok() {
+            return Ok(test::signing_key_file().to_path_buf());
+        }
+        match dirs::home_dir() {
+            Some(home_dir) => Ok(util::fs::oxen_hidden_dir(&home_dir).join(SIGNING_KEY_FILENAME)),
+            None => Err(OxenError::basic_str("Signing key could not find home dir")),
+        }
+    }
+
+    /// The user's signing key, if they generated one
+    pub fn get() -> Result<Option<SigningKey>, OxenError> {
+        let path = SigningKey::default_path()?;
+        if path.exists() {
+            Ok(Some(SigningKey::new(&path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), OxenError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        util::fs::write_to_path(path, &hex::encode(self.keypair.secret.as_bytes()))?;
+
+        // Only the user should be able to read their secret key
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    pub fn save_default(&self) -> Result<PathBuf, OxenError> {
+        let path = SigningKey::default_path()?;
+        log::debug!("Saving signing key to {:?}", path);
+        self.save(&path)?;
+        Ok(path)
+    }
+
+    /// The hex encoded public key, to share with whoever verifies the commits
+    pub fn public_key(&self) -> String {
+        hex::encode(self.keypair.public.as_bytes())
+    }
+
+    /// Signs the digest of the commit and the entries it contains
+    pub fn sign(&self, commit: &Commit, entries: &[CommitEntry]) -> CommitSignature {
+        let signature = self.keypair.sign(&commit_digest(commit, entries));
+        CommitSignature {
+            public_key: self.public_key(),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+}
+
+/// The fields of a commit that are signed, serialized in this order
+#[derive(Serialize)]
+struct SignedCommit<'a> {
+    id: &'a str,
+    parent_ids: &'a [String],
+    message: &'a str,
+    author: &'a str,
+    email: &'a str,
+    timestamp_nanos: i128,
+    metadata: BTreeMap<&'a String, &'a String>,
+    entries: Vec<(String, &'a str)>,
+}
+
+/// SHA-256 of the canonical json of the commit, with the metadata sorted by key and the
+/// entries sorted by path, so it does not depend on the order they were read in
+pub fn commit_digest(commit: &Commit, entries: &[CommitEntry]) -> [u8; 32] {
+    let mut entries: Vec<(String, &str)> = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry.path.to_string_lossy().replace('\\', "/"),
+                entry.hash.as_str(),
+            )
+        })
+        .collect();
+    entries.sort();
+
+    let signed = SignedCommit {
+        id: &commit.id,
+        parent_ids: &commit.parent_ids,
+        message: &commit.message,
+        author: &commit.author,
+        email: &commit.email,
+        timestamp_nanos: commit.timestamp.unix_timestamp_nanos(),
+        metadata: commit.metadata.iter().collect(),
+        entries,
+    };
+    // Only strings and numbers, serializing can not fail
+    let json = serde_json::to_string(&signed).unwrap();
+    openssl::sha::sha256(json.as_bytes())
+}
+
+/// Checks the signature of the commit against the digest of its entries in the repository, and
+/// that the key is trusted for the author's email on the branch
+pub fn verify(
+    repo: &LocalRepository,
+    commit: &Commit,
+    branch_name: Option<&str>,
+) -> Result<SignatureStatus, OxenError> {
+    let signature = match &commit.signature {
+        Some(signature) => signature,
+        None => return Ok(SignatureStatus::Unsigned),
+    };
+
+    let entries = CommitDirReader::new(repo, commit)?.list_entries()?;
+    let digest = commit_digest(commit, &entries);
+    let is_valid = hex::decode(&signature.public_key)
+        .ok()
+        .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+        .zip(
+            hex::decode(&signature.signature)
+                .ok()
+                .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok()),
+        )
+        .map(|(public_key, sig)| public_key.verify(&digest, &sig).is_ok())
+        .unwrap_or(false);
+
+    let public_key = signature.public_key.to_owned();
+    if !is_valid {
+        return Ok(SignatureStatus::Bad { public_key });
+    }
+
+    let config = TrustedKeysConfig::get(repo)?;
+    let trusted = config.entries_for(&public_key, branch_name);
+    if trusted.iter().any(|key| key.email == commit.email) {
+        Ok(SignatureStatus::Good { public_key })
+    } else if trusted.is_empty() {
+        Ok(SignatureStatus::Untrusted { public_key })
+    } else {
+        let key_emails = trusted.iter().map(|key| key.email.to_owned()).collect();
+        Ok(SignatureStatus::AuthorMismatch {
+            public_key,
+            key_emails,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::config::signing_key;
+    use crate::config::{SigningKey, TrustedKey, TrustedKeysConfig};
+    use crate::error::OxenError;
+    use crate::index::CommitDirReader;
+    use crate::model::SignatureStatus;
+    use crate::test;
+
+    #[test]
+    fn test_signing_key_save_and_read() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|dir| {
+            let path = dir.join(signing_key::SIGNING_KEY_FILENAME);
+            let key = SigningKey::generate();
+            key.save(&path)?;
+
+            let read_key = SigningKey::new(&path)?;
+            assert_eq!(key.public_key(), read_key.public_key());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_signing_key_verify_commit() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let mut commit = command::head_commit(&repo)?;
+            assert_eq!(
+                signing_key::verify(&repo, &commit, None)?,
+                SignatureStatus::Unsigned
+            );
+
+            let key = SigningKey::generate();
+            let public_key = key.public_key();
+            let entries = CommitDirReader::new(&repo, &commit)?.list_entries()?;
+            commit.signature = Some(key.sign(&commit, &entries));
+            assert_eq!(
+                signing_key::verify(&repo, &commit, None)?,
+                SignatureStatus::Untrusted {
+                    public_key: public_key.to_owned()
+                }
+            );
+
+            let mut config = TrustedKeysConfig::default();
+            config.trust(TrustedKey {
+                email: String::from("someone@else.com"),
+                public_key: public_key.to_owned(),
+                branches: vec![],
+            });
+            config.save(&repo)?;
+            assert_eq!(
+                signing_key::verify(&repo, &commit, None)?,
+                SignatureStatus::AuthorMismatch {
+                    public_key: public_key.to_owned(),
+                    key_emails: vec![String::from("someone@else.com")]
+                }
+            );
+
+            config.trust(TrustedKey {
+                email: commit.email.to_owned(),
+                public_key: public_key.to_owned(),
+                branches: vec![String::from("release/*")],
+            });
+            config.save(&repo)?;
+            assert!(signing_key::verify(&repo, &commit, Some("release/v1"))?.is_good());
+            assert!(!signing_key::verify(&repo, &commit, Some("main"))?.is_good());
+
+            // The signature does not hold for different entries or commit fields
+            assert_ne!(
+                signing_key::commit_digest(&commit, &entries),
+                signing_key::commit_digest(&commit, &entries[1..])
+            );
+            commit.message = format!("{} changed", commit.message);
+            assert_eq!(
+                signing_key::verify(&repo, &commit, Some("release/v1"))?,
+                SignatureStatus::Bad { public_key }
+            );
+
+            Ok(())
+        })
+    }
+}
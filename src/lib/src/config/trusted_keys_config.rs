@@ -0,0 +1,97 @@
+use crate::error::OxenError;
+use crate::model::LocalRepository;
+use crate::util;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const TRUSTED_KEYS_CONFIG_FILENAME: &str = "trusted_keys.toml";
+
+/// Public key that signs the commits of `email`, on every branch when `branches` is empty or
+/// only on the branches that match one of them. A branch ending in `*` matches by prefix.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    pub email: String,
+    pub public_key: String,
+    #[serde(default)]
+    pub branches: Vec<String>,
+}
+
+impl TrustedKey {
+    pub fn matches_branch(&self, branch_name: Option<&str>) -> bool {
+        if self.branches.is_empty() {
+            return true;
+        }
+        match branch_name {
+            Some(branch_name) => {
+                self.branches
+                    .iter()
+                    .any(|branch| match branch.strip_suffix('*') {
+                        Some(prefix) => branch_name.starts_with(prefix),
+                        None => branch == branch_name,
+                    })
+            }
+            None => false,
+        }
+    }
+}
+
+/// # TrustedKeysConfig
+/// The keys whose signatures are accepted for a repository, read from `.oxen/trusted_keys.toml`.
+/// A commit only has a good signature if it was signed by a key trusted for its author's email.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TrustedKeysConfig {
+    #[serde(default)]
+    pub keys: Vec<TrustedKey>,
+}
+
+impl TrustedKeysConfig {
+    pub fn path(repo: &LocalRepository) -> PathBuf {
+        util::fs::oxen_hidden_dir(&repo.path).join(TRUSTED_KEYS_CONFIG_FILENAME)
+    }
+
+    /// Loads the keys for the repository, a repository without the file trusts no keys
+    pub fn get(repo: &LocalRepository) -> Result<TrustedKeysConfig, OxenError> {
+        let path = TrustedKeysConfig::path(repo);
+        if !path.exists() {
+            return Ok(TrustedKeysConfig::default());
+        }
+        let contents = util::fs::read_from_path(&path)?;
+        match toml::from_str(&contents) {
+            Ok(config) => Ok(config),
+            Err(err) => Err(OxenError::basic_str(format!(
+                "Could not parse {TRUSTED_KEYS_CONFIG_FILENAME}: {err}"
+            ))),
+        }
+    }
+
+    pub fn save(&self, repo: &LocalRepository) -> Result<(), OxenError> {
+        match toml::to_string(self) {
+            Ok(contents) => util::fs::write_to_path(&TrustedKeysConfig::path(repo), &contents),
+            Err(err) => Err(OxenError::basic_str(format!(
+                "Could not serialize {TRUSTED_KEYS_CONFIG_FILENAME}: {err}"
+            ))),
+        }
+    }
+
+    /// Adds the key, replacing the entry that already exists for the same email and key
+    pub fn trust(&mut self, key: TrustedKey) {
+        self.untrust(&key.email, &key.public_key);
+        self.keys.push(key);
+    }
+
+    /// Removes the key for the email, returns false if it was not trusted
+    pub fn untrust(&mut self, email: &str, public_key: &str) -> bool {
+        let num_keys = self.keys.len();
+        self.keys
+            .retain(|key| !(key.email == email && key.public_key == public_key));
+        num_keys != self.keys.len()
+    }
+
+    /// The trusted entries for the public key that apply to the branch
+    pub fn entries_for(&self, public_key: &str, branch_name: Option<&str>) -> Vec<&TrustedKey> {
+        self.keys
+            .iter()
+            .filter(|key| key.public_key == public_key && key.matches_branch(branch_name))
+            .collect()
+    }
+}
@@ -19,6 +19,9 @@ pub struct UserConfig {
     pub email: String,
     pub default_host: Option<String>,
     host_configs: Vec<HostConfig>,
+    /// Gitignore style patterns ignored in every repository, like a global .oxenignore
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
 }
 
 impl UserConfig {
@@ -33,6 +36,7 @@ impl UserConfig {
             email: user.email.to_owned(),
             default_host: Some(String::from("hub.oxen.ai")),
             host_configs: Vec::new(),
+            ignore: Vec::new(),
         }
     }
 
@@ -42,6 +46,7 @@ impl UserConfig {
             email: String::from(""),
             default_host: Some(String::from("hub.oxen.ai")),
             host_configs: Vec::new(),
+            ignore: Vec::new(),
         }
     }
 
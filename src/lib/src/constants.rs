@@ -7,6 +7,8 @@ pub const REPO_CONFIG_FILENAME: &str = "config.toml";
 pub const HEAD_FILE: &str = "HEAD";
 /// refs/ is a key,val store of branch names to commit ids
 pub const REFS_DIR: &str = "refs";
+/// notes/ is a key-value database of commit ids to the notes on them, which can change after the commit
+pub const NOTES_DIR: &str = "notes";
 /// history/ dir is a list of directories named after commit ids
 pub const HISTORY_DIR: &str = "history";
 /// commits/ is a key-value database of commit ids to commit objects
@@ -29,12 +31,20 @@ pub const FIELDS_DIR: &str = "fields";
 pub const VERSIONS_DIR: &str = "versions";
 /// merge/ is where any merge conflicts are stored so that we can get rid of them
 pub const MERGE_DIR: &str = "merge";
+/// stat_cache/ is a key,val store of file paths to the stat data and hash they had when last hashed
+pub const STAT_CACHE_DIR: &str = "stat_cache";
 /// data.arrow
 pub const DATA_ARROW_FILE: &str = "data.arrow";
 
 /// if we have merge conflicts we write to MERGE_HEAD and ORIG_HEAD to keep track of the parents
 pub const MERGE_HEAD_FILE: &str = "MERGE_HEAD";
 pub const ORIG_HEAD_FILE: &str = "ORIG_HEAD";
+/// STASH has the ids of the stash commits, newest first, since they are not on any branch
+pub const STASH_FILE: &str = "STASH";
+/// REFLOG has a line of json for every time a rebase moved HEAD, so the commits it replaced stay reachable
+pub const REFLOG_FILE: &str = "REFLOG";
+/// REBASE has the state of a rebase that stopped on conflicts, until it is continued or aborted
+pub const REBASE_FILE: &str = "REBASE";
 
 // Precomputed vals
 pub const HASH_FILE: &str = "HASH";
@@ -54,6 +64,9 @@ pub const INITIAL_COMMIT_MSG: &str = "Initialized Repo 🐂";
 pub const ROW_NUM_COL_NAME: &str = "_row_num";
 pub const ROW_HASH_COL_NAME: &str = "_row_hash";
 pub const FILE_ROW_NUM_COL_NAME: &str = "_file_row_num";
+pub const BLAME_COMMIT_ID_COL_NAME: &str = "commit_id";
+pub const BLAME_AUTHOR_COL_NAME: &str = "author";
+pub const BLAME_TIMESTAMP_COL_NAME: &str = "timestamp";
 
 // Data transfer
 // Average chunk size of ~4mb
@@ -63,3 +76,5 @@ pub const NUM_HTTP_RETRIES: u64 = 6;
 // Pagination page size
 pub const DEFAULT_PAGE_SIZE: usize = 10;
 pub const DEFAULT_PAGE_NUM: usize = 1;
+// Most matches a search on the server pages through
+pub const MAX_SEARCH_MATCHES: usize = 10000;
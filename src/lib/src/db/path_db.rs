@@ -54,6 +54,25 @@ where
     }
 }
 
+/// # Serializes all the entries to json and writes them to the db in one batch
+pub fn put_batch<P: AsRef<Path>, T>(
+    db: &DBWithThreadMode<MultiThreaded>,
+    entries: &[(P, T)],
+) -> Result<(), OxenError>
+where
+    T: Serialize,
+{
+    let mut keyed: Vec<(&str, &T)> = Vec::with_capacity(entries.len());
+    for (path, entry) in entries.iter() {
+        let path = path.as_ref();
+        match path.to_str() {
+            Some(key) => keyed.push((key, entry)),
+            None => return Err(OxenError::could_not_convert_path_to_str(path)),
+        }
+    }
+    str_json_db::put_batch(db, &keyed)
+}
+
 /// # Removes path entry from database
 pub fn delete<P: AsRef<Path>>(
     db: &DBWithThreadMode<MultiThreaded>,
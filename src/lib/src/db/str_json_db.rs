@@ -2,7 +2,7 @@ use crate::db::kv_db;
 use crate::error::OxenError;
 use serde::{de, Serialize};
 
-use rocksdb::{DBWithThreadMode, IteratorMode, MultiThreaded};
+use rocksdb::{DBWithThreadMode, IteratorMode, MultiThreaded, WriteBatch};
 use std::str;
 
 /// More efficient than get since it does not actual deserialize the entry
@@ -87,6 +87,30 @@ where
     Ok(())
 }
 
+/// # Serializes all the entries to json and writes them to the db in one batch
+pub fn put_batch<S: AsRef<str>, T>(
+    db: &DBWithThreadMode<MultiThreaded>,
+    entries: &[(S, T)],
+) -> Result<(), OxenError>
+where
+    T: Serialize,
+{
+    let mut batch = WriteBatch::default();
+    for (key, entry) in entries.iter() {
+        let json_val = serde_json::to_string(entry)?;
+        batch.put(key.as_ref(), json_val.as_bytes());
+    }
+
+    log::debug!(
+        "kv_json_db::put_batch {} entries -> db: {:?}",
+        entries.len(),
+        db.path()
+    );
+
+    db.write(batch)?;
+    Ok(())
+}
+
 /// List Values
 pub fn list_vals<T>(db: &DBWithThreadMode<MultiThreaded>) -> Result<Vec<T>, OxenError>
 where
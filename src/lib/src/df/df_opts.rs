@@ -39,6 +39,8 @@ pub struct DFOpts {
     pub should_reverse: bool,
     pub page: Option<usize>,
     pub page_size: Option<usize>,
+    // Rows that match the filter, looked up from a schema field index instead of scanning
+    pub indexed_rows: Option<Vec<u32>>,
 }
 
 impl DFOpts {
@@ -60,6 +62,7 @@ impl DFOpts {
             should_reverse: false,
             page: None,
             page_size: None,
+            indexed_rows: None,
         }
     }
 
@@ -11,6 +11,7 @@ use comfy_table::Table;
 use indicatif::ProgressBar;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::path::Path;
@@ -25,6 +26,7 @@ const READ_ERROR: &str = "Could not read tabular data from path";
 const COLLECT_ERROR: &str = "Could not collect DataFrame";
 const TAKE_ERROR: &str = "Could not take DataFrame";
 const CSV_READ_ERROR: &str = "Could not read csv from path";
+const DEFAULT_BATCH_ROWS: usize = 10000;
 
 fn try_infer_schema_csv(reader: CsvReader<File>, delimiter: u8) -> Result<DataFrame, OxenError> {
     let result = reader
@@ -126,7 +128,46 @@ pub fn take(df: LazyFrame, indices: Vec<u32>) -> Result<DataFrame, OxenError> {
     let collected = df.collect().expect(COLLECT_ERROR);
     // log::debug!("take indices {:?}", indices);
     // log::debug!("from df {:?}", collected);
-    Ok(collected.take(&idx).expect(TAKE_ERROR))
+    collected
+        .take(&idx)
+        .map_err(|err| OxenError::basic_str(format!("{TAKE_ERROR}: {err}")))
+}
+
+/// Counts the rows of the frame without collecting all of its columns
+pub fn count_rows(df: LazyFrame) -> Result<usize, OxenError> {
+    let counted = df
+        .select(&[count()])
+        .collect()
+        .map_err(|err| OxenError::basic_str(format!("{COLLECT_ERROR}: {err}")))?;
+    let height = counted.get_columns()[0]
+        .get(0)
+        .map_err(|err| OxenError::basic_str(format!("{COLLECT_ERROR}: {err}")))?;
+    Ok(height.extract::<usize>().unwrap_or(0))
+}
+
+/// Keeps the rows at the indices without collecting the frame, the rows stay in file order.
+/// Each run of consecutive indices is sliced out of the frame, so scans only read the rows they need.
+pub fn filter_rows(df: LazyFrame, indices: &[u32]) -> Result<LazyFrame, OxenError> {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+
+    let mut runs: Vec<LazyFrame> = vec![];
+    let mut i = 0;
+    while i < indices.len() {
+        let start = indices[i];
+        let mut len = 1;
+        while i + len < indices.len() && indices[i + len] == start + len as u32 {
+            len += 1;
+        }
+        runs.push(df.clone().slice(start as i64, len as IdxSize));
+        i += len;
+    }
+
+    if runs.is_empty() {
+        return Ok(df.slice(0, 0));
+    }
+    concat(runs, false, false).map_err(|err| OxenError::basic_str(format!("{TAKE_ERROR}: {err}")))
 }
 
 pub fn add_col(df: LazyFrame, name: &str, val: &str, dtype: &str) -> Result<LazyFrame, OxenError> {
@@ -338,14 +379,19 @@ pub fn transform_df(mut df: LazyFrame, opts: DFOpts) -> Result<DataFrame, OxenEr
         }
     }
 
-    match opts.get_filter() {
-        Ok(filter) => {
-            if let Some(filter) = filter {
-                df = filter_df(df, &filter)?;
+    if let Some(rows) = &opts.indexed_rows {
+        log::debug!("Filtering to {} indexed rows", rows.len());
+        df = filter_rows(df, rows)?;
+    } else {
+        match opts.get_filter() {
+            Ok(filter) => {
+                if let Some(filter) = filter {
+                    df = filter_df(df, &filter)?;
+                }
+            }
+            Err(err) => {
+                log::error!("Could not parse filter: {err}");
             }
-        }
-        Err(err) => {
-            log::error!("Could not parse filter: {err}");
         }
     }
 
@@ -376,7 +422,7 @@ pub fn transform_df(mut df: LazyFrame, opts: DFOpts) -> Result<DataFrame, OxenEr
 
     // These ops should be the last ops since they depends on order
     if let Some(indices) = opts.take_indices() {
-        df = take(df, indices).unwrap().lazy();
+        df = take(df, indices)?.lazy();
     }
 
     // Maybe slice it up
@@ -505,6 +551,100 @@ pub fn df_hash_rows(df: DataFrame) -> Result<DataFrame, OxenError> {
     Ok(df)
 }
 
+/// The hash of each row of the data frame, in order
+pub fn df_row_hashes(df: &DataFrame) -> Result<Vec<String>, OxenError> {
+    let hashed = df_hash_rows(df.clone())?;
+    let hashes = hashed
+        .column(constants::ROW_HASH_COL_NAME)
+        .expect(COLLECT_ERROR)
+        .utf8()
+        .expect(COLLECT_ERROR)
+        .into_iter()
+        .map(|hash| String::from(hash.unwrap_or("")))
+        .collect();
+    Ok(hashes)
+}
+
+fn count_hashes(hashes: &[String]) -> HashMap<&str, usize> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for hash in hashes.iter() {
+        *counts.entry(hash.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+/// Applies the rows that were added and removed going from `base` to `merge` onto `head`, the added
+/// rows go after the rows of `head`. Returns None if the rows cannot be merged cleanly, because the
+/// schemas are different or a row that `merge` removed is no longer in `head`.
+pub fn df_merge_rows(
+    base: &DataFrame,
+    head: &DataFrame,
+    merge: &DataFrame,
+) -> Result<Option<DataFrame>, OxenError> {
+    if base.schema() != head.schema() || base.schema() != merge.schema() {
+        return Ok(None);
+    }
+
+    let base_hashes = df_row_hashes(base)?;
+    let head_hashes = df_row_hashes(head)?;
+    let merge_hashes = df_row_hashes(merge)?;
+    let base_counts = count_hashes(&base_hashes);
+    let head_counts = count_hashes(&head_hashes);
+    let merge_counts = count_hashes(&merge_hashes);
+
+    let mut to_remove: HashMap<&str, usize> = HashMap::new();
+    for (hash, base_count) in base_counts.iter() {
+        let merge_count = merge_counts.get(hash).copied().unwrap_or(0);
+        if *base_count > merge_count {
+            to_remove.insert(*hash, base_count - merge_count);
+        }
+    }
+    let keep: Vec<bool> = head_hashes
+        .iter()
+        .map(|hash| match to_remove.get_mut(hash.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    if to_remove.values().any(|count| *count > 0) {
+        return Ok(None);
+    }
+
+    // Rows that were added on both sides are only added once
+    let mut to_add: HashMap<&str, usize> = HashMap::new();
+    for (hash, merge_count) in merge_counts.iter() {
+        let base_count = base_counts.get(hash).copied().unwrap_or(0);
+        let head_count = head_counts.get(hash).copied().unwrap_or(0);
+        let num_added = merge_count.saturating_sub(base_count);
+        let num_added = num_added.saturating_sub(head_count.saturating_sub(base_count));
+        if num_added > 0 {
+            to_add.insert(*hash, num_added);
+        }
+    }
+    let add_indices: Vec<u32> = merge_hashes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, hash)| match to_add.get_mut(hash.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                Some(i as u32)
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mask = BooleanChunked::from_slice("keep", &keep);
+    let kept = head.filter(&mask).expect(COLLECT_ERROR);
+    let added = take(merge.clone().lazy(), add_indices)?;
+    match kept.vstack(&added) {
+        Ok(df) => Ok(Some(df)),
+        Err(err) => Err(OxenError::basic_str(format!("Could not merge rows: {err}"))),
+    }
+}
+
 pub fn read_df<P: AsRef<Path>>(path: P, opts: DFOpts) -> Result<DataFrame, OxenError> {
     let path = path.as_ref();
     if !path.exists() {
@@ -554,6 +694,71 @@ pub fn scan_df<P: AsRef<Path>>(path: P) -> Result<LazyFrame, OxenError> {
     }
 }
 
+/// Reads the file a batch of rows at a time, so the whole file is never in memory at once. `f` is
+/// called with the index of the first row of each batch and stops the reading when it returns false.
+/// Json files are a single document, so they are read as one batch.
+pub fn read_df_batches<P, F>(path: P, mut f: F) -> Result<(), OxenError>
+where
+    P: AsRef<Path>,
+    F: FnMut(usize, &DataFrame) -> Result<bool, OxenError>,
+{
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err(OxenError::file_does_not_exist(path));
+    }
+
+    let extension = path.extension().and_then(OsStr::to_str);
+    match extension {
+        Some("csv") => read_df_csv_batches(path, b',', f),
+        Some("tsv") => read_df_csv_batches(path, b'\t', f),
+        Some("json") => {
+            let df = read_df_json(path)?;
+            f(0, &df)?;
+            Ok(())
+        }
+        _ => {
+            let df = scan_df(path)?;
+            let mut offset = 0;
+            loop {
+                let batch = df
+                    .clone()
+                    .slice(offset as i64, DEFAULT_BATCH_ROWS as IdxSize)
+                    .collect()
+                    .map_err(|err| OxenError::basic_str(format!("{COLLECT_ERROR}: {err}")))?;
+                let num_rows = batch.height();
+                if num_rows == 0 || !f(offset, &batch)? || num_rows < DEFAULT_BATCH_ROWS {
+                    return Ok(());
+                }
+                offset += num_rows;
+            }
+        }
+    }
+}
+
+fn read_df_csv_batches<F>(path: &Path, delimiter: u8, mut f: F) -> Result<(), OxenError>
+where
+    F: FnMut(usize, &DataFrame) -> Result<bool, OxenError>,
+{
+    let csv_err = |err: PolarsError| OxenError::basic_str(format!("{CSV_READ_ERROR}: {err:?}"));
+    let mut reader = CsvReader::from_path(path)
+        .map_err(csv_err)?
+        .infer_schema(Some(DEFAULT_INFER_SCHEMA_LEN))
+        .has_header(true)
+        .with_delimiter(delimiter);
+    let mut batches = reader.batched_borrowed().map_err(csv_err)?;
+
+    let mut offset = 0;
+    while let Some(batch) = batches.next_batches(1).map_err(csv_err)? {
+        for (_, df) in batch.iter() {
+            if !f(offset, df)? {
+                return Ok(());
+            }
+            offset += df.height();
+        }
+    }
+    Ok(())
+}
+
 pub fn write_df_json<P: AsRef<Path>>(df: &mut DataFrame, output: P) -> Result<(), OxenError> {
     let output = output.as_ref();
     let error_str = format!("Could not save tabular data to path: {output:?}");
@@ -924,4 +1129,104 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_transform_df_uses_indexed_rows() -> Result<(), OxenError> {
+        let df = df!(
+            "image" => &["0000.jpg", "0001.jpg", "0002.jpg"],
+            "label" => &["cat", "dog", "unknown"],
+        )
+        .unwrap();
+
+        // The indexed rows win over scanning with the filter
+        assert_eq!(tabular::count_rows(df.clone().lazy())?, 3);
+
+        let mut opts = DFOpts::from_filter_query("label == dog");
+        opts.indexed_rows = Some(vec![0, 2]);
+        let df = tabular::transform_df(df.lazy(), opts)?;
+
+        assert_eq!(df.width(), 2);
+
+        assert_eq!(df.height(), 2);
+        let labels: Vec<Option<&str>> = df
+            .column("label")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(labels, vec![Some("cat"), Some("unknown")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_rows_slices_runs_in_file_order() -> Result<(), OxenError> {
+        let df = df!(
+            "id" => &[0, 1, 2, 3, 4, 5, 6],
+        )
+        .unwrap();
+
+        let filtered = tabular::filter_rows(df.clone().lazy(), &[5, 1, 2, 6, 2])?
+            .collect()
+            .unwrap();
+        let ids: Vec<Option<i32>> = filtered
+            .column("id")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(5), Some(6)]);
+
+        let filtered = tabular::filter_rows(df.lazy(), &[])?.collect().unwrap();
+        assert_eq!(filtered.height(), 0);
+        assert_eq!(filtered.width(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_df_merge_rows() -> Result<(), OxenError> {
+        let base = df!(
+            "image" => &["0000.jpg", "0001.jpg", "0002.jpg"],
+            "label" => &["cat", "dog", "unknown"],
+        )
+        .unwrap();
+        // Added a row at the end
+        let head = df!(
+            "image" => &["0000.jpg", "0001.jpg", "0002.jpg", "0003.jpg"],
+            "label" => &["cat", "dog", "unknown", "cat"],
+        )
+        .unwrap();
+        // Changed the unknown label
+        let merge = df!(
+            "image" => &["0000.jpg", "0001.jpg", "0002.jpg"],
+            "label" => &["cat", "dog", "bird"],
+        )
+        .unwrap();
+
+        let df = tabular::df_merge_rows(&base, &head, &merge)?.unwrap();
+        let labels: Vec<Option<&str>> = df
+            .column("label")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            labels,
+            vec![Some("cat"), Some("dog"), Some("cat"), Some("bird")]
+        );
+
+        // Both sides changed the same row
+        let head = df!(
+            "image" => &["0000.jpg", "0001.jpg", "0002.jpg"],
+            "label" => &["cat", "dog", "fish"],
+        )
+        .unwrap();
+        assert!(tabular::df_merge_rows(&base, &head, &merge)?.is_none());
+
+        Ok(())
+    }
 }
@@ -4,6 +4,8 @@ use std::io;
 use std::path::Path;
 
 use crate::model::Schema;
+use crate::view::http::{MSG_QUOTA_EXCEEDED, MSG_RATE_LIMITED, MSG_UPLOAD_TOO_LARGE};
+use crate::view::LimitExceededResponse;
 
 pub const NO_REPO_FOUND: &str = "No oxen repository exists, looking for directory: .oxen";
 
@@ -29,6 +31,7 @@ pub enum OxenError {
     Encoding(std::str::Utf8Error),
     DB(rocksdb::Error),
     ENV(std::env::VarError),
+    LimitExceeded(LimitExceededResponse),
 }
 
 impl OxenError {
@@ -40,6 +43,21 @@ impl OxenError {
         OxenError::Authentication(String::from(s.as_ref()))
     }
 
+    /// The server rejected the request with a 413 or 429
+    pub fn limit_exceeded(response: LimitExceededResponse) -> Self {
+        OxenError::LimitExceeded(response)
+    }
+
+    /// Seconds to wait before retrying, if the request was rate limited
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            OxenError::LimitExceeded(response) if response.status_message == MSG_RATE_LIMITED => {
+                response.retry_after_secs
+            }
+            _ => None,
+        }
+    }
+
     pub fn local_repo_not_found() -> OxenError {
         OxenError::basic_str(NO_REPO_FOUND)
     }
@@ -188,10 +206,18 @@ impl OxenError {
 
 impl fmt::Display for OxenError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let OxenError::Basic(err) = self {
-            write!(f, "{err}")
-        } else {
-            write!(f, "{self:?}")
+        match self {
+            OxenError::Basic(err) => write!(f, "{err}"),
+            OxenError::LimitExceeded(response) => {
+                let desc = &response.status_description;
+                match response.status_message.as_str() {
+                    MSG_QUOTA_EXCEEDED => write!(f, "Storage quota exceeded: {desc}"),
+                    MSG_UPLOAD_TOO_LARGE => write!(f, "Upload too large: {desc}"),
+                    MSG_RATE_LIMITED => write!(f, "Rate limited by the server: {desc}"),
+                    _ => write!(f, "{desc}"),
+                }
+            }
+            _ => write!(f, "{self:?}"),
         }
     }
 }
@@ -1,3 +1,4 @@
+pub mod blamer;
 pub mod commit_db_reader;
 pub mod commit_dir_entry_reader;
 pub mod commit_dir_entry_writer;
@@ -8,20 +9,30 @@ pub mod commit_validator;
 pub mod commit_writer;
 pub mod differ;
 pub mod entry_indexer;
+pub mod fsck;
+pub mod garbage_collector;
 pub mod merge_conflict_db_reader;
 pub mod merge_conflict_reader;
 pub mod merger;
+pub mod notes;
+pub mod oxenignore;
+pub mod rebaser;
 pub mod ref_db_reader;
 pub mod ref_reader;
 pub mod ref_writer;
+pub mod reflog;
 pub mod restore;
 pub mod schema_field_val_index;
 pub mod schema_index_reader;
 pub mod schema_index_writer;
+pub mod schema_indexer;
 pub mod schema_reader;
 pub mod schema_writer;
+pub mod searcher;
 pub mod staged_dir_entry_db;
 pub mod stager;
+pub mod stasher;
+pub mod stat_cache;
 
 pub use crate::index::commit_db_reader::CommitDBReader;
 pub use crate::index::commit_dir_entry_reader::CommitDirEntryReader;
@@ -35,6 +46,7 @@ pub use crate::index::entry_indexer::EntryIndexer;
 pub use crate::index::merge_conflict_db_reader::MergeConflictDBReader;
 pub use crate::index::merge_conflict_reader::MergeConflictReader;
 pub use crate::index::merger::Merger;
+pub use crate::index::oxenignore::OxenIgnore;
 pub use crate::index::ref_db_reader::RefDBReader;
 pub use crate::index::ref_reader::RefReader;
 pub use crate::index::ref_writer::RefWriter;
@@ -46,3 +58,4 @@ pub use crate::index::schema_reader::SchemaReader;
 pub use crate::index::schema_writer::SchemaWriter;
 pub use crate::index::staged_dir_entry_db::StagedDirEntryDB;
 pub use crate::index::stager::Stager;
+pub use crate::index::stat_cache::StatCache;
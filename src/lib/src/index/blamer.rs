@@ -0,0 +1,191 @@
+//! Finds the commit that introduced the current content of each row of a tabular file. The first
+//! parents are followed back from the commit, and a row is blamed on the oldest commit it has been
+//! in ever since, by comparing the row hashes of each version of the file.
+
+use crate::api;
+use crate::constants::{BLAME_AUTHOR_COL_NAME, BLAME_COMMIT_ID_COL_NAME, BLAME_TIMESTAMP_COL_NAME};
+use crate::df::{tabular, DFOpts};
+use crate::error::OxenError;
+use crate::index::CommitReader;
+use crate::model::{Commit, CommitEntry, LocalRepository};
+use crate::util;
+
+use polars::prelude::{DataFrame, NamedFrom, Series};
+use std::collections::HashMap;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+
+fn read_row_hashes(repo: &LocalRepository, entry: &CommitEntry) -> Result<Vec<String>, OxenError> {
+    let version = util::fs::local_version(repo, entry)?;
+    let df = tabular::read_df(version.path(), DFOpts::empty())?;
+    tabular::df_row_hashes(&df)
+}
+
+/// The rows of the file in the commit, or just the `key` column of them, with the commit id,
+/// author and timestamp of the commit that introduced each row.
+pub fn blame(
+    repo: &LocalRepository,
+    commit: &Commit,
+    path: &Path,
+    key: Option<&str>,
+) -> Result<DataFrame, OxenError> {
+    if !util::fs::is_tabular(path) {
+        let err = format!("Can only blame tabular files: {path:?}");
+        return Err(OxenError::basic_str(err));
+    }
+    let entry = api::local::entries::get_entry_for_commit(repo, commit, path)?
+        .ok_or_else(|| OxenError::file_does_not_exist(path))?;
+    let version = util::fs::local_version(repo, &entry)?;
+    let df = tabular::read_df(version.path(), DFOpts::empty())?;
+
+    // Rows that have not been blamed yet, by their hash
+    let mut unresolved: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, hash) in tabular::df_row_hashes(&df)?.into_iter().enumerate() {
+        unresolved.entry(hash).or_default().push(i);
+    }
+
+    // Index into the history of the commit each row is blamed on
+    let mut row_commits: Vec<usize> = vec![0; df.height()];
+    let mut history: Vec<Commit> = vec![commit.clone()];
+    let mut version_hash = entry.hash;
+    let commit_reader = CommitReader::new(repo)?;
+    while !unresolved.is_empty() {
+        let current = history.last().unwrap();
+        let parent = match current.parent_ids.first() {
+            Some(parent_id) => commit_reader.get_commit_by_id(parent_id)?,
+            None => None,
+        };
+        let parent = match parent {
+            Some(parent) => parent,
+            None => break,
+        };
+
+        let parent_entry = api::local::entries::get_entry_for_commit(repo, &parent, path)?;
+        let parent_hash = parent_entry.as_ref().map(|e| e.hash.clone());
+        if parent_hash.as_ref() != Some(&version_hash) {
+            // The rows that are not in the parent version were introduced by the current commit
+            let parent_hashes = match &parent_entry {
+                Some(parent_entry) => read_row_hashes(repo, parent_entry)?,
+                None => vec![],
+            };
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for hash in parent_hashes.iter() {
+                *counts.entry(hash.as_str()).or_default() += 1;
+            }
+
+            let current_idx = history.len() - 1;
+            for (hash, rows) in unresolved.iter_mut() {
+                let num_in_parent = counts.get(hash.as_str()).copied().unwrap_or(0);
+                while rows.len() > num_in_parent {
+                    let row = rows.pop().unwrap();
+                    row_commits[row] = current_idx;
+                }
+            }
+            unresolved.retain(|_, rows| !rows.is_empty());
+            version_hash = parent_hash.unwrap_or_default();
+        }
+        history.push(parent);
+    }
+
+    // Rows left were in the file since the first commit
+    let first_idx = history.len() - 1;
+    for rows in unresolved.values() {
+        for row in rows.iter() {
+            row_commits[*row] = first_idx;
+        }
+    }
+
+    let commit_ids: Vec<&str> = row_commits
+        .iter()
+        .map(|i| history[*i].id.as_str())
+        .collect();
+    let authors: Vec<&str> = row_commits
+        .iter()
+        .map(|i| history[*i].author.as_str())
+        .collect();
+    let timestamps: Vec<String> = row_commits
+        .iter()
+        .map(|i| history[*i].timestamp.format(&Rfc3339).unwrap_or_default())
+        .collect();
+
+    let mut output = match key {
+        Some(key) => match df.select([key]) {
+            Ok(output) => output,
+            Err(_) => {
+                let err = format!("Column {key:?} is not in {path:?}");
+                return Err(OxenError::basic_str(err));
+            }
+        },
+        None => df,
+    };
+    for series in [
+        Series::new(BLAME_COMMIT_ID_COL_NAME, commit_ids),
+        Series::new(BLAME_AUTHOR_COL_NAME, authors),
+        Series::new(BLAME_TIMESTAMP_COL_NAME, timestamps),
+    ] {
+        if let Err(err) = output.with_column(series) {
+            return Err(OxenError::basic_str(format!("Could not blame rows: {err}")));
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::constants::BLAME_COMMIT_ID_COL_NAME;
+    use crate::df::{tabular, DFOpts};
+    use crate::error::OxenError;
+    use crate::index::blamer;
+    use crate::test;
+
+    use polars::prelude::IntoLazy;
+    use std::path::Path;
+
+    #[test]
+    fn test_blame_rows() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let path = Path::new("annotations/train/bounding_box.csv");
+            let first = command::head_commit(&repo)?;
+
+            let df = tabular::read_df(repo.path.join(path), DFOpts::empty())?;
+            let height = df.height();
+            let row = vec!["train/new_1.jpg", "dog", "1.0", "2.0", "3", "4"];
+            let df = tabular::add_row(df.lazy(), row.into_iter().map(String::from).collect())?;
+            tabular::write_df(&mut df.collect().unwrap(), repo.path.join(path))?;
+            command::add(&repo, repo.path.join(path))?;
+            let second = command::commit(&repo, "Adding new_1")?.unwrap();
+
+            let df = blamer::blame(&repo, &second, path, Some("file"))?;
+            assert_eq!(df.height(), height + 1);
+            assert_eq!(df.width(), 4);
+            let commit_ids: Vec<Option<&str>> = df
+                .column(BLAME_COMMIT_ID_COL_NAME)
+                .unwrap()
+                .utf8()
+                .unwrap()
+                .into_iter()
+                .collect();
+            assert_eq!(commit_ids[height], Some(second.id.as_str()));
+            for commit_id in commit_ids[..height].iter() {
+                assert_eq!(*commit_id, Some(first.id.as_str()));
+            }
+
+            // Blaming the first commit does not know about the new row
+            let df = blamer::blame(&repo, &first, path, None)?;
+            assert_eq!(df.height(), height);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_blame_unknown_key() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let path = Path::new("annotations/train/bounding_box.csv");
+            assert!(blamer::blame(&repo, &commit, path, Some("unknown")).is_err());
+            Ok(())
+        })
+    }
+}
@@ -2,7 +2,7 @@ use crate::error::OxenError;
 use crate::index::RefReader;
 use crate::model::{Commit, LocalRepository};
 
-use rocksdb::{DBWithThreadMode, MultiThreaded};
+use rocksdb::{DBWithThreadMode, IteratorMode, MultiThreaded};
 use std::collections::{HashMap, HashSet};
 use std::str;
 
@@ -84,6 +84,16 @@ impl CommitDBReader {
         }
     }
 
+    pub fn list_all(db: &DBWithThreadMode<MultiThreaded>) -> Result<Vec<Commit>, OxenError> {
+        let mut commits: Vec<Commit> = vec![];
+        let iter = db.iterator(IteratorMode::Start);
+        for (_key, value) in iter {
+            let commit: Commit = serde_json::from_str(str::from_utf8(&value)?)?;
+            commits.push(commit);
+        }
+        Ok(commits)
+    }
+
     pub fn commit_id_exists(db: &DBWithThreadMode<MultiThreaded>, commit_id: &str) -> bool {
         match CommitDBReader::get_commit_by_id(db, commit_id) {
             Ok(Some(_commit)) => true,
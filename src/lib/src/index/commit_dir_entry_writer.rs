@@ -49,6 +49,12 @@ impl CommitDirEntryWriter {
         entry: &CommitEntry,
         time: &FileTime,
     ) -> Result<(), OxenError> {
+        // A moved entry points at the commit of its version, where it is under a different path
+        let path = entry.path.file_name().unwrap();
+        if !path_db::has_entry(&self.db, path) {
+            return Ok(());
+        }
+
         let entry = CommitEntry {
             commit_id: entry.commit_id.to_owned(),
             path: entry.path.to_owned(),
@@ -57,7 +63,6 @@ impl CommitDirEntryWriter {
             last_modified_seconds: time.unix_seconds(),
             last_modified_nanoseconds: time.nanoseconds(),
         };
-        let path = entry.path.file_name().unwrap();
         path_db::put(&self.db, path, &entry)
     }
 
@@ -2,11 +2,15 @@ use crate::constants::{self, DEFAULT_BRANCH_NAME, HISTORY_DIR, VERSIONS_DIR};
 use crate::db;
 use crate::db::path_db;
 use crate::error::OxenError;
-use crate::index::{CommitDirEntryWriter, RefReader, RefWriter, SchemaWriter};
+use crate::index::{
+    schema_indexer, CommitDirEntryReader, CommitDirEntryWriter, RefReader, RefWriter, SchemaWriter,
+    StatCache,
+};
 use crate::model::schema::Schema;
 use crate::model::{
     Commit, CommitEntry, LocalRepository, StagedData, StagedEntry, StagedEntryStatus,
 };
+use crate::storage::VersionStore;
 use crate::util;
 
 use filetime::FileTime;
@@ -22,6 +26,7 @@ use std::path::{Path, PathBuf};
 pub struct CommitEntryWriter {
     repository: LocalRepository,
     dir_db: DBWithThreadMode<MultiThreaded>,
+    version_store: Box<dyn VersionStore>,
     commit: Commit,
 }
 
@@ -54,6 +59,7 @@ impl CommitEntryWriter {
         Ok(CommitEntryWriter {
             repository: repository.clone(),
             dir_db: DBWithThreadMode::open(&opts, &db_path)?,
+            version_store: repository.version_store()?,
             commit: commit.to_owned(),
         })
     }
@@ -165,6 +171,60 @@ impl CommitEntryWriter {
         Ok(())
     }
 
+    /// A moved file keeps pointing at the version of the commit it came from, so it is not copied again
+    fn add_moved_entry_to_db(
+        &self,
+        writer: &CommitDirEntryWriter,
+        moved_entry: &CommitEntry,
+        path: &Path,
+    ) -> Result<(), OxenError> {
+        let full_path = self.repository.path.join(path);
+        let metadata = fs::metadata(&full_path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+
+        let entry = CommitEntry {
+            commit_id: moved_entry.commit_id.to_owned(),
+            path: path.to_path_buf(),
+            hash: moved_entry.hash.to_owned(),
+            num_bytes: metadata.len(),
+            last_modified_seconds: mtime.unix_seconds(),
+            last_modified_nanoseconds: mtime.nanoseconds(),
+        };
+        log::debug!(
+            "add_moved_entry_to_db {:?} -> {:?}",
+            moved_entry.path,
+            entry.path
+        );
+        writer.add_commit_entry(&entry)
+    }
+
+    /// The entries in the parent commit of the files that were moved, keyed by their new path
+    fn moved_entries(
+        &self,
+        commit: &Commit,
+        staged_data: &StagedData,
+    ) -> Result<HashMap<PathBuf, CommitEntry>, OxenError> {
+        let mut moved: HashMap<PathBuf, CommitEntry> = HashMap::new();
+        let parent_id = match commit.parent_ids.first() {
+            Some(parent_id) => parent_id,
+            None => return Ok(moved),
+        };
+
+        for (from, to) in staged_data.moved_files.iter() {
+            // The version file name has the extension in it
+            if from.extension() != to.extension() {
+                continue;
+            }
+            if let (Some(parent), Some(file_name)) = (from.parent(), from.file_name()) {
+                let reader = CommitDirEntryReader::new(&self.repository, parent_id, parent)?;
+                if let Some(entry) = reader.get_entry(file_name)? {
+                    moved.insert(to.to_path_buf(), entry);
+                }
+            }
+        }
+        Ok(moved)
+    }
+
     fn add_commit_entry(
         &self,
         writer: &CommitDirEntryWriter,
@@ -184,29 +244,17 @@ impl CommitEntryWriter {
         let full_path = self.repository.path.join(&entry.path);
         log::debug!("backup_file_to_versions_dir {:?}", entry.path);
 
-        // if util::fs::is_tabular(&entry.path) {
-        //     // We save off an .arrow file for tabular data for faster access and optimized DF commands
-        //     entry = self.backup_arrow_file(commit, entry, &full_path)?;
-        // } else {
-        // create a copy to our versions directory
+        // create a copy in our version store
         // .oxen/versions/ENTRY_HASH/COMMIT_ID.ext
         // where ENTRY_HASH is something like subdirs: 59/E029D4812AEBF0
-        let versions_entry_path = util::fs::version_path(&self.repository, &entry);
-        let versions_entry_dir = versions_entry_path.parent().unwrap();
-
         log::debug!(
-            "Copying commit entry for file: {:?} -> {:?}",
+            "Copying commit entry for file: {:?} -> {}/{:?}",
             entry.path,
-            versions_entry_path
+            entry.hash,
+            entry.filename()
         );
-
-        // Create dir if not exists
-        if !versions_entry_dir.exists() {
-            std::fs::create_dir_all(versions_entry_dir)?;
-        }
-
-        std::fs::copy(full_path, versions_entry_path)?;
-        // }
+        self.version_store
+            .store_version_from_path(&entry.hash, &entry.filename(), &full_path)?;
 
         Ok(entry)
     }
@@ -217,7 +265,8 @@ impl CommitEntryWriter {
         staged_data: &StagedData,
     ) -> Result<(), OxenError> {
         self.commit_staged_entries_with_prog(commit, staged_data)?;
-        self.commit_schemas(commit, &staged_data.added_schemas)
+        self.commit_schemas(commit, &staged_data.added_schemas)?;
+        schema_indexer::update_indices(&self.repository, commit, staged_data)
     }
 
     fn commit_schemas(
@@ -267,6 +316,7 @@ impl CommitEntryWriter {
         let size: u64 = unsafe { std::mem::transmute(staged_data.added_files.len()) };
         let bar = ProgressBar::new(size);
         let grouped = self.group_staged_files_to_dirs(&staged_data.added_files);
+        let moved = self.moved_entries(commit, staged_data)?;
         log::debug!(
             "commit_staged_entries_with_prog got groups {}",
             grouped.len()
@@ -283,6 +333,9 @@ impl CommitEntryWriter {
             }
         }
 
+        // Fill the stat cache with what we commit so the next status does not have to rehash it
+        let stat_cache = StatCache::new(&self.repository)?;
+
         // Do regular before tabular
         for (dir, files) in grouped.iter() {
             // Write entries per dir
@@ -291,7 +344,14 @@ impl CommitEntryWriter {
 
             // Commit entries data
             files.par_iter().for_each(|(path, entry)| {
-                self.commit_staged_entry(&entry_writer, commit, path, entry);
+                self.commit_staged_entry(&entry_writer, commit, path, entry, moved.get(path));
+                if entry.status != StagedEntryStatus::Removed
+                    && self.repository.path.join(path).exists()
+                {
+                    if let Err(err) = stat_cache.get_hash(path) {
+                        log::warn!("Could not update stat cache for {:?}: {}", path, err);
+                    }
+                }
                 bar.inc(1);
             });
         }
@@ -306,6 +366,7 @@ impl CommitEntryWriter {
         commit: &Commit,
         path: &Path,
         entry: &StagedEntry,
+        moved_entry: Option<&CommitEntry>,
     ) {
         match entry.status {
             StagedEntryStatus::Removed => match writer.remove_path_from_db(path) {
@@ -325,7 +386,14 @@ impl CommitEntryWriter {
                 }
             }
             StagedEntryStatus::Added => {
-                match self.add_staged_entry_to_db(writer, commit, entry, path) {
+                let result = match moved_entry {
+                    // Only reuse the version if the file was not changed before it was moved
+                    Some(moved_entry) if moved_entry.hash == entry.hash => {
+                        self.add_moved_entry_to_db(writer, moved_entry, path)
+                    }
+                    _ => self.add_staged_entry_to_db(writer, commit, entry, path),
+                };
+                match result {
                     Ok(_) => {}
                     Err(err) => {
                         let err = format!("Failed to ADD file: {err}");
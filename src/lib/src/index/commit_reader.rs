@@ -76,6 +76,11 @@ impl CommitReader {
         CommitDBReader::history_with_depth_from_commit(&self.db, &head)
     }
 
+    /// List every commit in the db, including ones no longer reachable from a branch
+    pub fn list_all(&self) -> Result<Vec<Commit>, OxenError> {
+        CommitDBReader::list_all(&self.db)
+    }
+
     /// See if a commit id exists
     pub fn commit_id_exists(&self, commit_id: &str) -> bool {
         CommitDBReader::commit_id_exists(&self.db, commit_id)
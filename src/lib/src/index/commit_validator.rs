@@ -37,9 +37,8 @@ fn compute_versions_hash(
         // Sometimes we have pre computed the HASH, so that we don't have to fully hash contents again to
         // check if data is synced (I guess this is already in the file path...should we just grab it from there instead?)
         // I think the extra hash computation on the server is nice so that you know the actual contents was saved to disk
-        let version_path = util::fs::version_path(repository, entry);
-
-        let maybe_hash_file = version_path.parent().unwrap().join(HASH_FILE);
+        let maybe_hash_file =
+            util::fs::version_dir_from_hash(repository, entry.hash.clone()).join(HASH_FILE);
         // log::debug!("Entry [{}]: {:?}", i, entry.path);
         if maybe_hash_file.exists() {
             let hash = util::fs::read_from_path(&maybe_hash_file)?;
@@ -51,7 +50,7 @@ fn compute_versions_hash(
             continue;
         }
 
-        let hash = util::hasher::hash_file_contents(&version_path)?;
+        let hash = util::hasher::hash_reader(util::fs::open_version(repository, entry)?)?;
         // log::debug!("Got hash: {:?} -> {}", entry.path, hash);
 
         hashes.push(SimpleHash { hash })
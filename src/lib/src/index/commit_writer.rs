@@ -1,4 +1,4 @@
-use crate::config::UserConfig;
+use crate::config::{SigningKey, UserConfig};
 use crate::constants::{COMMITS_DB, MERGE_HEAD_FILE, ORIG_HEAD_FILE};
 use crate::error::OxenError;
 use crate::index::{CommitDBReader, CommitDirReader, CommitEntryWriter, RefReader, RefWriter};
@@ -9,7 +9,7 @@ use crate::{command, db};
 
 use indicatif::ProgressBar;
 use rocksdb::{DBWithThreadMode, MultiThreaded};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::str;
 use time::OffsetDateTime;
@@ -64,6 +64,8 @@ impl CommitWriter {
                         author: cfg.name,
                         email: cfg.email,
                         timestamp,
+                        metadata: HashMap::new(),
+                        signature: None,
                     })
                 }
             }
@@ -76,6 +78,8 @@ impl CommitWriter {
                     author: cfg.name,
                     email: cfg.email,
                     timestamp,
+                    metadata: HashMap::new(),
+                    signature: None,
                 })
             }
         }
@@ -103,6 +107,8 @@ impl CommitWriter {
             author: cfg.name,
             email: cfg.email,
             timestamp,
+            metadata: HashMap::new(),
+            signature: None,
         })
     }
 
@@ -122,6 +128,16 @@ impl CommitWriter {
     //       image_2.png -> b"{entry_json}"
     //       image_2.png -> b"{entry_json}"
     pub fn commit(&self, status: &StagedData, message: &str) -> Result<Commit, OxenError> {
+        self.commit_with_metadata(status, message, &HashMap::new())
+    }
+
+    /// Commits with key value metadata, which is part of the commit id
+    pub fn commit_with_metadata(
+        &self,
+        status: &StagedData,
+        message: &str,
+        metadata: &HashMap<String, String>,
+    ) -> Result<Commit, OxenError> {
         // Generate uniq id for this commit
         // This is a hash of all the entries hashes to create a merkle tree
         // merkle trees are inherently resistent to tampering, and are verifyable
@@ -140,14 +156,15 @@ impl CommitWriter {
 
         // Create a commit object, that either points to parent or not
         // must create this before anything else so that we know if it has parent or not.
-        let new_commit = self.create_commit_data(message)?;
+        let mut new_commit = self.create_commit_data(message)?;
+        new_commit.metadata = metadata.to_owned();
         log::debug!("Created commit obj {:?}", new_commit);
 
         let commit = self.gen_commit(&new_commit, status);
         log::debug!("Commit Id computed {} -> [{}]", commit.id, commit.message,);
 
         // Write entries
-        self.add_commit_from_status(&commit, status)?;
+        let commit = self.add_signed_commit_from_status(&commit, status)?;
 
         log::debug!("COMMIT_COMPLETE {} -> {}", commit.id, commit.message);
 
@@ -166,6 +183,37 @@ impl CommitWriter {
         Commit::from_new_and_id(commit_data, id)
     }
 
+    /// Signs the commit and the entries that were written for it, if the user has a signing key
+    fn sign_commit(&self, commit: &Commit) -> Result<Commit, OxenError> {
+        let mut commit = commit.to_owned();
+        if let Some(key) = SigningKey::get()? {
+            let entries = CommitDirReader::new(&self.repository, &commit)?.list_entries()?;
+            commit.signature = Some(key.sign(&commit, &entries));
+        }
+        Ok(commit)
+    }
+
+    /// Writes the entries of a new commit, then signs it before it is added to the db and HEAD
+    /// is moved to it
+    fn add_signed_commit_from_status(
+        &self,
+        commit: &Commit,
+        status: &StagedData,
+    ) -> Result<Commit, OxenError> {
+        self.write_entries(commit, status)?;
+        let commit = self.sign_commit(commit)?;
+        self.add_commit_to_db(&commit)?;
+
+        let ref_writer = RefWriter::new(&self.repository)?;
+        ref_writer.set_head_commit_id(&commit.id)?;
+        Ok(commit)
+    }
+
+    fn write_entries(&self, commit: &Commit, status: &StagedData) -> Result<(), OxenError> {
+        let entry_writer = CommitEntryWriter::new(&self.repository, commit)?;
+        entry_writer.commit_staged_entries(commit, status)
+    }
+
     pub fn commit_with_parent_ids(
         &self,
         status: &StagedData,
@@ -181,11 +229,60 @@ impl CommitWriter {
             author: cfg.name,
             email: cfg.email,
             timestamp,
+            metadata: HashMap::new(),
+            signature: None,
         };
-        let entries: Vec<StagedEntry> = status.added_files.values().cloned().collect();
-        let id = util::hasher::compute_commit_hash(&commit, &entries);
-        let commit = Commit::from_new_and_id(&commit, id);
-        self.add_commit_from_status(&commit, status)?;
+        let commit = self.gen_commit(&commit, status);
+        self.add_signed_commit_from_status(&commit, status)
+    }
+
+    /// Commits on top of `parent_ids` keeping the author, email and metadata of `original`, for
+    /// the commits that rebase and cherry-pick replay
+    pub fn commit_as_author_of(
+        &self,
+        status: &StagedData,
+        parent_ids: Vec<String>,
+        message: &str,
+        original: &Commit,
+    ) -> Result<Commit, OxenError> {
+        let commit = NewCommit {
+            parent_ids,
+            message: String::from(message),
+            author: original.author.to_owned(),
+            email: original.email.to_owned(),
+            timestamp: OffsetDateTime::now_utc(),
+            metadata: original.metadata.to_owned(),
+            signature: None,
+        };
+        let commit = self.gen_commit(&commit, status);
+        self.add_signed_commit_from_status(&commit, status)
+    }
+
+    /// Writes the commit and its entries without moving HEAD, for commits that are only
+    /// referenced from outside of the branches such as stashes
+    pub fn commit_unreferenced(
+        &self,
+        status: &StagedData,
+        parent_ids: Vec<String>,
+        message: &str,
+    ) -> Result<Commit, OxenError> {
+        let cfg = UserConfig::get()?;
+        let timestamp = OffsetDateTime::now_utc();
+
+        let commit = NewCommit {
+            parent_ids,
+            message: String::from(message),
+            author: cfg.name,
+            email: cfg.email,
+            timestamp,
+            metadata: HashMap::new(),
+            signature: None,
+        };
+        let commit = self.gen_commit(&commit, status);
+
+        self.write_entries(&commit, status)?;
+        let commit = self.sign_commit(&commit)?;
+        self.add_commit_to_db(&commit)?;
         Ok(commit)
     }
 
@@ -223,6 +320,12 @@ impl CommitWriter {
         Ok(())
     }
 
+    pub fn delete_commit_from_db(&self, commit_id: &str) -> Result<(), OxenError> {
+        log::debug!("delete_commit_from_db [{}]", commit_id);
+        self.commits_db.delete(commit_id)?;
+        Ok(())
+    }
+
     pub fn set_working_repo_to_commit_id(&self, commit_id: &str) -> Result<(), OxenError> {
         if !CommitDBReader::commit_id_exists(&self.commits_db, commit_id) {
             return Err(OxenError::commit_id_does_not_exist(commit_id));
@@ -324,13 +427,12 @@ impl CommitWriter {
             }
 
             let dst_path = self.repository.path.join(path);
-            let version_path = util::fs::version_path(&self.repository, entry);
 
             // If we do not have the file, restore it from our versioned history
             if !dst_path.exists() {
                 log::debug!(
-                    "set_working_repo_to_commit_id restore file, she new 🙏 {:?} -> {:?}",
-                    version_path,
+                    "set_working_repo_to_commit_id restore file, she new 🙏 {} -> {:?}",
+                    entry.hash,
                     dst_path
                 );
 
@@ -349,17 +451,12 @@ impl CommitWriter {
                 // we do have it, check if we need to update it
                 let dst_hash = util::hasher::hash_file_contents(&dst_path)?;
 
-                // let old_contents = util::fs::read_from_path(&version_path)?;
-                // let current_contents = util::fs::read_from_path(&dst_path)?;
-                // log::debug!("old_contents {:?}\n{}", version_path, old_contents);
-                // log::debug!("current_contents {:?}\n{}", dst_path, current_contents);
-
                 // If the hash of the file from the commit is different than the one on disk, update it
                 if entry.hash != dst_hash {
                     // we need to update working dir
                     log::debug!(
-                        "set_working_repo_to_commit_id restore file diff hash 🙏 {:?} -> {:?}",
-                        version_path,
+                        "set_working_repo_to_commit_id restore file diff hash 🙏 {} -> {:?}",
+                        entry.hash,
                         dst_path
                     );
 
@@ -369,8 +466,8 @@ impl CommitWriter {
                     )?;
                 } else {
                     log::debug!(
-                        "set_working_repo_to_commit_id hashes match! {:?} -> {:?}",
-                        version_path,
+                        "set_working_repo_to_commit_id hashes match! {} -> {:?}",
+                        entry.hash,
                         dst_path
                     );
                 }
@@ -423,10 +520,14 @@ impl CommitWriter {
 
 #[cfg(test)]
 mod tests {
+    use crate::command;
     use crate::error::OxenError;
     use crate::index::{CommitDBReader, CommitDirReader, CommitWriter};
-    use crate::model::StagedData;
+    use crate::model::{NewCommit, StagedData, StagedEntry};
     use crate::test;
+    use crate::util;
+
+    use std::collections::HashMap;
 
     // This is how we initialize
     #[test]
@@ -487,4 +588,38 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_commit_with_metadata() -> Result<(), OxenError> {
+        test::run_empty_stager_test(|stager, repo| {
+            let entry_reader = CommitDirReader::new_from_head(&repo)?;
+            let commit_writer = CommitWriter::new(&repo)?;
+
+            let file = test::add_txt_file_to_dir(&repo.path, "some annotations...")?;
+            stager.add_file(&file, &entry_reader)?;
+            let status = stager.status(&entry_reader)?;
+
+            let metadata = HashMap::from([
+                (String::from("experiment"), String::from("baseline")),
+                (String::from("accuracy"), String::from("0.92")),
+            ]);
+            let commit = commit_writer.commit_with_metadata(&status, "Annotations", &metadata)?;
+            stager.unstage()?;
+
+            let head = command::head_commit(&repo)?;
+            assert_eq!(head.id, commit.id);
+            assert_eq!(head.metadata, metadata);
+
+            // The metadata is part of the id, and an empty map hashes like it did before
+            let mut new_commit = NewCommit::from_commit(&head);
+            let entries: Vec<StagedEntry> = vec![];
+            let with_metadata = util::hasher::compute_commit_hash(&new_commit, &entries);
+            new_commit.metadata = HashMap::new();
+            assert!(!format!("{new_commit:?}").contains("metadata"));
+            let without_metadata = util::hasher::compute_commit_hash(&new_commit, &entries);
+            assert_ne!(with_metadata, without_metadata);
+
+            Ok(())
+        })
+    }
 }
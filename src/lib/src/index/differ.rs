@@ -67,9 +67,9 @@ fn _diff_commit(repo: &LocalRepository, commit: &Commit, path: &Path) -> Result<
 
 pub fn diff_utf8(repo: &LocalRepository, entry: &CommitEntry) -> Result<String, OxenError> {
     let current_path = repo.path.join(&entry.path);
-    let version_path = util::fs::version_path(repo, entry);
+    let version = util::fs::local_version(repo, entry)?;
 
-    let original = util::fs::read_from_path(&version_path)?;
+    let original = util::fs::read_from_path(version.path())?;
     let modified = util::fs::read_from_path(&current_path)?;
     let Changeset { diffs, .. } = Changeset::new(&original, &modified, "\n");
 
@@ -140,7 +140,8 @@ fn compute_dataframe_diff(
     if let Some(entry) = commit_entry_reader.get_entry(filename)? {
         // Read current DF and get schema
         let current_path = repo.path.join(path);
-        let versioned_path = util::fs::version_path(repo, &entry);
+        let version = util::fs::local_version(repo, &entry)?;
+        let versioned_path = version.path();
         let current_df = tabular::read_df(&current_path, DFOpts::empty())?;
         let current_schema = Schema::from_polars(&current_df.schema());
 
@@ -148,7 +149,7 @@ fn compute_dataframe_diff(
         if versioned_schema.hash != current_schema.hash {
             compute_new_columns(
                 &current_path,
-                &versioned_path,
+                versioned_path,
                 &current_schema,
                 versioned_schema,
             )
@@ -156,7 +157,7 @@ fn compute_dataframe_diff(
             println!("Computing diff for {path:?}");
             // Schemas match, find added and removed rows
             // Read versioned df
-            let versioned_df = tabular::read_df(&versioned_path, DFOpts::empty())?;
+            let versioned_df = tabular::read_df(versioned_path, DFOpts::empty())?;
             compute_new_rows(current_df, versioned_df, versioned_schema)
         }
     } else {
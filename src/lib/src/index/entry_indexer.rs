@@ -6,21 +6,21 @@ use flate2::Compression;
 use indicatif::ProgressBar;
 use jwalk::WalkDirGeneric;
 use rayon::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::api;
 use crate::api::remote::commits::ChunkParams;
-use crate::constants::{AVG_CHUNK_SIZE, HISTORY_DIR};
+use crate::constants::{AVG_CHUNK_SIZE, HISTORY_DIR, OXEN_HIDDEN_DIR};
 use crate::error::OxenError;
 use crate::index::{
-    CommitDirEntryReader, CommitDirEntryWriter, CommitDirReader, CommitReader, CommitWriter,
+    notes, CommitDirEntryReader, CommitDirEntryWriter, CommitDirReader, CommitReader, CommitWriter,
     RefReader, RefWriter,
 };
 use crate::model::{Commit, CommitEntry, LocalRepository, RemoteBranch, RemoteRepository};
+use crate::storage::VersionStore;
 use crate::util;
 
 pub struct UnsyncedCommitEntries {
@@ -84,7 +84,8 @@ impl EntryIndexer {
 
             // recursively check commits against remote head
             // and sync ones that have not been synced
-            self.rpush_entries(&remote_repo, &unsynced_commits).await?;
+            self.rpush_entries(&remote_repo, &rb.branch, &unsynced_commits)
+                .await?;
 
             // update the branch after everything else is synced
             log::debug!(
@@ -106,9 +107,33 @@ impl EntryIndexer {
             &rb.branch, &head_commit.id
         );
 
+        // The branch is already pushed, so failing to sync the notes, for example because the
+        // remote does not support them, should not fail the push
+        if let Err(err) = self.push_notes(&remote_repo).await {
+            log::warn!(
+                "Pushed branch {} but could not push notes: {}",
+                rb.branch,
+                err
+            );
+            println!("Warning: could not push notes: {err}");
+        }
+
         Ok(remote_repo)
     }
 
+    /// Notes are pushed along with every branch, the remote keeps the newest of each and
+    /// stamps the ones that changed with the pusher, so its copy replaces ours
+    async fn push_notes(&self, remote_repo: &RemoteRepository) -> Result<(), OxenError> {
+        let local_notes = notes::list_all(&self.repository)?;
+        let remote_notes = api::remote::notes::post(remote_repo, local_notes).await?;
+        notes::replace(&self.repository, &remote_notes)
+    }
+
+    async fn pull_notes(&self, remote_repo: &RemoteRepository) -> Result<(), OxenError> {
+        let remote_notes = api::remote::notes::list(remote_repo).await?;
+        notes::merge(&self.repository, &remote_notes)
+    }
+
     async fn poll_until_synced(
         &self,
         remote_repo: &RemoteRepository,
@@ -251,6 +276,7 @@ impl EntryIndexer {
     async fn rpush_entries(
         &self,
         remote_repo: &RemoteRepository,
+        branch_name: &str,
         unsynced_commits: &VecDeque<UnsyncedCommitEntries>,
     ) -> Result<(), OxenError> {
         log::debug!("rpush_entries num unsynced {}", unsynced_commits.len());
@@ -265,7 +291,8 @@ impl EntryIndexer {
             );
 
             if !entries.is_empty() {
-                self.push_entries(remote_repo, entries, commit).await?;
+                self.push_entries(remote_repo, branch_name, entries, commit)
+                    .await?;
             }
         }
         Ok(())
@@ -288,6 +315,14 @@ impl EntryIndexer {
             grouped.len()
         );
 
+        // Files that were moved still point at the version file of the last commit, which is already synced
+        let last_commit_reader = CommitDirReader::new(&self.repository, last_commit)?;
+        let last_versions: HashSet<(String, String)> = last_commit_reader
+            .list_entries()?
+            .into_iter()
+            .map(|entry| (entry.hash, entry.commit_id))
+            .collect();
+
         let bar = ProgressBar::new(this_entries.len() as u64);
         let mut entries_to_sync: Vec<CommitEntry> = vec![];
         for (dir, dir_entries) in grouped.iter() {
@@ -308,7 +343,9 @@ impl EntryIndexer {
                             }
                         }
                         Ok(None) => {
-                            return true;
+                            let version = (entry.hash.to_owned(), entry.commit_id.to_owned());
+                            return entry.commit_id == this_commit.id
+                                || !last_versions.contains(&version);
                         }
                         Err(err) => {
                             panic!("Error filtering entries to sync: {}", err)
@@ -339,6 +376,7 @@ impl EntryIndexer {
     async fn push_entries(
         &self,
         remote_repo: &RemoteRepository,
+        branch_name: &str,
         entries: &[CommitEntry],
         commit: &Commit,
     ) -> Result<(), OxenError> {
@@ -394,7 +432,7 @@ impl EntryIndexer {
 
         match tokio::join!(large_entries_sync, small_entries_sync) {
             (Ok(_), Ok(_)) => {
-                api::remote::commits::post_push_complete(remote_repo, &commit.id).await
+                api::remote::commits::post_push_complete(remote_repo, branch_name, &commit.id).await
             }
             (Err(err), Ok(_)) => {
                 let err = format!("Error syncing large entries: {err}");
@@ -430,6 +468,7 @@ impl EntryIndexer {
         );
         type TaskQueue = deadqueue::limited::Queue<PieceOfWork>;
         type FinishedTaskQueue = deadqueue::limited::Queue<bool>;
+        type TaskErrors = std::sync::Mutex<Vec<OxenError>>;
 
         log::debug!("Chunking and sending {} larger files", entries.len());
         let entries: Vec<PieceOfWork> = entries
@@ -463,72 +502,22 @@ impl EntryIndexer {
             worker_count,
             entries.len()
         );
+        let errors: Arc<TaskErrors> = Arc::new(TaskErrors::new(vec![]));
         for worker in 0..worker_count {
             let queue = queue.clone();
             let finished_queue = finished_queue.clone();
+            let errors = errors.clone();
             tokio::spawn(async move {
                 loop {
                     let (entry, repo, commit, remote_repo, bar) = queue.pop().await;
                     log::debug!("worker[{}] processing task...", worker);
 
-                    // Open versioned file
-                    let version_path = util::fs::version_path(&repo, &entry);
-                    let f = std::fs::File::open(&version_path).unwrap();
-                    let mut reader = BufReader::new(f);
-
-                    // Read chunks
-                    let total_size = entry.num_bytes;
-                    let num_chunks = ((total_size / chunk_size) + 1) as usize;
-                    let mut total_read = 0;
-                    let mut chunk_size = chunk_size;
-
-                    // TODO: We could probably upload chunks in parallel too
-                    for i in 0..num_chunks {
-                        // Make sure we read the last size correctly
-                        if (total_read + chunk_size) > total_size {
-                            chunk_size = total_size % chunk_size;
-                        }
-
-                        // Only read as much as you need to send so we don't blow up memory on large files
-                        let mut buffer = vec![0u8; chunk_size as usize];
-                        reader.read_exact(&mut buffer).unwrap();
-                        total_read += chunk_size;
-
-                        let size = buffer.len() as u64;
-                        log::debug!("Got entry buffer of size {}", size);
-
-                        // Send data to server
-                        let is_compressed = false;
-                        let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
-                        let path =
-                            util::fs::path_relative_to_dir(&version_path, &hidden_dir).unwrap();
-                        let file_name = Some(String::from(path.to_str().unwrap()));
-
-                        let params = ChunkParams {
-                            chunk_num: i,
-                            total_chunks: num_chunks,
-                            total_size: total_size as usize,
-                        };
-
-                        match api::remote::commits::upload_data_chunk_to_server_with_retry(
-                            &remote_repo,
-                            &commit,
-                            &buffer,
-                            &entry.hash,
-                            &params,
-                            is_compressed,
-                            &file_name,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                bar.inc(buffer.len() as u64);
-                                log::debug!("Successfully uploaded chunk {}/{}", i, num_chunks)
-                            }
-                            Err(err) => {
-                                log::error!("Error uploading chunk: {:?}", err)
-                            }
-                        }
+                    if let Err(err) =
+                        upload_large_entry(&entry, &repo, &commit, &remote_repo, &bar, chunk_size)
+                            .await
+                    {
+                        log::error!("Error uploading {:?}: {}", entry.path, err);
+                        errors.lock().unwrap().push(err);
                     }
 
                     finished_queue.pop().await;
@@ -540,6 +529,9 @@ impl EntryIndexer {
             // log::debug!("Before waiting for {} workers to finish...", queue.len());
             sleep(Duration::from_secs(1)).await;
         }
+        if let Some(err) = errors.lock().unwrap().pop() {
+            return Err(err);
+        }
         log::debug!("All large file tasks done. :-)");
 
         Ok(())
@@ -578,6 +570,7 @@ impl EntryIndexer {
         );
         type TaskQueue = deadqueue::limited::Queue<PieceOfWork>;
         type FinishedTaskQueue = deadqueue::limited::Queue<bool>;
+        type TaskErrors = std::sync::Mutex<Vec<OxenError>>;
 
         log::debug!("Creating {num_chunks} chunks from {total_size} bytes with size {chunk_size}");
         let chunks: Vec<PieceOfWork> = entries
@@ -601,62 +594,21 @@ impl EntryIndexer {
             finished_queue.try_push(false).unwrap();
         }
 
+        let errors: Arc<TaskErrors> = Arc::new(TaskErrors::new(vec![]));
         for worker in 0..worker_count {
             let queue = queue.clone();
             let finished_queue = finished_queue.clone();
+            let errors = errors.clone();
             tokio::spawn(async move {
                 loop {
                     let (chunk, repo, commit, remote_repo, bar) = queue.pop().await;
                     log::debug!("worker[{}] processing task...", worker);
 
-                    let enc = GzEncoder::new(Vec::new(), Compression::default());
-                    let mut tar = tar::Builder::new(enc);
-                    log::debug!("Chunk size {}", chunk.len());
-                    log::debug!("got repo {:?}", &repo.path);
-                    for entry in chunk.into_iter() {
-                        let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
-                        let version_path = util::fs::version_path(&repo, &entry);
-                        let name =
-                            util::fs::path_relative_to_dir(&version_path, &hidden_dir).unwrap();
-
-                        tar.append_path_with_name(version_path, name).unwrap();
-                    }
-
-                    let buffer = match tar.into_inner() {
-                        Ok(gz_encoder) => match gz_encoder.finish() {
-                            Ok(buffer) => {
-                                let size = buffer.len() as u64;
-                                log::debug!("Got tarball buffer of size {}", size);
-                                buffer
-                            }
-                            Err(err) => {
-                                panic!("Error creating tar.gz on entries: {}", err)
-                            }
-                        },
-                        Err(err) => {
-                            panic!("Error creating tar of entries: {}", err)
-                        }
-                    };
-
-                    // Send tar.gz to server
-                    let is_compressed = true;
-                    let file_name = None;
-                    match api::remote::commits::post_data_to_server(
-                        &remote_repo,
-                        &commit,
-                        buffer,
-                        is_compressed,
-                        &file_name,
-                        bar,
-                    )
-                    .await
+                    if let Err(err) =
+                        upload_small_entries(chunk, repo, &commit, &remote_repo, bar).await
                     {
-                        Ok(_) => {
-                            log::debug!("Successfully uploaded data!")
-                        }
-                        Err(err) => {
-                            log::error!("Error uploading chunk: {:?}", err)
-                        }
+                        log::error!("Error uploading chunk: {}", err);
+                        errors.lock().unwrap().push(err);
                     }
                     finished_queue.pop().await;
                 }
@@ -666,6 +618,9 @@ impl EntryIndexer {
             // log::debug!("Waiting for {} workers to finish...", queue.len());
             sleep(Duration::from_millis(1)).await;
         }
+        if let Some(err) = errors.lock().unwrap().pop() {
+            return Err(err);
+        }
         log::debug!("All tasks done. :-)");
 
         Ok(())
@@ -690,6 +645,16 @@ impl EntryIndexer {
             self.pull_entries_for_commit(&remote_repo, &commit, limit)
                 .await?;
         }
+
+        // Like on push, the branch is already pulled so missing notes should not fail the pull
+        if let Err(err) = self.pull_notes(&remote_repo).await {
+            log::warn!(
+                "Pulled branch {} but could not pull notes: {}",
+                rb.branch,
+                err
+            );
+            println!("Warning: could not pull notes: {err}");
+        }
         Ok(())
     }
 
@@ -840,26 +805,27 @@ impl EntryIndexer {
         Ok(entries[0..limit].to_vec())
     }
 
-    fn get_missing_commit_entries(&self, entries: &[CommitEntry]) -> Vec<CommitEntry> {
+    fn get_missing_commit_entries(
+        &self,
+        entries: &[CommitEntry],
+    ) -> Result<Vec<CommitEntry>, OxenError> {
+        let version_store = self.repository.version_store()?;
         let mut missing_entries: Vec<CommitEntry> = vec![];
 
         for entry in entries {
-            let version_path = util::fs::version_path(&self.repository, entry);
-            if !version_path.exists() {
+            if !version_store.version_exists(&entry.hash, &entry.filename())? {
                 missing_entries.push(entry.to_owned())
             }
         }
 
-        missing_entries
+        Ok(missing_entries)
     }
 
     fn version_paths_from_entries(&self, entries: &[CommitEntry]) -> Vec<String> {
         let mut content_ids: Vec<String> = vec![];
 
         for entry in entries.iter() {
-            let version_path = util::fs::version_path(&self.repository, entry);
-            let version_path =
-                util::fs::path_relative_to_dir(&version_path, &self.repository.path).unwrap();
+            let version_path = Path::new(OXEN_HIDDEN_DIR).join(util::fs::version_name(entry));
             content_ids.push(String::from(version_path.to_str().unwrap()));
         }
 
@@ -901,7 +867,7 @@ impl EntryIndexer {
             let total = if limit > 0 { limit } else { entries.len() };
             println!("🐂 pulling commit {} with {} entries", commit.id, total);
 
-            let missing_entries = self.get_missing_commit_entries(&entries);
+            let missing_entries = self.get_missing_commit_entries(&entries)?;
             let total_size = self.compute_entries_size(&missing_entries)?;
             println!("Total size {}", ByteSize::b(total_size));
 
@@ -1144,6 +1110,7 @@ impl EntryIndexer {
         println!("Unpacking...");
         let bar = Arc::new(ProgressBar::new(entries.len() as u64));
         let dir_entries = self.group_entries_to_parent_dirs(&entries);
+        let version_store = self.repository.version_store()?;
 
         dir_entries.par_iter().for_each(|(dir, entries)| {
             let committer = CommitDirEntryWriter::new(&self.repository, &commit.id, dir).unwrap();
@@ -1158,10 +1125,12 @@ impl EntryIndexer {
                     }
 
                     log::debug!("pull_entries_for_commit unpack {:?}", entry.path);
-                    let version_path = util::fs::version_path(&self.repository, entry);
-                    // We will unpack tabular later into CADF
-                    if std::fs::copy(&version_path, &filepath).is_err() {
-                        log::error!("Could not unpack file {:?} -> {:?}", version_path, filepath);
+                    if let Err(err) = version_store.copy_version_to_path(
+                        &entry.hash,
+                        &entry.filename(),
+                        &filepath,
+                    ) {
+                        log::error!("Could not unpack file {:?}: {}", filepath, err);
                     }
 
                     log::debug!(
@@ -1253,6 +1222,136 @@ impl EntryIndexer {
     }
 }
 
+/// Reads the version of a large entry in chunks and uploads them one at a time. The version store
+/// may block on the network, so it is read on the blocking pool instead of the async workers
+async fn upload_large_entry(
+    entry: &CommitEntry,
+    repo: &LocalRepository,
+    commit: &Commit,
+    remote_repo: &RemoteRepository,
+    bar: &Arc<ProgressBar>,
+    chunk_size: u64,
+) -> Result<(), OxenError> {
+    let version_store: Arc<dyn VersionStore> = Arc::from(repo.version_store()?);
+    let version_name = util::fs::version_name(entry);
+    let filename = entry.filename();
+
+    // Read chunks
+    let total_size = entry.num_bytes;
+    let num_chunks = ((total_size / chunk_size) + 1) as usize;
+    let mut total_read = 0;
+    let mut chunk_size = chunk_size;
+
+    // TODO: We could probably upload chunks in parallel too
+    for i in 0..num_chunks {
+        // Make sure we read the last size correctly
+        if (total_read + chunk_size) > total_size {
+            chunk_size = total_size % chunk_size;
+        }
+
+        // Only read as much as you need to send so we don't blow up memory on large files
+        let store = version_store.clone();
+        let hash = entry.hash.to_owned();
+        let chunk_filename = filename.to_owned();
+        let offset = total_read;
+        let buffer = run_blocking(move || {
+            store.get_version_chunk(&hash, &chunk_filename, offset, chunk_size)
+        })
+        .await?;
+        total_read += chunk_size;
+
+        let size = buffer.len() as u64;
+        log::debug!("Got entry buffer of size {}", size);
+
+        // Send data to server
+        let is_compressed = false;
+        let file_name = Some(String::from(version_name.to_str().unwrap()));
+
+        let params = ChunkParams {
+            chunk_num: i,
+            total_chunks: num_chunks,
+            total_size: total_size as usize,
+        };
+
+        api::remote::commits::upload_data_chunk_to_server_with_retry(
+            remote_repo,
+            commit,
+            &buffer,
+            &entry.hash,
+            &params,
+            is_compressed,
+            &file_name,
+        )
+        .await?;
+        bar.inc(size);
+        log::debug!("Successfully uploaded chunk {}/{}", i, num_chunks);
+    }
+
+    Ok(())
+}
+
+/// Bundles the versions of the entries into a tarball on the blocking pool and uploads it
+async fn upload_small_entries(
+    entries: Vec<CommitEntry>,
+    repo: LocalRepository,
+    commit: &Commit,
+    remote_repo: &RemoteRepository,
+    bar: Arc<ProgressBar>,
+) -> Result<(), OxenError> {
+    let buffer = run_blocking(move || bundle_entries(&repo, entries)).await?;
+
+    // Send tar.gz to server
+    let is_compressed = true;
+    let file_name = None;
+    api::remote::commits::post_data_to_server(
+        remote_repo,
+        commit,
+        buffer,
+        is_compressed,
+        &file_name,
+        bar,
+    )
+    .await?;
+    log::debug!("Successfully uploaded data!");
+    Ok(())
+}
+
+fn bundle_entries(repo: &LocalRepository, entries: Vec<CommitEntry>) -> Result<Vec<u8>, OxenError> {
+    let enc = GzEncoder::new(Vec::new(), Compression::default());
+    let mut tar = tar::Builder::new(enc);
+    log::debug!("Chunk size {}", entries.len());
+    log::debug!("got repo {:?}", &repo.path);
+    let version_store = repo.version_store()?;
+    for entry in entries.into_iter() {
+        let name = util::fs::version_name(&entry);
+
+        // Stream each version into the tarball instead of reading it all into memory
+        let size = version_store.version_size(&entry.hash, &entry.filename())?;
+        let data = version_store.open_version(&entry.hash, &entry.filename())?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, name, data)?;
+    }
+
+    let buffer = tar.into_inner()?.finish()?;
+    log::debug!("Got tarball buffer of size {}", buffer.len());
+    Ok(buffer)
+}
+
+/// Runs blocking work, like reading from a remote version store, off the async workers
+async fn run_blocking<F, T>(f: F) -> Result<T, OxenError>
+where
+    F: FnOnce() -> Result<T, OxenError> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(err) => Err(OxenError::basic_str(format!("Blocking task failed: {err}"))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
@@ -0,0 +1,323 @@
+//! Verifies that the commits, history dbs and version files of a repository are intact,
+//! and can re-download the objects that are not from a remote.
+
+use crate::api;
+use crate::constants::{DIRS_DIR, HISTORY_DIR, OXEN_HIDDEN_DIR, REFS_DIR};
+use crate::db;
+use crate::error::OxenError;
+use crate::index::{CommitDirEntryReader, CommitDirReader, CommitReader, CommitWriter};
+use crate::model::{
+    Commit, CommitEntry, CorruptDB, FsckReport, LocalRepository, MissingParent, RemoteRepository,
+};
+use crate::storage::VersionStore;
+use crate::util;
+
+use rocksdb::{DBWithThreadMode, MultiThreaded};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Check every commit in the repository, or just `commit_id` if it is provided
+pub fn check(repo: &LocalRepository, commit_id: Option<&str>) -> Result<FsckReport, OxenError> {
+    let mut report = FsckReport::default();
+
+    let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
+    check_db(&hidden_dir.join(REFS_DIR), None, &mut report);
+    let commits_db = CommitWriter::commit_db_dir(&repo.path);
+    check_db(&commits_db, None, &mut report);
+    if !report.corrupt_dbs.is_empty() {
+        // Cannot walk the commits without the refs and commits dbs
+        return Ok(report);
+    }
+
+    let commit_reader = CommitReader::new(repo)?;
+    let commits: Vec<Commit> = match commit_id {
+        Some(commit_id) => match commit_reader.get_commit_by_id(commit_id)? {
+            Some(commit) => vec![commit],
+            None => return Err(OxenError::commit_id_does_not_exist(commit_id)),
+        },
+        None => commit_reader.list_all()?,
+    };
+
+    let version_store = repo.version_store()?;
+    // Entries that did not change between commits point to the same version, only check them once
+    let mut checked_versions: HashSet<(String, PathBuf)> = HashSet::new();
+    for commit in commits.iter() {
+        log::debug!("fsck checking commit {}", commit.id);
+        report.num_commits_checked += 1;
+
+        for parent_id in commit.parent_ids.iter() {
+            if !commit_reader.commit_id_exists(parent_id) {
+                report.missing_parents.push(MissingParent {
+                    commit_id: commit.id.to_owned(),
+                    parent_id: parent_id.to_owned(),
+                });
+            }
+        }
+
+        let entries = match check_commit_dbs(repo, commit, &mut report) {
+            Some(entries) => entries,
+            None => continue,
+        };
+
+        for entry in entries {
+            report.num_entries_checked += 1;
+            let key = (entry.hash.to_owned(), entry.filename());
+            if checked_versions.contains(&key) {
+                continue;
+            }
+            check_version(version_store.as_ref(), &entry, &mut report)?;
+            checked_versions.insert(key);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Re-downloads the bad objects found in a report from the remote, returns the number of objects repaired
+pub async fn repair(
+    repo: &LocalRepository,
+    report: &FsckReport,
+    remote_name: &str,
+) -> Result<usize, OxenError> {
+    let remote = repo
+        .get_remote(remote_name)
+        .ok_or_else(OxenError::remote_not_set)?;
+    let remote_repo = api::remote::repositories::get_by_remote(&remote)
+        .await?
+        .ok_or_else(|| OxenError::remote_repo_not_found(&remote.url))?;
+
+    let mut num_repaired = 0;
+
+    // Commits first, so that the history for them exists when we look at the versions again
+    let mut commit_ids: HashSet<String> = HashSet::new();
+    for missing in report.missing_parents.iter() {
+        if commit_ids.contains(&missing.parent_id) {
+            continue;
+        }
+        match api::remote::commits::get_by_id(&remote_repo, &missing.parent_id).await? {
+            Some(commit) => {
+                let commit_writer = CommitWriter::new(repo)?;
+                commit_writer.add_commit_to_db(&commit)?;
+                download_commit_history(repo, &remote_repo, &commit.id).await?;
+                commit_ids.insert(commit.id);
+                num_repaired += 1;
+            }
+            None => {
+                log::error!("Remote does not have commit {}", missing.parent_id);
+            }
+        }
+    }
+
+    for corrupt_db in report.corrupt_dbs.iter() {
+        match &corrupt_db.commit_id {
+            Some(commit_id) if !commit_ids.contains(commit_id) => {
+                download_commit_history(repo, &remote_repo, commit_id).await?;
+                commit_ids.insert(commit_id.to_owned());
+                num_repaired += 1;
+            }
+            Some(_) => {}
+            None => {
+                log::error!(
+                    "Cannot repair {:?} from remote: {}",
+                    corrupt_db.path,
+                    corrupt_db.error
+                );
+            }
+        }
+    }
+
+    let bad_entries: Vec<&CommitEntry> = report
+        .missing_versions
+        .iter()
+        .chain(report.corrupt_versions.iter())
+        .collect();
+    if !bad_entries.is_empty() {
+        let content_ids: Vec<String> = bad_entries
+            .iter()
+            .map(|entry| {
+                let version_path = Path::new(OXEN_HIDDEN_DIR).join(util::fs::version_name(entry));
+                String::from(version_path.to_string_lossy())
+            })
+            .collect();
+        api::remote::entries::download_data_from_version_paths(repo, &remote_repo, &content_ids)
+            .await?;
+        num_repaired += content_ids.len();
+    }
+
+    Ok(num_repaired)
+}
+
+async fn download_commit_history(
+    repo: &LocalRepository,
+    remote_repo: &RemoteRepository,
+    commit_id: &str,
+) -> Result<(), OxenError> {
+    let commit_dir = util::fs::oxen_hidden_dir(&repo.path)
+        .join(HISTORY_DIR)
+        .join(commit_id);
+    if commit_dir.exists() {
+        std::fs::remove_dir_all(&commit_dir)?;
+    }
+    api::remote::commits::download_commit_db_by_id(repo, remote_repo, commit_id).await
+}
+
+/// Makes sure the dbs in the commit's history dir open, and returns the entries if they do
+fn check_commit_dbs(
+    repo: &LocalRepository,
+    commit: &Commit,
+    report: &mut FsckReport,
+) -> Option<Vec<CommitEntry>> {
+    let dirs_db = util::fs::oxen_hidden_dir(&repo.path)
+        .join(HISTORY_DIR)
+        .join(&commit.id)
+        .join(DIRS_DIR);
+    // Check before opening the reader, which would create an empty db
+    if !check_db(&dirs_db, Some(&commit.id), report) {
+        return None;
+    }
+
+    let reader = match CommitDirReader::new(repo, commit) {
+        Ok(reader) => reader,
+        Err(err) => {
+            add_corrupt_db(&dirs_db, Some(&commit.id), err.to_string(), report);
+            return None;
+        }
+    };
+
+    let dirs = match reader.list_committed_dirs() {
+        Ok(dirs) => dirs,
+        Err(err) => {
+            add_corrupt_db(&dirs_db, Some(&commit.id), err.to_string(), report);
+            return None;
+        }
+    };
+
+    let mut is_ok = true;
+    for dir in dirs.iter() {
+        // Dirs that only contain other dirs never get a files db
+        let files_db = CommitDirEntryReader::db_dir(repo, &commit.id, dir);
+        if files_db.join("CURRENT").exists() {
+            is_ok = check_db(&files_db, Some(&commit.id), report) && is_ok;
+        }
+    }
+    if !is_ok {
+        return None;
+    }
+
+    match reader.list_entries() {
+        Ok(entries) => Some(entries),
+        Err(err) => {
+            add_corrupt_db(&dirs_db, Some(&commit.id), err.to_string(), report);
+            None
+        }
+    }
+}
+
+/// Returns true if the db exists and opens cleanly
+fn check_db(path: &Path, commit_id: Option<&str>, report: &mut FsckReport) -> bool {
+    if !path.join("CURRENT").exists() {
+        add_corrupt_db(path, commit_id, String::from("db does not exist"), report);
+        return false;
+    }
+
+    let opts = db::opts::default();
+    match DBWithThreadMode::<MultiThreaded>::open_for_read_only(&opts, path, false) {
+        Ok(_db) => true,
+        Err(err) => {
+            add_corrupt_db(path, commit_id, err.to_string(), report);
+            false
+        }
+    }
+}
+
+fn add_corrupt_db(path: &Path, commit_id: Option<&str>, error: String, report: &mut FsckReport) {
+    log::debug!("fsck corrupt db {:?}: {}", path, error);
+    report.corrupt_dbs.push(CorruptDB {
+        path: path.to_path_buf(),
+        commit_id: commit_id.map(String::from),
+        error,
+    });
+}
+
+fn check_version(
+    version_store: &dyn VersionStore,
+    entry: &CommitEntry,
+    report: &mut FsckReport,
+) -> Result<(), OxenError> {
+    let filename = entry.filename();
+    if !version_store.version_exists(&entry.hash, &filename)? {
+        log::debug!("fsck missing version for {:?}", entry.path);
+        report.missing_versions.push(entry.to_owned());
+        return Ok(());
+    }
+
+    // Stream the version through the hasher so large files are never held in memory
+    let reader = version_store.open_version(&entry.hash, &filename)?;
+    if util::hasher::hash_reader(reader)? != entry.hash {
+        log::debug!("fsck corrupt version for {:?}", entry.path);
+        report.corrupt_versions.push(entry.to_owned());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::constants;
+    use crate::error::OxenError;
+    use crate::index::{fsck, CommitDirReader};
+    use crate::test;
+    use crate::util;
+
+    #[test]
+    fn test_fsck_clean_repo() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let report = fsck::check(&repo, None)?;
+            assert!(report.is_ok());
+            assert!(report.num_commits_checked > 1);
+            assert!(report.num_entries_checked > 0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fsck_corrupt_and_missing_versions() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let reader = CommitDirReader::new(&repo, &commit)?;
+            let entries = reader.list_entries()?;
+
+            let corrupt = &entries[0];
+            util::fs::write_to_path(&util::fs::version_path(&repo, corrupt), "not the data")?;
+            let missing = entries.iter().find(|e| e.hash != corrupt.hash).unwrap();
+            std::fs::remove_file(util::fs::version_path(&repo, missing))?;
+
+            let report = fsck::check(&repo, Some(&commit.id))?;
+            assert!(!report.is_ok());
+            assert_eq!(report.num_commits_checked, 1);
+            assert_eq!(report.corrupt_versions.len(), 1);
+            assert_eq!(report.corrupt_versions[0].path, corrupt.path);
+            assert_eq!(report.missing_versions.len(), 1);
+            assert_eq!(report.missing_versions[0].path, missing.path);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fsck_missing_history_db() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let commit_dir = util::fs::oxen_hidden_dir(&repo.path)
+                .join(constants::HISTORY_DIR)
+                .join(&commit.id);
+            std::fs::remove_dir_all(commit_dir)?;
+
+            let report = fsck::check(&repo, None)?;
+            assert_eq!(report.corrupt_dbs.len(), 1);
+            assert_eq!(report.corrupt_dbs[0].commit_id, Some(commit.id));
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,275 @@
+//! Finds the version files and history dirs that are no longer reachable from any ref,
+//! for example after a branch was deleted or a push was aborted, and removes them.
+
+use crate::constants::{HISTORY_DIR, MERGE_HEAD_FILE, ORIG_HEAD_FILE};
+use crate::error::OxenError;
+use crate::index::{reflog, stasher};
+use crate::index::{CommitDirReader, CommitReader, CommitWriter, RefReader};
+use crate::model::{Commit, GCReport, LocalRepository};
+use crate::opts::GCOpts;
+use crate::util;
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Walks all the refs and reports the unreachable objects, deleting them unless `opts.dry_run` is set
+pub fn collect(repo: &LocalRepository, opts: &GCOpts) -> Result<GCReport, OxenError> {
+    let history_dir = util::fs::oxen_hidden_dir(&repo.path).join(HISTORY_DIR);
+    let commit_reader = CommitReader::new(repo)?;
+
+    // Expired reflog entries do not keep their commits around anyway, drop them from the file too
+    if !opts.dry_run {
+        let expiry = Duration::from_secs(reflog::DEFAULT_REFLOG_EXPIRY_SECS);
+        let num_expired = reflog::expire(repo, expiry)?;
+        log::debug!("gc expired {} reflog entries", num_expired);
+    }
+
+    let mut keep_commit_ids = reachable_commit_ids(repo, &commit_reader)?;
+    log::debug!("gc found {} reachable commits", keep_commit_ids.len());
+
+    // Every commit we know about, either from the commits db or from a history dir
+    let mut all_commits: HashMap<String, Option<Commit>> = commit_reader
+        .list_all()?
+        .into_iter()
+        .map(|commit| (commit.id.clone(), Some(commit)))
+        .collect();
+    for commit_id in list_history_commit_ids(&history_dir)? {
+        all_commits.entry(commit_id).or_insert(None);
+    }
+
+    let mut report = GCReport {
+        dry_run: opts.dry_run,
+        ..GCReport::default()
+    };
+    for (commit_id, commit) in all_commits.iter() {
+        if keep_commit_ids.contains(commit_id) {
+            continue;
+        }
+
+        // Commits that were just written may belong to a push that has not updated its branch yet
+        let commit_dir = history_dir.join(commit_id);
+        if is_within_grace_period(&commit_dir, commit.as_ref(), opts.grace_period) {
+            log::debug!("gc keeping recent unreachable commit {}", commit_id);
+            keep_commit_ids.insert(commit_id.to_owned());
+        } else {
+            report.num_bytes += util::fs::dir_size(&commit_dir);
+            report.unreachable_commits.push(commit_id.to_owned());
+        }
+    }
+
+    let mut reachable_hashes: HashSet<String> = HashSet::new();
+    for commit_id in keep_commit_ids.iter() {
+        // Do not create history dirs for commits that never had one
+        if !history_dir.join(commit_id).exists() {
+            continue;
+        }
+
+        if let Some(Some(commit)) = all_commits.get(commit_id) {
+            let reader = CommitDirReader::new(repo, commit)?;
+            for entry in reader.list_entries()? {
+                reachable_hashes.insert(entry.hash);
+            }
+        }
+    }
+    log::debug!("gc found {} reachable hashes", reachable_hashes.len());
+
+    let version_store = repo.version_store()?;
+    for hash in version_store.list_versions()? {
+        if !reachable_hashes.contains(&hash) {
+            report.num_bytes += version_store.hash_size(&hash)?;
+            report.unreachable_versions.push(hash);
+        }
+    }
+
+    report.unreachable_commits.sort();
+    report.unreachable_versions.sort();
+
+    if opts.dry_run || report.is_empty() {
+        return Ok(report);
+    }
+
+    for hash in report.unreachable_versions.iter() {
+        log::debug!("gc deleting version {}", hash);
+        version_store.delete_version(hash)?;
+    }
+
+    let commit_writer = CommitWriter::new(repo)?;
+    for commit_id in report.unreachable_commits.iter() {
+        log::debug!("gc deleting commit {}", commit_id);
+        // Remove the commit from the db too, otherwise a client would think the server already has its history
+        commit_writer.delete_commit_from_db(commit_id)?;
+        let commit_dir = history_dir.join(commit_id);
+        if commit_dir.exists() {
+            std::fs::remove_dir_all(commit_dir)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// All the commits reachable from a branch, HEAD, a stash, the reflog, or an in progress merge
+fn reachable_commit_ids(
+    repo: &LocalRepository,
+    commit_reader: &CommitReader,
+) -> Result<HashSet<String>, OxenError> {
+    let ref_reader = RefReader::new(repo)?;
+    let mut ref_commit_ids: Vec<String> = ref_reader
+        .list_branches()?
+        .into_iter()
+        .map(|branch| branch.commit_id)
+        .collect();
+
+    if let Some(head_commit_id) = ref_reader.head_commit_id()? {
+        ref_commit_ids.push(head_commit_id);
+    }
+
+    let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
+    for filename in [MERGE_HEAD_FILE, ORIG_HEAD_FILE] {
+        let path = hidden_dir.join(filename);
+        if path.exists() {
+            ref_commit_ids.push(util::fs::read_from_path(&path)?.trim().to_string());
+        }
+    }
+    ref_commit_ids.extend(stasher::list_commit_ids(repo)?);
+    ref_commit_ids.extend(reflog::list_commit_ids(repo)?);
+
+    let mut commit_ids: HashSet<String> = HashSet::new();
+    for commit_id in ref_commit_ids {
+        if commit_ids.contains(&commit_id) {
+            continue;
+        }
+
+        // This errors if any commit in the history is missing, we would rather stop
+        // than delete data for a history we cannot fully account for
+        for commit in commit_reader.history_from_commit_id(&commit_id)? {
+            commit_ids.insert(commit.id);
+        }
+    }
+    Ok(commit_ids)
+}
+
+fn list_history_commit_ids(history_dir: &Path) -> Result<Vec<String>, OxenError> {
+    let mut commit_ids: Vec<String> = vec![];
+    if !history_dir.exists() {
+        return Ok(commit_ids);
+    }
+
+    for entry in std::fs::read_dir(history_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            commit_ids.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(commit_ids)
+}
+
+fn is_within_grace_period(
+    commit_dir: &Path,
+    commit: Option<&Commit>,
+    grace_period: Duration,
+) -> bool {
+    if let Ok(modified) = std::fs::metadata(commit_dir).and_then(|m| m.modified()) {
+        return match SystemTime::now().duration_since(modified) {
+            Ok(age) => age < grace_period,
+            // Modified in the future, be safe and keep it
+            Err(_) => true,
+        };
+    }
+
+    match commit {
+        Some(commit) => {
+            let age = time::OffsetDateTime::now_utc() - commit.timestamp;
+            age < grace_period
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::error::OxenError;
+    use crate::index::{garbage_collector, CommitReader};
+    use crate::opts::GCOpts;
+    use crate::test;
+    use crate::util;
+
+    use std::time::Duration;
+
+    fn no_grace_period(dry_run: bool) -> GCOpts {
+        GCOpts {
+            dry_run,
+            grace_period: Duration::from_secs(0),
+        }
+    }
+
+    #[test]
+    fn test_gc_fully_reachable_repo_is_clean() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let report = garbage_collector::collect(&repo, &no_grace_period(false))?;
+            assert!(report.is_empty());
+            assert_eq!(report.num_bytes, 0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_gc_deleted_branch() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let og_branch = command::current_branch(&repo)?.unwrap();
+
+            let branch_name = "feature/to-delete";
+            command::create_checkout_branch(&repo, branch_name)?;
+            let hello_file = repo.path.join("hello.txt");
+            util::fs::write_to_path(&hello_file, "Hello World")?;
+            command::add(&repo, &hello_file)?;
+            let commit = command::commit(&repo, "Adding hello")?.unwrap();
+
+            command::checkout(&repo, &og_branch.name)?;
+            command::force_delete_branch(&repo, branch_name)?;
+
+            // Dry run reports but does not delete
+            let report = garbage_collector::collect(&repo, &no_grace_period(true))?;
+            assert_eq!(report.unreachable_commits, vec![commit.id.clone()]);
+            assert_eq!(report.unreachable_versions.len(), 1);
+            assert!(report.num_bytes > 0);
+            let version_store = repo.version_store()?;
+            assert_eq!(version_store.list_versions()?.len(), 1);
+
+            let report = garbage_collector::collect(&repo, &no_grace_period(false))?;
+            assert_eq!(report.unreachable_commits, vec![commit.id.clone()]);
+            assert!(version_store.list_versions()?.is_empty());
+            let commit_reader = CommitReader::new(&repo)?;
+            assert!(!commit_reader.commit_id_exists(&commit.id));
+
+            // Nothing left to collect
+            let report = garbage_collector::collect(&repo, &no_grace_period(false))?;
+            assert!(report.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_gc_keeps_recent_unreachable_commits() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let og_branch = command::current_branch(&repo)?.unwrap();
+
+            let branch_name = "add-hello";
+            command::create_checkout_branch(&repo, branch_name)?;
+            let hello_file = repo.path.join("hello.txt");
+            util::fs::write_to_path(&hello_file, "Hello World")?;
+            command::add(&repo, &hello_file)?;
+            command::commit(&repo, "Adding hello")?;
+
+            command::checkout(&repo, &og_branch.name)?;
+            command::force_delete_branch(&repo, branch_name)?;
+
+            let report = garbage_collector::collect(&repo, &GCOpts::default())?;
+            assert!(report.is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,7 @@
 use crate::command;
 use crate::constants::{MERGE_DIR, MERGE_HEAD_FILE, ORIG_HEAD_FILE};
 use crate::db;
+use crate::df::{tabular, DFOpts};
 use crate::error::OxenError;
 use crate::index::{
     CommitDirReader, CommitReader, CommitWriter, MergeConflictDBReader, RefReader, RefWriter,
@@ -11,7 +12,8 @@ use crate::model::{Commit, CommitEntry, LocalRepository, MergeConflict};
 use crate::util;
 
 use rocksdb::DB;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::str;
 
 use super::restore;
@@ -99,6 +101,14 @@ impl Merger {
         Ok(())
     }
 
+    /// Forgets all the recorded conflicts, for when the operation that made them is aborted
+    pub fn remove_all_conflicts(&self) -> Result<(), OxenError> {
+        for conflict in MergeConflictDBReader::list_conflicts(&self.merge_db)? {
+            self.remove_conflict_path(conflict.path())?;
+        }
+        Ok(())
+    }
+
     fn write_conflicts_to_disk(
         &self,
         merge_commits: &MergeCommits,
@@ -111,8 +121,13 @@ impl Merger {
         util::fs::write_to_path(&merge_head_path, &merge_commits.merge.id)?;
         util::fs::write_to_path(&orig_head_path, &merge_commits.head.id)?;
 
+        self.write_conflicts(conflicts)
+    }
+
+    /// Records the conflicts so they show up in status until they are resolved and added
+    pub fn write_conflicts(&self, conflicts: &[MergeConflict]) -> Result<(), OxenError> {
         for conflict in conflicts.iter() {
-            let key = conflict.head_entry.path.to_str().unwrap();
+            let key = conflict.path().to_str().unwrap();
             let key_bytes = key.as_bytes();
             let val_json = serde_json::to_string(&conflict)?;
 
@@ -122,6 +137,148 @@ impl Merger {
         Ok(())
     }
 
+    /// Applies the changes going from the `base` commit to the `target` commit onto the working files,
+    /// for stashes, cherry picks and reverts. Files that HEAD changed too are merged row by row if they
+    /// are tabular, otherwise they are recorded as conflicts and left as they are in HEAD, as are files
+    /// that one side removed and the other changed, or that both added.
+    /// Returns the paths that were updated and the conflicts, the caller stages the paths.
+    pub fn apply_delta(
+        &self,
+        base: &Commit,
+        target: &Commit,
+    ) -> Result<(Vec<PathBuf>, Vec<MergeConflict>), OxenError> {
+        let repo = &self.repository;
+        let head_commit = command::head_commit(repo)?;
+        let base_reader = CommitDirReader::new(repo, base)?;
+        let target_reader = CommitDirReader::new(repo, target)?;
+        let head_reader = CommitDirReader::new(repo, &head_commit)?;
+
+        // What changed going from the base to the target
+        let base_entries = base_reader.list_entries_set()?;
+        let target_entries = target_reader.list_entries_set()?;
+        let mut changes: Vec<(PathBuf, Option<CommitEntry>, Option<CommitEntry>)> = vec![];
+        for entry in target_entries.iter() {
+            match base_entries.get(entry) {
+                Some(base_entry) if base_entry.hash == entry.hash => {}
+                base_entry => {
+                    changes.push((entry.path.clone(), base_entry.cloned(), Some(entry.clone())))
+                }
+            }
+        }
+        for entry in base_entries.iter() {
+            if !target_entries.contains(entry) {
+                changes.push((entry.path.clone(), Some(entry.clone()), None));
+            }
+        }
+
+        // Do not overwrite work that has not been committed
+        let status = {
+            let stager = Stager::new(repo)?;
+            stager.status(&head_reader)?
+        };
+        let local_changes: HashSet<&Path> = status
+            .added_files
+            .keys()
+            .chain(status.modified_files.iter())
+            .chain(status.removed_files.iter())
+            .map(|path| path.as_path())
+            .collect();
+        let overwritten: Vec<String> = changes
+            .iter()
+            .filter(|(path, _, _)| local_changes.contains(path.as_path()))
+            .map(|(path, _, _)| format!("  {}", path.to_string_lossy()))
+            .collect();
+        if !overwritten.is_empty() {
+            let err = format!(
+                "Your local changes to the following files would be overwritten:\n{}\nCommit or stash them first.",
+                overwritten.join("\n")
+            );
+            return Err(OxenError::basic_str(err));
+        }
+
+        let mut paths: Vec<PathBuf> = vec![];
+        let mut conflicts: Vec<MergeConflict> = vec![];
+        for (path, base_entry, target_entry) in changes {
+            let head_entry = head_reader.get_entry(&path)?;
+            let head_hash = head_entry.as_ref().map(|e| e.hash.as_str());
+            let base_hash = base_entry.as_ref().map(|e| e.hash.as_str());
+            let target_hash = target_entry.as_ref().map(|e| e.hash.as_str());
+            if head_hash == target_hash {
+                continue;
+            }
+
+            if head_hash == base_hash {
+                // Only the target changed the file
+                match &target_entry {
+                    Some(entry) => self.update_entry(entry)?,
+                    None => {
+                        let working_path = repo.path.join(&path);
+                        if working_path.exists() {
+                            std::fs::remove_file(working_path)?;
+                        }
+                    }
+                }
+                paths.push(path);
+                continue;
+            }
+
+            match (base_entry, head_entry, target_entry) {
+                (Some(lca_entry), Some(head_entry), Some(merge_entry)) => {
+                    if self.merge_rows(&lca_entry, &head_entry, &merge_entry)? {
+                        paths.push(path);
+                    } else {
+                        conflicts.push(MergeConflict::Modified {
+                            lca_entry,
+                            head_entry,
+                            merge_entry,
+                        });
+                    }
+                }
+                (lca_entry, head_entry, merge_entry) => {
+                    // Removed on one side and changed on the other, or added on both sides
+                    conflicts.push(MergeConflict::Removed {
+                        lca_entry,
+                        head_entry,
+                        merge_entry,
+                    });
+                }
+            }
+        }
+
+        if !conflicts.is_empty() {
+            self.write_conflicts(&conflicts)?;
+        }
+        Ok((paths, conflicts))
+    }
+
+    /// Writes the rows of a tabular file that both sides changed to the working file, if they do not overlap
+    fn merge_rows(
+        &self,
+        lca_entry: &CommitEntry,
+        head_entry: &CommitEntry,
+        merge_entry: &CommitEntry,
+    ) -> Result<bool, OxenError> {
+        if !util::fs::is_tabular(&head_entry.path) {
+            return Ok(false);
+        }
+
+        let repo = &self.repository;
+        let lca_version = util::fs::local_version(repo, lca_entry)?;
+        let head_version = util::fs::local_version(repo, head_entry)?;
+        let merge_version = util::fs::local_version(repo, merge_entry)?;
+        let df_lca = tabular::read_df(lca_version.path(), DFOpts::empty())?;
+        let df_head = tabular::read_df(head_version.path(), DFOpts::empty())?;
+        let df_merge = tabular::read_df(merge_version.path(), DFOpts::empty())?;
+        match tabular::df_merge_rows(&df_lca, &df_head, &df_merge)? {
+            Some(mut df) => {
+                log::debug!("merge_rows merged {:?} {}", head_entry.path, df);
+                tabular::write_df(&mut df, repo.path.join(&head_entry.path))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     fn create_merge_commit<S: AsRef<str>>(
         &self,
         branch_name: S,
@@ -340,7 +497,7 @@ impl Merger {
                         && lca_entry.hash != merge_entry.hash
                         && head_entry.hash != merge_entry.hash
                     {
-                        conflicts.push(MergeConflict {
+                        conflicts.push(MergeConflict::Modified {
                             lca_entry: lca_entry.to_owned(),
                             head_entry: head_entry.to_owned(),
                             merge_entry: merge_entry.to_owned(),
@@ -709,7 +866,7 @@ mod tests {
             assert_eq!(conflicts.len(), 1);
 
             let local_a_path = util::fs::path_relative_to_dir(&a_path, &repo.path)?;
-            assert_eq!(conflicts[0].head_entry.path, local_a_path);
+            assert_eq!(conflicts[0].path(), local_a_path);
 
             Ok(())
         })
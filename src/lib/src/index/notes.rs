@@ -0,0 +1,221 @@
+//! Notes on commits, kept in the notes db by commit id. Notes live outside the commit so they
+//! can be added, edited and removed later, and are synced on push and pull.
+
+use crate::config::UserConfig;
+use crate::constants::NOTES_DIR;
+use crate::db::{self, str_json_db};
+use crate::error::OxenError;
+use crate::model::{LocalRepository, Note};
+use crate::util;
+
+use rocksdb::{DBWithThreadMode, MultiThreaded};
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+fn notes_db_dir(repo: &LocalRepository) -> PathBuf {
+    util::fs::oxen_hidden_dir(&repo.path).join(NOTES_DIR)
+}
+
+fn open_db(repo: &LocalRepository) -> Result<DBWithThreadMode<MultiThreaded>, OxenError> {
+    let db_path = notes_db_dir(repo);
+    if !db_path.exists() {
+        std::fs::create_dir_all(&db_path)?;
+    }
+    let opts = db::opts::default();
+    Ok(DBWithThreadMode::open(&opts, &db_path)?)
+}
+
+/// The note on the commit, if it has one that was not removed
+pub fn get(repo: &LocalRepository, commit_id: &str) -> Result<Option<Note>, OxenError> {
+    let db = open_db(repo)?;
+    let note: Option<Note> = str_json_db::get(&db, commit_id)?;
+    Ok(note.filter(|note| !note.deleted))
+}
+
+/// Adds the note to the commit, replacing the note it had
+pub fn set(repo: &LocalRepository, commit_id: &str, message: &str) -> Result<Note, OxenError> {
+    let cfg = UserConfig::get()?;
+    let note = Note {
+        commit_id: String::from(commit_id),
+        message: String::from(message),
+        author: cfg.name,
+        email: cfg.email,
+        timestamp: OffsetDateTime::now_utc(),
+        deleted: false,
+    };
+    let db = open_db(repo)?;
+    str_json_db::put(&db, commit_id, &note)?;
+    Ok(note)
+}
+
+/// Removes the note from the commit, returning the note that was removed
+pub fn remove(repo: &LocalRepository, commit_id: &str) -> Result<Option<Note>, OxenError> {
+    let note = match get(repo, commit_id)? {
+        Some(note) => note,
+        None => return Ok(None),
+    };
+
+    // Keep the removal around so it wins over the old note on the next sync
+    let removed = Note {
+        deleted: true,
+        timestamp: OffsetDateTime::now_utc(),
+        ..note.clone()
+    };
+    let db = open_db(repo)?;
+    str_json_db::put(&db, commit_id, &removed)?;
+    Ok(Some(note))
+}
+
+/// The notes that were not removed, in no particular order
+pub fn list(repo: &LocalRepository) -> Result<Vec<Note>, OxenError> {
+    Ok(list_all(repo)?
+        .into_iter()
+        .filter(|note| !note.deleted)
+        .collect())
+}
+
+/// All the notes including the removed ones, which is what gets synced
+pub fn list_all(repo: &LocalRepository) -> Result<Vec<Note>, OxenError> {
+    let db = open_db(repo)?;
+    str_json_db::list_vals(&db)
+}
+
+/// Merges notes from another copy of the repo, keeping the newest note for each commit
+pub fn merge(repo: &LocalRepository, notes: &[Note]) -> Result<(), OxenError> {
+    let db = open_db(repo)?;
+    for note in notes.iter() {
+        let current: Option<Note> = str_json_db::get(&db, &note.commit_id)?;
+        let is_newer = match current {
+            Some(current) => note.timestamp > current.timestamp,
+            None => true,
+        };
+        if is_newer {
+            str_json_db::put(&db, &note.commit_id, note)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merges notes pushed to a remote. The notes that are newer than the ones it has are stored
+/// with the time they were received, and with the author of the push when it is known, instead of
+/// what the client sent. Notes that did not change are left as they are.
+pub fn merge_pushed(
+    repo: &LocalRepository,
+    notes: &[Note],
+    pusher: Option<(&str, &str)>,
+) -> Result<(), OxenError> {
+    let db = open_db(repo)?;
+    let now = OffsetDateTime::now_utc();
+    for note in notes.iter() {
+        let current: Option<Note> = str_json_db::get(&db, &note.commit_id)?;
+        let is_changed = match &current {
+            Some(current) => {
+                note.timestamp > current.timestamp
+                    && (note.message != current.message || note.deleted != current.deleted)
+            }
+            None => true,
+        };
+        if !is_changed {
+            continue;
+        }
+
+        let mut note = note.clone();
+        note.timestamp = now;
+        if let Some((name, email)) = pusher {
+            note.author = String::from(name);
+            note.email = String::from(email);
+        }
+        str_json_db::put(&db, &note.commit_id, &note)?;
+    }
+    Ok(())
+}
+
+/// Stores the notes as the remote has them, replacing the local ones, after they were pushed
+pub fn replace(repo: &LocalRepository, notes: &[Note]) -> Result<(), OxenError> {
+    let db = open_db(repo)?;
+    for note in notes.iter() {
+        str_json_db::put(&db, &note.commit_id, note)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::error::OxenError;
+    use crate::index::notes;
+    use crate::model::Note;
+    use crate::test;
+
+    use time::{Duration, OffsetDateTime};
+
+    #[test]
+    fn test_notes_set_remove_and_merge() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let commit = command::head_commit(&repo)?;
+            assert!(notes::get(&repo, &commit.id)?.is_none());
+
+            notes::set(&repo, &commit.id, "Reviewed")?;
+            let note = notes::get(&repo, &commit.id)?.unwrap();
+            assert_eq!(note.message, "Reviewed");
+            assert_eq!(notes::list(&repo)?.len(), 1);
+
+            // Removing keeps a tombstone to sync, but hides the note
+            notes::remove(&repo, &commit.id)?;
+            assert!(notes::get(&repo, &commit.id)?.is_none());
+            assert_eq!(notes::list(&repo)?.len(), 0);
+            assert_eq!(notes::list_all(&repo)?.len(), 1);
+
+            // An older note does not bring it back, a newer one does
+            let old_note = Note {
+                message: String::from("Old"),
+                timestamp: note.timestamp - Duration::hours(1),
+                ..note.clone()
+            };
+            notes::merge(&repo, &[old_note])?;
+            assert!(notes::get(&repo, &commit.id)?.is_none());
+
+            let new_note = Note {
+                message: String::from("New"),
+                timestamp: note.timestamp + Duration::hours(1),
+                ..note
+            };
+            notes::merge(&repo, &[new_note])?;
+            assert_eq!(notes::get(&repo, &commit.id)?.unwrap().message, "New");
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_notes_merge_pushed_stamps_pusher() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let note = Note {
+                commit_id: commit.id.to_owned(),
+                message: String::from("Reviewed"),
+                author: String::from("Someone"),
+                email: String::from("someone@else.com"),
+                timestamp: OffsetDateTime::now_utc() + Duration::days(365),
+                deleted: false,
+            };
+            notes::merge_pushed(&repo, &[note.clone()], Some(("Ox", "ox@oxen.ai")))?;
+
+            let stored = notes::get(&repo, &commit.id)?.unwrap();
+            assert_eq!(stored.message, "Reviewed");
+            assert_eq!(stored.author, "Ox");
+            assert_eq!(stored.email, "ox@oxen.ai");
+            assert!(stored.timestamp < note.timestamp);
+
+            // Pushing the same note again from someone else does not take it over
+            let same = Note {
+                timestamp: stored.timestamp + Duration::hours(1),
+                ..stored.clone()
+            };
+            notes::merge_pushed(&repo, &[same], Some(("Bad", "bad@oxen.ai")))?;
+            assert_eq!(notes::get(&repo, &commit.id)?.unwrap(), stored);
+
+            Ok(())
+        })
+    }
+}
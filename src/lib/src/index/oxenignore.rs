@@ -0,0 +1,222 @@
+//! Gitignore compatible `.oxenignore` files. Any directory in the working tree can have one, its patterns
+//! are relative to that directory and take precedence over the files above it. The `ignore` patterns in
+//! the user config apply to every repository, with the lowest precedence.
+
+use crate::config::UserConfig;
+use crate::model::LocalRepository;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const OXENIGNORE_FILENAME: &str = ".oxenignore";
+
+/// The pattern that decided whether a path is ignored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreMatch {
+    /// The `.oxenignore` the pattern is from relative to the repository, None for the user config
+    pub source: Option<PathBuf>,
+    /// The line as written in the file, with the `!` of negated patterns
+    pub pattern: String,
+    /// Negated patterns (`!pattern`) re-include the path
+    pub negated: bool,
+}
+
+pub struct OxenIgnore {
+    repo_path: PathBuf,
+    global: Gitignore,
+    // Loaded the first time a path within the dir is checked, None if the dir has no .oxenignore
+    dirs: Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl OxenIgnore {
+    /// The ignore files in the repository plus the patterns from the user config, if it exists
+    pub fn new(repo: &LocalRepository) -> OxenIgnore {
+        let patterns = UserConfig::get()
+            .map(|config| config.ignore)
+            .unwrap_or_default();
+        OxenIgnore::with_global_patterns(&repo.path, &patterns)
+    }
+
+    pub fn with_global_patterns(repo_path: &Path, patterns: &[String]) -> OxenIgnore {
+        let mut builder = GitignoreBuilder::new(repo_path);
+        for pattern in patterns {
+            if let Err(err) = builder.add_line(None, pattern) {
+                log::warn!("Skipping invalid ignore pattern {:?}: {}", pattern, err);
+            }
+        }
+        let global = builder.build().unwrap_or_else(|err| {
+            log::warn!("Could not build global ignore patterns: {}", err);
+            Gitignore::empty()
+        });
+
+        OxenIgnore {
+            repo_path: repo_path.to_path_buf(),
+            global,
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the path relative to the repository is ignored, by itself or because a parent dir is
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        matches!(self.matched(path, is_dir), Some(m) if !m.negated)
+    }
+
+    /// The pattern that decides if the path relative to the repository is ignored. Like git, a file
+    /// cannot be re-included with a negated pattern if one of its parent dirs is ignored.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        let parents: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect();
+        for dir in parents.iter().rev() {
+            match self.matched_path(dir, true) {
+                Some(m) if !m.negated => return Some(m),
+                _ => {}
+            }
+        }
+        self.matched_path(path, is_dir)
+    }
+
+    fn matched_path(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        // The closest .oxenignore decides first
+        for dir in path.ancestors().skip(1) {
+            if let Some(gitignore) = self.gitignore_for_dir(dir) {
+                let relative = path.strip_prefix(dir).unwrap_or(path);
+                if let Some(m) = self.to_ignore_match(gitignore.matched(relative, is_dir)) {
+                    return Some(m);
+                }
+            }
+        }
+        self.to_ignore_match(self.global.matched(path, is_dir))
+    }
+
+    fn gitignore_for_dir(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        let mut dirs = self.dirs.lock().unwrap();
+        dirs.entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let full_dir = self.repo_path.join(dir);
+                let ignore_file = full_dir.join(OXENIGNORE_FILENAME);
+                if !ignore_file.is_file() {
+                    return None;
+                }
+
+                let mut builder = GitignoreBuilder::new(&full_dir);
+                if let Some(err) = builder.add(&ignore_file) {
+                    log::warn!("Skipping invalid patterns in {:?}: {}", ignore_file, err);
+                }
+                match builder.build() {
+                    Ok(gitignore) => Some(Arc::new(gitignore)),
+                    Err(err) => {
+                        log::warn!("Could not read {:?}: {}", ignore_file, err);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    fn to_ignore_match(&self, m: Match<&Glob>) -> Option<IgnoreMatch> {
+        m.inner().map(|glob| IgnoreMatch {
+            source: glob.from().map(|from| {
+                from.strip_prefix(&self.repo_path)
+                    .unwrap_or(from)
+                    .to_path_buf()
+            }),
+            pattern: String::from(glob.original()),
+            negated: glob.is_whitelist(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::index::oxenignore::{OxenIgnore, OXENIGNORE_FILENAME};
+    use crate::test;
+    use crate::util;
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_oxenignore_patterns() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            util::fs::write_to_path(
+                &repo.path.join(OXENIGNORE_FILENAME),
+                "*.ckpt\n!best.ckpt\n__pycache__/\n/wandb\n",
+            )?;
+            let ignore = OxenIgnore::with_global_patterns(&repo.path, &[]);
+
+            assert!(ignore.is_ignored(Path::new("model.ckpt"), false));
+            assert!(ignore.is_ignored(Path::new("runs/model.ckpt"), false));
+            assert!(!ignore.is_ignored(Path::new("best.ckpt"), false));
+            assert!(ignore.is_ignored(Path::new("src/__pycache__"), true));
+            assert!(ignore.is_ignored(Path::new("src/__pycache__/mod.pyc"), false));
+            // Dir patterns only match dirs
+            assert!(!ignore.is_ignored(Path::new("__pycache__"), false));
+            // Anchored to the dir of the ignore file
+            assert!(ignore.is_ignored(Path::new("wandb/run-1/log.txt"), false));
+            assert!(!ignore.is_ignored(Path::new("src/wandb"), true));
+            assert!(!ignore.is_ignored(Path::new("train.csv"), false));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_oxenignore_nested_files() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            util::fs::write_to_path(&repo.path.join(OXENIGNORE_FILENAME), "*.log\ntmp/\n")?;
+            let nested_dir = repo.path.join("logs");
+            std::fs::create_dir_all(&nested_dir)?;
+            util::fs::write_to_path(
+                &nested_dir.join(OXENIGNORE_FILENAME),
+                "!keep.log\n/local.csv\n",
+            )?;
+            let ignore = OxenIgnore::with_global_patterns(&repo.path, &[]);
+
+            assert!(ignore.is_ignored(Path::new("train.log"), false));
+            // The nested file re-includes what the root one ignores
+            assert!(!ignore.is_ignored(Path::new("logs/keep.log"), false));
+            assert!(ignore.is_ignored(Path::new("logs/other.log"), false));
+            assert!(ignore.is_ignored(Path::new("logs/local.csv"), false));
+            assert!(!ignore.is_ignored(Path::new("local.csv"), false));
+
+            let m = ignore.matched(Path::new("logs/local.csv"), false).unwrap();
+            assert_eq!(
+                m.source,
+                Some(PathBuf::from("logs").join(OXENIGNORE_FILENAME))
+            );
+            assert_eq!(m.pattern, "/local.csv");
+
+            // Files in an ignored dir cannot be re-included
+            util::fs::write_to_path(
+                &repo.path.join(OXENIGNORE_FILENAME),
+                "tmp/\n!tmp/keep.txt\n",
+            )?;
+            let ignore = OxenIgnore::with_global_patterns(&repo.path, &[]);
+            assert!(ignore.is_ignored(Path::new("tmp/keep.txt"), false));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_oxenignore_global_patterns() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            util::fs::write_to_path(&repo.path.join(OXENIGNORE_FILENAME), "!keep.DS_Store\n")?;
+            let patterns = vec![String::from("*.DS_Store")];
+            let ignore = OxenIgnore::with_global_patterns(&repo.path, &patterns);
+
+            assert!(ignore.is_ignored(Path::new("images/.DS_Store"), false));
+            assert!(!ignore.is_ignored(Path::new("keep.DS_Store"), false));
+            let m = ignore.matched(Path::new(".DS_Store"), false).unwrap();
+            assert_eq!(m.source, None);
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,435 @@
+//! Replays the commits of the current branch that are not in an upstream branch on top of it, so the
+//! history stays linear instead of getting a merge commit after a pull diverged. Each replayed commit
+//! gets a new id, the originals stay reachable from the reflog. When a commit does not apply cleanly
+//! the conflicts are recorded in the merge conflict db and the rebase stops until it is continued or
+//! aborted, the state in between is kept in the REBASE file.
+
+use crate::api;
+use crate::command;
+use crate::constants::REBASE_FILE;
+use crate::error::OxenError;
+use crate::index::{
+    reflog, CommitDirReader, CommitReader, CommitWriter, MergeConflictReader, Merger, RefReader,
+    RefWriter, Stager,
+};
+use crate::model::{Commit, LocalRepository, RebaseReport};
+use crate::util;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RebaseState {
+    branch: String,
+    upstream: String,
+    orig_head: String,
+    /// The commits left to replay, oldest first
+    todo: Vec<String>,
+    /// The commit that stopped on conflicts
+    current: Option<String>,
+}
+
+fn state_path(repo: &LocalRepository) -> PathBuf {
+    util::fs::oxen_hidden_dir(&repo.path).join(REBASE_FILE)
+}
+
+fn read_state(repo: &LocalRepository) -> Result<RebaseState, OxenError> {
+    let path = state_path(repo);
+    if !path.exists() {
+        return Err(OxenError::basic_str("No rebase in progress."));
+    }
+    let contents = util::fs::read_from_path(&path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn write_state(repo: &LocalRepository, state: &RebaseState) -> Result<(), OxenError> {
+    util::fs::write_to_path(&state_path(repo), &serde_json::to_string(state)?)
+}
+
+pub fn is_in_progress(repo: &LocalRepository) -> bool {
+    state_path(repo).exists()
+}
+
+/// Replays the commits of the current branch that are not in `upstream` on top of it.
+pub fn rebase(repo: &LocalRepository, upstream: &str) -> Result<RebaseReport, OxenError> {
+    if is_in_progress(repo) {
+        return Err(OxenError::basic_str(
+            "A rebase is already in progress, use --continue or --abort.",
+        ));
+    }
+    if MergeConflictReader::new(repo)?.has_conflicts()? {
+        return Err(OxenError::basic_str(
+            "There are unresolved conflicts, resolve them and commit first.",
+        ));
+    }
+    let status = command::status(repo)?;
+    if status.has_added_entries() || status.has_modified_entries() || status.has_removed_entries() {
+        return Err(OxenError::basic_str(
+            "You have local changes, commit or stash them first.",
+        ));
+    }
+
+    let branch = RefReader::new(repo)?
+        .get_current_branch()?
+        .ok_or_else(|| OxenError::basic_str("Must be on a branch to rebase."))?;
+    let onto = api::local::commits::get_by_id_or_branch(repo, upstream)?
+        .ok_or_else(|| OxenError::local_commit_or_branch_not_found(upstream))?;
+    let commit_reader = CommitReader::new(repo)?;
+    let head = commit_reader.head_commit()?;
+
+    let head_ids: HashSet<String> = commit_reader
+        .history_from_commit_id(&head.id)?
+        .into_iter()
+        .map(|commit| commit.id)
+        .collect();
+    if head_ids.contains(&onto.id) {
+        return Ok(RebaseReport::UpToDate {
+            branch: branch.name,
+            head,
+        });
+    }
+
+    // Walk back from HEAD until we reach the upstream history. Only a linear history can be replayed
+    // one commit at a time, the commits that came in through a merge would be lost otherwise.
+    let upstream_ids: HashSet<String> = commit_reader
+        .history_from_commit_id(&onto.id)?
+        .into_iter()
+        .map(|commit| commit.id)
+        .collect();
+    let mut todo: Vec<String> = vec![];
+    let mut commit = head.clone();
+    while !upstream_ids.contains(&commit.id) {
+        if commit.parent_ids.len() > 1 {
+            let err = format!(
+                "Cannot rebase {} onto {}, merge commit {} is not in {}. Merge {} instead.",
+                branch.name, upstream, commit.id, upstream, upstream
+            );
+            return Err(OxenError::basic_str(err));
+        }
+        todo.push(commit.id.clone());
+        let parent_id = match commit.parent_ids.first() {
+            Some(parent_id) => parent_id.to_owned(),
+            None => break,
+        };
+        commit = commit_reader
+            .get_commit_by_id(&parent_id)?
+            .ok_or_else(|| OxenError::commit_db_corrupted(&parent_id))?;
+    }
+    todo.reverse();
+
+    log::debug!(
+        "Rebasing {} commits of {} onto {}",
+        todo.len(),
+        branch.name,
+        onto.id
+    );
+    CommitWriter::new(repo)?.set_working_repo_to_commit_id(&onto.id)?;
+    RefWriter::new(repo)?.set_head_commit_id(&onto.id)?;
+    reflog::append(
+        repo,
+        &head.id,
+        &onto.id,
+        &format!("rebase (start): checkout {upstream}"),
+    )?;
+
+    let state = RebaseState {
+        branch: branch.name,
+        upstream: String::from(upstream),
+        orig_head: head.id,
+        todo,
+        current: None,
+    };
+    replay(repo, state, vec![])
+}
+
+/// Commits the resolved changes of the commit that stopped, and replays the rest
+pub fn continue_rebase(repo: &LocalRepository) -> Result<RebaseReport, OxenError> {
+    let mut state = read_state(repo)?;
+    if MergeConflictReader::new(repo)?.has_conflicts()? {
+        return Err(OxenError::basic_str(
+            "There are unresolved conflicts, resolve them and `oxen add` them before continuing.",
+        ));
+    }
+    let mut skipped: Vec<Commit> = vec![];
+    if let Some(commit_id) = state.current.take() {
+        let commit = get_commit(repo, &commit_id)?;
+        if commit_replayed(repo, &commit)?.is_none() {
+            skipped.push(commit);
+        }
+    }
+    replay(repo, state, skipped)
+}
+
+/// Puts the branch and the working files back to where they were before the rebase started
+pub fn abort(repo: &LocalRepository) -> Result<Commit, OxenError> {
+    let state = read_state(repo)?;
+    let orig_head = get_commit(repo, &state.orig_head)?;
+    let head = command::head_commit(repo)?;
+
+    Merger::new(repo)?.remove_all_conflicts()?;
+    let status = {
+        let stager = Stager::new(repo)?;
+        let reader = CommitDirReader::new(repo, &head)?;
+        let status = stager.status(&reader)?;
+        stager.unstage()?;
+        status
+    };
+
+    // Files the stopped commit added are in neither HEAD nor the original HEAD
+    let orig_reader = CommitDirReader::new(repo, &orig_head)?;
+    for path in status.added_files.keys() {
+        let working_path = repo.path.join(path);
+        if !orig_reader.has_file(path) && working_path.is_file() {
+            std::fs::remove_file(working_path)?;
+        }
+    }
+    CommitWriter::new(repo)?.set_working_repo_to_commit_id(&orig_head.id)?;
+    RefWriter::new(repo)?.set_head_commit_id(&orig_head.id)?;
+    reflog::append(
+        repo,
+        &head.id,
+        &orig_head.id,
+        &format!("rebase (abort): return to {}", state.branch),
+    )?;
+
+    std::fs::remove_file(state_path(repo))?;
+    Ok(orig_head)
+}
+
+fn replay(
+    repo: &LocalRepository,
+    mut state: RebaseState,
+    mut skipped: Vec<Commit>,
+) -> Result<RebaseReport, OxenError> {
+    while !state.todo.is_empty() {
+        let commit = get_commit(repo, &state.todo.remove(0))?;
+        let parent_id = commit
+            .parent_ids
+            .first()
+            .ok_or_else(|| OxenError::commit_db_corrupted(&commit.id))?;
+        let parent = get_commit(repo, parent_id)?;
+
+        state.current = Some(commit.id.clone());
+        write_state(repo, &state)?;
+
+        let (paths, conflicts) = {
+            let merger = Merger::new(repo)?;
+            merger.apply_delta(&parent, &commit)?
+        };
+        stage(repo, &paths)?;
+        if !conflicts.is_empty() {
+            return Ok(RebaseReport::Stopped { commit, skipped });
+        }
+        if commit_replayed(repo, &commit)?.is_none() {
+            skipped.push(commit);
+        }
+        state.current = None;
+    }
+
+    let head = command::head_commit(repo)?;
+    reflog::append(
+        repo,
+        &state.orig_head,
+        &head.id,
+        &format!("rebase (finish): {} onto {}", state.branch, state.upstream),
+    )?;
+    if is_in_progress(repo) {
+        std::fs::remove_file(state_path(repo))?;
+    }
+    Ok(RebaseReport::Rebased {
+        branch: state.branch,
+        upstream: state.upstream,
+        head,
+        skipped,
+    })
+}
+
+fn stage(repo: &LocalRepository, paths: &[PathBuf]) -> Result<(), OxenError> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let head = command::head_commit(repo)?;
+    let reader = CommitDirReader::new(repo, &head)?;
+    let stager = Stager::new(repo)?;
+    for path in paths.iter() {
+        stager.add(&repo.path.join(path), &reader)?;
+    }
+    Ok(())
+}
+
+/// Commits what is staged on top of HEAD as the commit being replayed, keeping its message, author
+/// and metadata. Returns None if there was nothing to commit because its changes are already upstream
+fn commit_replayed(repo: &LocalRepository, commit: &Commit) -> Result<Option<Commit>, OxenError> {
+    let head = command::head_commit(repo)?;
+    let reader = CommitDirReader::new(repo, &head)?;
+    let stager = Stager::new(repo)?;
+    let status = stager.status(&reader)?;
+    if !status.has_added_entries() {
+        log::debug!(
+            "rebase skipping {}, its changes are already upstream",
+            commit.id
+        );
+        return Ok(None);
+    }
+
+    let commit_writer = CommitWriter::new(repo)?;
+    let new_commit = commit_writer.commit_as_author_of(
+        &status,
+        vec![head.id.clone()],
+        &commit.message,
+        commit,
+    )?;
+    stager.unstage()?;
+    reflog::append(
+        repo,
+        &head.id,
+        &new_commit.id,
+        &format!("rebase (pick): {}", commit.message),
+    )?;
+    log::debug!("rebase replayed {} as {}", commit.id, new_commit.id);
+    Ok(Some(new_commit))
+}
+
+fn get_commit(repo: &LocalRepository, commit_id: &str) -> Result<Commit, OxenError> {
+    CommitReader::new(repo)?
+        .get_commit_by_id(commit_id)?
+        .ok_or_else(|| OxenError::commit_db_corrupted(commit_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::error::OxenError;
+    use crate::index::{rebaser, reflog};
+    use crate::test;
+    use crate::util;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_rebase_replays_commits_onto_upstream() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let og_branch = command::current_branch(&repo)?.unwrap();
+
+            command::create_checkout_branch(&repo, "feature")?;
+            let feature_path = repo.path.join("feature.txt");
+            util::fs::write_to_path(&feature_path, "feature")?;
+            command::add(&repo, &feature_path)?;
+            let metadata = HashMap::from([(String::from("run"), String::from("42"))]);
+            let feature_commit =
+                command::commit_with_metadata(&repo, "Adding feature", &metadata)?.unwrap();
+
+            command::checkout(&repo, &og_branch.name)?;
+            let main_path = repo.path.join("main.txt");
+            util::fs::write_to_path(&main_path, "main")?;
+            command::add(&repo, &main_path)?;
+            let main_commit = command::commit(&repo, "Adding main")?.unwrap();
+
+            command::checkout(&repo, "feature")?;
+            let commit = rebaser::rebase(&repo, &og_branch.name)?
+                .head()
+                .cloned()
+                .unwrap();
+
+            assert_ne!(commit.id, feature_commit.id);
+            assert_eq!(commit.message, feature_commit.message);
+            assert_eq!(commit.author, feature_commit.author);
+            assert_eq!(commit.email, feature_commit.email);
+            assert_eq!(commit.metadata, metadata);
+            assert_eq!(commit.parent_ids, vec![main_commit.id]);
+            assert!(feature_path.exists());
+            assert!(main_path.exists());
+            assert!(!rebaser::is_in_progress(&repo));
+
+            let commit_ids = reflog::list_commit_ids(&repo)?;
+            assert!(commit_ids.contains(&feature_commit.id));
+            assert!(commit_ids.contains(&commit.id));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_rebase_refuses_merge_commits() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let og_branch = command::current_branch(&repo)?.unwrap();
+
+            command::create_checkout_branch(&repo, "other")?;
+            let other_path = repo.path.join("other.txt");
+            util::fs::write_to_path(&other_path, "other")?;
+            command::add(&repo, &other_path)?;
+            command::commit(&repo, "Adding other")?;
+
+            command::checkout(&repo, &og_branch.name)?;
+            command::create_checkout_branch(&repo, "feature")?;
+            let feature_path = repo.path.join("feature.txt");
+            util::fs::write_to_path(&feature_path, "feature")?;
+            command::add(&repo, &feature_path)?;
+            command::commit(&repo, "Adding feature")?;
+            command::merge(&repo, "other")?.unwrap();
+            let head = command::head_commit(&repo)?;
+            assert_eq!(head.parent_ids.len(), 2);
+
+            command::checkout(&repo, &og_branch.name)?;
+            let main_path = repo.path.join("main.txt");
+            util::fs::write_to_path(&main_path, "main")?;
+            command::add(&repo, &main_path)?;
+            command::commit(&repo, "Adding main")?;
+
+            // Replaying only the first parents would drop the commit from the other branch
+            command::checkout(&repo, "feature")?;
+            assert!(rebaser::rebase(&repo, &og_branch.name).is_err());
+            assert!(!rebaser::is_in_progress(&repo));
+            assert_eq!(command::head_commit(&repo)?.id, head.id);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_rebase_conflict_continue_and_abort() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let og_branch = command::current_branch(&repo)?.unwrap();
+            let readme_path = repo.path.join("README.md");
+
+            command::create_checkout_branch(&repo, "feature")?;
+            util::fs::write_to_path(&readme_path, "feature readme")?;
+            command::add(&repo, &readme_path)?;
+            let feature_commit = command::commit(&repo, "Feature readme")?.unwrap();
+
+            command::checkout(&repo, &og_branch.name)?;
+            util::fs::write_to_path(&readme_path, "main readme")?;
+            command::add(&repo, &readme_path)?;
+            let main_commit = command::commit(&repo, "Main readme")?.unwrap();
+
+            command::checkout(&repo, "feature")?;
+            assert!(rebaser::rebase(&repo, &og_branch.name)?.head().is_none());
+            assert!(rebaser::is_in_progress(&repo));
+            assert_eq!(command::status(&repo)?.merge_conflicts.len(), 1);
+
+            // Abort goes back to the original commit
+            let head = rebaser::abort(&repo)?;
+            assert_eq!(head.id, feature_commit.id);
+            assert_eq!(command::head_commit(&repo)?.id, feature_commit.id);
+            assert_eq!(util::fs::read_from_path(&readme_path)?, "feature readme");
+            assert!(command::status(&repo)?.merge_conflicts.is_empty());
+            assert!(!rebaser::is_in_progress(&repo));
+
+            // Resolve and continue
+            assert!(rebaser::rebase(&repo, &og_branch.name)?.head().is_none());
+            util::fs::write_to_path(&readme_path, "main and feature readme")?;
+            command::add(&repo, &readme_path)?;
+            let commit = rebaser::continue_rebase(&repo)?.head().cloned().unwrap();
+            assert_eq!(commit.message, feature_commit.message);
+            assert_eq!(commit.parent_ids, vec![main_commit.id]);
+            assert_eq!(
+                util::fs::read_from_path(&readme_path)?,
+                "main and feature readme"
+            );
+            assert!(!rebaser::is_in_progress(&repo));
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,123 @@
+//! Records where HEAD pointed before and after a rebase rewrote it, in the REFLOG file, so the
+//! commits that were replaced stay reachable and can be checked out or branched from again.
+//! Entries expire after `DEFAULT_REFLOG_EXPIRY_SECS`, then gc is free to collect their commits.
+
+use crate::constants::REFLOG_FILE;
+use crate::error::OxenError;
+use crate::model::{LocalRepository, ReflogEntry};
+use crate::util;
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How long a reflog entry keeps the commits it mentions from being garbage collected
+pub const DEFAULT_REFLOG_EXPIRY_SECS: u64 = 60 * 60 * 24 * 90;
+
+fn reflog_path(repo: &LocalRepository) -> PathBuf {
+    util::fs::oxen_hidden_dir(&repo.path).join(REFLOG_FILE)
+}
+
+/// Appends a move of HEAD from `old_commit_id` to `new_commit_id`
+pub fn append(
+    repo: &LocalRepository,
+    old_commit_id: &str,
+    new_commit_id: &str,
+    message: &str,
+) -> Result<ReflogEntry, OxenError> {
+    let entry = ReflogEntry {
+        old_commit_id: String::from(old_commit_id),
+        new_commit_id: String::from(new_commit_id),
+        message: String::from(message),
+        timestamp: OffsetDateTime::now_utc(),
+    };
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(reflog_path(repo))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(entry)
+}
+
+/// The reflog entries, newest first
+pub fn list(repo: &LocalRepository) -> Result<Vec<ReflogEntry>, OxenError> {
+    let path = reflog_path(repo);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents = util::fs::read_from_path(&path)?;
+    let mut entries: Vec<ReflogEntry> = vec![];
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        entries.push(serde_json::from_str(line)?);
+    }
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Every commit id the entries that have not expired mention, to keep them from being garbage collected
+pub fn list_commit_ids(repo: &LocalRepository) -> Result<Vec<String>, OxenError> {
+    let cutoff = OffsetDateTime::now_utc() - Duration::from_secs(DEFAULT_REFLOG_EXPIRY_SECS);
+    Ok(list(repo)?
+        .into_iter()
+        .filter(|entry| entry.timestamp >= cutoff)
+        .flat_map(|entry| [entry.old_commit_id, entry.new_commit_id])
+        .collect())
+}
+
+/// Removes the entries older than `older_than`, returns how many were removed
+pub fn expire(repo: &LocalRepository, older_than: Duration) -> Result<usize, OxenError> {
+    let cutoff = OffsetDateTime::now_utc() - older_than;
+    let entries = list(repo)?;
+    let num_entries = entries.len();
+    let mut kept: Vec<String> = vec![];
+    // Write them back oldest first, the order they were appended in
+    for entry in entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.timestamp >= cutoff)
+    {
+        kept.push(serde_json::to_string(entry)?);
+    }
+    let num_expired = num_entries - kept.len();
+    if num_expired == 0 {
+        return Ok(0);
+    }
+
+    let path = reflog_path(repo);
+    if kept.is_empty() {
+        std::fs::remove_file(path)?;
+    } else {
+        util::fs::write_to_path(&path, &format!("{}\n", kept.join("\n")))?;
+    }
+    Ok(num_expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::index::reflog;
+    use crate::test;
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_reflog_expire() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            reflog::append(&repo, "a", "b", "rebase (start): checkout main")?;
+            reflog::append(&repo, "b", "c", "rebase (finish): feature onto main")?;
+            assert_eq!(reflog::list_commit_ids(&repo)?.len(), 4);
+
+            // Nothing is older than a day
+            assert_eq!(reflog::expire(&repo, Duration::from_secs(60 * 60 * 24))?, 0);
+            assert_eq!(reflog::list(&repo)?.len(), 2);
+
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(reflog::expire(&repo, Duration::from_millis(1))?, 2);
+            assert!(reflog::list(&repo)?.is_empty());
+            assert!(reflog::list_commit_ids(&repo)?.is_empty());
+
+            Ok(())
+        })
+    }
+}
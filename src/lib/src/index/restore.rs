@@ -6,7 +6,7 @@ use crate::index::Stager;
 use crate::index::{CommitDirEntryWriter, CommitDirReader};
 use crate::model::{Commit, CommitEntry, LocalRepository};
 use crate::opts::RestoreOpts;
-use crate::util::{self, resource};
+use crate::util::resource;
 
 use super::CommitDirEntryReader;
 
@@ -94,8 +94,7 @@ fn restore_regular(
     path: &Path,
     entry: &CommitEntry,
 ) -> Result<(), OxenError> {
-    let version_path = util::fs::version_path(repo, entry);
+    let version_store = repo.version_store()?;
     let working_path = repo.path.join(path);
-    std::fs::copy(version_path, working_path)?;
-    Ok(())
+    version_store.copy_version_to_path(&entry.hash, &entry.filename(), &working_path)
 }
@@ -4,8 +4,10 @@ use crate::error::OxenError;
 use crate::model::{schema::Field, Commit, Schema};
 use crate::util;
 
-use rocksdb::{DBWithThreadMode, MultiThreaded};
-use std::path::PathBuf;
+use polars::prelude::{DataFrame, DataType};
+use rocksdb::{DBWithThreadMode, Direction, IteratorMode, MultiThreaded};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str;
 
 use crate::model::LocalRepository;
@@ -50,6 +52,19 @@ impl SchemaFieldValIndex {
         })
     }
 
+    pub fn new_read_only(
+        repository: &LocalRepository,
+        commit: &Commit,
+        schema: &Schema,
+        field: &Field,
+    ) -> Result<SchemaFieldValIndex, OxenError> {
+        let db_path = SchemaFieldValIndex::db_dir(repository, commit, schema, field);
+        let opts = db::opts::default();
+        Ok(SchemaFieldValIndex {
+            field_indices_db: DBWithThreadMode::open_for_read_only(&opts, &db_path, false)?,
+        })
+    }
+
     pub fn insert_index<S: AsRef<str>>(&self, key: S, indices: Vec<u32>) -> Result<(), OxenError> {
         db::index_db::insert_indices(&self.field_indices_db, key, indices)
     }
@@ -57,16 +72,97 @@ impl SchemaFieldValIndex {
     pub fn get_indices<S: AsRef<str>>(&self, key: S) -> Result<Option<Vec<u32>>, OxenError> {
         db::index_db::get_indices(&self.field_indices_db, key)
     }
+
+    // Many files can share a schema, so the row indices are stored per file
+    fn file_prefix(path: &Path) -> String {
+        format!("{}\t", path.to_str().unwrap())
+    }
+
+    fn file_key<S: AsRef<str>>(path: &Path, value: S) -> String {
+        format!(
+            "{}{}",
+            SchemaFieldValIndex::file_prefix(path),
+            value.as_ref()
+        )
+    }
+
+    pub fn insert_file_indices<S: AsRef<str>>(
+        &self,
+        path: &Path,
+        value: S,
+        indices: Vec<u32>,
+    ) -> Result<(), OxenError> {
+        self.insert_index(SchemaFieldValIndex::file_key(path, value), indices)
+    }
+
+    /// Row indices within the file where the field is equal to the value
+    pub fn get_file_indices<S: AsRef<str>>(
+        &self,
+        path: &Path,
+        value: S,
+    ) -> Result<Option<Vec<u32>>, OxenError> {
+        self.get_indices(SchemaFieldValIndex::file_key(path, value))
+    }
+
+    /// Removes the row indices of every value within the file
+    pub fn delete_file_indices(&self, path: &Path) -> Result<(), OxenError> {
+        let prefix = SchemaFieldValIndex::file_prefix(path);
+        let iter = self
+            .field_indices_db
+            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+        for (key, _) in iter {
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            self.field_indices_db.delete(key)?;
+        }
+        Ok(())
+    }
+
+    /// Groups the rows of the data frame by the value of the field, and stores the row indices
+    /// of each value for the file. Returns the number of unique values.
+    pub fn index_file_df(
+        &self,
+        path: &Path,
+        df: &DataFrame,
+        field: &Field,
+    ) -> Result<usize, OxenError> {
+        let column = df
+            .column(&field.name)
+            .map_err(|_| OxenError::schema_does_not_have_field(&field.name))?;
+        let column = column.cast(&DataType::Utf8).map_err(|err| {
+            OxenError::basic_str(format!("Could not index field {:?}: {err}", field.name))
+        })?;
+
+        let mut postings: HashMap<String, Vec<u32>> = HashMap::new();
+        for (i, value) in column.utf8().unwrap().into_iter().enumerate() {
+            if let Some(value) = value {
+                postings
+                    .entry(String::from(value))
+                    .or_default()
+                    .push(i as u32);
+            }
+        }
+
+        let num_values = postings.len();
+        for (value, indices) in postings {
+            self.insert_file_indices(path, value, indices)?;
+        }
+        Ok(num_values)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::command;
+    use crate::df::{tabular, DFOpts};
     use crate::error::OxenError;
     use crate::index::SchemaFieldValIndex;
     use crate::model::schema;
     use crate::test;
 
+    use std::path::Path;
+
     #[test]
     fn test_list_empty_indices() -> Result<(), OxenError> {
         test::run_training_data_repo_test_fully_committed(|repo| {
@@ -155,4 +251,41 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_index_file_df() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let history = command::log(&repo)?;
+            let last_commit = history.first().unwrap();
+            let schemas = command::schema_list(&repo, Some(&last_commit.id))?;
+            let schema = schemas
+                .iter()
+                .find(|s| s.name.as_ref().unwrap() == "bounding_box")
+                .unwrap();
+
+            let field = schema::Field {
+                name: String::from("label"),
+                dtype: String::from("str"),
+            };
+
+            let index = SchemaFieldValIndex::new(&repo, last_commit, schema, &field)?;
+            let path = Path::new("annotations/train/bounding_box.csv");
+            let df = tabular::read_df(repo.path.join(path), DFOpts::empty())?;
+            let num_values = index.index_file_df(path, &df, &field)?;
+            assert_eq!(num_values, 2);
+
+            assert_eq!(
+                index.get_file_indices(path, "dog")?.unwrap(),
+                vec![0, 1, 2, 3]
+            );
+            assert_eq!(index.get_file_indices(path, "cat")?.unwrap(), vec![4, 5]);
+            let other_path = Path::new("annotations/train/one_shot.csv");
+            assert!(index.get_file_indices(other_path, "dog")?.is_none());
+
+            index.delete_file_indices(path)?;
+            assert!(index.get_file_indices(path, "dog")?.is_none());
+
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,254 @@
+//! Builds and queries the value indices of schema fields. Once a field is indexed with
+//! `create_field_index`, every file with the schema gets a map from each value of the field to the
+//! rows it is in, and the indices are kept up to date as new versions of the files are committed.
+
+use crate::constants::INDICES_DIR;
+use crate::df::filter::{DFFilterExp, DFFilterOp, DFLogicalOp};
+use crate::df::{tabular, DFOpts};
+use crate::error::OxenError;
+use crate::index::{
+    CommitDirReader, CommitEntryWriter, SchemaFieldValIndex, SchemaIndexReader, SchemaIndexWriter,
+    SchemaReader,
+};
+use crate::model::schema::{DataType, Field};
+use crate::model::{Commit, LocalRepository, Schema, StagedData, StagedEntryStatus};
+use crate::util;
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Indexes the field for every file with the schema in the commit. Returns the number of files indexed.
+pub fn create_field_index(
+    repo: &LocalRepository,
+    commit: &Commit,
+    schema: &Schema,
+    field: &Field,
+) -> Result<usize, OxenError> {
+    {
+        let writer = SchemaIndexWriter::new(repo, commit, schema)?;
+        writer.create_field_index(field)?;
+    }
+
+    // Start from scratch in case the field was indexed before
+    let db_dir = SchemaFieldValIndex::db_dir(repo, commit, schema, field);
+    if db_dir.exists() {
+        std::fs::remove_dir_all(&db_dir)?;
+    }
+
+    let schema_reader = SchemaReader::new(repo, &commit.id)?;
+    let entry_reader = CommitDirReader::new(repo, commit)?;
+    let index = SchemaFieldValIndex::new(repo, commit, schema, field)?;
+    let paths = schema_reader.list_files_for_schema_hash(&schema.hash)?;
+    let mut num_indexed = 0;
+    for path in paths.iter() {
+        if let Some(entry) = entry_reader.get_entry(path)? {
+            let version = util::fs::local_version(repo, &entry)?;
+            let df = tabular::read_df(version.path(), DFOpts::empty())?;
+            let num_values = index.index_file_df(path, &df, field)?;
+            log::debug!(
+                "Indexed {} values of {:?} in {:?}",
+                num_values,
+                field.name,
+                path
+            );
+            num_indexed += 1;
+        }
+    }
+    Ok(num_indexed)
+}
+
+/// Updates the indexed fields for the files that were added, modified or removed in the commit
+pub fn update_indices(
+    repo: &LocalRepository,
+    commit: &Commit,
+    staged_data: &StagedData,
+) -> Result<(), OxenError> {
+    // Nothing has been indexed, which is the case for most repositories
+    let indices_dir = CommitEntryWriter::commit_dir(&repo.path, &commit.id).join(INDICES_DIR);
+    if !indices_dir.exists() || staged_data.added_files.is_empty() {
+        return Ok(());
+    }
+
+    let schema_reader = SchemaReader::new(repo, &commit.id)?;
+    let entry_reader = CommitDirReader::new(repo, commit)?;
+    let mut indices: HashMap<String, Vec<(Field, SchemaFieldValIndex)>> = HashMap::new();
+    for schema in schema_reader.list_schemas()? {
+        if !SchemaIndexReader::field_indices_db_dir(repo, commit, &schema).exists() {
+            continue;
+        }
+        let index_reader = SchemaIndexReader::new(repo, commit, &schema)?;
+        for field in index_reader.list_field_indices()? {
+            let index = SchemaFieldValIndex::new(repo, commit, &schema, &field)?;
+            indices
+                .entry(schema.hash.clone())
+                .or_default()
+                .push((field, index));
+        }
+    }
+    if indices.is_empty() {
+        return Ok(());
+    }
+
+    for (path, entry) in staged_data.added_files.iter() {
+        // The rows of the previous version no longer apply, the schema may have changed too
+        for (_, index) in indices.values().flatten() {
+            index.delete_file_indices(path)?;
+        }
+        if entry.status == StagedEntryStatus::Removed {
+            continue;
+        }
+
+        if let Some(schema_hash) = schema_reader.get_schema_hash_for_file(path)? {
+            if let Some(field_indices) = indices.get(&schema_hash) {
+                // Read the committed version, the working file may have changed since it was staged
+                let entry = entry_reader
+                    .get_entry(path)?
+                    .ok_or_else(|| OxenError::file_does_not_exist(path))?;
+                let version = util::fs::local_version(repo, &entry)?;
+                let df = tabular::read_df(version.path(), DFOpts::empty())?;
+                for (field, index) in field_indices.iter() {
+                    index.index_file_df(path, &df, field)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Looks up the rows of the file that match the filter from the indices, if every part of the
+/// filter is an `==` on an indexed field. Returns None if the file would have to be scanned.
+pub fn filter_indices(
+    repo: &LocalRepository,
+    commit: &Commit,
+    path: &Path,
+    filter: &DFFilterExp,
+) -> Result<Option<Vec<u32>>, OxenError> {
+    let schema_reader = SchemaReader::new(repo, &commit.id)?;
+    let schema = match schema_reader.get_schema_for_file(path)? {
+        Some(schema) => schema,
+        None => return Ok(None),
+    };
+    if !SchemaIndexReader::field_indices_db_dir(repo, commit, &schema).exists() {
+        return Ok(None);
+    }
+
+    let index_reader = SchemaIndexReader::new(repo, commit, &schema)?;
+    let mut rows: Vec<BTreeSet<u32>> = vec![];
+    for val in filter.vals.iter() {
+        if val.op != DFFilterOp::EQ {
+            return Ok(None);
+        }
+        let field = match index_reader.get_field_index_by_name(&val.field)? {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+        // Floats are not compared by their string value
+        if matches!(
+            DataType::from_string(&field.dtype),
+            DataType::Float32 | DataType::Float64
+        ) {
+            return Ok(None);
+        }
+
+        let index = SchemaFieldValIndex::new_read_only(repo, commit, &schema, &field)?;
+        let indices = index
+            .get_file_indices(path, &val.value)?
+            .unwrap_or_default();
+        rows.push(indices.into_iter().collect());
+    }
+
+    // Combine left to right the same way the filter is applied when scanning
+    let mut rows = rows.into_iter();
+    let mut result = rows.next().unwrap_or_default();
+    for (op, next) in filter.logical_ops.iter().zip(rows) {
+        result = match op {
+            DFLogicalOp::AND => result.intersection(&next).cloned().collect(),
+            DFLogicalOp::OR => result.union(&next).cloned().collect(),
+        };
+    }
+    Ok(Some(result.into_iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::df::filter;
+    use crate::error::OxenError;
+    use crate::index::schema_indexer;
+    use crate::index::SchemaIndexReader;
+    use crate::model::schema;
+    use crate::test;
+    use crate::util;
+
+    use std::path::Path;
+
+    fn label_field() -> schema::Field {
+        schema::Field {
+            name: String::from("label"),
+            dtype: String::from("str"),
+        }
+    }
+
+    #[test]
+    fn test_schema_indexer_create_field_index() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let schema = command::schema_get(&repo, Some(&commit.id), "bounding_box")?.unwrap();
+
+            let num_files =
+                schema_indexer::create_field_index(&repo, &commit, &schema, &label_field())?;
+            assert_eq!(num_files, 4);
+
+            let reader = SchemaIndexReader::new(&repo, &commit, &schema)?;
+            assert!(reader.field_index_exists("label"));
+
+            let path = Path::new("annotations/train/bounding_box.csv");
+            let filter = filter::parse(Some(String::from("label == cat")))?.unwrap();
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter)?;
+            assert_eq!(rows, Some(vec![4, 5]));
+
+            let filter = filter::parse(Some(String::from("label == cat || label == dog")))?;
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter.unwrap())?;
+            assert_eq!(rows, Some(vec![0, 1, 2, 3, 4, 5]));
+
+            let filter = filter::parse(Some(String::from("label == unknown")))?.unwrap();
+            let path = Path::new("annotations/test/annotations.csv");
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter)?;
+            assert_eq!(rows, Some(vec![2]));
+
+            // Fields that are not indexed have to be scanned
+            let filter = filter::parse(Some(String::from("width == 385")))?.unwrap();
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter)?;
+            assert_eq!(rows, None);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_schema_indexer_updates_index_on_commit() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let schema = command::schema_get(&repo, Some(&commit.id), "bounding_box")?.unwrap();
+            schema_indexer::create_field_index(&repo, &commit, &schema, &label_field())?;
+
+            let path = Path::new("annotations/train/two_shot.csv");
+            util::fs::write_to_path(
+                &repo.path.join(path),
+                "file,label,min_x,min_y,width,height\ntrain/cat_1.jpg,cat,57.0,35.5,304,427\ntrain/cat_2.jpg,cat,30.5,44.0,333,396\n",
+            )?;
+            command::add(&repo, repo.path.join(path))?;
+            let commit = command::commit(&repo, "Only cats")?.unwrap();
+
+            let filter = filter::parse(Some(String::from("label == cat")))?.unwrap();
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter)?;
+            assert_eq!(rows, Some(vec![0, 1]));
+
+            let filter = filter::parse(Some(String::from("label == dog")))?.unwrap();
+            let rows = schema_indexer::filter_indices(&repo, &commit, path, &filter)?;
+            assert_eq!(rows, Some(vec![]));
+
+            Ok(())
+        })
+    }
+}
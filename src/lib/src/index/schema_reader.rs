@@ -95,6 +95,19 @@ impl SchemaReader {
     pub fn list_schemas(&self) -> Result<Vec<Schema>, OxenError> {
         str_json_db::list_vals(&self.schema_db)
     }
+
+    /// List the paths of all the files that have the schema
+    pub fn list_files_for_schema_hash<S: AsRef<str>>(
+        &self,
+        hash: S,
+    ) -> Result<Vec<PathBuf>, OxenError> {
+        let files: Vec<(String, String)> = str_val_db::list(&self.schema_files_db)?;
+        Ok(files
+            .into_iter()
+            .filter(|(_, file_hash)| file_hash == hash.as_ref())
+            .map(|(path, _)| PathBuf::from(path))
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,271 @@
+//! Searches the files of a commit for a regex without checking the commit out. Each version file
+//! is read straight from the version store, text files line by line and tabular files cell by cell.
+
+use crate::df::tabular;
+use crate::error::OxenError;
+use crate::index::CommitDirReader;
+use crate::model::{Commit, CommitEntry, LocalRepository, SearchMatch};
+use crate::util;
+
+use ignore::overrides::{Override, OverrideBuilder};
+use polars::prelude::DataType;
+use regex::Regex;
+use std::io::{BufRead, BufReader};
+
+/// The lines and cells that match `pattern` in the files of the commit, ordered by path. Only
+/// the files that match the gitignore style `path_glob` are searched if there is one, and the
+/// search stops once it found `max_matches` if it is set.
+pub fn search(
+    repo: &LocalRepository,
+    commit: &Commit,
+    pattern: &str,
+    path_glob: Option<&str>,
+    max_matches: Option<usize>,
+) -> Result<Vec<SearchMatch>, OxenError> {
+    let (regex, glob) = parse_query(repo, pattern, path_glob)?;
+    let max_matches = max_matches.unwrap_or(usize::MAX);
+
+    let reader = CommitDirReader::new(repo, commit)?;
+    let mut entries = reader.list_entries()?;
+    entries.retain(|entry| match &glob {
+        Some(glob) => glob.matched(&entry.path, false).is_whitelist(),
+        None => true,
+    });
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut matches: Vec<SearchMatch> = vec![];
+    for entry in entries.iter() {
+        if matches.len() >= max_matches {
+            break;
+        }
+        if util::fs::is_tabular(&entry.path) {
+            // One bad file should not fail the whole search
+            if let Err(err) = search_tabular(repo, entry, &regex, max_matches, &mut matches) {
+                log::warn!(
+                    "Skipped searching {:?}, could not read it: {}",
+                    entry.path,
+                    err
+                );
+            }
+        } else if !is_binary(entry) {
+            if let Err(err) = search_text(repo, entry, &regex, max_matches, &mut matches) {
+                log::warn!(
+                    "Skipped searching {:?}, could not read it: {}",
+                    entry.path,
+                    err
+                );
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Makes sure the pattern and glob are valid before searching, so a bad query can be told apart
+/// from a search that failed
+pub fn check_query(
+    repo: &LocalRepository,
+    pattern: &str,
+    path_glob: Option<&str>,
+) -> Result<(), OxenError> {
+    parse_query(repo, pattern, path_glob)?;
+    Ok(())
+}
+
+fn parse_query(
+    repo: &LocalRepository,
+    pattern: &str,
+    path_glob: Option<&str>,
+) -> Result<(Regex, Option<Override>), OxenError> {
+    let regex = Regex::new(pattern)
+        .map_err(|err| OxenError::basic_str(format!("Invalid pattern {pattern:?}: {err}")))?;
+    let glob = match path_glob {
+        Some(path_glob) => Some(build_glob(repo, path_glob)?),
+        None => None,
+    };
+    Ok((regex, glob))
+}
+
+fn build_glob(repo: &LocalRepository, path_glob: &str) -> Result<Override, OxenError> {
+    let mut builder = OverrideBuilder::new(&repo.path);
+    builder
+        .add(path_glob)
+        .and_then(|builder| builder.build())
+        .map_err(|err| OxenError::basic_str(format!("Invalid glob {path_glob:?}: {err}")))
+}
+
+fn is_binary(entry: &CommitEntry) -> bool {
+    util::fs::is_image(&entry.path)
+        || util::fs::is_video(&entry.path)
+        || util::fs::is_audio(&entry.path)
+}
+
+fn search_text(
+    repo: &LocalRepository,
+    entry: &CommitEntry,
+    regex: &Regex,
+    max_matches: usize,
+    matches: &mut Vec<SearchMatch>,
+) -> Result<(), OxenError> {
+    let file = util::fs::open_version(repo, entry)?;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        if matches.len() >= max_matches {
+            break;
+        }
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                // Not a text file after all
+                log::debug!("Stopped searching {:?}: {}", entry.path, err);
+                break;
+            }
+        };
+        if regex.is_match(&line) {
+            matches.push(SearchMatch {
+                path: entry.path.to_owned(),
+                line: Some(i + 1),
+                column: None,
+                row: None,
+                text: line,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn search_tabular(
+    repo: &LocalRepository,
+    entry: &CommitEntry,
+    regex: &Regex,
+    max_matches: usize,
+    matches: &mut Vec<SearchMatch>,
+) -> Result<(), OxenError> {
+    let version = util::fs::local_version(repo, entry)?;
+    tabular::read_df_batches(version.path(), |offset, df| {
+        // Row by row, so the matches read in the order of the file
+        let mut cells: Vec<(usize, usize, String)> = vec![];
+        for (col_idx, series) in df.get_columns().iter().enumerate() {
+            let values = series
+                .cast(&DataType::Utf8)
+                .map_err(|err| OxenError::basic_str(format!("{err:?}")))?;
+            let values = values
+                .utf8()
+                .map_err(|err| OxenError::basic_str(format!("{err:?}")))?;
+            for (row, value) in values.into_iter().enumerate() {
+                if let Some(value) = value {
+                    if regex.is_match(value) {
+                        cells.push((offset + row, col_idx, String::from(value)));
+                    }
+                }
+            }
+        }
+        cells.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        let names = df.get_column_names();
+        for (row, col_idx, text) in cells {
+            if matches.len() >= max_matches {
+                return Ok(false);
+            }
+            matches.push(SearchMatch {
+                path: entry.path.to_owned(),
+                line: None,
+                column: Some(String::from(names[col_idx])),
+                row: Some(row),
+                text,
+            });
+        }
+        Ok(matches.len() < max_matches)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api;
+    use crate::command;
+    use crate::error::OxenError;
+    use crate::index::searcher;
+    use crate::test;
+    use crate::util;
+
+    use std::path::Path;
+
+    #[test]
+    fn test_search_text_and_tabular_files() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let commit = command::head_commit(&repo)?;
+            let matches =
+                searcher::search(&repo, &commit, "cat_2", Some("annotations/train/*"), None)?;
+            assert_eq!(matches.len(), 2);
+
+            let text_match = &matches[0];
+            assert_eq!(
+                text_match.path,
+                Path::new("annotations/train/annotations.txt")
+            );
+            assert_eq!(text_match.line, Some(6));
+            assert_eq!(text_match.text, "train/cat_2.jpg 1");
+
+            let cell_match = &matches[1];
+            assert_eq!(
+                cell_match.path,
+                Path::new("annotations/train/bounding_box.csv")
+            );
+            assert_eq!(cell_match.column, Some(String::from("file")));
+            assert_eq!(cell_match.row, Some(5));
+            assert_eq!(cell_match.text, "train/cat_2.jpg");
+
+            // Cells match the whole pattern on their own
+            let matches =
+                searcher::search(&repo, &commit, "^cat$", Some("annotations/**/*.csv"), None)?;
+            assert_eq!(matches.len(), 4);
+            assert!(matches
+                .iter()
+                .all(|m| m.column == Some(String::from("label"))));
+
+            // Stops once it has enough matches
+            let matches = searcher::search(&repo, &commit, "cat", None, Some(3))?;
+            assert_eq!(matches.len(), 3);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_search_skips_unreadable_tabular_files() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            // Not valid utf8, so the csv reader fails on it
+            std::fs::write(repo.path.join("bad.csv"), b"label,file\n\xff\xfe,cat.jpg\n")?;
+            let good_path = repo.path.join("good.txt");
+            util::fs::write_to_path(&good_path, "the cat")?;
+            command::add(&repo, &repo.path)?;
+            let commit = command::commit(&repo, "Adding files")?.unwrap();
+
+            let matches = searcher::search(&repo, &commit, "cat", None, None)?;
+            assert!(matches
+                .iter()
+                .any(|m| m.path == Path::new("good.txt") && m.text == "the cat"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_search_skips_missing_text_versions() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            util::fs::write_to_path(&repo.path.join("a.txt"), "the cat")?;
+            util::fs::write_to_path(&repo.path.join("b.txt"), "another cat")?;
+            command::add(&repo, &repo.path)?;
+            let commit = command::commit(&repo, "Adding files")?.unwrap();
+
+            let entry =
+                api::local::entries::get_entry_for_commit(&repo, &commit, Path::new("a.txt"))?
+                    .unwrap();
+            std::fs::remove_file(util::fs::version_path(&repo, &entry))?;
+
+            let matches = searcher::search(&repo, &commit, "cat", None, None)?;
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].path, Path::new("b.txt"));
+
+            Ok(())
+        })
+    }
+}
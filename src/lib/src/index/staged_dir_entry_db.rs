@@ -81,6 +81,14 @@ impl StagedDirEntryDB {
         path_db::put(&self.db, path, staged_entry)
     }
 
+    /// # Serializes all the entries to json and writes them to db in one batch
+    pub fn add_staged_entries_to_db<P: AsRef<Path>>(
+        &self,
+        entries: &[(P, StagedEntry)],
+    ) -> Result<(), OxenError> {
+        path_db::put_batch(&self.db, entries)
+    }
+
     /// # List the file paths in the staged dir
     /// More efficient than list_added_path_entries since it does not deserialize the entries
     pub fn list_added_paths(&self) -> Result<Vec<PathBuf>, OxenError> {
@@ -5,8 +5,8 @@ use crate::df::tabular;
 use crate::df::DFOpts;
 use crate::error::OxenError;
 use crate::index::{
-    CommitDirEntryReader, CommitDirReader, CommitReader, MergeConflictReader, Merger,
-    StagedDirEntryDB,
+    CommitDirEntryReader, CommitDirReader, CommitReader, MergeConflictReader, Merger, OxenIgnore,
+    StagedDirEntryDB, StatCache,
 };
 
 use crate::model::schema;
@@ -26,6 +26,7 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str;
+use std::sync::Arc;
 
 pub const STAGED_DIR: &str = "staged";
 
@@ -42,6 +43,7 @@ pub struct Stager {
     schemas_db: DBWithThreadMode<MultiThreaded>,
     pub repository: LocalRepository,
     merger: Option<Merger>,
+    ignore: Arc<OxenIgnore>,
 }
 
 impl Stager {
@@ -79,6 +81,7 @@ impl Stager {
             schemas_db: DBWithThreadMode::open(&opts, schemas_db_path)?,
             repository: repository.clone(),
             merger: None,
+            ignore: Arc::new(OxenIgnore::new(repository)),
         })
     }
 
@@ -92,10 +95,31 @@ impl Stager {
             schemas_db: DBWithThreadMode::open(&opts, schemas_db_path)?,
             repository: repository.clone(),
             merger: Some(Merger::new(&repository.clone())?),
+            ignore: Arc::new(OxenIgnore::new(repository)),
         })
     }
 
     pub fn add(&self, path: &Path, commit_reader: &CommitDirReader) -> Result<(), OxenError> {
+        // Only warn about the path that was asked for, not the ignored paths within a dir
+        if path != Path::new(".") && path.exists() {
+            let relative_path = util::fs::path_relative_to_dir(path, &self.repository.path)?;
+            if self.is_ignored_untracked(&relative_path, path.is_dir(), commit_reader) {
+                println!("Warning: not adding {relative_path:?}, it is ignored by an .oxenignore or the user config");
+                return Ok(());
+            }
+        }
+
+        // Open the stat cache once for every file that is added
+        let stat_cache = StatCache::new(&self.repository)?;
+        self.add_path(path, commit_reader, &stat_cache)
+    }
+
+    fn add_path(
+        &self,
+        path: &Path,
+        commit_reader: &CommitDirReader,
+        stat_cache: &StatCache,
+    ) -> Result<(), OxenError> {
         if path
             .to_str()
             .unwrap()
@@ -112,12 +136,20 @@ impl Stager {
             for entry in (std::fs::read_dir(path)?).flatten() {
                 let path = entry.path();
                 let entry_path = self.repository.path.join(path);
-                self.add(&entry_path, commit_reader)?;
+                self.add_path(&entry_path, commit_reader, stat_cache)?;
             }
             log::debug!("ADD CURRENT DIR: {:?}", path);
             return Ok(());
         }
 
+        if path.exists() {
+            let relative_path = util::fs::path_relative_to_dir(path, &self.repository.path)?;
+            if self.is_ignored_untracked(&relative_path, path.is_dir(), commit_reader) {
+                log::debug!("Stager.add() skipping ignored path {:?}", relative_path);
+                return Ok(());
+            }
+        }
+
         // If it doesn't exist on disk, it might have been removed, and we can't tell if it is a file or dir
         // so we have to check if it is committed, and what the backup version is
         if !path.exists() {
@@ -129,6 +161,11 @@ impl Stager {
             // Since entries that are committed are only files.. we will have to have different logic for dirs
             if let Ok(Some(value)) = commit_reader.get_entry(&relative_path) {
                 self.add_removed_file(&relative_path, &value)?;
+                self.resolve_removed_conflict(&relative_path)?;
+                return Ok(());
+            }
+            // Keeping a file removed in HEAD that the other side changed resolves the conflict
+            if self.resolve_removed_conflict(&relative_path)? {
                 return Ok(());
             }
 
@@ -153,32 +190,63 @@ impl Stager {
 
         log::debug!("Stager.add() is_dir? {} path: {:?}", path.is_dir(), path);
         if path.is_dir() {
-            match self.add_dir(path, commit_reader) {
+            match self.add_dir_with_stat_cache(path, commit_reader, stat_cache) {
                 Ok(_) => Ok(()),
                 Err(err) => Err(err),
             }
         } else {
-            match self.add_file(path, commit_reader) {
+            match self.add_file_with_stat_cache(path, commit_reader, stat_cache) {
                 Ok(_) => Ok(()),
                 Err(err) => Err(err),
             }
         }
     }
 
+    /// Forgets the merge conflict of a file that was resolved by removing it, returns true if it had one
+    fn resolve_removed_conflict(&self, path: &Path) -> Result<bool, OxenError> {
+        if let Some(merger) = &self.merger {
+            if merger.has_file(path)? {
+                merger.remove_conflict_path(path)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Paths that match an .oxenignore are skipped, unless they are already tracked
+    fn is_ignored_untracked(
+        &self,
+        relative: &Path,
+        is_dir: bool,
+        commit_reader: &CommitDirReader,
+    ) -> bool {
+        if relative.as_os_str().is_empty() || !self.ignore.is_ignored(relative, is_dir) {
+            return false;
+        }
+        if is_dir {
+            !commit_reader.has_dir(relative)
+        } else {
+            !commit_reader.has_file(relative)
+        }
+    }
+
     pub fn status(&self, entry_reader: &CommitDirReader) -> Result<StagedData, OxenError> {
         log::debug!("-----STATUS START-----");
-        let result = self.compute_staged_data(&self.repository.path, entry_reader);
+        let result = self.compute_staged_data(&self.repository.path, entry_reader, None);
         log::debug!("-----STATUS END-----");
         result
     }
 
+    /// Status starting from `dir`, if `path_filter` is set only the files and dirs within
+    /// that path relative to the repository are checked, so we do not walk the whole tree
     pub fn status_from_dir(
         &self,
         entry_reader: &CommitDirReader,
         dir: &Path,
+        path_filter: Option<&Path>,
     ) -> Result<StagedData, OxenError> {
         log::debug!("-----STATUS START-----");
-        let result = self.compute_staged_data(dir, entry_reader);
+        let result = self.compute_staged_data(dir, entry_reader, path_filter);
         log::debug!("-----STATUS END-----");
         result
     }
@@ -192,20 +260,29 @@ impl Stager {
         &self,
         dir: &Path,
         entry_reader: &CommitDirReader,
+        path_filter: Option<&Path>,
     ) -> Result<StagedData, OxenError> {
         log::debug!("compute_staged_data listing eligable {:?}", dir);
         let mut staged_data = StagedData::empty();
+        // Status only reads the cache, anything it had to rehash is written back at the end
+        let stat_cache = StatCache::new_read_only(&self.repository)?;
 
         let mut candidate_dirs: HashSet<PathBuf> = HashSet::new();
         // Start with candidate dirs from committed and added, not all the dirs
         let added_dirs = self.list_added_dirs()?;
         log::debug!("compute_staged_data Got <added> dirs: {}", added_dirs.len());
         for dir in added_dirs {
+            if !Stager::dir_matches_filter(&dir, path_filter) {
+                continue;
+            }
             log::debug!("compute_staged_data considering added dir {:?}", dir);
-            let fullpath = self.repository.path.join(&dir);
-            let stats = self.compute_staged_dir_stats(&fullpath)?;
-            staged_data.added_dirs.add_stats(&stats);
-            log::debug!("compute_staged_data got stats {:?}", stats);
+            // Parents of the filter are still candidates, but their stats would count files outside of it
+            if Stager::path_matches_filter(&dir, path_filter) {
+                let fullpath = self.repository.path.join(&dir);
+                let stats = self.compute_staged_dir_stats(&fullpath)?;
+                staged_data.added_dirs.add_stats(&stats);
+                log::debug!("compute_staged_data got stats {:?}", stats);
+            }
 
             log::debug!("compute_staged_data adding <added> dir {:?}", dir);
             candidate_dirs.insert(self.repository.path.join(dir));
@@ -217,16 +294,32 @@ impl Stager {
             committed_dirs.len()
         );
         for dir in committed_dirs.iter() {
+            if !Stager::dir_matches_filter(dir, path_filter) {
+                continue;
+            }
             log::debug!("compute_staged_data adding <committed> dir {:?}", dir);
             candidate_dirs.insert(self.repository.path.join(dir));
         }
 
-        log::debug!("compute_staged_data Considering <current> dir: {:?}", dir);
-        candidate_dirs.insert(dir.to_path_buf());
+        let relative_dir = util::fs::path_relative_to_dir(dir, &self.repository.path)?;
+        if Stager::dir_matches_filter(&relative_dir, path_filter) {
+            log::debug!("compute_staged_data Considering <current> dir: {:?}", dir);
+            candidate_dirs.insert(dir.to_path_buf());
+        }
+        if let Some(path_filter) = path_filter {
+            // The parent lists the filtered path, so it shows up even if it is not tracked yet
+            if let Some(parent) = self.repository.path.join(path_filter).parent() {
+                candidate_dirs.insert(parent.to_path_buf());
+            }
+        }
 
         for dir in candidate_dirs.iter() {
             log::debug!("compute_staged_data CANDIDATE DIR {:?}", dir);
-            self.process_dir(dir, &mut staged_data)?;
+            self.process_dir(dir, &mut staged_data, &stat_cache, path_filter)?;
+        }
+        // Another process may be holding the cache, it is fine to rehash next time
+        if let Err(err) = stat_cache.flush() {
+            log::warn!("compute_staged_data could not update stat cache: {}", err);
         }
 
         // Find merge conflicts
@@ -235,14 +328,72 @@ impl Stager {
         // Populate schemas from db
         let mut schemas: HashMap<PathBuf, schema::Schema> = HashMap::new();
         for (path, schema) in path_db::list_path_entries(&self.schemas_db, Path::new(""))? {
-            schemas.insert(path, schema);
+            if Stager::path_matches_filter(&path, path_filter) {
+                schemas.insert(path, schema);
+            }
         }
         staged_data.added_schemas = schemas;
 
+        // Pair up removed and added files with the same content
+        staged_data.moved_files = Stager::compute_moved_files(&staged_data.added_files);
+
         Ok(staged_data)
     }
 
-    fn process_dir(&self, full_dir: &Path, staged_data: &mut StagedData) -> Result<(), OxenError> {
+    /// A staged file that was removed and a staged file that was added with the same hash are a rename
+    fn compute_moved_files(added_files: &HashMap<PathBuf, StagedEntry>) -> Vec<(PathBuf, PathBuf)> {
+        let mut removed: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+        for (path, entry) in added_files
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .rev()
+        {
+            if entry.status == StagedEntryStatus::Removed {
+                removed.entry(entry.hash.as_str()).or_default().push(path);
+            }
+        }
+        if removed.is_empty() {
+            return vec![];
+        }
+
+        let mut moved: Vec<(PathBuf, PathBuf)> = vec![];
+        for (path, entry) in added_files.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+            if entry.status != StagedEntryStatus::Added {
+                continue;
+            }
+            if let Some(from) = removed
+                .get_mut(entry.hash.as_str())
+                .and_then(|paths| paths.pop())
+            {
+                moved.push((from.to_path_buf(), path.to_path_buf()));
+            }
+        }
+        moved
+    }
+
+    /// Dirs within the filter need to be processed, and so do their parents because
+    /// they list the filtered path itself
+    fn dir_matches_filter(dir: &Path, path_filter: Option<&Path>) -> bool {
+        match path_filter {
+            Some(path_filter) => dir.starts_with(path_filter) || path_filter.starts_with(dir),
+            None => true,
+        }
+    }
+
+    fn path_matches_filter(path: &Path, path_filter: Option<&Path>) -> bool {
+        match path_filter {
+            Some(path_filter) => path.starts_with(path_filter),
+            None => true,
+        }
+    }
+
+    fn process_dir(
+        &self,
+        full_dir: &Path,
+        staged_data: &mut StagedData,
+        stat_cache: &StatCache,
+        path_filter: Option<&Path>,
+    ) -> Result<(), OxenError> {
         log::debug!("process_dir {:?}", full_dir);
         // Only check at level of this dir, no need to deep dive recursively
         let committer = CommitReader::new(&self.repository)?;
@@ -280,6 +431,9 @@ impl Stager {
         );
 
         for relative in candidate_files.iter() {
+            if !Stager::path_matches_filter(relative, path_filter) {
+                continue;
+            }
             log::debug!("process_dir checking relative path {:?}", relative);
             let fullpath = self.repository.path.join(relative);
             if util::fs::is_in_oxen_hidden_dir(&fullpath) {
@@ -296,6 +450,7 @@ impl Stager {
                 if !self.has_staged_dir(relative)
                     && !staged_data.added_dirs.contains_key(relative)
                     && !root_commit_dir_reader.has_dir(relative)
+                    && !self.ignore.is_ignored(relative, true)
                 {
                     log::debug!("process_dir adding untracked dir {:?}", relative);
                     let count = util::fs::count_items_in_dir(&fullpath);
@@ -310,6 +465,7 @@ impl Stager {
                     relative,
                     &staged_dir_db,
                     &root_commit_entry_reader,
+                    stat_cache,
                 );
                 log::debug!("process_dir got status {:?} {:?}", relative, file_status);
                 if let Some(file_type) = file_status {
@@ -324,7 +480,9 @@ impl Stager {
                             }
                         }
                         FileStatus::Untracked => {
-                            staged_data.untracked_files.push(relative.to_path_buf());
+                            if !self.ignore.is_ignored(relative, false) {
+                                staged_data.untracked_files.push(relative.to_path_buf());
+                            }
                         }
                         FileStatus::Modified => {
                             staged_data.modified_files.push(relative.to_path_buf());
@@ -344,6 +502,7 @@ impl Stager {
         path: &Path,
         staged_dir_db: &StagedDirEntryDB,
         commit_dir_db: &CommitDirEntryReader,
+        stat_cache: &StatCache,
     ) -> Option<FileStatus> {
         let file_name = path.file_name().unwrap();
         log::debug!("get_file_status check path in staging? {:?}", file_name);
@@ -359,7 +518,7 @@ impl Stager {
                 if let Ok(Some(commit_entry)) = commit_dir_db.get_entry(file_name) {
                     if Stager::file_is_removed(full_dir, &commit_entry) {
                         return Some(FileStatus::Removed);
-                    } else if Stager::file_is_modified(full_dir, &commit_entry) {
+                    } else if Stager::file_is_modified(full_dir, &commit_entry, stat_cache) {
                         return Some(FileStatus::Modified);
                     }
                 } else {
@@ -382,7 +541,11 @@ impl Stager {
         !full_path.exists()
     }
 
-    fn file_is_modified(repo_path: &Path, commit_entry: &CommitEntry) -> bool {
+    fn file_is_modified(
+        repo_path: &Path,
+        commit_entry: &CommitEntry,
+        stat_cache: &StatCache,
+    ) -> bool {
         // Get last modified time
         let full_path = repo_path.join(&commit_entry.path);
         log::debug!(
@@ -413,9 +576,17 @@ impl Stager {
             );
 
             // Then check the hashes, because the data might not be different, timestamp is just an optimization
-            let hash = util::hasher::hash_file_contents(&full_path).unwrap();
-            if hash != commit_entry.hash {
-                return true;
+            // The stat cache only rehashes if the file changed since the last time we looked
+            match stat_cache.get_hash(&commit_entry.path) {
+                Ok(hash) => {
+                    if hash != commit_entry.hash {
+                        return true;
+                    }
+                }
+                Err(err) => {
+                    log::error!("file_is_modified could not hash {:?}: {}", full_path, err);
+                    return true;
+                }
             }
         }
 
@@ -452,15 +623,61 @@ impl Stager {
         }
     }
 
+    /// Stages a committed entry as moved to the path `to` relative to the repository. A rename keeps
+    /// the size, mtime and inode of the file, so if they still match what was cached when it was
+    /// hashed the hash of the entry is reused, otherwise the file is hashed again
+    pub fn add_moved_file(
+        &self,
+        entry: &CommitEntry,
+        to: &Path,
+        commit_reader: &CommitDirReader,
+        stat_cache: &StatCache,
+    ) -> Result<(), OxenError> {
+        log::debug!("add_moved_file {:?} -> {:?}", entry.path, to);
+        self.add_removed_file(&entry.path, entry)?;
+
+        let metadata = fs::metadata(self.repository.path.join(to))?;
+        let hash = match stat_cache.get_entry(&entry.path)? {
+            Some(cached) if cached.hash == entry.hash && cached.matches(&metadata) => {
+                stat_cache.move_entry(&entry.path, to)?;
+                entry.hash.to_owned()
+            }
+            _ => {
+                log::debug!("add_moved_file {:?} changed, hashing again", entry.path);
+                stat_cache.remove_entry(&entry.path)?;
+                stat_cache.get_hash(to)?
+            }
+        };
+
+        let status = match commit_reader.get_entry(to)? {
+            Some(existing) if existing.hash == hash => return Ok(()),
+            Some(_) => StagedEntryStatus::Modified,
+            None => StagedEntryStatus::Added,
+        };
+        let staged_entry = StagedEntry { hash, status };
+
+        match to.parent() {
+            Some(parent) => {
+                let staged_db = StagedDirEntryDB::new(&self.repository, parent)?;
+                self.add_staged_entry_to_db(to, &staged_entry, &staged_db)
+            }
+            None => Err(OxenError::file_has_no_parent(to)),
+        }
+    }
+
     // Returns a map of directories to files to add, and a total count
     // Reads the dirs in parallel to quickly find out what needs to be added
     fn list_unadded_files_in_dir<P: AsRef<Path>>(
         &self,
         dir: P,
-    ) -> (HashMap<PathBuf, Vec<PathBuf>>, usize) {
+        entry_reader: &CommitDirReader,
+    ) -> Result<(HashMap<PathBuf, Vec<PathBuf>>, usize), OxenError> {
         let mut files: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
         let mut total: usize = 0;
         let repository = self.repository.to_owned();
+        let ignore = self.ignore.clone();
+        let committed_dirs: HashSet<PathBuf> =
+            entry_reader.list_committed_dirs()?.into_iter().collect();
         for dir_entry_result in WalkDirGeneric::<((), Option<bool>)>::new(&dir)
             .skip_hidden(true)
             .parallelism(jwalk::Parallelism::RayonDefaultPool)
@@ -481,7 +698,18 @@ impl Stager {
                             //     "list_unadded_files_in_dir checking file type {:?}",
                             //     dir_entry
                             // );
-                            if !dir_entry.file_type.is_dir() {
+                            if dir_entry.file_type.is_dir() {
+                                // Do not walk ignored dirs, unless they have tracked files in them
+                                let path = util::fs::path_relative_to_dir(
+                                    &dir_entry.path(),
+                                    &repository.path,
+                                )
+                                .unwrap();
+                                if ignore.is_ignored(&path, true) && !committed_dirs.contains(&path)
+                                {
+                                    dir_entry.read_children_path = None;
+                                }
+                            } else {
                                 // Entry is file
                                 let path = dir_entry.path();
                                 let path = util::fs::path_relative_to_dir(&path, &repository.path)
@@ -508,6 +736,10 @@ impl Stager {
                                 &self.repository.path,
                             )
                             .unwrap();
+                            if self.ignore.is_ignored(&path, false) && !entry_reader.has_file(&path)
+                            {
+                                continue;
+                            }
                             if let Some(parent) = path.parent() {
                                 // log::debug!(
                                 //     "list_unadded_files_in_dir adding {:?} -> {:?}",
@@ -526,10 +758,20 @@ impl Stager {
                 }
             }
         }
-        (files, total)
+        Ok((files, total))
     }
 
     pub fn add_dir(&self, dir: &Path, entry_reader: &CommitDirReader) -> Result<(), OxenError> {
+        let stat_cache = StatCache::new(&self.repository)?;
+        self.add_dir_with_stat_cache(dir, entry_reader, &stat_cache)
+    }
+
+    fn add_dir_with_stat_cache(
+        &self,
+        dir: &Path,
+        entry_reader: &CommitDirReader,
+        stat_cache: &StatCache,
+    ) -> Result<(), OxenError> {
         if !dir.exists() || !dir.is_dir() {
             let err = format!("Cannot stage non-existant dir: {dir:?}");
             return Err(OxenError::basic_str(err));
@@ -541,33 +783,94 @@ impl Stager {
         path_db::put(&self.dir_db, &short_path, &0)?;
 
         // Add all untracked files and modified files
-        let (dir_paths, total) = self.list_unadded_files_in_dir(dir);
+        let (dir_paths, total) = self.list_unadded_files_in_dir(dir, entry_reader)?;
         log::debug!("Stager.add_dir {:?} -> {}", dir, total);
 
         println!("Adding files in directory: {short_path:?}");
-        let size: u64 = unsafe { std::mem::transmute(total) };
-        let bar = ProgressBar::new(size);
+        let bar = ProgressBar::new(total as u64);
         dir_paths.par_iter().for_each(|(parent, paths)| {
-            let staged_db = StagedDirEntryDB::new(&self.repository, parent).unwrap();
-            let entry_reader =
-                CommitDirEntryReader::new(&self.repository, &entry_reader.commit_id, parent)
-                    .unwrap();
-
-            paths.par_iter().for_each(|path| {
-                let full_path = self.repository.path.join(path);
-                match self.add_staged_entry_in_dir_db(&full_path, &entry_reader, &staged_db) {
-                    Ok(_) => {
-                        // all good
-                    }
+            if let Err(err) =
+                self.add_files_in_dir(parent, paths, &entry_reader.commit_id, stat_cache, &bar)
+            {
+                log::error!("Could not add files in dir: {:?}\nErr: {}", parent, err);
+            }
+        });
+
+        bar.finish();
+
+        Ok(())
+    }
+
+    /// Hashes all the files within a single dir in parallel, then writes the staged entries,
+    /// parent dirs and schemas to their dbs in batches instead of one write per file
+    fn add_files_in_dir(
+        &self,
+        parent: &Path,
+        paths: &[PathBuf],
+        commit_id: &str,
+        stat_cache: &StatCache,
+        bar: &ProgressBar,
+    ) -> Result<(), OxenError> {
+        let staged_db = StagedDirEntryDB::new(&self.repository, parent)?;
+        let entry_reader = CommitDirEntryReader::new(&self.repository, commit_id, parent)?;
+
+        let staged_entries: Vec<(PathBuf, StagedEntry)> = paths
+            .par_iter()
+            .filter_map(|path| {
+                let result = self.compute_staged_entry(path, &entry_reader, stat_cache);
+                bar.inc(1);
+                match result {
+                    Ok(Some(entry)) => Some((path.to_owned(), entry)),
+                    Ok(None) => None,
                     Err(err) => {
                         log::error!("Could not add file: {:?}\nErr: {}", path, err);
+                        None
                     }
                 }
-                bar.inc(1);
-            });
-        });
+            })
+            .collect();
 
-        bar.finish();
+        if staged_entries.is_empty() {
+            return Ok(());
+        }
+        log::debug!(
+            "add_files_in_dir {:?} staging {} entries",
+            parent,
+            staged_entries.len()
+        );
+
+        // Every file shares the same parents, add them all up to root once
+        let parents: Vec<(PathBuf, i32)> = parent
+            .ancestors()
+            .map(|dir| (dir.to_path_buf(), 0))
+            .collect();
+        path_db::put_batch(&self.dir_db, &parents)?;
+
+        let schemas: Vec<(PathBuf, schema::Schema)> = staged_entries
+            .par_iter()
+            .filter_map(|(path, _)| self.compute_schema(path).map(|s| (path.to_owned(), s)))
+            .collect();
+        if !schemas.is_empty() {
+            path_db::put_batch(&self.schemas_db, &schemas)?;
+        }
+
+        let file_entries: Vec<(PathBuf, StagedEntry)> = staged_entries
+            .iter()
+            .filter_map(|(path, entry)| {
+                path.file_name()
+                    .map(|file_name| (PathBuf::from(file_name), entry.to_owned()))
+            })
+            .collect();
+        staged_db.add_staged_entries_to_db(&file_entries)?;
+
+        // Only remove the conflicts once the resolved entries are staged
+        if let Some(merger) = &self.merger {
+            for (path, _) in staged_entries.iter() {
+                if merger.has_file(path)? {
+                    merger.remove_conflict_path(path)?;
+                }
+            }
+        }
 
         Ok(())
     }
@@ -618,9 +921,19 @@ impl Stager {
         &self,
         path: &Path,
         entry_reader: &CommitDirReader,
+    ) -> Result<PathBuf, OxenError> {
+        let stat_cache = StatCache::new(&self.repository)?;
+        self.add_file_with_stat_cache(path, entry_reader, &stat_cache)
+    }
+
+    fn add_file_with_stat_cache(
+        &self,
+        path: &Path,
+        entry_reader: &CommitDirReader,
+        stat_cache: &StatCache,
     ) -> Result<PathBuf, OxenError> {
         log::debug!("--- START OXEN ADD {:?} ---", path);
-        let relative = self.add_staged_entry(path, entry_reader)?;
+        let relative = self.add_staged_entry(path, entry_reader, stat_cache)?;
 
         // We should tracking changes to this parent dir too
         let path_parent = path.parent();
@@ -671,6 +984,7 @@ impl Stager {
         &self,
         path: &Path,
         entry_reader: &CommitDirReader,
+        stat_cache: &StatCache,
     ) -> Result<PathBuf, OxenError> {
         log::debug!("add_staged_entry {:?}", path);
         if let Some(parent) = path.parent() {
@@ -682,7 +996,7 @@ impl Stager {
                 &relative_parent,
             )?;
 
-            self.add_staged_entry_in_dir_db(path, &entry_reader, &staged_db)
+            self.add_staged_entry_in_dir_db(path, &entry_reader, &staged_db, stat_cache)
         } else {
             log::error!("add_staged_entry no parent... {:?}", path);
             Err(OxenError::file_has_no_parent(path))
@@ -694,6 +1008,7 @@ impl Stager {
         path: &Path,
         entry_reader: &CommitDirEntryReader,
         staged_db: &StagedDirEntryDB,
+        stat_cache: &StatCache,
     ) -> Result<PathBuf, OxenError> {
         // We should have normalized to path past repo at this point
         log::debug!("Add file: {:?} to {:?}", path, self.repository.path);
@@ -701,15 +1016,42 @@ impl Stager {
             return Err(OxenError::file_does_not_exist(path));
         }
 
-        // compute the hash to know if it has changed
-        let hash = util::hasher::hash_file_contents(path)?;
-
         // Key is the filename relative to the repository
         // if repository: /Users/username/Datasets/MyRepo
         //   /Users/username/Datasets/MyRepo/train -> train
         //   /Users/username/Datasets/MyRepo/annotations/train.txt -> annotations/train.txt
         let path = util::fs::path_relative_to_dir(path, &self.repository.path)?;
 
+        if let Some(staged_entry) = self.compute_staged_entry(&path, entry_reader, stat_cache)? {
+            log::debug!("add_staged_entry_in_dir_db {:?} {:?}", path, staged_entry);
+            self.add_staged_entry_to_db(&path, &staged_entry, staged_db)?;
+
+            if let Some(merger) = &self.merger {
+                if merger.has_file(&path)? {
+                    merger.remove_conflict_path(&path)?;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Hashes the file at the path relative to the repository, and returns the entry to stage,
+    /// or None if it has not changed since the commit the entry_reader points to
+    fn compute_staged_entry(
+        &self,
+        path: &Path,
+        entry_reader: &CommitDirEntryReader,
+        stat_cache: &StatCache,
+    ) -> Result<Option<StagedEntry>, OxenError> {
+        let full_path = self.repository.path.join(path);
+        if !full_path.exists() {
+            return Err(OxenError::file_does_not_exist(&full_path));
+        }
+
+        // compute the hash to know if it has changed, caching it so status does not have to rehash
+        let hash = stat_cache.get_hash(path)?;
+
         let mut staged_entry = StagedEntry {
             hash: hash.to_owned(),
             status: StagedEntryStatus::Added,
@@ -717,11 +1059,9 @@ impl Stager {
 
         // Check if it is a merge conflict, then we can add it
         if let Some(merger) = &self.merger {
-            if merger.has_file(&path)? {
-                log::debug!("add_staged_entry_in_dir_db merger has file! {:?}", path);
-                self.add_staged_entry_to_db(&path, &staged_entry, staged_db)?;
-                merger.remove_conflict_path(&path)?;
-                return Ok(path);
+            if merger.has_file(path)? {
+                log::debug!("compute_staged_entry merger has file! {:?}", path);
+                return Ok(Some(staged_entry));
             }
         }
 
@@ -730,31 +1070,46 @@ impl Stager {
         let basename = path.file_name().unwrap().to_str().unwrap();
         if let Ok(Some(entry)) = entry_reader.get_entry(basename) {
             log::debug!(
-                "add_staged_entry_in_dir_db comparing hashes {:?} -> {:?}",
+                "compute_staged_entry comparing hashes {:?} -> {:?}",
                 staged_entry,
                 entry
             );
             if entry.hash == hash {
                 // file has not changed, don't add it
                 log::debug!(
-                    "add_staged_entry_in_dir_db do not add file, it hasn't changed: {:?}",
+                    "compute_staged_entry do not add file, it hasn't changed: {:?}",
                     path
                 );
-                return Ok(path);
+                return Ok(None);
             } else {
                 // Hash doesn't match, mark it as modified
-                log::debug!(
-                    "add_staged_entry_in_dir_db HASH DOESN'T MATCH {:?}",
-                    entry.path
-                );
+                log::debug!("compute_staged_entry HASH DOESN'T MATCH {:?}", entry.path);
                 staged_entry.status = StagedEntryStatus::Modified;
             }
         }
 
-        log::debug!("add_staged_entry_in_dir_db {:?} {:?}", path, staged_entry);
-        self.add_staged_entry_to_db(&path, &staged_entry, staged_db)?;
+        Ok(Some(staged_entry))
+    }
 
-        Ok(path)
+    /// Reads the schema of a tabular file at the path relative to the repository
+    fn compute_schema(&self, path: &Path) -> Option<schema::Schema> {
+        if !util::fs::is_tabular(path) {
+            return None;
+        }
+
+        log::debug!("compute_schema is tabular! {:?}", path);
+        let full_path = self.repository.path.join(path);
+        match tabular::read_df(&full_path, DFOpts::empty()) {
+            Ok(df) => {
+                let schema = schema::Schema::from_polars(&df.schema());
+                log::debug!("compute_schema got schema {:?} -> {:?}", full_path, schema);
+                Some(schema)
+            }
+            Err(err) => {
+                log::warn!("Could not compute schema for file: {}", err);
+                None
+            }
+        }
     }
 
     fn add_staged_entry_to_db(
@@ -771,35 +1126,14 @@ impl Stager {
             while !components.is_empty() {
                 if let Some(_component) = components.pop() {
                     let parent: PathBuf = components.iter().collect();
-                    log::debug!("add_staged_entry_to_db got parent {:?}", parent);
                     log::debug!("add_staged_entry_to_db adding parent {:?}", parent);
                     path_db::put(&self.dir_db, parent, &0)?;
                 }
             }
 
             // If tabular, add schema
-            if util::fs::is_tabular(path) {
-                log::debug!(
-                    "add_staged_entry_to_db is tabular! compute schema {:?}",
-                    path
-                );
-                let full_path = self.repository.path.join(path);
-
-                match tabular::read_df(&full_path, DFOpts::empty()) {
-                    Ok(df) => {
-                        let schema = schema::Schema::from_polars(&df.schema());
-                        log::debug!(
-                            "add_staged_entry_to_db is tabular! got schema {:?} -> {:?}",
-                            full_path,
-                            schema
-                        );
-
-                        path_db::put(&self.schemas_db, path, &schema)?;
-                    }
-                    Err(err) => {
-                        log::warn!("Could not compute schema for file: {}", err);
-                    }
-                }
+            if let Some(schema) = self.compute_schema(path) {
+                path_db::put(&self.schemas_db, path, &schema)?;
             }
 
             staged_db.add_staged_entry_to_db(file_name, staged_entry)
@@ -894,6 +1228,10 @@ impl Stager {
                     continue;
                 }
 
+                if self.ignore.is_ignored(&relative_path, false) {
+                    continue;
+                }
+
                 // File is staged
                 if !self.has_entry(&relative_path) {
                     paths.push(relative_path);
@@ -946,11 +1284,13 @@ impl Stager {
 #[cfg(test)]
 mod tests {
     use crate::error::OxenError;
-    use crate::index::{CommitDirReader, CommitReader, CommitWriter, Stager};
+    use crate::index::{CommitDirReader, CommitReader, CommitWriter, Stager, StatCache};
     use crate::model::StagedEntryStatus;
     use crate::test;
     use crate::util;
 
+    use filetime::FileTime;
+
     use std::path::{Path, PathBuf};
 
     #[test]
@@ -1350,6 +1690,39 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_stager_status_from_dir_with_path_filter() -> Result<(), OxenError> {
+        test::run_empty_stager_test(|stager, repo| {
+            let commit_reader = CommitReader::new(&repo)?;
+            let commit = commit_reader.head_commit()?;
+            let entry_reader = CommitDirReader::new(&stager.repository, &commit)?;
+            let repo_path = &stager.repository.path;
+
+            let train_dir = repo_path.join("data").join("train");
+            let test_dir = repo_path.join("data").join("test");
+            std::fs::create_dir_all(&train_dir)?;
+            std::fs::create_dir_all(&test_dir)?;
+            test::write_txt_file_to_path(train_dir.join("a.txt"), "a")?;
+            test::write_txt_file_to_path(train_dir.join("b.txt"), "b")?;
+            test::write_txt_file_to_path(test_dir.join("c.txt"), "c")?;
+            test::write_txt_file_to_path(repo_path.join("README.md"), "readme")?;
+
+            let filter = Path::new("data").join("train");
+            let status = stager.status_from_dir(&entry_reader, repo_path, Some(&filter))?;
+            assert_eq!(status.untracked_dirs.len(), 1);
+            assert_eq!(status.untracked_dirs[0].0, filter);
+            assert!(status.untracked_files.is_empty());
+
+            // Filtering on a single file only reports that file
+            let filter = Path::new("README.md");
+            let status = stager.status_from_dir(&entry_reader, repo_path, Some(filter))?;
+            assert!(status.untracked_dirs.is_empty());
+            assert_eq!(status.untracked_files, vec![PathBuf::from("README.md")]);
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_stager_list_one_untracked_directory() -> Result<(), OxenError> {
         test::run_empty_stager_test(|stager, repo| {
@@ -1419,6 +1792,58 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_stager_add_dir_skips_ignored() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_no_commits(|repo| {
+            test::write_txt_file_to_path(repo.path.join(".oxenignore"), "one_shot.csv\ntest/\n")?;
+            let stager = Stager::new(&repo)?;
+            let commit_reader = CommitReader::new(&repo)?;
+            let commit = commit_reader.head_commit()?;
+            let entry_reader = CommitDirReader::new(&repo, &commit)?;
+
+            let annotations_dir = PathBuf::from("annotations");
+            stager.add(&repo.path.join(&annotations_dir), &entry_reader)?;
+
+            // annotations/train/one_shot.csv and annotations/test/ are ignored
+            let status = stager.status(&entry_reader)?;
+            let added_dir = status.added_dirs.get(&annotations_dir).unwrap();
+            assert_eq!(added_dir.num_files_staged, 4);
+
+            // Adding an ignored file directly does nothing
+            let one_shot = repo.path.join("annotations/train/one_shot.csv");
+            stager.add(&one_shot, &entry_reader)?;
+            assert!(!stager.has_entry(&one_shot));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stager_status_skips_ignored_untracked() -> Result<(), OxenError> {
+        test::run_empty_stager_test(|stager, repo| {
+            let repo_path = &repo.path;
+            // The ignore files are read the first time a path in their dir is checked
+            test::write_txt_file_to_path(repo_path.join(".oxenignore"), "*.txt\ncache/\n")?;
+            test::add_txt_file_to_dir(repo_path, "Hello 1")?;
+            let csv_file = test::add_csv_file_to_dir(repo_path, "a,b\n1,2")?;
+            let cache_dir = repo_path.join("cache");
+            std::fs::create_dir_all(&cache_dir)?;
+            test::add_csv_file_to_dir(&cache_dir, "a,b\n1,2")?;
+
+            let entry_reader = CommitDirReader::new_from_head(&repo)?;
+            let status = stager.status(&entry_reader)?;
+            let csv_path = util::fs::path_relative_to_dir(&csv_file, repo_path)?;
+            assert_eq!(status.untracked_files.len(), 2);
+            assert!(status.untracked_files.contains(&csv_path));
+            assert!(status
+                .untracked_files
+                .contains(&PathBuf::from(".oxenignore")));
+            assert!(status.untracked_dirs.is_empty());
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_stager_modify_file_recursive() -> Result<(), OxenError> {
         test::run_training_data_repo_test_fully_committed(|repo| {
@@ -1580,4 +2005,61 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_stager_add_fills_stat_cache() -> Result<(), OxenError> {
+        test::run_empty_stager_test(|stager, repo| {
+            let entry_reader = CommitDirReader::new_from_head(&repo)?;
+
+            let hello_file = test::add_txt_file_to_dir(&repo.path, "Hello World")?;
+            // Set the mtime far enough in the past that the entry is not racy
+            filetime::set_file_mtime(&hello_file, FileTime::from_unix_time(1_000_000, 0))?;
+            stager.add_file(&hello_file, &entry_reader)?;
+
+            let relative = util::fs::path_relative_to_dir(&hello_file, &repo.path)?;
+            let stat_cache = StatCache::new_read_only(&repo)?;
+            let entry = stat_cache.get_entry(&relative)?.unwrap();
+            assert_eq!(entry.hash, util::hasher::hash_file_contents(&hello_file)?);
+
+            Ok(())
+        })
+    }
+
+    // Benchmark for adding a large directory, run with
+    // cargo test --release test_stager_add_dir_100k_files -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn test_stager_add_dir_100k_files() -> Result<(), OxenError> {
+        test::run_empty_stager_test(|stager, repo| {
+            let commit_reader = CommitReader::new(&repo)?;
+            let commit = commit_reader.head_commit()?;
+            let entry_reader = CommitDirReader::new(&repo, &commit)?;
+
+            let num_dirs = 100;
+            let num_files_per_dir = 1000;
+            let data_dir = PathBuf::from("data");
+            for i in 0..num_dirs {
+                let dir = repo.path.join(&data_dir).join(format!("dir_{i}"));
+                std::fs::create_dir_all(&dir)?;
+                for j in 0..num_files_per_dir {
+                    let file = dir.join(format!("file_{j}.txt"));
+                    util::fs::write_to_path(&file, &format!("dir {i} file {j}"))?;
+                }
+            }
+
+            let start = std::time::Instant::now();
+            stager.add(&repo.path.join(&data_dir), &entry_reader)?;
+            println!(
+                "Added {} files in {:?}",
+                num_dirs * num_files_per_dir,
+                start.elapsed()
+            );
+
+            let status = stager.status(&entry_reader)?;
+            let added_dir = status.added_dirs.get(&data_dir).unwrap();
+            assert_eq!(added_dir.num_files_staged, num_dirs * num_files_per_dir);
+
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,333 @@
+//! Shelves the staged and modified files as a commit that is not on any branch, and puts the
+//! working files back to HEAD. The stashed commits are kept in the STASH file, newest first, and
+//! can be applied back on top of whatever is checked out later.
+
+use crate::constants::STASH_FILE;
+use crate::error::OxenError;
+use crate::index::{CommitDirReader, CommitReader, CommitWriter, Merger, RefReader, Stager};
+use crate::model::{Commit, LocalRepository, MergeConflict};
+use crate::util;
+
+use super::restore;
+use std::path::PathBuf;
+
+fn stash_path(repo: &LocalRepository) -> PathBuf {
+    util::fs::oxen_hidden_dir(&repo.path).join(STASH_FILE)
+}
+
+/// The stash commit ids, newest first
+pub fn list_commit_ids(repo: &LocalRepository) -> Result<Vec<String>, OxenError> {
+    let path = stash_path(repo);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents = util::fs::read_from_path(&path)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn write_commit_ids(repo: &LocalRepository, commit_ids: &[String]) -> Result<(), OxenError> {
+    let path = stash_path(repo);
+    if commit_ids.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    util::fs::write_to_path(&path, &commit_ids.join("\n"))
+}
+
+/// The stash commits, newest first, so `stash@{0}` is the first one
+pub fn list(repo: &LocalRepository) -> Result<Vec<Commit>, OxenError> {
+    let commit_reader = CommitReader::new(repo)?;
+    let mut commits: Vec<Commit> = vec![];
+    for commit_id in list_commit_ids(repo)? {
+        let commit = commit_reader
+            .get_commit_by_id(&commit_id)?
+            .ok_or_else(|| OxenError::commit_db_corrupted(&commit_id))?;
+        commits.push(commit);
+    }
+    Ok(commits)
+}
+
+fn get(repo: &LocalRepository, index: usize) -> Result<Commit, OxenError> {
+    list(repo)?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| OxenError::basic_str(format!("stash@{{{index}}} does not exist")))
+}
+
+/// Commits the staged, modified and removed files to a new stash, and restores them to HEAD.
+/// Untracked files are left alone. Returns None if there was nothing to stash.
+pub fn push(repo: &LocalRepository, message: Option<&str>) -> Result<Option<Commit>, OxenError> {
+    let commit_reader = CommitReader::new(repo)?;
+    let head_commit = commit_reader.head_commit()?;
+    let head_reader = CommitDirReader::new(repo, &head_commit)?;
+    let stager = Stager::new(repo)?;
+
+    let status = stager.status(&head_reader)?;
+    if !status.merge_conflicts.is_empty() {
+        return Err(OxenError::basic_str(
+            "Cannot stash while there are merge conflicts, resolve them first.",
+        ));
+    }
+    if !status.has_added_entries()
+        && !status.has_modified_entries()
+        && !status.has_removed_entries()
+    {
+        return Ok(None);
+    }
+
+    // Stage what has not been staged so it is all in the stash commit
+    for path in status
+        .modified_files
+        .iter()
+        .chain(status.removed_files.iter())
+    {
+        stager.add(&repo.path.join(path), &head_reader)?;
+    }
+    let status = stager.status(&head_reader)?;
+
+    let branch = RefReader::new(repo)?
+        .get_current_branch()?
+        .map(|branch| branch.name)
+        .unwrap_or_else(|| String::from("HEAD"));
+    let message = match message {
+        Some(message) => format!("On {branch}: {message}"),
+        None => format!("WIP on {}: {}", branch, head_commit.message),
+    };
+
+    let commit_writer = CommitWriter::new(repo)?;
+    let commit =
+        commit_writer.commit_unreferenced(&status, vec![head_commit.id.clone()], &message)?;
+    stager.unstage()?;
+
+    // Put the working files back the way they are in HEAD
+    for path in status.added_files.keys() {
+        match head_reader.get_entry(path)? {
+            Some(entry) => restore::restore_file(repo, path, &head_commit.id, &entry)?,
+            None => {
+                let working_path = repo.path.join(path);
+                if working_path.exists() {
+                    std::fs::remove_file(working_path)?;
+                }
+            }
+        }
+    }
+
+    let mut commit_ids = list_commit_ids(repo)?;
+    commit_ids.insert(0, commit.id.clone());
+    write_commit_ids(repo, &commit_ids)?;
+
+    log::debug!("Saved stash {} -> {}", commit.id, commit.message);
+    Ok(Some(commit))
+}
+
+/// Applies the changes of `stash@{index}` to the working files. Files that changed in both HEAD and
+/// the stash since the stash was made are merged row by row if they are tabular, otherwise they are
+/// recorded as merge conflicts and left as they are in HEAD, and the files that applied are staged.
+pub fn apply(repo: &LocalRepository, index: usize) -> Result<Vec<MergeConflict>, OxenError> {
+    let stash_commit = get(repo, index)?;
+    let commit_reader = CommitReader::new(repo)?;
+    let base_id = stash_commit
+        .parent_ids
+        .first()
+        .ok_or_else(|| OxenError::commit_db_corrupted(&stash_commit.id))?;
+    let base_commit = commit_reader
+        .get_commit_by_id(base_id)?
+        .ok_or_else(|| OxenError::commit_db_corrupted(base_id))?;
+
+    let (paths, conflicts) = {
+        let merger = Merger::new(repo)?;
+        merger.apply_delta(&base_commit, &stash_commit)?
+    };
+    if !conflicts.is_empty() {
+        // Stage what applied cleanly, so only the conflicts are left to resolve and add
+        let head_commit = commit_reader.head_commit()?;
+        let head_reader = CommitDirReader::new(repo, &head_commit)?;
+        let stager = Stager::new(repo)?;
+        for path in paths.iter() {
+            stager.add(&repo.path.join(path), &head_reader)?;
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Removes `stash@{index}`, the commit stays around until it is garbage collected
+pub fn drop(repo: &LocalRepository, index: usize) -> Result<Commit, OxenError> {
+    let commit = get(repo, index)?;
+    let mut commit_ids = list_commit_ids(repo)?;
+    commit_ids.remove(index);
+    write_commit_ids(repo, &commit_ids)?;
+    Ok(commit)
+}
+
+/// Applies `stash@{index}` and drops it, only if every file applied without conflicts. Files the
+/// stash changed that HEAD removed, or that both added, are conflicts too so the stash is kept.
+pub fn pop(repo: &LocalRepository, index: usize) -> Result<Vec<MergeConflict>, OxenError> {
+    let conflicts = apply(repo, index)?;
+    if conflicts.is_empty() {
+        drop(repo, index)?;
+    } else {
+        log::debug!(
+            "Keeping stash@{{{}}}, it had {} conflicts",
+            index,
+            conflicts.len()
+        );
+    }
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command;
+    use crate::error::OxenError;
+    use crate::index::{garbage_collector, stasher};
+    use crate::opts::GCOpts;
+    use crate::test;
+    use crate::util;
+
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_stash_push_and_pop() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let labels_path = repo.path.join("labels.txt");
+            let readme_path = repo.path.join("README.md");
+            let new_path = repo.path.join("new.txt");
+            let original = util::fs::read_from_path(&labels_path)?;
+            util::fs::write_to_path(&labels_path, "dog\ncat\nbird")?;
+            std::fs::remove_file(&readme_path)?;
+            util::fs::write_to_path(&new_path, "new file")?;
+            command::add(&repo, &new_path)?;
+
+            let stash = stasher::push(&repo, Some("adding birds"))?.unwrap();
+            assert_eq!(stash.message, "On main: adding birds");
+            let status = command::status(&repo)?;
+            assert!(!status.has_added_entries());
+            assert!(!status.has_modified_entries());
+            assert!(!status.has_removed_entries());
+            assert_eq!(util::fs::read_from_path(&labels_path)?, original);
+            assert!(readme_path.exists());
+            assert!(!new_path.exists());
+
+            let stashes = stasher::list(&repo)?;
+            assert_eq!(stashes.len(), 1);
+            assert_eq!(stashes[0].id, stash.id);
+
+            let conflicts = stasher::pop(&repo, 0)?;
+            assert!(conflicts.is_empty());
+            assert_eq!(util::fs::read_from_path(&labels_path)?, "dog\ncat\nbird");
+            assert!(!readme_path.exists());
+            assert_eq!(util::fs::read_from_path(&new_path)?, "new file");
+            assert!(stasher::list(&repo)?.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stash_nothing_to_stash() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            assert!(stasher::push(&repo, None)?.is_none());
+            assert!(stasher::list(&repo)?.is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stash_apply_conflict() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let labels_path = repo.path.join("labels.txt");
+            util::fs::write_to_path(&labels_path, "dog\ncat\nbird")?;
+            stasher::push(&repo, None)?.unwrap();
+
+            // Change the same file on HEAD
+            util::fs::write_to_path(&labels_path, "dog\ncat\nfish")?;
+            command::add(&repo, &labels_path)?;
+            command::commit(&repo, "Adding fish")?;
+
+            let conflicts = stasher::pop(&repo, 0)?;
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].path().to_str(), Some("labels.txt"));
+            // The stash is kept until the conflict is resolved
+            assert_eq!(stasher::list(&repo)?.len(), 1);
+            assert_eq!(command::status(&repo)?.merge_conflicts.len(), 1);
+            assert_eq!(util::fs::read_from_path(&labels_path)?, "dog\ncat\nfish");
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stash_pop_keeps_stash_when_head_removed_file() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let labels_path = repo.path.join("labels.txt");
+            let new_path = repo.path.join("new.txt");
+            util::fs::write_to_path(&labels_path, "dog\ncat\nbird")?;
+            util::fs::write_to_path(&new_path, "new file")?;
+            command::add(&repo, &new_path)?;
+            stasher::push(&repo, None)?.unwrap();
+
+            // Remove the file the stash changed on HEAD
+            std::fs::remove_file(&labels_path)?;
+            command::add(&repo, &labels_path)?;
+            command::commit(&repo, "Removing labels")?;
+
+            let conflicts = stasher::pop(&repo, 0)?;
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].path().to_str(), Some("labels.txt"));
+            assert!(conflicts[0].head_entry().is_none());
+            assert_eq!(stasher::list(&repo)?.len(), 1);
+            assert!(!labels_path.exists());
+            // The rest of the stash applied and is staged
+            assert_eq!(util::fs::read_from_path(&new_path)?, "new file");
+            let status = command::status(&repo)?;
+            assert!(status.added_files.contains_key(Path::new("new.txt")));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stash_apply_would_overwrite_local_changes() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let labels_path = repo.path.join("labels.txt");
+            util::fs::write_to_path(&labels_path, "dog\ncat\nbird")?;
+            stasher::push(&repo, None)?.unwrap();
+
+            util::fs::write_to_path(&labels_path, "dog\ncat\nfish")?;
+            assert!(stasher::apply(&repo, 0).is_err());
+            assert_eq!(util::fs::read_from_path(&labels_path)?, "dog\ncat\nfish");
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stash_is_not_garbage_collected() -> Result<(), OxenError> {
+        test::run_training_data_repo_test_fully_committed(|repo| {
+            let labels_path = repo.path.join("labels.txt");
+            util::fs::write_to_path(&labels_path, "dog\ncat\nbird")?;
+            let stash = stasher::push(&repo, None)?.unwrap();
+
+            let opts = GCOpts {
+                dry_run: true,
+                grace_period: Duration::from_secs(0),
+            };
+            let report = garbage_collector::collect(&repo, &opts)?;
+            assert!(!report.unreachable_commits.contains(&stash.id));
+
+            stasher::drop(&repo, 0)?;
+            let report = garbage_collector::collect(&repo, &opts)?;
+            assert!(report.unreachable_commits.contains(&stash.id));
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,250 @@
+use crate::constants::STAT_CACHE_DIR;
+use crate::db;
+use crate::db::path_db;
+use crate::error::OxenError;
+use crate::model::{LocalRepository, StatEntry};
+use crate::util;
+
+use rocksdb::{DBWithThreadMode, MultiThreaded};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Files modified this recently may still be written to within the same mtime tick,
+/// so we do not trust their stat data enough to cache the hash
+const RACY_MTIME_WINDOW: Duration = Duration::from_secs(2);
+
+/// # StatCache
+/// Maps file paths to the (size, mtime, inode) they had when they were last hashed,
+/// so that `oxen status` only has to rehash the files whose stat data changed.
+///
+/// `add` and `commit` fill the cache as they hash files. `status` opens it read only and
+/// collects what it had to rehash, which `flush` writes back only if there is anything to write.
+pub struct StatCache {
+    db: Option<DBWithThreadMode<MultiThreaded>>,
+    repository: LocalRepository,
+    read_only: bool,
+    /// Changes a read only cache could not write yet, None removes the entry
+    pending: Mutex<HashMap<PathBuf, Option<StatEntry>>>,
+}
+
+impl StatCache {
+    pub fn db_dir(repo: &LocalRepository) -> PathBuf {
+        util::fs::oxen_hidden_dir(&repo.path).join(STAT_CACHE_DIR)
+    }
+
+    pub fn new(repository: &LocalRepository) -> Result<StatCache, OxenError> {
+        let db_path = StatCache::db_dir(repository);
+        if !db_path.exists() {
+            std::fs::create_dir_all(&db_path)?;
+        }
+        let opts = db::opts::default();
+        Ok(StatCache {
+            db: Some(DBWithThreadMode::open(&opts, &db_path)?),
+            repository: repository.clone(),
+            read_only: false,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Opens the cache without taking the write lock on the db, or without a db if there is no cache yet
+    pub fn new_read_only(repository: &LocalRepository) -> Result<StatCache, OxenError> {
+        let db_path = StatCache::db_dir(repository);
+        let db = if db_path.exists() {
+            let opts = db::opts::default();
+            Some(DBWithThreadMode::open_for_read_only(
+                &opts, &db_path, false,
+            )?)
+        } else {
+            None
+        };
+        Ok(StatCache {
+            db,
+            repository: repository.clone(),
+            read_only: true,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// # Get the hash of a file relative to the repository
+    /// Returns the cached hash if the stat data has not changed, otherwise hashes the file and caches it
+    pub fn get_hash<P: AsRef<Path>>(&self, path: P) -> Result<String, OxenError> {
+        let path = path.as_ref();
+        let full_path = self.repository.path.join(path);
+        let metadata = std::fs::metadata(&full_path)?;
+
+        if let Some(entry) = self.get_entry(path)? {
+            if entry.matches(&metadata) {
+                log::debug!("StatCache hit {:?}", path);
+                return Ok(entry.hash);
+            }
+        }
+
+        log::debug!("StatCache miss {:?}", path);
+        let hash = util::hasher::hash_file_contents(&full_path)?;
+        if StatCache::is_racy(&metadata) {
+            // Make sure we do not cache a stale hash, and check again next time
+            self.update(path, None)?;
+        } else {
+            let entry = StatEntry::from_metadata(&hash, &metadata);
+            self.update(path, Some(entry))?;
+        }
+        Ok(hash)
+    }
+
+    pub fn get_entry<P: AsRef<Path>>(&self, path: P) -> Result<Option<StatEntry>, OxenError> {
+        if let Some(entry) = self.pending.lock().unwrap().get(path.as_ref()) {
+            return Ok(entry.clone());
+        }
+        match &self.db {
+            Some(db) => path_db::get_entry(db, path),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_entry<P: AsRef<Path>>(&self, path: P) -> Result<(), OxenError> {
+        self.update(path.as_ref(), None)
+    }
+
+    /// Moves the cached entry of a renamed file, a rename keeps the size, mtime and inode
+    pub fn move_entry<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<(), OxenError> {
+        let entry = self.get_entry(&from)?;
+        self.update(from.as_ref(), None)?;
+        self.update(to.as_ref(), entry)
+    }
+
+    pub fn clear(&self) -> Result<(), OxenError> {
+        self.pending.lock().unwrap().clear();
+        match &self.db {
+            Some(db) if !self.read_only => path_db::clear(db),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the entries a read only cache collected, only opening the db for writing if there are any
+    pub fn flush(self) -> Result<(), OxenError> {
+        let pending = self.pending.into_inner().unwrap();
+        if !self.read_only || pending.is_empty() {
+            return Ok(());
+        }
+        // Release the read only handle before opening for writing
+        drop(self.db);
+
+        let cache = StatCache::new(&self.repository)?;
+        for (path, entry) in pending.into_iter() {
+            cache.update(&path, entry)?;
+        }
+        Ok(())
+    }
+
+    fn update(&self, path: &Path, entry: Option<StatEntry>) -> Result<(), OxenError> {
+        match &self.db {
+            Some(db) if !self.read_only => match entry {
+                Some(entry) => path_db::put(db, path, &entry),
+                None => path_db::delete(db, path),
+            },
+            _ => {
+                self.pending
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_path_buf(), entry);
+                Ok(())
+            }
+        }
+    }
+
+    fn is_racy(metadata: &std::fs::Metadata) -> bool {
+        match metadata.modified() {
+            Ok(modified) => match SystemTime::now().duration_since(modified) {
+                Ok(age) => age < RACY_MTIME_WINDOW,
+                Err(_) => true,
+            },
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::index::StatCache;
+    use crate::test;
+    use crate::util;
+
+    use filetime::FileTime;
+    use std::path::Path;
+
+    #[test]
+    fn test_stat_cache_only_rehashes_changed_files() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = Path::new("hello.txt");
+            let full_path = repo.path.join(path);
+            util::fs::write_to_path(&full_path, "Hello World")?;
+            // Set the mtime far enough in the past that the entry is not racy
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(1_000_000, 0))?;
+
+            let stat_cache = StatCache::new(&repo)?;
+            let hash = stat_cache.get_hash(path)?;
+            assert_eq!(hash, util::hasher::hash_file_contents(&full_path)?);
+            let entry = stat_cache.get_entry(path)?.unwrap();
+            assert_eq!(entry.hash, hash);
+            assert_eq!(entry.num_bytes, 11);
+
+            // Tamper with the cached hash, the stat data did not change so it is trusted
+            let mut fake = entry.clone();
+            fake.hash = String::from("fake");
+            crate::db::path_db::put(stat_cache.db.as_ref().unwrap(), path, &fake)?;
+            assert_eq!(stat_cache.get_hash(path)?, "fake");
+
+            // Once the file changes on disk it is rehashed
+            util::fs::write_to_path(&full_path, "Goodbye World")?;
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(2_000_000, 0))?;
+            let new_hash = stat_cache.get_hash(path)?;
+            assert_ne!(new_hash, "fake");
+            assert_ne!(new_hash, hash);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stat_cache_does_not_cache_racy_files() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = Path::new("hello.txt");
+            util::fs::write_to_path(&repo.path.join(path), "Hello World")?;
+
+            let stat_cache = StatCache::new(&repo)?;
+            stat_cache.get_hash(path)?;
+            assert!(stat_cache.get_entry(path)?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stat_cache_read_only_writes_back_on_flush() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let path = Path::new("hello.txt");
+            let full_path = repo.path.join(path);
+            util::fs::write_to_path(&full_path, "Hello World")?;
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(1_000_000, 0))?;
+
+            // No cache on disk yet, reading does not create one
+            let stat_cache = StatCache::new_read_only(&repo)?;
+            let hash = stat_cache.get_hash(path)?;
+            assert!(stat_cache.get_entry(path)?.is_none());
+            assert!(!StatCache::db_dir(&repo).exists());
+
+            stat_cache.flush()?;
+            let stat_cache = StatCache::new_read_only(&repo)?;
+            assert_eq!(stat_cache.get_entry(path)?.unwrap().hash, hash);
+
+            Ok(())
+        })
+    }
+}
@@ -23,6 +23,7 @@ pub mod error;
 pub mod index;
 pub mod model;
 pub mod opts;
+pub mod storage;
 pub mod test;
 pub mod util;
 pub mod view;
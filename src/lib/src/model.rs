@@ -2,24 +2,31 @@ pub mod branch;
 pub mod commit;
 pub mod data_frame_diff;
 pub mod entry;
+pub mod fsck_report;
+pub mod gc_report;
 pub mod merge_conflict;
 pub mod namespace;
+pub mod note;
+pub mod rebase_report;
+pub mod reflog_entry;
 pub mod remote;
 pub mod remote_branch;
 pub mod repository;
 pub mod schema;
+pub mod search_match;
 pub mod staged_data;
 pub mod staged_dir_stats;
 pub mod summarized_staged_dir_stats;
 pub mod user;
 
 // Repository
+pub use crate::model::repository::fork::{ForkOrigin, MergeRequest, MergeRequestStatus};
 pub use crate::model::repository::local_repository::{LocalRepository, RepositoryNew};
 pub use crate::model::repository::remote_repository::RemoteRepository;
 pub use crate::model::repository::repo_stats::{DataTypeStat, RepoStats};
 
 // Commit
-pub use crate::model::commit::{Commit, CommitStats, NewCommit};
+pub use crate::model::commit::{Commit, CommitSignature, CommitStats, NewCommit, SignatureStatus};
 
 // Merge
 pub use crate::model::merge_conflict::MergeConflict;
@@ -33,6 +40,7 @@ pub use crate::model::entry::commit_entry::CommitEntry;
 pub use crate::model::entry::dir_entry::DirEntry;
 pub use crate::model::entry::remote_entry::RemoteEntry;
 pub use crate::model::entry::staged_entry::{StagedEntry, StagedEntryStatus};
+pub use crate::model::entry::stat_entry::StatEntry;
 pub use crate::model::entry::ContentHashable;
 
 pub use crate::model::user::User;
@@ -46,5 +54,12 @@ pub use crate::model::remote::Remote;
 pub use crate::model::data_frame_diff::DataFrameDiff;
 
 pub use crate::model::schema::Schema;
+pub use crate::model::search_match::SearchMatch;
 
 pub use crate::model::namespace::Namespace;
+
+pub use crate::model::fsck_report::{CorruptDB, FsckReport, MissingParent};
+pub use crate::model::gc_report::GCReport;
+pub use crate::model::note::Note;
+pub use crate::model::rebase_report::RebaseReport;
+pub use crate::model::reflog_entry::ReflogEntry;
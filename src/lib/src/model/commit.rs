@@ -1,8 +1,46 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::hash::{Hash, Hasher};
 use time::OffsetDateTime;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Signature by the author's Ed25519 key of the SHA-256 digest of the commit and its entries,
+/// both hex encoded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CommitSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Whether a commit was signed, if the signature matches its contents, and if the key is
+/// trusted for the author of the commit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Unsigned,
+    Good {
+        public_key: String,
+    },
+    Bad {
+        public_key: String,
+    },
+    /// The signature matches, but the key is not in the trusted keys of the repository
+    Untrusted {
+        public_key: String,
+    },
+    /// The signature matches, but the key is only trusted for other authors
+    AuthorMismatch {
+        public_key: String,
+        key_emails: Vec<String>,
+    },
+}
+
+impl SignatureStatus {
+    pub fn is_good(&self) -> bool {
+        matches!(self, SignatureStatus::Good { .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct NewCommit {
     pub parent_ids: Vec<String>,
     pub message: String,
@@ -10,6 +48,32 @@ pub struct NewCommit {
     pub email: String,
     #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Key value pairs such as training run ids or reviewers, part of the commit id
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Ed25519 signature of the commit and its entries, if the author had a signing key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<CommitSignature>,
+}
+
+// The Debug output is what `compute_commit_hash` hashes, so it has to stay the same for existing
+// commits. The signature is made from the id and entries, so it can not be part of it, and the metadata is only
+// added when there is some, sorted by key so the id does not depend on the order of the map.
+impl fmt::Debug for NewCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("NewCommit");
+        debug
+            .field("parent_ids", &self.parent_ids)
+            .field("message", &self.message)
+            .field("author", &self.author)
+            .field("email", &self.email)
+            .field("timestamp", &self.timestamp);
+        if !self.metadata.is_empty() {
+            let metadata: BTreeMap<&String, &String> = self.metadata.iter().collect();
+            debug.field("metadata", &metadata);
+        }
+        debug.finish()
+    }
 }
 
 impl NewCommit {
@@ -20,6 +84,8 @@ impl NewCommit {
             author: commit.author.to_owned(),
             email: commit.email.to_owned(),
             timestamp: commit.timestamp.to_owned(),
+            metadata: commit.metadata.to_owned(),
+            signature: commit.signature.to_owned(),
         }
     }
 }
@@ -33,6 +99,12 @@ pub struct Commit {
     pub email: String,
     #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Key value pairs such as training run ids or reviewers, part of the commit id
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Ed25519 signature of the commit and its entries, if the author had a signing key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<CommitSignature>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,6 +117,10 @@ pub struct CommitWithSize {
     #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
     pub size: u64,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<CommitSignature>,
 }
 
 // Hash on the id field so we can quickly look up
@@ -69,6 +145,8 @@ impl Commit {
             author: new_commit.author.to_owned(),
             email: new_commit.email.to_owned(),
             timestamp: new_commit.timestamp.to_owned(),
+            metadata: new_commit.metadata.to_owned(),
+            signature: new_commit.signature.to_owned(),
         }
     }
 
@@ -80,6 +158,8 @@ impl Commit {
             author: commit.author.to_owned(),
             email: commit.email.to_owned(),
             timestamp: commit.timestamp.to_owned(),
+            metadata: commit.metadata.to_owned(),
+            signature: commit.signature.to_owned(),
         }
     }
 
@@ -98,6 +178,8 @@ impl CommitWithSize {
             email: commit.email.to_owned(),
             timestamp: commit.timestamp.to_owned(),
             size,
+            metadata: commit.metadata.to_owned(),
+            signature: commit.signature.to_owned(),
         }
     }
 }
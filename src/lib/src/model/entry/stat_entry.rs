@@ -0,0 +1,46 @@
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+
+/// The stat data of a file when it was last hashed, so we only need to rehash when it changes
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatEntry {
+    pub hash: String,
+    pub num_bytes: u64,
+    pub last_modified_seconds: i64,
+    pub last_modified_nanoseconds: u32,
+    pub inode: u64,
+}
+
+impl StatEntry {
+    pub fn from_metadata(hash: &str, metadata: &Metadata) -> StatEntry {
+        let mtime = FileTime::from_last_modification_time(metadata);
+        StatEntry {
+            hash: hash.to_owned(),
+            num_bytes: metadata.len(),
+            last_modified_seconds: mtime.unix_seconds(),
+            last_modified_nanoseconds: mtime.nanoseconds(),
+            inode: inode(metadata),
+        }
+    }
+
+    /// True if the file looks the same as when it was hashed
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let mtime = FileTime::from_last_modification_time(metadata);
+        self.num_bytes == metadata.len()
+            && self.last_modified_seconds == mtime.unix_seconds()
+            && self.last_modified_nanoseconds == mtime.nanoseconds()
+            && self.inode == inode(metadata)
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::model::CommitEntry;
+
+/// A commit that points to a parent we do not have in the commits db
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MissingParent {
+    pub commit_id: String,
+    pub parent_id: String,
+}
+
+/// A RocksDB store that is missing or could not be opened
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CorruptDB {
+    pub path: PathBuf,
+    /// The commit the db belongs to, if it lives in the history dir
+    pub commit_id: Option<String>,
+    pub error: String,
+}
+
+/// Result of `oxen fsck`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FsckReport {
+    pub num_commits_checked: usize,
+    pub num_entries_checked: usize,
+    /// Entries whose version file is not in the version store
+    pub missing_versions: Vec<CommitEntry>,
+    /// Entries whose version file does not hash to the entry's hash
+    pub corrupt_versions: Vec<CommitEntry>,
+    pub missing_parents: Vec<MissingParent>,
+    pub corrupt_dbs: Vec<CorruptDB>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing_versions.is_empty()
+            && self.corrupt_versions.is_empty()
+            && self.missing_parents.is_empty()
+            && self.corrupt_dbs.is_empty()
+    }
+}
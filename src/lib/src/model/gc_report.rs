@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Summary of the objects that were not reachable from any ref during `oxen gc`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GCReport {
+    /// Hashes of the version files nothing references
+    pub unreachable_versions: Vec<String>,
+    /// Commit ids whose history dirs nothing references
+    pub unreachable_commits: Vec<String>,
+    /// Number of bytes freed, or that would be freed on a dry run
+    pub num_bytes: u64,
+    pub dry_run: bool,
+}
+
+impl GCReport {
+    pub fn is_empty(&self) -> bool {
+        self.unreachable_versions.is_empty() && self.unreachable_commits.is_empty()
+    }
+}
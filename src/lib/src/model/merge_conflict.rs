@@ -1,9 +1,62 @@
 use crate::model::CommitEntry;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct MergeConflict {
-    pub lca_entry: CommitEntry,   // Least Common Ancestor Entry
-    pub head_entry: CommitEntry,  // Entry that existed in the HEAD commit
-    pub merge_entry: CommitEntry, // Entry we are trying to merge in
+#[serde(untagged)]
+pub enum MergeConflict {
+    /// Both sides modified a file that is in the common ancestor
+    Modified {
+        lca_entry: CommitEntry,   // Least Common Ancestor Entry
+        head_entry: CommitEntry,  // Entry that existed in the HEAD commit
+        merge_entry: CommitEntry, // Entry we are trying to merge in
+    },
+    /// One side removed a file the other side modified, or both sides added it
+    Removed {
+        lca_entry: Option<CommitEntry>,
+        head_entry: Option<CommitEntry>,
+        merge_entry: Option<CommitEntry>,
+    },
+}
+
+impl MergeConflict {
+    pub fn lca_entry(&self) -> Option<&CommitEntry> {
+        match self {
+            MergeConflict::Modified { lca_entry, .. } => Some(lca_entry),
+            MergeConflict::Removed { lca_entry, .. } => lca_entry.as_ref(),
+        }
+    }
+
+    pub fn head_entry(&self) -> Option<&CommitEntry> {
+        match self {
+            MergeConflict::Modified { head_entry, .. } => Some(head_entry),
+            MergeConflict::Removed { head_entry, .. } => head_entry.as_ref(),
+        }
+    }
+
+    pub fn merge_entry(&self) -> Option<&CommitEntry> {
+        match self {
+            MergeConflict::Modified { merge_entry, .. } => Some(merge_entry),
+            MergeConflict::Removed { merge_entry, .. } => merge_entry.as_ref(),
+        }
+    }
+
+    /// The path of the conflicting file relative to the repository
+    pub fn path(&self) -> &Path {
+        self.head_entry()
+            .or_else(|| self.merge_entry())
+            .or_else(|| self.lca_entry())
+            .map(|entry| entry.path.as_path())
+            .unwrap_or_else(|| Path::new(""))
+    }
+
+    /// How the file conflicts, as shown in `status`
+    pub fn description(&self) -> &'static str {
+        match (self.lca_entry(), self.head_entry(), self.merge_entry()) {
+            (None, _, _) => "both added",
+            (_, None, _) => "deleted by us",
+            (_, _, None) => "deleted by them",
+            _ => "both modified",
+        }
+    }
 }
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A message attached to a commit after the fact. Unlike the commit message it can be
+/// edited or removed without changing the commit id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub commit_id: String,
+    pub message: String,
+    pub author: String,
+    pub email: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    /// Removed notes are kept so the removal syncs with push and pull
+    #[serde(default)]
+    pub deleted: bool,
+}
@@ -0,0 +1,32 @@
+use crate::model::Commit;
+
+/// What `oxen rebase` did, for the caller to report
+#[derive(Debug, Clone)]
+pub enum RebaseReport {
+    /// The branch already has the upstream commit in its history
+    UpToDate { branch: String, head: Commit },
+    /// Every commit was replayed on top of upstream
+    Rebased {
+        branch: String,
+        upstream: String,
+        head: Commit,
+        /// Commits whose changes were already upstream, so nothing was replayed for them
+        skipped: Vec<Commit>,
+    },
+    /// Replaying the commit stopped on conflicts, they have to be resolved before continuing
+    Stopped {
+        commit: Commit,
+        skipped: Vec<Commit>,
+    },
+}
+
+impl RebaseReport {
+    /// HEAD once the rebase is done, None if it stopped on conflicts
+    pub fn head(&self) -> Option<&Commit> {
+        match self {
+            RebaseReport::UpToDate { head, .. } => Some(head),
+            RebaseReport::Rebased { head, .. } => Some(head),
+            RebaseReport::Stopped { .. } => None,
+        }
+    }
+}
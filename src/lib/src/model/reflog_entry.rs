@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A move of HEAD that rewrote history, from `old_commit_id` to `new_commit_id`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReflogEntry {
+    pub old_commit_id: String,
+    pub new_commit_id: String,
+    pub message: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
@@ -1,3 +1,4 @@
+pub mod fork;
 pub mod local_repository;
 pub mod remote_repository;
 pub mod repo_stats;
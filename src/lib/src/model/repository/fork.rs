@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// The repository a fork was made from, saved in .oxen/fork_origin.json of the fork
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForkOrigin {
+    pub namespace: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeRequestStatus {
+    /// Only a record in the upstream, nothing has been copied into it yet
+    #[default]
+    Open,
+    /// The commits were copied into the upstream on `branch`
+    Accepted,
+}
+
+/// A branch pushed to a fork that was opened as a merge back into the upstream repository.
+/// Once a user who can write to the upstream accepts it, the commits are copied into the
+/// upstream on `branch`, so they can be merged into `target_branch`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MergeRequest {
+    pub id: String,
+    pub source_namespace: String,
+    pub source_name: String,
+    pub source_branch: String,
+    pub commit_id: String,
+    /// Branch in the upstream repository that holds the commits from the fork once accepted
+    pub branch: String,
+    pub target_branch: String,
+    #[serde(default)]
+    pub status: MergeRequestStatus,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
@@ -3,6 +3,7 @@ use crate::constants;
 use crate::error::OxenError;
 use crate::index::EntryIndexer;
 use crate::model::{Commit, Remote, RemoteBranch, RemoteRepository};
+use crate::storage::{self, StorageConfig, VersionStore};
 use crate::util;
 use crate::view::RepositoryView;
 
@@ -43,6 +44,8 @@ pub struct LocalRepository {
     pub path: PathBuf,
     remote_name: Option<String>, // this is the current remote name
     pub remotes: Vec<Remote>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfig>, // where version files live, defaults to .oxen/versions
 }
 
 impl LocalRepository {
@@ -52,6 +55,7 @@ impl LocalRepository {
             path: path.to_path_buf(),
             remotes: vec![],
             remote_name: None,
+            storage: None,
         })
     }
 
@@ -60,6 +64,7 @@ impl LocalRepository {
             path: std::env::current_dir()?.join(view.name),
             remotes: vec![],
             remote_name: None,
+            storage: None,
         })
     }
 
@@ -68,6 +73,7 @@ impl LocalRepository {
             path: path.to_owned(),
             remotes: vec![repo.remote],
             remote_name: Some(String::from(constants::DEFAULT_REMOTE_NAME)),
+            storage: None,
         })
     }
 
@@ -86,6 +92,19 @@ impl LocalRepository {
         Ok(repo)
     }
 
+    /// Where we read and write the versions of files, configured in the [storage] section of the repo config
+    pub fn version_store(&self) -> Result<Box<dyn VersionStore>, OxenError> {
+        storage::create_version_store(self)
+    }
+
+    /// Whether the version files live in the local .oxen/versions dir
+    pub fn stores_versions_locally(&self) -> bool {
+        self.storage
+            .as_ref()
+            .map(|storage| storage.is_local())
+            .unwrap_or(true)
+    }
+
     pub fn dirname(&self) -> String {
         String::from(self.path.file_name().unwrap().to_str().unwrap())
     }
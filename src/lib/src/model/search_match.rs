@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A line of a text file, or a cell of a tabular file, that matched a search
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    /// The line number in a text file, starting at 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The column of the cell in a tabular file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    /// The row index of the cell in a tabular file, starting at 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    /// The whole line or cell value
+    pub text: String,
+}
@@ -30,6 +30,7 @@ pub struct StagedData {
     pub untracked_files: Vec<PathBuf>,
     pub modified_files: Vec<PathBuf>,
     pub removed_files: Vec<PathBuf>,
+    pub moved_files: Vec<(PathBuf, PathBuf)>, // (from, to) pairs of the staged entries that were renamed
     pub merge_conflicts: Vec<MergeConflict>,
 }
 
@@ -43,6 +44,7 @@ impl StagedData {
             untracked_files: vec![],
             modified_files: vec![],
             removed_files: vec![],
+            moved_files: vec![],
             merge_conflicts: vec![],
         }
     }
@@ -142,7 +144,7 @@ impl StagedData {
         self.__collapse_outputs(
             &self.merge_conflicts,
             |conflict| {
-                let path = &conflict.head_entry.path;
+                let path = conflict.path();
 
                 // println!(
                 //     "    LCA {} {:?}",
@@ -161,7 +163,7 @@ impl StagedData {
                 // );
 
                 vec![
-                    "  both modified: ".to_string().yellow(),
+                    format!("  {}: ", conflict.description()).yellow(),
                     format!("{}\n", path.to_str().unwrap()).yellow().bold(),
                 ]
             },
@@ -231,12 +233,32 @@ impl StagedData {
             outputs.push(MSG_OXEN_RESTORE_STAGED_FILE.normal())
         }
 
-        let mut files_vec: Vec<(&PathBuf, &StagedEntry)> =
-            self.added_files.iter().map(|(k, v)| (k, v)).collect();
+        let moved: HashMap<&PathBuf, &PathBuf> = self
+            .moved_files
+            .iter()
+            .flat_map(|(from, to)| [(from, to), (to, from)])
+            .collect();
+        let mut files_vec: Vec<(&PathBuf, &StagedEntry)> = self
+            .added_files
+            .iter()
+            .filter(|(k, v)| !(moved.contains_key(k) && v.status == StagedEntryStatus::Removed))
+            .collect();
         files_vec.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
         self.__collapse_outputs(
             &files_vec,
             |(path, entry)| match entry.status {
+                _ if moved.contains_key(path) => {
+                    vec![
+                        "  renamed: ".green(),
+                        format!(
+                            "{} -> {}\n",
+                            moved[path].to_str().unwrap(),
+                            path.to_str().unwrap()
+                        )
+                        .green()
+                        .bold(),
+                    ]
+                }
                 StagedEntryStatus::Removed => {
                     vec![
                         "  removed: ".green(),
@@ -582,6 +604,38 @@ mod tests {
         assert_eq!(outputs[8], "  ... and 2 others\n".normal());
     }
 
+    #[test]
+    fn test_staged_data_collect_moved_files() {
+        let mut staged_data = StagedData::empty();
+        staged_data.added_files.insert(
+            PathBuf::from("images/file_1.jpg"),
+            StagedEntry::empty_status(StagedEntryStatus::Removed),
+        );
+        staged_data.added_files.insert(
+            PathBuf::from("train/file_1.jpg"),
+            StagedEntry::empty_status(StagedEntryStatus::Added),
+        );
+        staged_data.added_files.insert(
+            PathBuf::from("train/file_2.jpg"),
+            StagedEntry::empty_status(StagedEntryStatus::Added),
+        );
+        staged_data.moved_files.push((
+            PathBuf::from("images/file_1.jpg"),
+            PathBuf::from("train/file_1.jpg"),
+        ));
+
+        let outputs = staged_data.__collect_outputs(0, 10, false);
+        assert_eq!(outputs[0], "Files to be committed\n".normal());
+        assert_eq!(outputs[1], MSG_OXEN_RESTORE_STAGED_FILE.normal());
+        assert_eq!(outputs[2], "  renamed: ".green());
+        assert_eq!(
+            outputs[3],
+            "images/file_1.jpg -> train/file_1.jpg\n".green().bold()
+        );
+        assert_eq!(outputs[4], "  new file: ".green());
+        assert_eq!(outputs[5], "train/file_2.jpg\n".green().bold());
+    }
+
     #[test]
     fn test_staged_data_collect_untracked_files() {
         let mut staged_data = StagedData::empty();
@@ -1,3 +1,5 @@
+pub mod gc_opts;
 pub mod restore_opts;
 
+pub use crate::opts::gc_opts::GCOpts;
 pub use crate::opts::restore_opts::RestoreOpts;
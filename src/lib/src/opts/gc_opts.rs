@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+/// How long a new history dir is protected from gc, so we do not collect a push that is still in progress
+pub const DEFAULT_GC_GRACE_PERIOD_SECS: u64 = 60 * 60;
+
+#[derive(Clone, Debug)]
+pub struct GCOpts {
+    pub dry_run: bool,
+    pub grace_period: Duration,
+}
+
+impl GCOpts {
+    pub fn dry_run() -> GCOpts {
+        GCOpts {
+            dry_run: true,
+            ..GCOpts::default()
+        }
+    }
+}
+
+impl Default for GCOpts {
+    fn default() -> GCOpts {
+        GCOpts {
+            dry_run: false,
+            grace_period: Duration::from_secs(DEFAULT_GC_GRACE_PERIOD_SECS),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod local_version_store;
+pub mod s3_version_store;
+pub mod version_store;
+
+pub use crate::storage::local_version_store::LocalVersionStore;
+pub use crate::storage::s3_version_store::{S3Config, S3VersionStore};
+pub use crate::storage::version_store::{
+    create_version_store, LocalVersion, StorageConfig, VersionStore,
+};
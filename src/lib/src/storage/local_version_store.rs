@@ -0,0 +1,267 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::constants::{FILES_DIR, VERSIONS_DIR};
+use crate::error::OxenError;
+use crate::storage::version_store::{self, LocalVersion, VersionStore};
+use crate::util;
+
+/// Stores versions on the local disk in .oxen/versions/files/HASH[..2]/HASH[2..]/filename
+#[derive(Debug, Clone)]
+pub struct LocalVersionStore {
+    root: PathBuf,
+}
+
+impl LocalVersionStore {
+    pub fn new(repo_path: &Path) -> LocalVersionStore {
+        LocalVersionStore {
+            root: util::fs::oxen_hidden_dir(repo_path)
+                .join(VERSIONS_DIR)
+                .join(FILES_DIR),
+        }
+    }
+
+    pub fn version_path(&self, hash: &str, filename: &Path) -> PathBuf {
+        self.root.join(version_store::version_key(hash, filename))
+    }
+
+    fn version_dir(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(&hash[2..])
+    }
+
+    /// Version files can be hard linked into forks, so they are replaced with a rename instead of
+    /// written in place, which would change the contents for every repository sharing the inode
+    fn replace_version(
+        version_path: &Path,
+        write: impl FnOnce(&Path) -> Result<(), OxenError>,
+    ) -> Result<(), OxenError> {
+        LocalVersionStore::create_parent_dir(version_path)?;
+        let tmp_path = version_path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        if let Err(err) = write(&tmp_path) {
+            if tmp_path.exists() {
+                fs::remove_file(&tmp_path)?;
+            }
+            return Err(err);
+        }
+        fs::rename(&tmp_path, version_path)?;
+        Ok(())
+    }
+
+    fn create_parent_dir(path: &Path) -> Result<(), OxenError> {
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VersionStore for LocalVersionStore {
+    fn init(&self) -> Result<(), OxenError> {
+        if !self.root.exists() {
+            fs::create_dir_all(&self.root)?;
+        }
+        Ok(())
+    }
+
+    fn store_version_from_path(
+        &self,
+        hash: &str,
+        filename: &Path,
+        file_path: &Path,
+    ) -> Result<(), OxenError> {
+        let version_path = self.version_path(hash, filename);
+        if version_path == file_path {
+            return Ok(());
+        }
+        LocalVersionStore::replace_version(&version_path, |tmp_path| {
+            fs::copy(file_path, tmp_path)?;
+            Ok(())
+        })
+    }
+
+    fn store_version(&self, hash: &str, filename: &Path, data: &[u8]) -> Result<(), OxenError> {
+        let version_path = self.version_path(hash, filename);
+        LocalVersionStore::replace_version(&version_path, |tmp_path| {
+            let mut file = File::create(tmp_path)?;
+            file.write_all(data)?;
+            Ok(())
+        })
+    }
+
+    fn open_version(&self, hash: &str, filename: &Path) -> Result<Box<dyn Read + Send>, OxenError> {
+        let version_path = self.version_path(hash, filename);
+        if !version_path.exists() {
+            return Err(OxenError::file_does_not_exist(version_path));
+        }
+        Ok(Box::new(File::open(version_path)?))
+    }
+
+    fn local_version(&self, hash: &str, filename: &Path) -> Result<LocalVersion, OxenError> {
+        let version_path = self.version_path(hash, filename);
+        if !version_path.exists() {
+            return Err(OxenError::file_does_not_exist(version_path));
+        }
+        Ok(LocalVersion::stored(version_path))
+    }
+
+    fn get_version(&self, hash: &str, filename: &Path) -> Result<Vec<u8>, OxenError> {
+        let version_path = self.version_path(hash, filename);
+        if !version_path.exists() {
+            return Err(OxenError::file_does_not_exist(version_path));
+        }
+        Ok(fs::read(version_path)?)
+    }
+
+    fn get_version_chunk(
+        &self,
+        hash: &str,
+        filename: &Path,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, OxenError> {
+        let version_path = self.version_path(hash, filename);
+        let mut file = File::open(&version_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = vec![0u8; size as usize];
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn copy_version_to_path(
+        &self,
+        hash: &str,
+        filename: &Path,
+        dest_path: &Path,
+    ) -> Result<(), OxenError> {
+        let version_path = self.version_path(hash, filename);
+        LocalVersionStore::create_parent_dir(dest_path)?;
+        fs::copy(version_path, dest_path)?;
+        Ok(())
+    }
+
+    fn version_exists(&self, hash: &str, filename: &Path) -> Result<bool, OxenError> {
+        Ok(self.version_path(hash, filename).exists())
+    }
+
+    fn version_size(&self, hash: &str, filename: &Path) -> Result<u64, OxenError> {
+        let version_path = self.version_path(hash, filename);
+        if !version_path.exists() {
+            return Err(OxenError::file_does_not_exist(version_path));
+        }
+        Ok(fs::metadata(version_path)?.len())
+    }
+
+    fn hash_size(&self, hash: &str) -> Result<u64, OxenError> {
+        Ok(util::fs::dir_size(&self.version_dir(hash)))
+    }
+
+    fn delete_version(&self, hash: &str) -> Result<(), OxenError> {
+        let version_dir = self.version_dir(hash);
+        if version_dir.exists() {
+            fs::remove_dir_all(version_dir)?;
+        }
+        Ok(())
+    }
+
+    fn list_versions(&self) -> Result<Vec<String>, OxenError> {
+        let mut hashes: Vec<String> = vec![];
+        if !self.root.exists() {
+            return Ok(hashes);
+        }
+
+        for top_dir in fs::read_dir(&self.root)? {
+            let top_dir = top_dir?;
+            if !top_dir.file_type()?.is_dir() {
+                continue;
+            }
+            let prefix = top_dir.file_name().to_string_lossy().to_string();
+            for sub_dir in fs::read_dir(top_dir.path())? {
+                let sub_dir = sub_dir?;
+                if sub_dir.file_type()?.is_dir() {
+                    let suffix = sub_dir.file_name().to_string_lossy().to_string();
+                    hashes.push(format!("{prefix}{suffix}"));
+                }
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::storage::{LocalVersionStore, VersionStore};
+    use crate::test;
+
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_local_version_store_put_get_list_delete() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let store = LocalVersionStore::new(&repo.path);
+            store.init()?;
+
+            let hash = "59E029D4812AEBF0";
+            let filename = Path::new("1234.txt");
+            store.store_version(hash, filename, b"hello world")?;
+
+            assert!(store.version_exists(hash, filename)?);
+            assert_eq!(store.version_size(hash, filename)?, 11);
+            assert_eq!(store.hash_size(hash)?, 11);
+            assert_eq!(store.get_version(hash, filename)?, b"hello world");
+            assert_eq!(store.get_version_chunk(hash, filename, 6, 5)?, b"world");
+            assert_eq!(store.list_versions()?, vec![String::from(hash)]);
+
+            let mut contents = String::new();
+            store
+                .open_version(hash, filename)?
+                .read_to_string(&mut contents)?;
+            assert_eq!(contents, "hello world");
+            assert_eq!(
+                store.local_version(hash, filename)?.path(),
+                store.version_path(hash, filename)
+            );
+
+            let restored = repo.path.join("restored.txt");
+            store.copy_version_to_path(hash, filename, &restored)?;
+            assert_eq!(std::fs::read(&restored)?, b"hello world");
+
+            store.delete_version(hash)?;
+            assert!(!store.version_exists(hash, filename)?);
+            assert!(store.list_versions()?.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_local_version_store_does_not_write_through_hard_links() -> Result<(), OxenError> {
+        test::run_empty_local_repo_test(|repo| {
+            let store = LocalVersionStore::new(&repo.path);
+            store.init()?;
+
+            let hash = "59E029D4812AEBF0";
+            let filename = Path::new("1234.txt");
+            store.store_version(hash, filename, b"hello world")?;
+            let linked = repo.path.join("linked.txt");
+            std::fs::hard_link(store.version_path(hash, filename), &linked)?;
+
+            store.store_version(hash, filename, b"goodbye world")?;
+            assert_eq!(store.get_version(hash, filename)?, b"goodbye world");
+            assert_eq!(std::fs::read(&linked)?, b"hello world");
+
+            let src = repo.path.join("src.txt");
+            std::fs::write(&src, b"hello again")?;
+            store.store_version_from_path(hash, filename, &src)?;
+            assert_eq!(store.get_version(hash, filename)?, b"hello again");
+            assert_eq!(std::fs::read(&linked)?, b"hello world");
+            assert_eq!(store.list_versions()?, vec![String::from(hash)]);
+
+            Ok(())
+        })
+    }
+}
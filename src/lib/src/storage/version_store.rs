@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{FILES_DIR, VERSIONS_DIR};
+use crate::error::OxenError;
+use crate::model::LocalRepository;
+use crate::storage::{LocalVersionStore, S3Config, S3VersionStore};
+
+pub const LOCAL_STORAGE_TYPE: &str = "local";
+pub const S3_STORAGE_TYPE: &str = "s3";
+
+/// Where the version files for a repository live, saved in the `[storage]` section of .oxen/config.toml
+///
+/// ```toml
+/// [storage]
+/// type = "s3"
+///
+/// [storage.s3]
+/// bucket = "oxen-versions"
+/// endpoint = "http://localhost:9000"
+/// region = "us-east-1"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StorageConfig {
+    #[serde(rename = "type")]
+    pub storage_type: String,
+    pub s3: Option<S3Config>,
+}
+
+impl StorageConfig {
+    pub fn local() -> StorageConfig {
+        StorageConfig {
+            storage_type: String::from(LOCAL_STORAGE_TYPE),
+            s3: None,
+        }
+    }
+
+    pub fn s3(config: S3Config) -> StorageConfig {
+        StorageConfig {
+            storage_type: String::from(S3_STORAGE_TYPE),
+            s3: Some(config),
+        }
+    }
+
+    /// Whether version files are kept on the local disk in .oxen/versions
+    pub fn is_local(&self) -> bool {
+        self.storage_type == LOCAL_STORAGE_TYPE
+    }
+
+    /// The storage for one repository when this is shared by many, like the default storage of a
+    /// server. Every repository gets its own prefix in the bucket so that garbage collecting one
+    /// never deletes the versions of another.
+    pub fn for_repo(&self, namespace: &str, name: &str) -> StorageConfig {
+        let s3 = self.s3.as_ref().map(|s3| {
+            let repo_prefix = format!("{namespace}/{name}");
+            let prefix = match &s3.prefix {
+                Some(prefix) if !prefix.is_empty() => {
+                    format!("{}/{}", prefix.trim_end_matches('/'), repo_prefix)
+                }
+                _ => repo_prefix,
+            };
+            S3Config {
+                prefix: Some(prefix),
+                ..s3.clone()
+            }
+        });
+        StorageConfig {
+            storage_type: self.storage_type.to_owned(),
+            s3,
+        }
+    }
+}
+
+/// A version file on the local disk, for readers that need a path instead of a stream like polars.
+/// Versions that are not kept on the local disk are downloaded to a temp file, which is removed
+/// when this is dropped.
+#[derive(Debug)]
+pub struct LocalVersion {
+    path: PathBuf,
+    temp_dir: Option<PathBuf>,
+}
+
+impl LocalVersion {
+    /// A version that already lives at `path`
+    pub fn stored(path: PathBuf) -> LocalVersion {
+        LocalVersion {
+            path,
+            temp_dir: None,
+        }
+    }
+
+    /// Downloads the version into a new temp dir, keeping the filename so the extension is kept
+    pub fn download(
+        store: &dyn VersionStore,
+        hash: &str,
+        filename: &Path,
+    ) -> Result<LocalVersion, OxenError> {
+        let temp_dir = std::env::temp_dir().join(format!("oxen-{}", uuid::Uuid::new_v4()));
+        let path = temp_dir.join(filename);
+        let version = LocalVersion {
+            path,
+            temp_dir: Some(temp_dir),
+        };
+        store.copy_version_to_path(hash, filename, &version.path)?;
+        Ok(version)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for LocalVersion {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LocalVersion {
+    fn drop(&mut self) {
+        if let Some(temp_dir) = &self.temp_dir {
+            if let Err(err) = std::fs::remove_dir_all(temp_dir) {
+                log::warn!(
+                    "Could not remove downloaded version {:?}: {}",
+                    temp_dir,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Content addressed storage for the versions of files in a repository.
+///
+/// Versions are keyed by the hash of the file contents, and the filename we gave the version
+/// when it was committed (COMMIT_ID.ext), which mirrors the .oxen/versions/files/HASH[..2]/HASH[2..]/ layout.
+pub trait VersionStore: Send + Sync {
+    /// Make sure the underlying storage exists and is ready to write to
+    fn init(&self) -> Result<(), OxenError>;
+
+    /// Copy a file from the local disk into the store
+    fn store_version_from_path(
+        &self,
+        hash: &str,
+        filename: &Path,
+        file_path: &Path,
+    ) -> Result<(), OxenError>;
+
+    /// Write a buffer of bytes into the store
+    fn store_version(&self, hash: &str, filename: &Path, data: &[u8]) -> Result<(), OxenError>;
+
+    /// Read a version as a stream, so large files never have to fit in memory
+    fn open_version(&self, hash: &str, filename: &Path) -> Result<Box<dyn Read + Send>, OxenError>;
+
+    /// A path to the version on the local disk, downloading it first if the store is remote
+    fn local_version(&self, hash: &str, filename: &Path) -> Result<LocalVersion, OxenError>;
+
+    /// Read the full contents of a version, only meant for small files
+    fn get_version(&self, hash: &str, filename: &Path) -> Result<Vec<u8>, OxenError>;
+
+    /// Read `size` bytes starting at `offset`, so that large files can be streamed in chunks
+    fn get_version_chunk(
+        &self,
+        hash: &str,
+        filename: &Path,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, OxenError>;
+
+    /// Stream a version out to a path on local disk, for restoring into the working directory
+    fn copy_version_to_path(
+        &self,
+        hash: &str,
+        filename: &Path,
+        dest_path: &Path,
+    ) -> Result<(), OxenError>;
+
+    fn version_exists(&self, hash: &str, filename: &Path) -> Result<bool, OxenError>;
+
+    fn version_size(&self, hash: &str, filename: &Path) -> Result<u64, OxenError>;
+
+    /// Total bytes stored across every version of a hash
+    fn hash_size(&self, hash: &str) -> Result<u64, OxenError>;
+
+    /// Remove every file that has been stored for a hash
+    fn delete_version(&self, hash: &str) -> Result<(), OxenError>;
+
+    /// List all the hashes that have at least one version stored
+    fn list_versions(&self) -> Result<Vec<String>, OxenError>;
+}
+
+/// Returns the version store configured for the repository, defaulting to the local .oxen/versions dir
+pub fn create_version_store(repo: &LocalRepository) -> Result<Box<dyn VersionStore>, OxenError> {
+    let config = repo.storage.clone().unwrap_or_else(StorageConfig::local);
+    match config.storage_type.as_str() {
+        LOCAL_STORAGE_TYPE => Ok(Box::new(LocalVersionStore::new(&repo.path))),
+        S3_STORAGE_TYPE => match config.s3 {
+            Some(s3_config) => Ok(Box::new(S3VersionStore::new(s3_config))),
+            None => Err(OxenError::basic_str(
+                "Storage type is s3 but no [storage.s3] config was found",
+            )),
+        },
+        storage_type => {
+            let err = format!("Unknown storage type: {storage_type}");
+            Err(OxenError::basic_str(err))
+        }
+    }
+}
+
+/// Relative key for a version within a store: HASH[..2]/HASH[2..]/filename
+pub fn version_key(hash: &str, filename: &Path) -> PathBuf {
+    let topdir = &hash[..2];
+    let subdir = &hash[2..];
+    Path::new(topdir).join(subdir).join(filename)
+}
+
+/// Parses a path relative to the .oxen dir, like versions/files/59/E029D4812AEBF0/COMMIT_ID.txt,
+/// into the hash and filename of the version
+pub fn hash_and_filename_from_version_path(path: &Path) -> Option<(String, PathBuf)> {
+    let rest = path
+        .strip_prefix(Path::new(VERSIONS_DIR).join(FILES_DIR))
+        .ok()?;
+    let components: Vec<&str> = rest.iter().filter_map(|c| c.to_str()).collect();
+    if components.len() != 3 {
+        return None;
+    }
+    let hash = format!("{}{}", components[0], components[1]);
+    Some((hash, PathBuf::from(components[2])))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::storage::version_store;
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_hash_and_filename_from_version_path() -> Result<(), OxenError> {
+        let path = Path::new("versions/files/59/E029D4812AEBF0/1234.txt");
+        let result = version_store::hash_and_filename_from_version_path(path);
+        assert_eq!(
+            result,
+            Some((String::from("59E029D4812AEBF0"), PathBuf::from("1234.txt")))
+        );
+
+        let path = Path::new("history/1234/dirs");
+        assert!(version_store::hash_and_filename_from_version_path(path).is_none());
+
+        Ok(())
+    }
+}
@@ -499,6 +499,10 @@ pub fn repo_cfg_file() -> &'static Path {
     Path::new("data/test/config/repo_config.toml")
 }
 
+pub fn signing_key_file() -> &'static Path {
+    Path::new("data/test/config/signing_key")
+}
+
 pub fn test_jpeg_file() -> &'static Path {
     Path::new("data/test/images/dwight_vince.jpeg")
 }
@@ -17,6 +17,8 @@ use crate::constants::HISTORY_DIR;
 use crate::error::OxenError;
 use crate::model::Commit;
 use crate::model::{CommitEntry, LocalRepository};
+use crate::storage::version_store;
+use crate::storage::LocalVersion;
 
 pub fn oxen_hidden_dir(repo_path: &Path) -> PathBuf {
     PathBuf::from(&repo_path).join(Path::new(constants::OXEN_HIDDEN_DIR))
@@ -37,20 +39,21 @@ pub fn commit_content_is_valid_path(repo: &LocalRepository, commit: &Commit) ->
         .join(CONTENT_IS_VALID)
 }
 
-pub fn version_path_for_commit_id(
+/// The version of the file at the commit on the local disk, preferring the cached arrow file for
+/// tabular data
+pub fn local_version_for_commit_id(
     repo: &LocalRepository,
     commit_id: &str,
     filepath: &Path,
-) -> Result<PathBuf, OxenError> {
+) -> Result<LocalVersion, OxenError> {
     match api::local::commits::get_by_id(repo, commit_id)? {
         Some(commit) => match api::local::entries::get_entry_for_commit(repo, &commit, filepath)? {
             Some(entry) => {
-                let path = version_path(repo, &entry);
-                let arrow_path = path.parent().unwrap().join(DATA_ARROW_FILE);
+                let arrow_path = df_version_path(repo, &entry);
                 if arrow_path.exists() {
-                    Ok(arrow_path)
+                    Ok(LocalVersion::stored(arrow_path))
                 } else {
-                    Ok(path)
+                    local_version(repo, &entry)
                 }
             }
             None => Err(OxenError::file_does_not_exist(filepath)),
@@ -60,31 +63,43 @@ pub fn version_path_for_commit_id(
 }
 
 pub fn version_file_size(repo: &LocalRepository, entry: &CommitEntry) -> Result<u64, OxenError> {
-    let version_path = version_path(repo, entry);
-    // if is_tabular(&version_path) {
-    //     let data_file = version_path.parent().unwrap().join(DATA_ARROW_FILE);
-    //     if !data_file.exists() {
-    //         // just for unit tests to pass for now, we only really call file size from the server
-    //         // on the client we don't have the data.arrow file and would have to compute size on the fly
-    //         // but a warning for now should be good
-    //         log::warn!("TODO: compute size of data file: {:?}", data_file);
-    //         return Ok(0);
-    //     }
-    //     let meta = std::fs::metadata(&data_file)?;
-    //     Ok(meta.len())
-    // } else {
-    if !version_path.exists() {
-        return Err(OxenError::file_does_not_exist(version_path));
-    }
-    let meta = std::fs::metadata(&version_path)?;
-    Ok(meta.len())
-    // }
+    repo.version_store()?
+        .version_size(&entry.hash, &entry.filename())
 }
 
+/// Where the version of the entry lives in the local .oxen/versions dir. Read versions through
+/// `local_version` or `open_version` instead, which also work when the versions are stored remotely.
 pub fn version_path(repo: &LocalRepository, entry: &CommitEntry) -> PathBuf {
     version_path_from_hash_and_file(repo, entry.hash.clone(), entry.filename())
 }
 
+/// The name of the entry's version relative to the .oxen dir, like versions/files/59/E029D4812AEBF0/COMMIT_ID.txt,
+/// which is how versions are named when they are sent to and from a remote
+pub fn version_name(entry: &CommitEntry) -> PathBuf {
+    Path::new(constants::VERSIONS_DIR)
+        .join(constants::FILES_DIR)
+        .join(version_store::version_key(&entry.hash, &entry.filename()))
+}
+
+/// The version of the entry as a file on the local disk, downloaded from the repo's version store
+/// if the versions are not kept locally
+pub fn local_version(
+    repo: &LocalRepository,
+    entry: &CommitEntry,
+) -> Result<LocalVersion, OxenError> {
+    repo.version_store()?
+        .local_version(&entry.hash, &entry.filename())
+}
+
+/// Streams the version of the entry from the repo's version store
+pub fn open_version(
+    repo: &LocalRepository,
+    entry: &CommitEntry,
+) -> Result<Box<dyn Read + Send>, OxenError> {
+    repo.version_store()?
+        .open_version(&entry.hash, &entry.filename())
+}
+
 pub fn df_version_path(repo: &LocalRepository, entry: &CommitEntry) -> PathBuf {
     let version_dir = version_dir_from_hash(repo, entry.hash.clone());
     version_dir.join(DATA_ARROW_FILE)
@@ -286,6 +301,15 @@ pub fn rlist_files_in_dir(dir: &Path) -> Vec<PathBuf> {
     files
 }
 
+/// Total size in bytes of all the files within a directory, recursively
+pub fn dir_size(dir: &Path) -> u64 {
+    rlist_files_in_dir(dir)
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
 /// Recursively tries to traverse up for an .oxen directory, returns None if not found
 pub fn get_repo_root(path: &Path) -> Option<PathBuf> {
     if path.join(".oxen").exists() {
@@ -313,6 +337,31 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<
     Ok(())
 }
 
+/// Hard links every file in `src` into `dst` that is not already there, copying instead when
+/// the file cannot be linked (for example if `dst` is on another device). Returns the number of new files.
+pub fn link_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<usize> {
+    fs::create_dir_all(&dst)?;
+    let mut num_files = 0;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.as_ref().join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            num_files += link_dir_all(entry.path(), dst_path)?;
+        } else if !dst_path.exists() {
+            if let Err(err) = fs::hard_link(entry.path(), &dst_path) {
+                log::warn!(
+                    "Could not link {:?}, copying instead: {}",
+                    entry.path(),
+                    err
+                );
+                fs::copy(entry.path(), &dst_path)?;
+            }
+            num_files += 1;
+        }
+    }
+    Ok(num_files)
+}
+
 pub fn is_tabular(path: &Path) -> bool {
     let exts: HashSet<String> = vec!["csv", "tsv", "parquet", "arrow", "ndjson", "jsonl"]
         .into_iter()
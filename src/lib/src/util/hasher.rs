@@ -1,11 +1,17 @@
 use crate::error::OxenError;
 use crate::model::{ContentHashable, NewCommit};
 
+use memmap2::Mmap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::path::Path;
-use xxhash_rust::xxh3::xxh3_128;
+use xxhash_rust::xxh3::{xxh3_128, Xxh3};
+
+/// Files at least this many bytes are streamed, or memory mapped if they are version files
+pub const MMAP_HASH_THRESHOLD: u64 = 1024 * 1024;
+/// How much of a stream is read at a time when hashing it
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
 
 pub fn hash_buffer(buffer: &[u8]) -> String {
     let val = xxh3_128(buffer);
@@ -43,32 +49,41 @@ where
     format!("{val:x}")
 }
 
-pub fn hash_file_contents(path: &Path) -> Result<String, OxenError> {
-    match File::open(path) {
-        Ok(file) => {
-            let mut reader = BufReader::new(file);
-            let mut buffer = Vec::new();
-            match reader.read_to_end(&mut buffer) {
-                Ok(_) => {
-                    let result = hash_buffer(&buffer);
-                    Ok(result)
-                }
-                Err(_) => {
-                    eprintln!("Could not read file to end {path:?}");
-                    Err(OxenError::basic_str("Could not read file to end"))
-                }
-            }
-        }
-        Err(_) => {
-            let err = format!("util::hasher::hash_file_contents Could not open file {path:?}");
-            Err(OxenError::basic_str(err))
+/// Hashes a stream a buffer at a time, giving the same hash as `hash_file_contents` without
+/// holding the whole contents in memory
+pub fn hash_reader<R: Read>(reader: R) -> Result<String, OxenError> {
+    let val = hash_reader_128bit(reader)?;
+    Ok(format!("{val:x}"))
+}
+
+fn hash_reader_128bit<R: Read>(mut reader: R) -> Result<u128, OxenError> {
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    loop {
+        let num_read = reader.read(&mut buffer)?;
+        if num_read == 0 {
+            break;
         }
+        hasher.update(&buffer[..num_read]);
     }
+    Ok(hasher.digest128())
+}
+
+pub fn hash_file_contents(path: &Path) -> Result<String, OxenError> {
+    let val = hash_file_contents_128bit(path)?;
+    Ok(format!("{val:x}"))
 }
 
 pub fn hash_file_contents_128bit(path: &Path) -> Result<u128, OxenError> {
     match File::open(path) {
         Ok(file) => {
+            // Stream large files through the hasher instead of copying them into a buffer, so
+            // that hashing many of them in parallel does not blow up memory. These can be files
+            // in the working dir that other processes write to, so they are never memory mapped.
+            if file.metadata()?.len() >= MMAP_HASH_THRESHOLD {
+                return hash_reader_128bit(BufReader::new(file));
+            }
+
             let mut reader = BufReader::new(file);
             let mut buffer = Vec::new();
             match reader.read_to_end(&mut buffer) {
@@ -88,3 +103,46 @@ pub fn hash_file_contents_128bit(path: &Path) -> Result<u128, OxenError> {
         }
     }
 }
+
+/// Hashes a file in .oxen/versions by memory mapping it if it is large. Only for version files
+/// this process wrote and nothing else modifies: if a mapped file is truncated while it is being
+/// hashed, reading past the new end raises SIGBUS and kills the process.
+pub fn hash_version_file(path: &Path) -> Result<String, OxenError> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() < MMAP_HASH_THRESHOLD {
+        return hash_file_contents(path);
+    }
+    // Safety: see above, the version file is owned by this process and is not truncated
+    let mmap = unsafe { Mmap::map(&file)? };
+    Ok(hash_buffer(&mmap))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::OxenError;
+    use crate::test;
+    use crate::util::hasher;
+
+    #[test]
+    fn test_hash_reader_matches_hash_buffer() -> Result<(), OxenError> {
+        // Larger than the read buffer so the stream is hashed in several updates
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        assert_eq!(hasher::hash_reader(&data[..])?, hasher::hash_buffer(&data));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_large_file_streamed_and_mapped() -> Result<(), OxenError> {
+        test::run_empty_dir_test(|dir| {
+            let size = hasher::MMAP_HASH_THRESHOLD as usize + 1024;
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let path = dir.join("large.bin");
+            std::fs::write(&path, &data)?;
+
+            let expected = hasher::hash_buffer(&data);
+            assert_eq!(hasher::hash_file_contents(&path)?, expected);
+            assert_eq!(hasher::hash_version_file(&path)?, expected);
+            Ok(())
+        })
+    }
+}
@@ -5,3 +5,48 @@ pub fn split_and_trim(input: &str, delimiter: &str) -> Vec<String> {
         .map(String::from)
         .collect::<Vec<String>>()
 }
+
+/// Matches the input against a pattern where `*` matches any sequence of characters
+pub fn wildcard_match(pattern: &str, input: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let input: Vec<char> = input.chars().collect();
+
+    // Greedy match that backtracks to the last `*` on a mismatch
+    let (mut p, mut i) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while i < input.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == input[i] {
+            p += 1;
+            i += 1;
+        } else if let Some((star_p, star_i)) = last_star {
+            p = star_p + 1;
+            i = star_i + 1;
+            last_star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::str::wildcard_match;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("annotations/*.csv", "annotations/train.csv"));
+        assert!(wildcard_match("annotations/*.csv", "annotations/a/b.csv"));
+        assert!(!wildcard_match(
+            "annotations/*.csv",
+            "annotations/train.tsv"
+        ));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("data.csv", "data.csv"));
+        assert!(!wildcard_match("data.csv", "other/data.csv"));
+        assert!(wildcard_match("*train*", "images/train/cat.jpg"));
+    }
+}
@@ -4,21 +4,30 @@ pub mod entry;
 pub mod entry_meta_data;
 pub mod http;
 pub mod json_data_frame;
+pub mod merge_request;
 pub mod namespace;
+pub mod note;
 pub mod oxen_response;
 pub mod repository;
 pub mod schema;
+pub mod search;
 pub mod status_message;
 pub mod version;
 
-pub use crate::view::status_message::{IsValidStatusMessage, StatusMessage};
+pub use crate::view::status_message::{IsValidStatusMessage, LimitExceededResponse, StatusMessage};
 
 pub use crate::view::json_data_frame::{JsonDataFrame, JsonDataFrameSliceResponse};
 pub use crate::view::namespace::{ListNamespacesResponse, NamespaceResponse, NamespaceView};
 pub use crate::view::schema::{ListSchemaResponse, SchemaResponse};
+pub use crate::view::search::PaginatedSearchMatches;
 
 pub use crate::view::repository::{
-    ListRepositoryResponse, RepositoryResolveResponse, RepositoryResponse, RepositoryView,
+    ListRepositoryResponse, RepositoryFork, RepositoryResolveResponse, RepositoryResponse,
+    RepositoryView,
+};
+
+pub use crate::view::merge_request::{
+    ListMergeRequestsResponse, MergeRequestNew, MergeRequestResponse,
 };
 
 pub use crate::view::entry::{
@@ -30,6 +39,8 @@ pub use crate::view::commit::{
     PaginatedCommits,
 };
 
+pub use crate::view::note::{ListNotesResponse, NotesUpdate};
+
 pub use crate::view::branch::{BranchNew, BranchResponse, BranchUpdate, ListBranchesResponse};
 
 pub use crate::view::entry_meta_data::EntryMetaDataResponse;
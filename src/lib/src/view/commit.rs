@@ -1,4 +1,4 @@
-use crate::model::{Commit, CommitStats};
+use crate::model::{Commit, CommitStats, Note};
 use crate::view::http::{MSG_RESOURCE_FOUND, STATUS_SUCCESS};
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +7,8 @@ pub struct CommitResponse {
     pub status: String,
     pub status_message: String,
     pub commit: Commit,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<Note>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -28,14 +30,22 @@ pub struct ListCommitResponse {
     pub status: String,
     pub status_message: String,
     pub commits: Vec<Commit>,
+    /// The notes on the listed commits, for the commits that have one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
 }
 
 impl ListCommitResponse {
     pub fn success(commits: Vec<Commit>) -> ListCommitResponse {
+        ListCommitResponse::success_with_notes(commits, vec![])
+    }
+
+    pub fn success_with_notes(commits: Vec<Commit>, notes: Vec<Note>) -> ListCommitResponse {
         ListCommitResponse {
             status: String::from(STATUS_SUCCESS),
             status_message: String::from(MSG_RESOURCE_FOUND),
             commits,
+            notes,
         }
     }
 }
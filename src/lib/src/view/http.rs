@@ -1,3 +1,6 @@
+/// Header the client tags its requests with, so they can be found in the server logs
+pub const REQUEST_ID_HEADER: &str = "X-Oxen-Request-Id";
+
 pub const STATUS_ERROR: &str = "error";
 pub const STATUS_SUCCESS: &str = "success";
 pub const STATUS_WARNING: &str = "warning";
@@ -11,3 +14,6 @@ pub const MSG_RESOURCE_IS_PROCESSING: &str = "resource_is_processing";
 pub const MSG_FAILED_PROCESS: &str = "failed_process";
 pub const MSG_INTERNAL_SERVER_ERROR: &str = "internal_server_error";
 pub const MSG_NOT_IMPLEMENTED: &str = "not_implemented";
+pub const MSG_QUOTA_EXCEEDED: &str = "quota_exceeded";
+pub const MSG_UPLOAD_TOO_LARGE: &str = "upload_too_large";
+pub const MSG_RATE_LIMITED: &str = "rate_limited";
@@ -0,0 +1,25 @@
+use crate::model::MergeRequest;
+use serde::{Deserialize, Serialize};
+
+/// Opens a merge of `branch` of the fork `source_namespace/source_name` into `target_branch` of its upstream
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MergeRequestNew {
+    pub source_namespace: String,
+    pub source_name: String,
+    pub branch: String,
+    pub target_branch: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MergeRequestResponse {
+    pub status: String,
+    pub status_message: String,
+    pub merge_request: MergeRequest,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListMergeRequestsResponse {
+    pub status: String,
+    pub status_message: String,
+    pub merge_requests: Vec<MergeRequest>,
+}
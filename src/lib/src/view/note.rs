@@ -0,0 +1,25 @@
+use crate::model::Note;
+use crate::view::http::{MSG_RESOURCE_FOUND, STATUS_SUCCESS};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NotesUpdate {
+    pub notes: Vec<Note>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListNotesResponse {
+    pub status: String,
+    pub status_message: String,
+    pub notes: Vec<Note>,
+}
+
+impl ListNotesResponse {
+    pub fn success(notes: Vec<Note>) -> ListNotesResponse {
+        ListNotesResponse {
+            status: String::from(STATUS_SUCCESS),
+            status_message: String::from(MSG_RESOURCE_FOUND),
+            notes,
+        }
+    }
+}
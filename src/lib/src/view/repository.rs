@@ -1,4 +1,4 @@
-use crate::model::RemoteRepository;
+use crate::model::{ForkOrigin, RemoteRepository};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -6,6 +6,15 @@ pub struct RepositoryView {
     pub namespace: String,
     pub name: String,
     // pub api_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_of: Option<ForkOrigin>,
+}
+
+/// Where to create a fork of a repository
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepositoryFork {
+    pub namespace: String,
+    pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,6 +63,7 @@ impl RepositoryView {
         RepositoryView {
             namespace: repository.namespace.clone(),
             name: repository.name,
+            fork_of: None,
         }
     }
 }
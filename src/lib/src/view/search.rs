@@ -0,0 +1,18 @@
+use crate::model::SearchMatch;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PaginatedSearchMatches {
+    pub status: String,
+    pub status_message: String,
+    pub commit_id: String,
+    pub matches: Vec<SearchMatch>,
+    pub page_size: usize,
+    pub page_number: usize,
+    pub total_pages: usize,
+    pub total_entries: usize,
+    /// The search stopped before it found every match, so the totals are lower bounds that only
+    /// count the matches up to the end of this page, or up to `MAX_SEARCH_MATCHES`
+    #[serde(default)]
+    pub truncated: bool,
+}
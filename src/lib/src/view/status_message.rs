@@ -16,6 +16,31 @@ pub struct IsValidStatusMessage {
     pub is_valid: bool,
 }
 
+/// Sent with a 413 or 429 when a request goes over one of the server limits, `status_message`
+/// is `quota_exceeded`, `upload_too_large` or `rate_limited` so clients can tell them apart
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LimitExceededResponse {
+    pub status: String,
+    pub status_message: String,
+    pub status_description: String,
+    pub limit: Option<u64>,
+    pub used: Option<u64>,
+    pub retry_after_secs: Option<u64>,
+}
+
+impl LimitExceededResponse {
+    pub fn new(reason: &str, description: &str) -> LimitExceededResponse {
+        LimitExceededResponse {
+            status: String::from(view::http::STATUS_ERROR),
+            status_message: String::from(reason),
+            status_description: String::from(description),
+            limit: None,
+            used: None,
+            retry_after_secs: None,
+        }
+    }
+}
+
 impl StatusMessage {
     pub fn success(msg: &str) -> StatusMessage {
         StatusMessage {
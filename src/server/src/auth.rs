@@ -1,2 +1,3 @@
 pub mod access_keys;
+pub mod permissions;
 pub mod validator;
@@ -4,24 +4,108 @@ use liboxen::util;
 
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use rand_core::OsRng;
-use rocksdb::{DBWithThreadMode, LogLevel, MultiThreaded, Options};
+use rocksdb::{DBWithThreadMode, IteratorMode, LogLevel, MultiThreaded, Options};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::str;
+use std::time::Duration;
+use time::OffsetDateTime;
 use x25519_dalek::{EphemeralSecret, PublicKey};
 
+use crate::auth::permissions::Role;
+
 pub const SECRET_KEY_FILENAME: &str = "SECRET_KEY_BASE";
+pub const KEYS_DIR: &str = "keys";
+pub const REVOKED_KEYS_DIR: &str = "revoked_keys";
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct JWTClaim {
     id: String,
     name: String,
     email: String,
+    /// Seconds since the epoch after which the token is no longer valid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    /// Read only tokens cannot push or modify repositories
+    #[serde(default, skip_serializing_if = "is_false")]
+    read_only: bool,
+    /// Restricts the token to a single `namespace/name` repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repo: Option<String>,
+    /// Server admins can reach the routes for the whole server, like `/metrics`
+    #[serde(default, skip_serializing_if = "is_false")]
+    admin: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !val
+}
+
+impl JWTClaim {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    pub fn expires_at(&self) -> Option<OffsetDateTime> {
+        self.exp
+            .and_then(|exp| OffsetDateTime::from_unix_timestamp(exp).ok())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.exp {
+            Some(exp) => OffsetDateTime::now_utc().unix_timestamp() >= exp,
+            None => false,
+        }
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn repo(&self) -> Option<&str> {
+        self.repo.as_deref()
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    /// True if the scopes of the token allow the role on the repository,
+    /// `repo` is None for requests that are not for a single repository, like creating one
+    pub fn allows(&self, repo: Option<(&str, &str)>, role: Role) -> bool {
+        if self.read_only && role > Role::Read {
+            return false;
+        }
+
+        match (&self.repo, repo) {
+            (Some(scope), Some((namespace, name))) => *scope == format!("{namespace}/{name}"),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+}
+
+/// Optional restrictions on a new token
+#[derive(Debug, Clone, Default)]
+pub struct TokenOpts {
+    pub expires_in: Option<Duration>,
+    pub read_only: bool,
+    pub repo: Option<String>,
+    pub admin: bool,
 }
 
 pub struct AccessKeyManager {
     sync_dir: PathBuf,
     db: DBWithThreadMode<MultiThreaded>,
+    revoked_db: DBWithThreadMode<MultiThreaded>,
 }
 
 impl AccessKeyManager {
@@ -46,38 +130,63 @@ impl AccessKeyManager {
             std::fs::create_dir_all(&hidden_dir)?;
         }
 
-        let db_dir = hidden_dir.join("keys");
+        let db_dir = hidden_dir.join(KEYS_DIR);
+        let revoked_db_dir = hidden_dir.join(REVOKED_KEYS_DIR);
         let mut opts = Options::default();
         opts.set_log_level(LogLevel::Fatal);
         opts.create_if_missing(true);
 
         let secret_file = AccessKeyManager::secret_key_path(sync_dir);
         if !secret_file.exists() {
-            // Not really using this in the right context...but fine to generate random hash for now
-            let secret = EphemeralSecret::new(OsRng);
-            let public = PublicKey::from(&secret);
-            let key = hex::encode(public.as_bytes());
+            let key = AccessKeyManager::generate_secret();
             log::debug!("Got secret key: {}", key);
             util::fs::write_to_path(&secret_file, &key)?;
         }
 
-        let db = if read_only {
-            DBWithThreadMode::open_for_read_only(&opts, &db_dir, false)?
+        if read_only && !revoked_db_dir.exists() {
+            // Servers that ran before tokens could be revoked will not have the db yet
+            DBWithThreadMode::<MultiThreaded>::open(&opts, &revoked_db_dir)?;
+        }
+
+        let (db, revoked_db) = if read_only {
+            (
+                DBWithThreadMode::open_for_read_only(&opts, &db_dir, false)?,
+                DBWithThreadMode::open_for_read_only(&opts, &revoked_db_dir, false)?,
+            )
         } else {
-            DBWithThreadMode::open(&opts, &db_dir)?
+            (
+                DBWithThreadMode::open(&opts, &db_dir)?,
+                DBWithThreadMode::open(&opts, &revoked_db_dir)?,
+            )
         };
 
         Ok(AccessKeyManager {
             sync_dir: sync_dir.to_path_buf(),
             db,
+            revoked_db,
         })
     }
 
     pub fn create(&self, user: &User) -> Result<(User, String), OxenError> {
+        self.create_with_opts(user, &TokenOpts::default())
+    }
+
+    pub fn create_with_opts(
+        &self,
+        user: &User,
+        opts: &TokenOpts,
+    ) -> Result<(User, String), OxenError> {
+        let exp = opts.expires_in.map(|expires_in| {
+            OffsetDateTime::now_utc().unix_timestamp() + expires_in.as_secs() as i64
+        });
         let user_claims = JWTClaim {
             id: format!("{}", uuid::Uuid::new_v4()),
             name: user.name.to_owned(),
             email: user.email.to_owned(),
+            exp,
+            read_only: opts.read_only,
+            repo: opts.repo.to_owned(),
+            admin: opts.admin,
         };
 
         let secret_key = self.read_secret_key()?;
@@ -125,9 +234,72 @@ impl AccessKeyManager {
         }
     }
 
+    /// Every token that was issued, and the claim it maps to
+    pub fn list_claims(&self) -> Result<Vec<(String, JWTClaim)>, OxenError> {
+        let mut claims: Vec<(String, JWTClaim)> = vec![];
+        for (key, value) in self.db.iterator(IteratorMode::Start) {
+            let token = str::from_utf8(&key)?.to_string();
+            let claim: JWTClaim = serde_json::from_str(str::from_utf8(&value)?)?;
+            claims.push((token, claim));
+        }
+        Ok(claims)
+    }
+
+    /// Revokes a token by its id or the full token, returns the revoked claim if it exists
+    pub fn revoke(&self, id_or_token: &str) -> Result<Option<JWTClaim>, OxenError> {
+        let claim = match self.get_claim(id_or_token)? {
+            Some(claim) => Some(claim),
+            None => self
+                .list_claims()?
+                .into_iter()
+                .map(|(_token, claim)| claim)
+                .find(|claim| claim.id == id_or_token),
+        };
+
+        if let Some(claim) = &claim {
+            let revoked_at = OffsetDateTime::now_utc().unix_timestamp().to_string();
+            self.revoked_db.put(&claim.id, revoked_at)?;
+        }
+        Ok(claim)
+    }
+
+    pub fn is_revoked(&self, claim: &JWTClaim) -> bool {
+        matches!(self.revoked_db.get(claim.id.as_bytes()), Ok(Some(_)))
+    }
+
+    /// Generates a new secret, which invalidates every token that was signed with the old one.
+    /// Returns the number of tokens that were removed.
+    pub fn rotate_secret(&self) -> Result<usize, OxenError> {
+        let claims = self.list_claims()?;
+        for (token, _claim) in claims.iter() {
+            self.db.delete(token)?;
+        }
+
+        let secret_file = AccessKeyManager::secret_key_path(&self.sync_dir);
+        util::fs::write_to_path(&secret_file, &AccessKeyManager::generate_secret())?;
+        Ok(claims.len())
+    }
+
+    fn generate_secret() -> String {
+        // Not really using this in the right context...but fine to generate random hash for now
+        let secret = EphemeralSecret::new(OsRng);
+        let public = PublicKey::from(&secret);
+        hex::encode(public.as_bytes())
+    }
+
     pub fn token_is_valid(&self, token: &str) -> bool {
         match self.get_claim(token) {
             Ok(Some(claim)) => {
+                if self.is_revoked(&claim) {
+                    log::info!("auth token has been revoked: {}", claim.id);
+                    return false;
+                }
+
+                if claim.is_expired() {
+                    log::info!("auth token has expired: {}", claim.id);
+                    return false;
+                }
+
                 let secret = self.read_secret_key();
                 if secret.is_err() {
                     return false;
@@ -164,10 +336,12 @@ impl AccessKeyManager {
 #[cfg(test)]
 mod tests {
 
-    use crate::auth::access_keys::AccessKeyManager;
+    use crate::auth::access_keys::{AccessKeyManager, TokenOpts};
+    use crate::auth::permissions::Role;
     use crate::test;
     use liboxen::error::OxenError;
     use liboxen::model::User;
+    use std::time::Duration;
 
     #[test]
     fn test_constructor() -> Result<(), OxenError> {
@@ -227,6 +401,125 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_expired_key_is_invalid() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let keygen = AccessKeyManager::new(sync_dir)?;
+            let new_user = User {
+                name: String::from("Ox"),
+                email: String::from("ox@oxen.ai"),
+            };
+            let opts = TokenOpts {
+                expires_in: Some(Duration::from_secs(0)),
+                ..TokenOpts::default()
+            };
+            let (_user, token) = keygen.create_with_opts(&new_user, &opts)?;
+            assert!(!keygen.token_is_valid(&token));
+
+            let opts = TokenOpts {
+                expires_in: Some(Duration::from_secs(60 * 60)),
+                ..TokenOpts::default()
+            };
+            let (_user, token) = keygen.create_with_opts(&new_user, &opts)?;
+            assert!(keygen.token_is_valid(&token));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_revoke_key() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let keygen = AccessKeyManager::new(sync_dir)?;
+            let new_user = User {
+                name: String::from("Ox"),
+                email: String::from("ox@oxen.ai"),
+            };
+            let (_user, token) = keygen.create(&new_user)?;
+            let (_user, other_token) = keygen.create(&new_user)?;
+            assert_eq!(keygen.list_claims()?.len(), 2);
+
+            // Revoke by id
+            let claim = keygen.get_claim(&token)?.unwrap();
+            let revoked = keygen.revoke(claim.id())?;
+            assert_eq!(revoked, Some(claim));
+            assert!(!keygen.token_is_valid(&token));
+            assert!(keygen.token_is_valid(&other_token));
+
+            // Revoke by token
+            assert!(keygen.revoke(&other_token)?.is_some());
+            assert!(!keygen.token_is_valid(&other_token));
+
+            assert!(keygen.revoke("not-a-token")?.is_none());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_rotate_secret_invalidates_keys() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let keygen = AccessKeyManager::new(sync_dir)?;
+            let new_user = User {
+                name: String::from("Ox"),
+                email: String::from("ox@oxen.ai"),
+            };
+            let (_user, token) = keygen.create(&new_user)?;
+            assert_eq!(keygen.rotate_secret()?, 1);
+            assert!(!keygen.token_is_valid(&token));
+            assert!(keygen.list_claims()?.is_empty());
+
+            let (_user, token) = keygen.create(&new_user)?;
+            assert!(keygen.token_is_valid(&token));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_scoped_key() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let keygen = AccessKeyManager::new(sync_dir)?;
+            let new_user = User {
+                name: String::from("CI"),
+                email: String::from("ci@oxen.ai"),
+            };
+            let opts = TokenOpts {
+                read_only: true,
+                repo: Some(String::from("ox/data")),
+                ..TokenOpts::default()
+            };
+            let (_user, token) = keygen.create_with_opts(&new_user, &opts)?;
+            assert!(keygen.token_is_valid(&token));
+
+            let claim = keygen.get_claim(&token)?.unwrap();
+            assert!(claim.allows(Some(("ox", "data")), Role::Read));
+            assert!(!claim.allows(Some(("ox", "data")), Role::Write));
+            assert!(!claim.allows(Some(("ox", "other")), Role::Read));
+            assert!(!claim.allows(None, Role::Write));
+            assert!(!claim.is_admin());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_admin_key() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let keygen = AccessKeyManager::new(sync_dir)?;
+            let new_user = User {
+                name: String::from("Ops"),
+                email: String::from("ops@oxen.ai"),
+            };
+            let opts = TokenOpts {
+                admin: true,
+                ..TokenOpts::default()
+            };
+            let (_user, token) = keygen.create_with_opts(&new_user, &opts)?;
+            assert!(keygen.get_claim(&token)?.unwrap().is_admin());
+
+            let (_user, token) = keygen.create(&new_user)?;
+            assert!(!keygen.get_claim(&token)?.unwrap().is_admin());
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_invalid_key() -> Result<(), OxenError> {
         test::run_empty_sync_dir_test(|sync_dir| {
@@ -0,0 +1,302 @@
+use liboxen::error::OxenError;
+use liboxen::util;
+
+use rocksdb::{DBWithThreadMode, LogLevel, MultiThreaded, Options};
+use serde::{de, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str;
+use std::str::FromStr;
+
+pub const PERMISSIONS_DIR: &str = "permissions";
+
+/// Roles a collaborator can have on a repository, each role includes the ones before it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Read,
+    Write,
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = OxenError;
+
+    fn from_str(role: &str) -> Result<Role, OxenError> {
+        match role.to_lowercase().as_str() {
+            "read" => Ok(Role::Read),
+            "write" => Ok(Role::Write),
+            "admin" => Ok(Role::Admin),
+            _ => Err(OxenError::basic_str(format!(
+                "Invalid role `{role}`, must be one of read, write, admin"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let role = match self {
+            Role::Read => "read",
+            Role::Write => "write",
+            Role::Admin => "admin",
+        };
+        write!(f, "{role}")
+    }
+}
+
+/// Owners of a namespace have admin access to every repository in it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NamespacePermissions {
+    pub owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoPermissions {
+    pub is_public: bool,
+    /// email -> role
+    pub collaborators: HashMap<String, Role>,
+}
+
+/// # PermissionsManager
+/// Keeps track of who can access which namespaces and repositories, users are identified
+/// by the email in their access token. Repositories are private by default, only the namespace
+/// owners and the collaborators on a repository can access it, and anyone can read a public one.
+pub struct PermissionsManager {
+    db: DBWithThreadMode<MultiThreaded>,
+}
+
+impl PermissionsManager {
+    pub fn new(sync_dir: &Path) -> Result<PermissionsManager, OxenError> {
+        let read_only = false;
+        PermissionsManager::p_new(sync_dir, read_only)
+    }
+
+    pub fn new_read_only(sync_dir: &Path) -> Result<PermissionsManager, OxenError> {
+        let read_only = true;
+        PermissionsManager::p_new(sync_dir, read_only)
+    }
+
+    fn p_new(sync_dir: &Path, read_only: bool) -> Result<PermissionsManager, OxenError> {
+        let hidden_dir = util::fs::oxen_hidden_dir(sync_dir);
+        if !hidden_dir.exists() {
+            std::fs::create_dir_all(&hidden_dir)?;
+        }
+
+        let db_dir = hidden_dir.join(PERMISSIONS_DIR);
+        let mut opts = Options::default();
+        opts.set_log_level(LogLevel::Fatal);
+        opts.create_if_missing(true);
+
+        if read_only && !db_dir.exists() {
+            // Cannot open a db that does not exist as read only, so create it first
+            DBWithThreadMode::<MultiThreaded>::open(&opts, &db_dir)?;
+        }
+
+        let db = if read_only {
+            DBWithThreadMode::open_for_read_only(&opts, &db_dir, false)?
+        } else {
+            DBWithThreadMode::open(&opts, &db_dir)?
+        };
+
+        Ok(PermissionsManager { db })
+    }
+
+    fn namespace_key(namespace: &str) -> String {
+        format!("namespace/{namespace}")
+    }
+
+    fn repo_key(namespace: &str, name: &str) -> String {
+        format!("repo/{namespace}/{name}")
+    }
+
+    pub fn get_namespace(
+        &self,
+        namespace: &str,
+    ) -> Result<Option<NamespacePermissions>, OxenError> {
+        self.get(&PermissionsManager::namespace_key(namespace))
+    }
+
+    pub fn get_repo(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Option<RepoPermissions>, OxenError> {
+        self.get(&PermissionsManager::repo_key(namespace, name))
+    }
+
+    pub fn add_namespace_owner(&self, namespace: &str, email: &str) -> Result<(), OxenError> {
+        let mut perms = self.get_namespace(namespace)?.unwrap_or_default();
+        if !perms.owners.iter().any(|owner| owner == email) {
+            perms.owners.push(email.to_owned());
+        }
+        self.put(&PermissionsManager::namespace_key(namespace), &perms)
+    }
+
+    pub fn remove_namespace_owner(&self, namespace: &str, email: &str) -> Result<(), OxenError> {
+        if let Some(mut perms) = self.get_namespace(namespace)? {
+            perms.owners.retain(|owner| owner != email);
+            self.put(&PermissionsManager::namespace_key(namespace), &perms)?;
+        }
+        Ok(())
+    }
+
+    pub fn grant(
+        &self,
+        namespace: &str,
+        name: &str,
+        email: &str,
+        role: Role,
+    ) -> Result<(), OxenError> {
+        let mut perms = self.get_repo(namespace, name)?.unwrap_or_default();
+        perms.collaborators.insert(email.to_owned(), role);
+        self.put(&PermissionsManager::repo_key(namespace, name), &perms)
+    }
+
+    pub fn revoke(&self, namespace: &str, name: &str, email: &str) -> Result<(), OxenError> {
+        if let Some(mut perms) = self.get_repo(namespace, name)? {
+            perms.collaborators.remove(email);
+            self.put(&PermissionsManager::repo_key(namespace, name), &perms)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_public(
+        &self,
+        namespace: &str,
+        name: &str,
+        is_public: bool,
+    ) -> Result<(), OxenError> {
+        let mut perms = self.get_repo(namespace, name)?.unwrap_or_default();
+        perms.is_public = is_public;
+        self.put(&PermissionsManager::repo_key(namespace, name), &perms)
+    }
+
+    /// Removes everything granted on a repository, for when it is deleted
+    pub fn delete_repo(&self, namespace: &str, name: &str) -> Result<(), OxenError> {
+        self.db
+            .delete(PermissionsManager::repo_key(namespace, name))?;
+        Ok(())
+    }
+
+    /// The role the user has on the repository, owning the namespace makes you an admin
+    pub fn role_for(
+        &self,
+        namespace: &str,
+        name: &str,
+        email: &str,
+    ) -> Result<Option<Role>, OxenError> {
+        if let Some(ns_perms) = self.get_namespace(namespace)? {
+            if ns_perms.owners.iter().any(|owner| owner == email) {
+                return Ok(Some(Role::Admin));
+            }
+        }
+
+        Ok(self
+            .get_repo(namespace, name)?
+            .and_then(|perms| perms.collaborators.get(email).copied()))
+    }
+
+    /// True if the user has at least the required role on the repository,
+    /// `email` is None for anonymous requests, which can only read public repositories
+    pub fn is_authorized(
+        &self,
+        namespace: &str,
+        name: &str,
+        email: Option<&str>,
+        required: Role,
+    ) -> Result<bool, OxenError> {
+        if let Some(email) = email {
+            if let Some(role) = self.role_for(namespace, name, email)? {
+                if role >= required {
+                    return Ok(true);
+                }
+            }
+        }
+
+        let is_public = self
+            .get_repo(namespace, name)?
+            .map(|perms| perms.is_public)
+            .unwrap_or(false);
+        Ok(required == Role::Read && is_public)
+    }
+
+    fn get<T: de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>, OxenError> {
+        match self.db.get(key.as_bytes()) {
+            Ok(Some(value)) => {
+                let value = str::from_utf8(&value)?;
+                Ok(Some(serde_json::from_str(value)?))
+            }
+            Ok(None) => Ok(None),
+            Err(err) => {
+                let err = format!("Err could not read from permissions db: {err}");
+                Err(OxenError::basic_str(err))
+            }
+        }
+    }
+
+    fn put<T: Serialize>(&self, key: &str, value: &T) -> Result<(), OxenError> {
+        let value = serde_json::to_string(value)?;
+        self.db.put(key, value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::permissions::{PermissionsManager, Role};
+    use crate::test;
+    use liboxen::error::OxenError;
+
+    #[test]
+    fn test_repo_without_permissions_is_private() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let perms = PermissionsManager::new(sync_dir)?;
+            assert!(!perms.is_authorized("ox", "data", Some("ox@oxen.ai"), Role::Read)?);
+            assert!(!perms.is_authorized("ox", "data", None, Role::Read)?);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_collaborator_roles() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let perms = PermissionsManager::new(sync_dir)?;
+            perms.grant("ox", "data", "reader@oxen.ai", Role::Read)?;
+            perms.grant("ox", "data", "writer@oxen.ai", Role::Write)?;
+
+            assert!(perms.is_authorized("ox", "data", Some("reader@oxen.ai"), Role::Read)?);
+            assert!(!perms.is_authorized("ox", "data", Some("reader@oxen.ai"), Role::Write)?);
+            assert!(perms.is_authorized("ox", "data", Some("writer@oxen.ai"), Role::Write)?);
+            assert!(!perms.is_authorized("ox", "data", Some("writer@oxen.ai"), Role::Admin)?);
+            assert!(!perms.is_authorized("ox", "data", Some("other@oxen.ai"), Role::Read)?);
+
+            perms.revoke("ox", "data", "writer@oxen.ai")?;
+            assert!(!perms.is_authorized("ox", "data", Some("writer@oxen.ai"), Role::Read)?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_namespace_owner_and_public_repo() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let perms = PermissionsManager::new(sync_dir)?;
+            perms.add_namespace_owner("ox", "owner@oxen.ai")?;
+
+            // Every repo in the namespace is now managed
+            assert!(perms.is_authorized("ox", "data", Some("owner@oxen.ai"), Role::Admin)?);
+            assert!(!perms.is_authorized("ox", "data", Some("other@oxen.ai"), Role::Read)?);
+
+            perms.set_public("ox", "data", true)?;
+            assert!(perms.is_authorized("ox", "data", Some("other@oxen.ai"), Role::Read)?);
+            assert!(!perms.is_authorized("ox", "data", Some("other@oxen.ai"), Role::Write)?);
+            // Anonymous users can read public repositories too
+            assert!(perms.is_authorized("ox", "data", None, Role::Read)?);
+            assert!(!perms.is_authorized("ox", "data", None, Role::Write)?);
+
+            Ok(())
+        })
+    }
+}
@@ -1,25 +1,448 @@
 use crate::app_data::OxenAppData;
 use crate::auth;
+use crate::auth::access_keys::JWTClaim;
+use crate::auth::permissions::{PermissionsManager, Role};
 
-use actix_web::dev::ServiceRequest;
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use liboxen::error::OxenError;
+use liboxen::view::StatusMessage;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::path::Path;
+
+/// Who is making a request, added to the request extensions when auth is enabled
+#[derive(Debug, Clone)]
+pub enum Identity {
+    Anonymous,
+    User(JWTClaim),
+}
+
+impl Identity {
+    pub fn claim(&self) -> Option<&JWTClaim> {
+        match self {
+            Identity::Anonymous => None,
+            Identity::User(claim) => Some(claim),
+        }
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.claim().map(|claim| claim.email())
+    }
+}
+
+/// The access a route needs, declared next to the route in `routes.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Anyone, even without a token
+    Public,
+    /// Any valid token
+    User,
+    /// A token that is not read only or scoped down to a single repository, to create repositories
+    Unscoped,
+    /// A token created with `oxen-server add-user --admin`, for routes about the whole server
+    Admin,
+    /// At least the role on the `{namespace}/{repo_name}` repository in the path
+    Repo(Role),
+}
+
+/// The `namespace/repo_name` a request was allowed to access, added to the request extensions
+/// by `Authorize` so that nothing outside of auth trusts the repository in the path
+#[derive(Debug, Clone)]
+pub struct AuthorizedRepo(pub String);
+
+/// Middleware that validates the bearer token of each request and adds the `Identity` making it.
+/// Requests without a token are anonymous, it is up to `Authorize` on each route to let them through.
+pub struct Authenticate;
+
+impl<S, B> Transform<S, ServiceRequest> for Authenticate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticateMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticateMiddleware { service }))
+    }
+}
+
+pub struct AuthenticateMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match authenticate(&req) {
+            Ok(identity) => {
+                if let Identity::User(claim) = &identity {
+                    // Controllers look up who is making the request from the claim
+                    req.extensions_mut().insert(claim.clone());
+                }
+                req.extensions_mut().insert(identity);
+                let fut = self.service.call(req);
+                Box::pin(fut)
+            }
+            Err(err) => Box::pin(async move { Err(err) }),
+        }
+    }
+}
+
+fn authenticate(req: &ServiceRequest) -> Result<Identity, Error> {
+    let token = match req.headers().get(header::AUTHORIZATION) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim())
+            .ok_or_else(|| actix_web::error::ErrorUnauthorized("unauthorized"))?,
+        None => return Ok(Identity::Anonymous),
+    };
 
-pub async fn validate(
-    req: ServiceRequest,
-    credentials: BearerAuth,
-) -> Result<ServiceRequest, actix_web::Error> {
     let app_data = req.app_data::<OxenAppData>().unwrap();
-    match auth::access_keys::AccessKeyManager::new_read_only(&app_data.path) {
-        Ok(keygen) => {
-            let token = credentials.token();
-            if keygen.token_is_valid(token) {
-                Ok(req)
-            } else {
-                Err(actix_web::error::ErrorUnauthorized("unauthorized"))
+    let keygen =
+        auth::access_keys::AccessKeyManager::new_read_only(&app_data.path).map_err(|err| {
+            actix_web::error::ErrorInternalServerError(format!("Err could not get keygen: {err}"))
+        })?;
+    if !keygen.token_is_valid(token) {
+        return Err(actix_web::error::ErrorUnauthorized("unauthorized"));
+    }
+
+    match keygen.get_claim(token) {
+        Ok(Some(claim)) => Ok(Identity::User(claim)),
+        _ => Err(actix_web::error::ErrorUnauthorized("unauthorized")),
+    }
+}
+
+/// Middleware for a single route that makes sure the `Identity` has the `Access` the route needs.
+/// Every request is allowed when there is no identity, because auth is not enabled.
+pub struct Authorize {
+    access: Access,
+}
+
+impl Authorize {
+    pub fn new(access: Access) -> Authorize {
+        Authorize { access }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authorize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthorizeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizeMiddleware {
+            service,
+            access: self.access,
+        }))
+    }
+}
+
+pub struct AuthorizeMiddleware<S> {
+    service: S,
+    access: Access,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let identity = req.extensions().get::<Identity>().cloned();
+        if let Some(identity) = identity {
+            if let Err(err) = authorize(&req, &identity, self.access) {
+                return Box::pin(async move { Err(err) });
+            }
+        }
+        if let Access::Repo(_) = self.access {
+            let repo = match (
+                req.match_info().get("namespace"),
+                req.match_info().get("repo_name"),
+            ) {
+                (Some(namespace), Some(name)) => Some(format!("{namespace}/{name}")),
+                _ => None,
+            };
+            if let Some(repo) = repo {
+                req.extensions_mut().insert(AuthorizedRepo(repo));
             }
         }
+
+        let fut = self.service.call(req);
+        Box::pin(fut)
+    }
+}
+
+/// Whether the identity of the request has the role on a repository other than the one in the
+/// path, for routes that also read from a second repository. Always true when auth is not enabled.
+pub fn has_role(
+    req: &HttpRequest,
+    sync_dir: &Path,
+    namespace: &str,
+    name: &str,
+    role: Role,
+) -> Result<bool, OxenError> {
+    let identity = match req.extensions().get::<Identity>().cloned() {
+        Some(identity) => identity,
+        None => return Ok(true),
+    };
+    if let Identity::User(claim) = &identity {
+        if !claim.allows(Some((namespace, name)), role) {
+            return Ok(false);
+        }
+    }
+    let permissions = PermissionsManager::new_read_only(sync_dir)?;
+    permissions.is_authorized(namespace, name, identity.email(), role)
+}
+
+/// Makes sure the identity has the access the route needs
+fn authorize(req: &ServiceRequest, identity: &Identity, access: Access) -> Result<(), Error> {
+    let role = match (access, identity) {
+        (Access::Public, _) => return Ok(()),
+        (_, Identity::Anonymous) => {
+            // Anonymous requests can still read public repositories
+            if access != Access::Repo(Role::Read) {
+                return Err(actix_web::error::ErrorUnauthorized("unauthorized"));
+            }
+            Role::Read
+        }
+        (Access::User, Identity::User(_)) => return Ok(()),
+        (Access::Unscoped, Identity::User(claim)) => {
+            if claim.allows(None, Role::Write) {
+                return Ok(());
+            }
+            return Err(forbidden(format!(
+                "token for {} is not allowed to {} {}",
+                claim.email(),
+                req.method(),
+                req.path()
+            )));
+        }
+        (Access::Admin, Identity::User(claim)) => {
+            if claim.is_admin() {
+                return Ok(());
+            }
+            return Err(forbidden(format!(
+                "token for {} is not an admin token, cannot {} {}",
+                claim.email(),
+                req.method(),
+                req.path()
+            )));
+        }
+        (Access::Repo(role), Identity::User(_)) => role,
+    };
+
+    let (namespace, name) = match (
+        req.match_info().get("namespace"),
+        req.match_info().get("repo_name"),
+    ) {
+        (Some(namespace), Some(name)) => (namespace, name),
+        _ => {
+            log::error!("authorize route {} has no repository", req.path());
+            return Err(forbidden(format!("cannot access {}", req.path())));
+        }
+    };
+
+    if let Identity::User(claim) = identity {
+        if !claim.allows(Some((namespace, name)), role) {
+            return Err(forbidden(format!(
+                "token for {} does not have {} access to {}/{}",
+                claim.email(),
+                role,
+                namespace,
+                name
+            )));
+        }
+    }
+
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let permissions = PermissionsManager::new_read_only(&app_data.path).map_err(|err| {
+        actix_web::error::ErrorInternalServerError(format!("Err could not get permissions: {err}"))
+    })?;
+    match permissions.is_authorized(namespace, name, identity.email(), role) {
+        Ok(true) => Ok(()),
+        Ok(false) => match identity.email() {
+            Some(email) => Err(forbidden(format!(
+                "{email} does not have {role} access to {namespace}/{name}"
+            ))),
+            None => Err(actix_web::error::ErrorUnauthorized("unauthorized")),
+        },
         Err(err) => Err(actix_web::error::ErrorInternalServerError(format!(
-            "Err could not get keygen: {err}"
+            "Err could not check permissions: {err}"
         ))),
     }
 }
+
+fn forbidden(msg: String) -> Error {
+    log::info!("forbidden: {}", msg);
+    actix_web::error::InternalError::from_response(
+        "forbidden",
+        HttpResponse::Forbidden().json(StatusMessage::error(&msg)),
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app_data::OxenAppData;
+    use crate::auth::access_keys::AccessKeyManager;
+    use crate::auth::permissions::{PermissionsManager, Role};
+    use crate::auth::validator::{Access, Authenticate, Authorize};
+    use crate::test;
+
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse};
+    use liboxen::error::OxenError;
+    use liboxen::model::User;
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_authorize_repo_routes() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let keygen = AccessKeyManager::new(&sync_dir)?;
+        let (_, reader_token) = keygen.create(&User {
+            name: String::from("Reader"),
+            email: String::from("reader@oxen.ai"),
+        })?;
+        let (_, other_token) = keygen.create(&User {
+            name: String::from("Other"),
+            email: String::from("other@oxen.ai"),
+        })?;
+        drop(keygen);
+        let permissions = PermissionsManager::new(&sync_dir)?;
+        permissions.grant("ox", "data", "reader@oxen.ai", Role::Read)?;
+        drop(permissions);
+
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(OxenAppData {
+                    path: sync_dir.clone(),
+                })
+                .wrap(Authenticate)
+                .service(
+                    web::resource("/api/repos/{namespace}/{repo_name}/commits")
+                        .guard(actix_web::guard::Get())
+                        .wrap(Authorize::new(Access::Repo(Role::Read)))
+                        .route(web::get().to(ok)),
+                )
+                .service(
+                    web::resource("/api/repos/{namespace}/{repo_name}/commits")
+                        .guard(actix_web::guard::Post())
+                        .wrap(Authorize::new(Access::Repo(Role::Write)))
+                        .route(web::post().to(ok)),
+                ),
+        )
+        .await;
+
+        let status = |method: actix_web::test::TestRequest, token: Option<&str>| {
+            let mut req = method.uri("/api/repos/ox/data/commits");
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", format!("Bearer {token}")));
+            }
+            req.to_request()
+        };
+
+        // The reader can read but not write
+        let resp = actix_web::test::call_service(
+            &app,
+            status(actix_web::test::TestRequest::get(), Some(&reader_token)),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .call(status(
+                actix_web::test::TestRequest::post(),
+                Some(&reader_token),
+            ))
+            .await;
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        // The repository is private to everyone else
+        let resp = app
+            .call(status(
+                actix_web::test::TestRequest::get(),
+                Some(&other_token),
+            ))
+            .await;
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+        let resp = app
+            .call(status(actix_web::test::TestRequest::get(), None))
+            .await;
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Anyone can read it once it is public, but only collaborators can write
+        PermissionsManager::new(&sync_dir)?.set_public("ox", "data", true)?;
+        let resp =
+            actix_web::test::call_service(&app, status(actix_web::test::TestRequest::get(), None))
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .call(status(actix_web::test::TestRequest::post(), None))
+            .await;
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // An invalid token is rejected even for public repositories
+        let resp = app
+            .call(status(
+                actix_web::test::TestRequest::get(),
+                Some("not-a-token"),
+            ))
+            .await;
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        std::fs::remove_dir_all(sync_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+//! Per repository rules that protect branches from being deleted, rewritten, or pushed to
+//! by anyone other than a list of allowed users.
+
+use liboxen::api;
+use liboxen::compute::cachers::content_validator;
+use liboxen::compute::commit_cacher;
+use liboxen::compute::commit_cacher::CacherStatusType;
+use liboxen::config::signing_key;
+use liboxen::error::OxenError;
+use liboxen::index::CommitReader;
+use liboxen::model::LocalRepository;
+use liboxen::util;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+pub const BRANCH_PROTECTION_FILE: &str = "branch_protection.json";
+
+/// Protection rule for the branches that match `branch`, which is either an exact
+/// branch name or a prefix ending in `*` such as `release/*`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchProtection {
+    pub branch: String,
+    /// The branch can not be deleted
+    #[serde(default)]
+    pub no_delete: bool,
+    /// The branch can only be moved forward to a commit that has its current commit as an ancestor
+    #[serde(default)]
+    pub no_force_update: bool,
+    /// The branch can only be moved to a commit that finished processing and passed validation
+    #[serde(default)]
+    pub require_valid: bool,
+    /// The branch can only be moved to a commit whose new commits are all signed by a key that is
+    /// trusted for their author, see `TrustedKeysConfig`
+    #[serde(default)]
+    pub require_signed: bool,
+    /// Emails of the users that can push to the branch, anyone with write access can if empty
+    #[serde(default)]
+    pub allowed_pushers: Vec<String>,
+}
+
+impl BranchProtection {
+    pub fn matches(&self, branch_name: &str) -> bool {
+        match self.branch.strip_suffix('*') {
+            Some(prefix) => branch_name.starts_with(prefix),
+            None => self.branch == branch_name,
+        }
+    }
+
+    fn allows_pusher(&self, email: Option<&str>) -> bool {
+        if self.allowed_pushers.is_empty() {
+            return true;
+        }
+        match email {
+            Some(email) => self.allowed_pushers.iter().any(|pusher| pusher == email),
+            None => false,
+        }
+    }
+}
+
+/// All the protection rules for a repository, saved in `.oxen/branch_protection.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BranchProtectionConfig {
+    pub rules: Vec<BranchProtection>,
+}
+
+impl BranchProtectionConfig {
+    pub fn path(repo: &LocalRepository) -> PathBuf {
+        util::fs::oxen_hidden_dir(&repo.path).join(BRANCH_PROTECTION_FILE)
+    }
+
+    /// Loads the rules for the repository, a repository without the file has no protected branches
+    pub fn load(repo: &LocalRepository) -> Result<BranchProtectionConfig, OxenError> {
+        let path = BranchProtectionConfig::path(repo);
+        if !path.exists() {
+            return Ok(BranchProtectionConfig::default());
+        }
+        let contents = util::fs::read_from_path(&path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, repo: &LocalRepository) -> Result<(), OxenError> {
+        let contents = serde_json::to_string_pretty(self)?;
+        util::fs::write_to_path(&BranchProtectionConfig::path(repo), &contents)?;
+        Ok(())
+    }
+
+    /// Adds the rule, replacing any rule that already exists for the same branch pattern
+    pub fn protect(&mut self, rule: BranchProtection) {
+        self.unprotect(&rule.branch);
+        self.rules.push(rule);
+    }
+
+    /// Removes the rule for the branch pattern, returns false if there was none
+    pub fn unprotect(&mut self, branch: &str) -> bool {
+        let num_rules = self.rules.len();
+        self.rules.retain(|rule| rule.branch != branch);
+        num_rules != self.rules.len()
+    }
+
+    pub fn rules_for(&self, branch_name: &str) -> Vec<&BranchProtection> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(branch_name))
+            .collect()
+    }
+}
+
+/// Returns the reason the branch can not be deleted, if it is protected
+pub fn check_delete(
+    repo: &LocalRepository,
+    branch_name: &str,
+) -> Result<Option<String>, OxenError> {
+    let config = BranchProtectionConfig::load(repo)?;
+    if config
+        .rules_for(branch_name)
+        .iter()
+        .any(|rule| rule.no_delete)
+    {
+        return Ok(Some(format!(
+            "Branch '{branch_name}' is protected and cannot be deleted"
+        )));
+    }
+    Ok(None)
+}
+
+/// Returns the reason the user cannot push data for the branch, if they are not an allowed pusher
+pub fn check_push(
+    repo: &LocalRepository,
+    branch_name: &str,
+    pusher: Option<&str>,
+) -> Result<Option<String>, OxenError> {
+    let config = BranchProtectionConfig::load(repo)?;
+    for rule in config.rules_for(branch_name) {
+        if !rule.allows_pusher(pusher) {
+            return Ok(Some(format!(
+                "Branch '{}' is protected, {} is not allowed to push to it",
+                branch_name,
+                pusher.unwrap_or("an anonymous user")
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the reason the branch cannot be moved to the commit, if it breaks one of the rules
+pub fn check_update(
+    repo: &LocalRepository,
+    branch_name: &str,
+    commit_id: &str,
+    pusher: Option<&str>,
+) -> Result<Option<String>, OxenError> {
+    let config = BranchProtectionConfig::load(repo)?;
+    let rules = config.rules_for(branch_name);
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(reason) = check_push(repo, branch_name, pusher)? {
+        return Ok(Some(reason));
+    }
+
+    if rules.iter().any(|rule| rule.no_force_update) {
+        if let Some(branch) = api::local::branches::get_by_name(repo, branch_name)? {
+            if branch.commit_id != commit_id && !is_ancestor(repo, &branch.commit_id, commit_id)? {
+                return Ok(Some(format!(
+                    "Branch '{}' is protected against force updates, commit {} does not contain the current commit {}. Pull and merge the changes before pushing",
+                    branch_name, commit_id, branch.commit_id
+                )));
+            }
+        }
+    }
+
+    if rules.iter().any(|rule| rule.require_valid) {
+        let commit = match api::local::commits::get_by_id(repo, commit_id)? {
+            Some(commit) => commit,
+            None => {
+                return Ok(Some(format!(
+                    "Branch '{branch_name}' is protected, commit {commit_id} does not exist"
+                )))
+            }
+        };
+        let is_valid = match commit_cacher::get_status(repo, &commit)? {
+            Some(CacherStatusType::Success) => content_validator::is_valid(repo, &commit)?,
+            Some(CacherStatusType::Pending) => {
+                return Ok(Some(format!(
+                "Branch '{branch_name}' is protected, commit {commit_id} is still being validated"
+            )))
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return Ok(Some(format!(
+                "Branch '{branch_name}' is protected, commit {commit_id} did not pass validation"
+            )));
+        }
+    }
+
+    if rules.iter().any(|rule| rule.require_signed) {
+        if let Some(unsigned_id) = first_unsigned_commit(repo, branch_name, commit_id)? {
+            return Ok(Some(format!(
+                "Branch '{branch_name}' is protected, commit {unsigned_id} is not signed by a key trusted for its author. Sign your commits with `oxen config --generate-signing-key` and ask an admin to trust the public key"
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The first commit leading up to `commit_id` that is not on the branch yet and is not signed by a
+/// trusted key. For a new branch, the commits that are already on another branch were accepted
+/// before and are skipped.
+fn first_unsigned_commit(
+    repo: &LocalRepository,
+    branch_name: &str,
+    commit_id: &str,
+) -> Result<Option<String>, OxenError> {
+    let commit_reader = CommitReader::new(repo)?;
+    let branch_commit_ids: Vec<String> = match api::local::branches::get_by_name(repo, branch_name)?
+    {
+        Some(branch) => vec![branch.commit_id],
+        None => api::local::branches::list(repo)?
+            .into_iter()
+            .map(|branch| branch.commit_id)
+            .collect(),
+    };
+    let mut known_ids: HashSet<String> = HashSet::new();
+    for branch_commit_id in branch_commit_ids {
+        for commit in commit_reader.history_from_commit_id(&branch_commit_id)? {
+            known_ids.insert(commit.id);
+        }
+    }
+
+    for commit in commit_reader.history_from_commit_id(commit_id)? {
+        if !known_ids.contains(&commit.id)
+            && !signing_key::verify(repo, &commit, Some(branch_name))?.is_good()
+        {
+            return Ok(Some(commit.id));
+        }
+    }
+    Ok(None)
+}
+
+fn is_ancestor(
+    repo: &LocalRepository,
+    ancestor_id: &str,
+    commit_id: &str,
+) -> Result<bool, OxenError> {
+    let commit_reader = CommitReader::new(repo)?;
+    let history = commit_reader.history_from_commit_id(commit_id)?;
+    Ok(history.iter().any(|commit| commit.id == ancestor_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::branch_protection;
+    use crate::branch_protection::{BranchProtection, BranchProtectionConfig};
+    use crate::test;
+
+    use liboxen::command;
+    use liboxen::config::{SigningKey, TrustedKey, TrustedKeysConfig};
+    use liboxen::error::OxenError;
+    use liboxen::index::{CommitDirReader, CommitWriter, RefWriter};
+    use liboxen::util;
+
+    #[test]
+    fn test_branch_protection_patterns() {
+        let rule = BranchProtection {
+            branch: String::from("release/*"),
+            ..BranchProtection::default()
+        };
+        assert!(rule.matches("release/v1"));
+        assert!(!rule.matches("main"));
+
+        let rule = BranchProtection {
+            branch: String::from("main"),
+            ..BranchProtection::default()
+        };
+        assert!(rule.matches("main"));
+        assert!(!rule.matches("main-2"));
+    }
+
+    #[test]
+    fn test_branch_protection_no_delete_and_pushers() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let repo = test::create_local_repo(sync_dir, "ox", "protected")?;
+            assert!(branch_protection::check_delete(&repo, "main")?.is_none());
+
+            let mut config = BranchProtectionConfig::load(&repo)?;
+            config.protect(BranchProtection {
+                branch: String::from("main"),
+                no_delete: true,
+                allowed_pushers: vec![String::from("ox@oxen.ai")],
+                ..BranchProtection::default()
+            });
+            config.save(&repo)?;
+
+            assert!(branch_protection::check_delete(&repo, "main")?.is_some());
+            assert!(branch_protection::check_delete(&repo, "feature")?.is_none());
+
+            assert!(branch_protection::check_push(&repo, "main", Some("ox@oxen.ai"))?.is_none());
+            assert!(branch_protection::check_push(&repo, "main", Some("bad@oxen.ai"))?.is_some());
+            assert!(branch_protection::check_push(&repo, "main", None)?.is_some());
+            assert!(branch_protection::check_push(&repo, "feature", None)?.is_none());
+
+            let mut config = BranchProtectionConfig::load(&repo)?;
+            assert!(config.unprotect("main"));
+            config.save(&repo)?;
+            assert!(branch_protection::check_delete(&repo, "main")?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_branch_protection_no_force_update() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let repo = test::create_local_repo(sync_dir, "ox", "protected")?;
+            let first_commit = command::head_commit(&repo)?;
+
+            util::fs::write_to_path(&repo.path.join("hello.txt"), "Hello")?;
+            command::add(&repo, &repo.path.join("hello.txt"))?;
+            let second_commit = command::commit(&repo, "Adding hello")?.unwrap();
+
+            let mut config = BranchProtectionConfig::default();
+            config.protect(BranchProtection {
+                branch: String::from("main"),
+                no_force_update: true,
+                ..BranchProtection::default()
+            });
+            config.save(&repo)?;
+
+            // Moving main back to the first commit would drop the second one
+            let reason = branch_protection::check_update(&repo, "main", &first_commit.id, None)?;
+            assert!(reason.is_some());
+
+            let reason = branch_protection::check_update(&repo, "main", &second_commit.id, None)?;
+            assert!(reason.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_branch_protection_require_signed() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let repo = test::create_local_repo(sync_dir, "ox", "protected")?;
+
+            util::fs::write_to_path(&repo.path.join("hello.txt"), "Hello")?;
+            command::add(&repo, &repo.path.join("hello.txt"))?;
+            let commit = command::commit(&repo, "Adding hello")?.unwrap();
+
+            let mut config = BranchProtectionConfig::default();
+            config.protect(BranchProtection {
+                branch: String::from("main"),
+                require_signed: true,
+                ..BranchProtection::default()
+            });
+            config.save(&repo)?;
+
+            // Main already points at the commit, so there is nothing new to check
+            let reason = branch_protection::check_update(&repo, "main", &commit.id, None)?;
+            assert!(reason.is_none());
+
+            // A new branch with the unsigned commit is fine since it is already on main
+            let reason = branch_protection::check_update(&repo, "feature", &commit.id, None)?;
+            assert!(reason.is_none());
+
+            util::fs::write_to_path(&repo.path.join("world.txt"), "World")?;
+            command::add(&repo, &repo.path.join("world.txt"))?;
+            let mut unsigned = command::commit(&repo, "Adding world")?.unwrap();
+            // Put main back so the new commit is not on it yet
+            RefWriter::new(&repo)?.set_branch_commit_id("main", &commit.id)?;
+
+            let reason = branch_protection::check_update(&repo, "main", &unsigned.id, None)?;
+            assert!(reason.is_some());
+
+            let key = SigningKey::generate();
+            let entries = CommitDirReader::new(&repo, &unsigned)?.list_entries()?;
+            unsigned.signature = Some(key.sign(&unsigned, &entries));
+            CommitWriter::new(&repo)?.add_commit_to_db(&unsigned)?;
+
+            // A valid signature is not enough until the key is trusted for the author
+            let reason = branch_protection::check_update(&repo, "main", &unsigned.id, None)?;
+            assert!(reason.is_some());
+
+            let mut trusted = TrustedKeysConfig::default();
+            trusted.trust(TrustedKey {
+                email: String::from("someone@else.com"),
+                public_key: key.public_key(),
+                branches: vec![],
+            });
+            trusted.save(&repo)?;
+            let reason = branch_protection::check_update(&repo, "main", &unsigned.id, None)?;
+            assert!(reason.is_some());
+
+            trusted.trust(TrustedKey {
+                email: unsigned.email.to_owned(),
+                public_key: key.public_key(),
+                branches: vec![String::from("main")],
+            });
+            trusted.save(&repo)?;
+            let reason = branch_protection::check_update(&repo, "main", &unsigned.id, None)?;
+            assert!(reason.is_none());
+
+            // The signature no longer holds if the commit is changed
+            unsigned.message = String::from("Adding something else");
+            CommitWriter::new(&repo)?.add_commit_to_db(&unsigned)?;
+            let reason = branch_protection::check_update(&repo, "main", &unsigned.id, None)?;
+            assert!(reason.is_some());
+
+            Ok(())
+        })
+    }
+}
@@ -4,7 +4,13 @@ pub mod df;
 pub mod dir;
 pub mod entries;
 pub mod file;
+pub mod jobs;
+pub mod merge_requests;
+pub mod metrics;
 pub mod namespaces;
+pub mod notes;
 pub mod repositories;
 pub mod schemas;
+pub mod search;
 pub mod version;
+pub mod webhooks;
@@ -1,8 +1,15 @@
 use crate::app_data::OxenAppData;
+use crate::auth::access_keys::JWTClaim;
+use crate::branch_protection;
+use crate::webhooks;
+use crate::webhooks::WebhookEvent;
 
-use actix_web::{HttpRequest, HttpResponse};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
 
 use liboxen::api;
+use liboxen::compute::cachers::pre_receive;
+use liboxen::error::OxenError;
+use liboxen::model::{Branch, LocalRepository};
 use liboxen::view::http::{
     MSG_RESOURCE_CREATED, MSG_RESOURCE_DELETED, MSG_RESOURCE_FOUND, MSG_RESOURCE_UPDATED,
     STATUS_SUCCESS,
@@ -117,11 +124,19 @@ pub async fn create_or_get(req: HttpRequest, body: String) -> HttpResponse {
                     Ok(None) => match api::local::branches::create(&repository, &data.name) {
                         Ok(branch) => {
                             // Set the remote to this server
-                            HttpResponse::Ok().json(BranchResponse {
+                            let view = BranchResponse {
                                 status: String::from(STATUS_SUCCESS),
                                 status_message: String::from(MSG_RESOURCE_CREATED),
                                 branch,
-                            })
+                            };
+                            webhooks::notify(
+                                &req,
+                                namespace,
+                                name,
+                                WebhookEvent::BranchCreated,
+                                &view,
+                            );
+                            HttpResponse::Ok().json(view)
                         }
                         Err(err) => {
                             log::error!("Err api::local::branches::create: {:?}", err);
@@ -170,12 +185,36 @@ pub async fn delete(req: HttpRequest) -> HttpResponse {
             Ok(Some(repository)) => {
                 match api::local::branches::get_by_name(&repository, branch_name) {
                     Ok(Some(branch)) => {
+                        match branch_protection::check_delete(&repository, branch_name) {
+                            Ok(None) => {}
+                            Ok(Some(reason)) => {
+                                log::info!("Rejected delete of branch {}: {}", branch_name, reason);
+                                return HttpResponse::Forbidden()
+                                    .json(StatusMessage::error(&reason));
+                            }
+                            Err(err) => {
+                                log::error!("Delete could not check branch protection: {}", err);
+                                return HttpResponse::InternalServerError()
+                                    .json(StatusMessage::internal_server_error());
+                            }
+                        }
+
                         match api::local::branches::force_delete(&repository, branch_name) {
-                            Ok(_) => HttpResponse::Ok().json(BranchResponse {
-                                status: String::from(STATUS_SUCCESS),
-                                status_message: String::from(MSG_RESOURCE_DELETED),
-                                branch,
-                            }),
+                            Ok(_) => {
+                                let view = BranchResponse {
+                                    status: String::from(STATUS_SUCCESS),
+                                    status_message: String::from(MSG_RESOURCE_DELETED),
+                                    branch,
+                                };
+                                webhooks::notify(
+                                    &req,
+                                    namespace,
+                                    name,
+                                    WebhookEvent::BranchDeleted,
+                                    &view,
+                                );
+                                HttpResponse::Ok().json(view)
+                            }
                             Err(err) => {
                                 log::error!("Delete could not delete branch: {}", err);
                                 HttpResponse::InternalServerError()
@@ -227,12 +266,52 @@ pub async fn update(req: HttpRequest, body: String) -> HttpResponse {
                 name,
             ) {
                 Ok(Some(repo)) => {
+                    let pusher = req
+                        .extensions()
+                        .get::<JWTClaim>()
+                        .map(|claim| claim.email().to_owned());
+                    match check_update(&repo, branch_name, &data.commit_id, pusher.as_deref()).await
+                    {
+                        Ok(None) => {}
+                        Ok(Some(reason)) => {
+                            log::info!("Rejected update of branch {}: {}", branch_name, reason);
+                            return HttpResponse::Forbidden().json(StatusMessage::error(&reason));
+                        }
+                        Err(err) => {
+                            log::error!("Update could not check branch update: {}", err);
+                            return HttpResponse::InternalServerError()
+                                .json(StatusMessage::internal_server_error());
+                        }
+                    }
+
+                    let existed = matches!(
+                        api::local::branches::get_by_name(&repo, branch_name),
+                        Ok(Some(_))
+                    );
                     match api::local::branches::update(&repo, branch_name, &data.commit_id) {
-                        Ok(branch) => HttpResponse::Ok().json(BranchResponse {
-                            status: String::from(STATUS_SUCCESS),
-                            status_message: String::from(MSG_RESOURCE_UPDATED),
-                            branch,
-                        }),
+                        Ok(branch) => {
+                            // Let webhooks know where the branch points to now
+                            let updated = BranchResponse {
+                                status: String::from(STATUS_SUCCESS),
+                                status_message: String::from(MSG_RESOURCE_UPDATED),
+                                branch: Branch {
+                                    commit_id: data.commit_id.clone(),
+                                    ..branch.clone()
+                                },
+                            };
+                            let event = if existed {
+                                WebhookEvent::BranchUpdated
+                            } else {
+                                WebhookEvent::BranchCreated
+                            };
+                            webhooks::notify(&req, namespace, name, event, &updated);
+
+                            HttpResponse::Ok().json(BranchResponse {
+                                status: String::from(STATUS_SUCCESS),
+                                status_message: String::from(MSG_RESOURCE_UPDATED),
+                                branch,
+                            })
+                        }
                         Err(err) => {
                             log::debug!("Error updating branch {}: {}", branch_name, err);
                             HttpResponse::InternalServerError()
@@ -263,6 +342,46 @@ pub async fn update(req: HttpRequest, body: String) -> HttpResponse {
     }
 }
 
+/// Returns why the branch cannot move to the commit, because of the branch protection rules
+/// or because the commit did not pass the pre-receive checks. The checks may have to read every
+/// file the commit changed, so they run off the workers
+pub async fn check_update(
+    repo: &LocalRepository,
+    branch_name: &str,
+    commit_id: &str,
+    pusher: Option<&str>,
+) -> Result<Option<String>, OxenError> {
+    let repo = repo.clone();
+    let branch_name = branch_name.to_owned();
+    let commit_id = commit_id.to_owned();
+    let pusher = pusher.map(String::from);
+    let result =
+        web::block(move || p_check_update(&repo, &branch_name, &commit_id, pusher.as_deref()))
+            .await;
+    match result {
+        Ok(result) => result,
+        Err(err) => Err(OxenError::basic_str(format!(
+            "Could not check branch update: {err}"
+        ))),
+    }
+}
+
+fn p_check_update(
+    repo: &LocalRepository,
+    branch_name: &str,
+    commit_id: &str,
+    pusher: Option<&str>,
+) -> Result<Option<String>, OxenError> {
+    if let Some(reason) = branch_protection::check_update(repo, branch_name, commit_id, pusher)? {
+        return Ok(Some(reason));
+    }
+
+    match api::local::commits::get_by_id(repo, commit_id)? {
+        Some(commit) => pre_receive::validate(repo, &commit),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -273,9 +392,10 @@ mod tests {
     use liboxen::api;
     use liboxen::constants::DEFAULT_BRANCH_NAME;
     use liboxen::error::OxenError;
-    use liboxen::view::http::STATUS_SUCCESS;
-    use liboxen::view::{BranchResponse, ListBranchesResponse};
+    use liboxen::view::http::{STATUS_ERROR, STATUS_SUCCESS};
+    use liboxen::view::{BranchResponse, ListBranchesResponse, StatusMessage};
 
+    use crate::branch_protection::{BranchProtection, BranchProtectionConfig};
     use crate::controllers;
     use crate::test;
 
@@ -365,6 +485,49 @@ mod tests {
         Ok(())
     }
 
+    #[actix_web::test]
+    async fn test_controllers_branch_delete_protected() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+
+        let namespace = "Testing-Namespace";
+        let repo_name = "Testing-Branches-Protected";
+        let repo = test::create_local_repo(&sync_dir, namespace, repo_name)?;
+        let branch_name = "release/v1";
+        api::local::branches::create(&repo, branch_name)?;
+
+        let mut config = BranchProtectionConfig::default();
+        config.protect(BranchProtection {
+            branch: String::from("release/*"),
+            no_delete: true,
+            ..BranchProtection::default()
+        });
+        config.save(&repo)?;
+
+        let uri = format!("/oxen/{namespace}/{repo_name}/branches");
+        let req = test::repo_request_with_param(
+            &sync_dir,
+            &uri,
+            namespace,
+            repo_name,
+            "branch_name",
+            branch_name,
+        );
+
+        let resp = controllers::branches::delete(req).await;
+        assert_eq!(resp.status(), http::StatusCode::FORBIDDEN);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let status: StatusMessage = serde_json::from_str(text)?;
+        assert_eq!(status.status, STATUS_ERROR);
+        assert!(status.status_message.contains("protected"));
+        assert!(api::local::branches::get_by_name(&repo, branch_name)?.is_some());
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+
     #[actix_web::test]
     async fn test_controllers_branch_create() -> Result<(), OxenError> {
         let sync_dir = test::get_sync_dir()?;
@@ -6,8 +6,9 @@ use liboxen::compute::commit_cacher::CacherStatusType;
 use liboxen::constants::HASH_FILE;
 use liboxen::constants::HISTORY_DIR;
 use liboxen::error::OxenError;
-use liboxen::index::CommitWriter;
-use liboxen::model::{Commit, LocalRepository};
+use liboxen::index::{notes, CommitWriter};
+use liboxen::model::{Commit, LocalRepository, Note};
+use liboxen::storage;
 use liboxen::util;
 use liboxen::view::http::MSG_FAILED_PROCESS;
 use liboxen::view::http::MSG_INTERNAL_SERVER_ERROR;
@@ -19,14 +20,20 @@ use liboxen::view::{
 };
 
 use crate::app_data::OxenAppData;
+use crate::auth::access_keys::JWTClaim;
+use crate::branch_protection;
+use crate::job_queue::{Job, JobQueue, JobStatus};
+use crate::limits;
+use crate::metrics::RequestId;
 
-use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse};
 use bytesize::ByteSize;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use futures_util::stream::StreamExt as _;
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::io::Read;
 use std::io::Write;
@@ -44,6 +51,11 @@ pub struct ChunkedDataUploadQuery {
     filename: Option<String>, // maybe a file name if !compressed
 }
 
+#[derive(Deserialize, Debug)]
+pub struct PushCompleteQuery {
+    branch: Option<String>, // branch the commit is being pushed to, if the client sent it
+}
+
 // List commits for a repository
 pub async fn index(req: HttpRequest) -> HttpResponse {
     let app_data = req.app_data::<OxenAppData>().unwrap();
@@ -98,11 +110,18 @@ pub async fn show(req: HttpRequest) -> HttpResponse {
     if let (Some(namespace), Some(name), Some(commit_id)) = (namespace, name, commit_id) {
         match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
             Ok(Some(repository)) => match api::local::commits::get_by_id(&repository, commit_id) {
-                Ok(Some(commit)) => HttpResponse::Ok().json(CommitResponse {
-                    status: String::from(STATUS_SUCCESS),
-                    status_message: String::from(MSG_RESOURCE_FOUND),
-                    commit,
-                }),
+                Ok(Some(commit)) => {
+                    let note = notes::get(&repository, &commit.id).unwrap_or_else(|err| {
+                        log::error!("Could not get note for commit {}: {}", commit.id, err);
+                        None
+                    });
+                    HttpResponse::Ok().json(CommitResponse {
+                        status: String::from(STATUS_SUCCESS),
+                        status_message: String::from(MSG_RESOURCE_FOUND),
+                        commit,
+                        note,
+                    })
+                }
                 Ok(None) => {
                     log::debug!("commit_id {} does not exist for repo: {}", commit_id, name);
                     HttpResponse::NotFound().json(StatusMessage::resource_not_found())
@@ -127,6 +146,46 @@ pub async fn show(req: HttpRequest) -> HttpResponse {
     }
 }
 
+/// Reports the commit as processing or failed if it has a job in the queue
+fn job_status(
+    req: &HttpRequest,
+    namespace: &str,
+    name: &str,
+    commit_id: &str,
+) -> Option<HttpResponse> {
+    let queue = req.app_data::<web::Data<JobQueue>>()?;
+    let job = match queue.get(&Job::process_commit(namespace, name, commit_id).id) {
+        Ok(job) => job?,
+        Err(err) => {
+            log::error!("Could not get job for commit {}: {}", commit_id, err);
+            return None;
+        }
+    };
+
+    match job.status {
+        JobStatus::Queued | JobStatus::Running => {
+            Some(HttpResponse::Ok().json(IsValidStatusMessage {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_IS_PROCESSING),
+                status_description: format!("Commit is {}", job.status),
+                is_processing: true,
+                is_valid: false,
+            }))
+        }
+        JobStatus::Failed => Some(HttpResponse::Ok().json(IsValidStatusMessage {
+            status: String::from(STATUS_ERROR),
+            status_message: String::from(MSG_FAILED_PROCESS),
+            status_description: format!(
+                "Err: processing failed after {} attempts: {}",
+                job.attempts,
+                job.error.unwrap_or_default()
+            ),
+            is_processing: false,
+            is_valid: false,
+        })),
+    }
+}
+
 pub async fn is_synced(req: HttpRequest) -> HttpResponse {
     let app_data = req.app_data::<OxenAppData>().unwrap();
     let namespace: Option<&str> = req.match_info().get("namespace");
@@ -139,70 +198,78 @@ pub async fn is_synced(req: HttpRequest) -> HttpResponse {
         match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
             Ok(Some(repository)) => {
                 match api::local::commits::get_by_id_or_branch(&repository, commit_or_branch) {
-                    Ok(Some(commit)) => match commit_cacher::get_status(&repository, &commit) {
-                        Ok(Some(CacherStatusType::Success)) => {
-                            match content_validator::is_valid(&repository, &commit) {
-                                Ok(true) => HttpResponse::Ok().json(IsValidStatusMessage {
+                    Ok(Some(commit)) => {
+                        // Commits waiting in the job queue have not started processing yet
+                        if let Some(response) = job_status(&req, namespace, name, &commit.id) {
+                            return response;
+                        }
+
+                        match commit_cacher::get_status(&repository, &commit) {
+                            Ok(Some(CacherStatusType::Success)) => {
+                                match content_validator::is_valid(&repository, &commit) {
+                                    Ok(true) => HttpResponse::Ok().json(IsValidStatusMessage {
+                                        status: String::from(STATUS_SUCCESS),
+                                        status_message: String::from(MSG_RESOURCE_FOUND),
+                                        status_description: String::from(""),
+                                        is_processing: false,
+                                        is_valid: true,
+                                    }),
+                                    err => HttpResponse::Ok().json(IsValidStatusMessage {
+                                        status: String::from(STATUS_ERROR),
+                                        status_message: String::from(MSG_INTERNAL_SERVER_ERROR),
+                                        status_description: format!("Err: {err:?}"),
+                                        is_processing: false,
+                                        is_valid: false,
+                                    }),
+                                }
+                            }
+                            Ok(Some(CacherStatusType::Pending)) => {
+                                HttpResponse::Ok().json(IsValidStatusMessage {
                                     status: String::from(STATUS_SUCCESS),
-                                    status_message: String::from(MSG_RESOURCE_FOUND),
-                                    status_description: String::from(""),
+                                    status_message: String::from(MSG_RESOURCE_IS_PROCESSING),
+                                    status_description: String::from("Commit is still processing"),
+                                    is_processing: true,
+                                    is_valid: false,
+                                })
+                            }
+                            Ok(Some(CacherStatusType::Failed)) => {
+                                let errors =
+                                    commit_cacher::get_failures(&repository, &commit).unwrap();
+                                let error_str = errors
+                                    .into_iter()
+                                    .map(|e| e.status_message)
+                                    .collect::<Vec<String>>()
+                                    .join(", ");
+
+                                HttpResponse::Ok().json(IsValidStatusMessage {
+                                    status: String::from(STATUS_ERROR),
+                                    status_message: String::from(MSG_FAILED_PROCESS),
+                                    status_description: format!("Err: {error_str}"),
                                     is_processing: false,
-                                    is_valid: true,
-                                }),
-                                err => HttpResponse::Ok().json(IsValidStatusMessage {
+                                    is_valid: false,
+                                })
+                            }
+                            Ok(None) => {
+                                // This means background status was never kicked off...
+                                log::debug!(
+                                    "get_status commit {} no status kicked off for repo: {}",
+                                    commit_or_branch,
+                                    name
+                                );
+                                HttpResponse::NotFound().json(StatusMessage::resource_not_found())
+                            }
+                            err => {
+                                log::debug!("Error getting status... {:?}", err);
+                                HttpResponse::Ok().json(IsValidStatusMessage {
                                     status: String::from(STATUS_ERROR),
                                     status_message: String::from(MSG_INTERNAL_SERVER_ERROR),
                                     status_description: format!("Err: {err:?}"),
                                     is_processing: false,
                                     is_valid: false,
-                                }),
+                                })
                             }
                         }
-                        Ok(Some(CacherStatusType::Pending)) => {
-                            HttpResponse::Ok().json(IsValidStatusMessage {
-                                status: String::from(STATUS_SUCCESS),
-                                status_message: String::from(MSG_RESOURCE_IS_PROCESSING),
-                                status_description: String::from("Commit is still processing"),
-                                is_processing: true,
-                                is_valid: false,
-                            })
-                        }
-                        Ok(Some(CacherStatusType::Failed)) => {
-                            let errors = commit_cacher::get_failures(&repository, &commit).unwrap();
-                            let error_str = errors
-                                .into_iter()
-                                .map(|e| e.status_message)
-                                .collect::<Vec<String>>()
-                                .join(", ");
-
-                            HttpResponse::Ok().json(IsValidStatusMessage {
-                                status: String::from(STATUS_ERROR),
-                                status_message: String::from(MSG_FAILED_PROCESS),
-                                status_description: format!("Err: {error_str}"),
-                                is_processing: false,
-                                is_valid: false,
-                            })
-                        }
-                        Ok(None) => {
-                            // This means background status was never kicked off...
-                            log::debug!(
-                                "get_status commit {} no status kicked off for repo: {}",
-                                commit_or_branch,
-                                name
-                            );
-                            HttpResponse::NotFound().json(StatusMessage::resource_not_found())
-                        }
-                        err => {
-                            log::debug!("Error getting status... {:?}", err);
-                            HttpResponse::Ok().json(IsValidStatusMessage {
-                                status: String::from(STATUS_ERROR),
-                                status_message: String::from(MSG_INTERNAL_SERVER_ERROR),
-                                status_description: format!("Err: {err:?}"),
-                                is_processing: false,
-                                is_valid: false,
-                            })
-                        }
-                    },
+                    }
                     Ok(None) => {
                         log::debug!(
                             "commit or branch {} does not exist for repo: {}",
@@ -286,7 +353,8 @@ fn p_get_parents(
 fn p_index(repo_dir: &Path) -> Result<ListCommitResponse, OxenError> {
     let repo = LocalRepository::new(repo_dir)?;
     let commits = command::log(&repo)?;
-    Ok(ListCommitResponse::success(commits))
+    let notes = notes_for_commits(&repo, &commits)?;
+    Ok(ListCommitResponse::success_with_notes(commits, notes))
 }
 
 fn p_index_commit_or_branch_history(
@@ -296,7 +364,16 @@ fn p_index_commit_or_branch_history(
     let repo = LocalRepository::new(repo_dir)?;
     let commits = command::log_commit_or_branch_history(&repo, commit_or_branch)?;
     log::debug!("controllers::commits: : {:#?}", commits);
-    Ok(ListCommitResponse::success(commits))
+    let notes = notes_for_commits(&repo, &commits)?;
+    Ok(ListCommitResponse::success_with_notes(commits, notes))
+}
+
+fn notes_for_commits(repo: &LocalRepository, commits: &[Commit]) -> Result<Vec<Note>, OxenError> {
+    let commit_ids: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+    Ok(notes::list(repo)?
+        .into_iter()
+        .filter(|note| commit_ids.contains(note.commit_id.as_str()))
+        .collect())
 }
 
 pub async fn download_commit_db(req: HttpRequest) -> HttpResponse {
@@ -393,6 +470,7 @@ pub async fn create(req: HttpRequest, body: String) -> HttpResponse {
                     status: String::from(STATUS_SUCCESS),
                     status_message: String::from(MSG_RESOURCE_CREATED),
                     commit: commit.to_owned(),
+                    note: None,
                 }),
                 Err(err) => {
                     log::error!("Err create_commit: {}", err);
@@ -426,6 +504,10 @@ pub async fn upload_chunk(
     match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, repo_name)
     {
         Ok(Some(repo)) => {
+            if let Some(response) = limits::check_upload_quota(&req, &app_data.path, namespace) {
+                return Ok(response);
+            }
+
             match api::local::commits::get_by_id(&repo, commit_id) {
                 Ok(Some(commit)) => {
                     let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
@@ -443,7 +525,11 @@ pub async fn upload_chunk(
                     // mkdir if !exists
                     if !tmp_dir.exists() {
                         if let Err(err) = std::fs::create_dir_all(&tmp_dir) {
-                            log::error!("Could not complete chunk upload, mkdir failed: {:?}", err);
+                            log::error!(
+                                "[{}] Could not complete chunk upload, mkdir failed: {:?}",
+                                RequestId::of(&req),
+                                err
+                            );
                             return Ok(HttpResponse::InternalServerError()
                                 .json(StatusMessage::internal_server_error()));
                         }
@@ -452,7 +538,7 @@ pub async fn upload_chunk(
                     // Read bytes from body
                     let mut bytes = web::BytesMut::new();
                     while let Some(item) = chunk.next().await {
-                        bytes.extend_from_slice(&item.unwrap());
+                        bytes.extend_from_slice(&item?);
                     }
 
                     // Write to tmp file
@@ -467,24 +553,41 @@ pub async fn upload_chunk(
                                         chunk_file
                                     );
 
-                                    check_if_upload_complete_and_unpack(
-                                        hidden_dir,
-                                        tmp_dir,
-                                        total_chunks,
-                                        size,
-                                        query.is_compressed,
-                                        query.filename.to_owned(),
-                                    );
+                                    // Storing the versions may upload them, so do it off the workers
+                                    let is_compressed = query.is_compressed;
+                                    let filename = query.filename.to_owned();
+                                    let unpacked = web::block(move || {
+                                        check_if_upload_complete_and_unpack(
+                                            &repo,
+                                            tmp_dir,
+                                            total_chunks,
+                                            size,
+                                            is_compressed,
+                                            filename,
+                                        )
+                                    })
+                                    .await?;
+                                    if let Err(err) = unpacked {
+                                        log::error!(
+                                            "[{}] Could not store uploaded versions: {}",
+                                            RequestId::of(&req),
+                                            err
+                                        );
+                                        return Ok(HttpResponse::InternalServerError()
+                                            .json(StatusMessage::internal_server_error()));
+                                    }
 
                                     Ok(HttpResponse::Ok().json(CommitResponse {
                                         status: String::from(STATUS_SUCCESS),
                                         status_message: String::from(MSG_RESOURCE_CREATED),
                                         commit: commit.to_owned(),
+                                        note: None,
                                     }))
                                 }
                                 Err(err) => {
                                     log::error!(
-                                        "Could not complete chunk upload, file create failed: {:?}",
+                                        "[{}] Could not complete chunk upload, file write failed: {:?}",
+                                        RequestId::of(&req),
                                         err
                                     );
                                     Ok(HttpResponse::InternalServerError()
@@ -494,7 +597,8 @@ pub async fn upload_chunk(
                         }
                         Err(err) => {
                             log::error!(
-                                "Could not complete chunk upload, file create failed: {:?}",
+                                "[{}] Could not complete chunk upload, file create failed: {:?}",
+                                RequestId::of(&req),
                                 err
                             );
                             Ok(HttpResponse::InternalServerError()
@@ -524,18 +628,19 @@ pub async fn upload_chunk(
     }
 }
 
+/// Unpacks the upload once every chunk arrived, returning an error if its versions could not be stored
 fn check_if_upload_complete_and_unpack(
-    hidden_dir: PathBuf,
+    repo: &LocalRepository,
     tmp_dir: PathBuf,
     total_chunks: usize,
     total_size: usize,
     is_compressed: bool,
     filename: Option<String>,
-) {
+) -> Result<(), OxenError> {
     let mut files = util::fs::list_files_in_dir(&tmp_dir);
 
     if total_chunks < files.len() {
-        return;
+        return Ok(());
     }
     files.sort();
 
@@ -558,6 +663,7 @@ fn check_if_upload_complete_and_unpack(
     );
 
     if total_size == (uploaded_size as usize) {
+        let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
         // std::thread::spawn(move || {
         // Get tar.gz bytes for history/COMMIT_ID data
         log::debug!("Decompressing {} bytes to {:?}", total_size, hidden_dir);
@@ -575,7 +681,11 @@ fn check_if_upload_complete_and_unpack(
         if is_compressed {
             // Unpack tarball to our hidden dir
             let mut archive = Archive::new(GzDecoder::new(&buffer[..]));
-            unpack_entry_tarball(&hidden_dir, &mut archive);
+            let unpacked = unpack_entry_tarball(repo, &mut archive);
+            if unpacked.is_err() {
+                std::fs::remove_dir_all(tmp_dir)?;
+                return unpacked;
+            }
         } else {
             // just write buffer to disk
             match filename {
@@ -583,7 +693,7 @@ fn check_if_upload_complete_and_unpack(
                     // TODO: better error handling...
 
                     log::debug!("Got filename {}", filename);
-                    let full_path = hidden_dir.join(filename);
+                    let full_path = hidden_dir.join(&filename);
                     log::debug!("Unpack to {:?}", full_path);
                     if let Some(parent) = full_path.parent() {
                         if !parent.exists() {
@@ -595,6 +705,11 @@ fn check_if_upload_complete_and_unpack(
                     match f.write_all(&buffer) {
                         Ok(_) => {
                             log::debug!("Unpack successful! {:?}", full_path);
+                            let stored = store_version_file(repo, Path::new(&filename), &full_path);
+                            if stored.is_err() {
+                                std::fs::remove_dir_all(tmp_dir)?;
+                                return stored;
+                            }
                         }
                         Err(err) => {
                             log::error!("Could not write all data to disk {:?}", err);
@@ -611,6 +726,7 @@ fn check_if_upload_complete_and_unpack(
         std::fs::remove_dir_all(tmp_dir).unwrap();
         // });
     }
+    Ok(())
 }
 
 /// Controller to upload the commit database
@@ -627,6 +743,10 @@ pub async fn upload(
     match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, repo_name)
     {
         Ok(Some(repo)) => {
+            if let Some(response) = limits::check_upload_quota(&req, &app_data.path, namespace) {
+                return Ok(response);
+            }
+
             match api::local::commits::get_by_id(&repo, commit_id) {
                 Ok(Some(commit)) => {
                     let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
@@ -634,7 +754,7 @@ pub async fn upload(
                     // Read bytes from body
                     let mut bytes = web::BytesMut::new();
                     while let Some(item) = body.next().await {
-                        bytes.extend_from_slice(&item.unwrap());
+                        bytes.extend_from_slice(&item?);
                     }
 
                     // Compute total size as u64
@@ -645,19 +765,31 @@ pub async fn upload(
                         ByteSize::b(total_size)
                     );
 
-                    // Unpack in background thread because could take awhile
-                    // std::thread::spawn(move || {
+                    // Unpack off the workers because it could take awhile
                     // Get tar.gz bytes for history/COMMIT_ID data
                     log::debug!("Decompressing {} bytes to {:?}", bytes.len(), hidden_dir);
                     // Unpack tarball to our hidden dir
-                    let mut archive = Archive::new(GzDecoder::new(&bytes[..]));
-                    unpack_entry_tarball(&hidden_dir, &mut archive);
-                    // });
+                    let unpack_repo = repo.clone();
+                    let unpacked = web::block(move || {
+                        let mut archive = Archive::new(GzDecoder::new(&bytes[..]));
+                        unpack_entry_tarball(&unpack_repo, &mut archive)
+                    })
+                    .await?;
+                    if let Err(err) = unpacked {
+                        log::error!(
+                            "[{}] Could not store uploaded versions: {}",
+                            RequestId::of(&req),
+                            err
+                        );
+                        return Ok(HttpResponse::InternalServerError()
+                            .json(StatusMessage::internal_server_error()));
+                    }
 
                     Ok(HttpResponse::Ok().json(CommitResponse {
                         status: String::from(STATUS_SUCCESS),
                         status_message: String::from(MSG_RESOURCE_CREATED),
                         commit: commit.to_owned(),
+                        note: None,
                     }))
                 }
                 Ok(None) => {
@@ -683,7 +815,10 @@ pub async fn upload(
 }
 
 /// Notify that the push should be complete, and we should start doing our background processing
-pub async fn complete(req: HttpRequest) -> Result<HttpResponse, Error> {
+pub async fn complete(
+    req: HttpRequest,
+    query: web::Query<PushCompleteQuery>,
+) -> Result<HttpResponse, Error> {
     let app_data = req.app_data::<OxenAppData>().unwrap();
     // name to the repo, should be in url path so okay to unwrap
     let namespace: &str = req.match_info().get("namespace").unwrap();
@@ -693,29 +828,42 @@ pub async fn complete(req: HttpRequest) -> Result<HttpResponse, Error> {
     match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, repo_name)
     {
         Ok(Some(repo)) => {
+            // Do not bother processing commits for a branch the user is not allowed to push to
+            if let Some(branch_name) = &query.branch {
+                let pusher = req
+                    .extensions()
+                    .get::<JWTClaim>()
+                    .map(|claim| claim.email().to_owned());
+                match branch_protection::check_push(&repo, branch_name, pusher.as_deref()) {
+                    Ok(None) => {}
+                    Ok(Some(reason)) => {
+                        log::info!("Rejected push of commit {}: {}", commit_id, reason);
+                        return Ok(HttpResponse::Forbidden().json(StatusMessage::error(&reason)));
+                    }
+                    Err(err) => {
+                        log::error!("Could not check branch protection: {}", err);
+                        return Ok(HttpResponse::InternalServerError()
+                            .json(StatusMessage::internal_server_error()));
+                    }
+                }
+            }
+
             match api::local::commits::get_by_id(&repo, commit_id) {
                 Ok(Some(commit)) => {
-                    // Kick off processing in background thread because could take awhile
-                    std::thread::spawn(move || {
-                        log::debug!("Processing commit {:?} on repo {:?}", commit, repo.path);
-                        match commit_cacher::run_all(&repo, &commit) {
-                            Ok(_) => {
-                                log::debug!(
-                                    "Success processing commit {:?} on repo {:?}",
-                                    commit,
-                                    repo.path
-                                );
-                            }
-                            Err(err) => {
-                                log::error!(
-                                    "Could not process commit {:?} on repo {:?}: {}",
-                                    commit,
-                                    repo.path,
-                                    err
-                                );
-                            }
+                    // Processing could take awhile, so the job queue runs it in the background
+                    let queue = match req.app_data::<web::Data<JobQueue>>() {
+                        Some(queue) => queue,
+                        None => {
+                            log::error!("Job queue is not running, cannot process {}", commit_id);
+                            return Ok(HttpResponse::InternalServerError()
+                                .json(StatusMessage::internal_server_error()));
                         }
-                    });
+                    };
+                    if let Err(err) = queue.enqueue_commit(namespace, repo_name, &commit.id) {
+                        log::error!("Could not queue commit {}: {}", commit.id, err);
+                        return Ok(HttpResponse::InternalServerError()
+                            .json(StatusMessage::internal_server_error()));
+                    }
 
                     Ok(HttpResponse::Ok().json(StatusMessage {
                         status: String::from(STATUS_SUCCESS),
@@ -744,7 +892,36 @@ pub async fn complete(req: HttpRequest) -> Result<HttpResponse, Error> {
     }
 }
 
-fn unpack_entry_tarball(hidden_dir: &Path, archive: &mut Archive<GzDecoder<&[u8]>>) {
+/// Make sure an uploaded version file lands in the repo's version store, which is a no-op for local storage.
+/// Versions that are stored remotely do not keep a copy on the server's disk once they are uploaded.
+fn store_version_file(
+    repo: &LocalRepository,
+    path: &Path,
+    full_path: &Path,
+) -> Result<(), OxenError> {
+    if let Some((hash, filename)) =
+        storage::version_store::hash_and_filename_from_version_path(path)
+    {
+        let version_store = repo.version_store()?;
+        if let Err(err) = version_store.store_version_from_path(&hash, &filename, full_path) {
+            log::error!("Could not store version {:?}: {}", path, err);
+            return Err(err);
+        }
+        if !repo.stores_versions_locally() {
+            if let Err(err) = std::fs::remove_file(full_path) {
+                log::warn!("Could not remove uploaded version {:?}: {}", full_path, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unpacks the uploaded versions, returning an error if one of them could not be stored
+fn unpack_entry_tarball(
+    repo: &LocalRepository,
+    archive: &mut Archive<GzDecoder<&[u8]>>,
+) -> Result<(), OxenError> {
+    let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
     // Unpack and compute HASH and save next to the file to speed up computation later
     match archive.entries() {
         Ok(entries) => {
@@ -754,15 +931,16 @@ fn unpack_entry_tarball(hidden_dir: &Path, archive: &mut Archive<GzDecoder<&[u8]
                     // When we want to check is_synced, it is expensive to rehash everything
                     // But since upload is network bound already, hashing here makes sense, and we will just
                     // load the HASH file later
-                    file.unpack_in(hidden_dir).unwrap();
+                    file.unpack_in(&hidden_dir).unwrap();
                     let path = file.path().unwrap();
                     let full_path = hidden_dir.join(&path);
                     let hash_dir = full_path.parent().unwrap();
                     let hash_file = hash_dir.join(HASH_FILE);
                     if path.starts_with("versions/files/") {
-                        let hash = util::hasher::hash_file_contents(&full_path).unwrap();
+                        let hash = util::hasher::hash_version_file(&full_path).unwrap();
                         util::fs::write_to_path(&hash_file, &hash)
                             .expect("Could not write hash file");
+                        store_version_file(repo, &path, &full_path)?;
                     }
                 } else {
                     log::error!("Could not unpack file in archive...");
@@ -775,6 +953,7 @@ fn unpack_entry_tarball(hidden_dir: &Path, archive: &mut Archive<GzDecoder<&[u8]
         }
     }
     log::debug!("Done decompressing.");
+    Ok(())
 }
 
 fn create_commit(repo_dir: &Path, commit: &Commit) -> Result<(), OxenError> {
@@ -4,12 +4,14 @@ use liboxen::{api, constants};
 
 use actix_web::{web, HttpRequest, HttpResponse};
 use liboxen::df::{tabular, DFOpts};
-use liboxen::model::Schema;
+use liboxen::error::OxenError;
+use liboxen::index::{blamer, schema_indexer, SchemaReader};
+use liboxen::model::{Commit, LocalRepository, Schema};
 use liboxen::view::http::{MSG_RESOURCE_FOUND, STATUS_SUCCESS};
 use liboxen::view::json_data_frame::JsonDataSize;
 use liboxen::view::{JsonDataFrame, JsonDataFrameSliceResponse, StatusMessage};
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use liboxen::util;
 
@@ -46,37 +48,58 @@ pub async fn get(req: HttpRequest, query: web::Query<DFOptsQuery>) -> HttpRespon
                     filepath
                 );
 
-                match util::fs::version_path_for_commit_id(&repo, &commit_id, &filepath) {
-                    Ok(version_path) => match tabular::scan_df(&version_path) {
+                match util::fs::local_version_for_commit_id(&repo, &commit_id, &filepath) {
+                    Ok(version) => match tabular::scan_df(version.path()) {
                         Ok(lazy_df) => {
-                            log::debug!("Read version file {:?}", version_path);
+                            log::debug!("Read version file {:?}", version.path());
                             let polars_schema = lazy_df.schema().unwrap();
                             let schema = Schema::from_polars(&polars_schema);
                             let mut filter = DFOpts::from_schema_columns_exclude_hidden(&schema);
                             log::debug!("Initial filter {:?}", filter);
                             filter = parse_opts(&query, &mut filter);
+                            filter.indexed_rows =
+                                indexed_rows(&repo, &commit_id, &filepath, &filter);
 
                             log::debug!("Got filter {:?}", filter);
                             let lazy_cp = lazy_df.clone();
-                            let mut df = tabular::transform_df(lazy_cp, filter).unwrap();
-                            let full_df = lazy_df.collect().unwrap();
+                            let mut df = match tabular::transform_df(lazy_cp, filter) {
+                                Ok(df) => df,
+                                Err(err) => {
+                                    log::error!(
+                                        "df::get could not transform {:?}: {}",
+                                        resource,
+                                        err
+                                    );
+                                    return HttpResponse::InternalServerError()
+                                        .json(StatusMessage::internal_server_error());
+                                }
+                            };
+                            let full_width = polars_schema.len();
+                            let full_height = match tabular::count_rows(lazy_df) {
+                                Ok(height) => height,
+                                Err(err) => {
+                                    log::error!("df::get could not count {:?}: {}", resource, err);
+                                    return HttpResponse::InternalServerError()
+                                        .json(StatusMessage::internal_server_error());
+                                }
+                            };
                             let page_size = query.page_size.unwrap_or(constants::DEFAULT_PAGE_SIZE);
                             let page = query.page.unwrap_or(constants::DEFAULT_PAGE_NUM);
 
-                            let total_pages = (full_df.height() / page_size) + 1;
+                            let total_pages = (full_height / page_size) + 1;
 
                             let response = JsonDataFrameSliceResponse {
                                 status: String::from(STATUS_SUCCESS),
                                 status_message: String::from(MSG_RESOURCE_FOUND),
                                 df: JsonDataFrame::from_df(&mut df),
                                 full_size: JsonDataSize {
-                                    width: full_df.width(),
-                                    height: full_df.height(),
+                                    width: full_width,
+                                    height: full_height,
                                 },
                                 page_number: page,
                                 page_size,
                                 total_pages,
-                                total_entries: full_df.height(),
+                                total_entries: full_height,
                             };
                             HttpResponse::Ok().json(response)
                         }
@@ -110,6 +133,163 @@ pub async fn get(req: HttpRequest, query: web::Query<DFOptsQuery>) -> HttpRespon
     }
 }
 
+#[derive(Deserialize, Debug)]
+pub struct BlameQuery {
+    pub key: Option<String>,
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+/// A page of the rows of a tabular file with the commit that introduced each of them
+pub async fn blame(req: HttpRequest, query: web::Query<BlameQuery>) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    let resource: PathBuf = req.match_info().query("resource").parse().unwrap();
+
+    log::debug!("df::blame repo name [{}] resource [{:?}]", name, resource);
+    let repo = match api::local::repositories::get_by_namespace_and_name(
+        &app_data.path,
+        namespace,
+        name,
+    ) {
+        Ok(Some(repo)) => repo,
+        Ok(None) => {
+            log::debug!("df::blame could not find repo with name {}", name);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+        Err(err) => {
+            log::error!("df::blame Err: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    };
+
+    let commit = match util::resource::parse_resource(&repo, &resource) {
+        Ok(Some((commit_id, _, filepath))) => {
+            match api::local::commits::get_by_id(&repo, &commit_id) {
+                Ok(Some(commit)) => Some((commit, filepath)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let (commit, filepath) = match commit {
+        Some(commit) => commit,
+        None => {
+            log::debug!("df::blame could not find resource from uri {:?}", resource);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+    };
+
+    let page_size = query.page_size.unwrap_or(constants::DEFAULT_PAGE_SIZE);
+    let page = query.page.unwrap_or(constants::DEFAULT_PAGE_NUM).max(1);
+    if page_size == 0 {
+        return HttpResponse::BadRequest()
+            .json(StatusMessage::error("page_size must be greater than 0"));
+    }
+    if !util::fs::is_tabular(&filepath) {
+        let err = format!("Can only blame tabular files: {filepath:?}");
+        return HttpResponse::BadRequest().json(StatusMessage::error(&err));
+    }
+    match api::local::entries::get_entry_for_commit(&repo, &commit, &filepath) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            log::debug!("df::blame {:?} is not in commit {}", filepath, commit.id);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+        Err(err) => {
+            log::error!("df::blame could not get entry {:?}: {}", filepath, err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    }
+    if let Some(key) = &query.key {
+        match key_in_schema(&repo, &commit, &filepath, key) {
+            Ok(true) => {}
+            Ok(false) => {
+                let err = format!("Column {key:?} is not in {filepath:?}");
+                return HttpResponse::BadRequest().json(StatusMessage::error(&err));
+            }
+            Err(err) => {
+                log::error!("df::blame could not read schema of {:?}: {}", filepath, err);
+                return HttpResponse::InternalServerError()
+                    .json(StatusMessage::internal_server_error());
+            }
+        }
+    }
+
+    // Blaming walks the history of the file, keep it off of the workers that serve requests
+    let key = query.key.clone();
+    let blame_path = filepath.clone();
+    let result =
+        web::block(move || blamer::blame(&repo, &commit, &blame_path, key.as_deref())).await;
+    match result {
+        Ok(Ok(df)) => {
+            let total_entries = df.height();
+            let total_pages = (total_entries / page_size) + 1;
+            let offset = ((page - 1) * page_size) as i64;
+            let mut page_df = df.slice(offset, page_size);
+
+            let response = JsonDataFrameSliceResponse {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_FOUND),
+                df: JsonDataFrame::from_df(&mut page_df),
+                full_size: JsonDataSize {
+                    width: df.width(),
+                    height: df.height(),
+                },
+                page_number: page,
+                page_size,
+                total_pages,
+                total_entries,
+            };
+            HttpResponse::Ok().json(response)
+        }
+        Ok(Err(err)) => {
+            log::error!("df::blame could not blame {:?}: {}", filepath, err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+        Err(err) => {
+            log::error!("df::blame blocking error {:?}: {}", filepath, err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Whether the schema of the file at the commit has the column, files without a schema are not checked
+fn key_in_schema(
+    repo: &LocalRepository,
+    commit: &Commit,
+    filepath: &Path,
+    key: &str,
+) -> Result<bool, OxenError> {
+    let reader = SchemaReader::new(repo, &commit.id)?;
+    match reader.get_schema_for_file(filepath)? {
+        Some(schema) => Ok(schema.fields.iter().any(|field| field.name == key)),
+        None => Ok(true),
+    }
+}
+
+/// Look up the rows for `field == value` filters from the schema field indices, if they exist
+fn indexed_rows(
+    repo: &LocalRepository,
+    commit_id: &str,
+    filepath: &Path,
+    opts: &DFOpts,
+) -> Option<Vec<u32>> {
+    let filter = opts.get_filter().ok()??;
+    let commit = api::local::commits::get_by_id(repo, commit_id).ok()??;
+    match schema_indexer::filter_indices(repo, &commit, filepath, &filter) {
+        Ok(rows) => rows,
+        Err(err) => {
+            log::error!("Could not look up indexed rows for {:?}: {}", filepath, err);
+            None
+        }
+    }
+}
+
 /// Provide some default vals for opts
 fn parse_opts(query: &web::Query<DFOptsQuery>, filter_ops: &mut DFOpts) -> DFOpts {
     // Default to 0..10 unless they ask for "all"
@@ -142,3 +322,73 @@ fn parse_opts(query: &web::Query<DFOptsQuery>, filter_ops: &mut DFOpts) -> DFOpt
 
     filter_ops.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{web, App};
+
+    use liboxen::command;
+    use liboxen::error::OxenError;
+    use liboxen::view::JsonDataFrameSliceResponse;
+
+    use crate::app_data::OxenAppData;
+    use crate::controllers;
+    use crate::test;
+
+    #[actix_web::test]
+    async fn test_controllers_df_blame() -> Result<(), OxenError> {
+        test::init_test_env();
+        let sync_dir = test::get_sync_dir()?;
+        let repo = test::create_local_repo(&sync_dir, "Testing-Namespace", "Testing-Name")?;
+        liboxen::test::populate_dir_with_training_data(&repo.path)?;
+        command::add(&repo, repo.path.join("annotations"))?;
+        command::add(&repo, repo.path.join("labels.txt"))?;
+        let commit = command::commit(&repo, "Adding annotations")?.unwrap();
+
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(OxenAppData {
+                    path: sync_dir.clone(),
+                })
+                .route(
+                    "/oxen/{namespace}/{repo_name}/blame/{resource:.*}",
+                    web::get().to(controllers::df::blame),
+                ),
+        )
+        .await;
+        let base_uri = format!("/oxen/Testing-Namespace/Testing-Name/blame/{}", commit.id);
+
+        let uri = format!("{base_uri}/annotations/train/bounding_box.csv?page_size=2");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+        let bytes = actix_http::body::to_bytes(resp.into_body()).await.unwrap();
+        let body: JsonDataFrameSliceResponse = serde_json::from_str(std::str::from_utf8(&bytes)?)?;
+        assert_eq!(body.total_entries, 6);
+        assert_eq!(body.df.size.height, 2);
+
+        let uri = format!("{base_uri}/annotations/train/bounding_box.csv?page_size=0");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("{base_uri}/annotations/train/bounding_box.csv?key=not_a_column");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("{base_uri}/labels.txt");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("{base_uri}/annotations/train/missing.csv");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use liboxen::constants::AVG_CHUNK_SIZE;
 use liboxen::error::OxenError;
 use liboxen::index::CommitDirReader;
 use liboxen::model::{Commit, CommitEntry, LocalRepository, RemoteEntry};
+use liboxen::storage;
 use liboxen::util;
 use liboxen::view::http::{MSG_RESOURCE_CREATED, MSG_RESOURCE_FOUND, STATUS_SUCCESS};
 use liboxen::view::{PaginatedEntries, RemoteEntryResponse, StatusMessage};
@@ -17,7 +18,6 @@ use flate2::Compression;
 use futures_util::stream::StreamExt as _;
 use serde::Deserialize;
 
-use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
@@ -67,7 +67,10 @@ pub async fn download_data_from_version_paths(
         Ok(Some(repo)) => {
             let mut bytes = web::BytesMut::new();
             while let Some(item) = body.next().await {
-                bytes.extend_from_slice(&item.unwrap());
+                match item {
+                    Ok(item) => bytes.extend_from_slice(&item),
+                    Err(err) => return HttpResponse::from_error(err),
+                }
             }
             log::debug!(
                 "download_data_from_version_paths got repo [{}] and content_ids size {}",
@@ -79,34 +82,21 @@ pub async fn download_data_from_version_paths(
             let mut line_delimited_files = String::new();
             gz.read_to_string(&mut line_delimited_files).unwrap();
 
-            let content_files: Vec<&str> = line_delimited_files.split('\n').collect();
-
-            let enc = GzEncoder::new(Vec::new(), Compression::default());
-            let mut tar = tar::Builder::new(enc);
+            let content_files: Vec<String> =
+                line_delimited_files.split('\n').map(String::from).collect();
 
-            log::debug!("Got {} content ids", content_files.len());
-            for content_file in content_files.iter() {
-                if content_file.is_empty() {
-                    // last line might be empty on split \n
-                    continue;
+            // The version store may block on the network, so read the versions off the workers
+            match web::block(move || tar_content_files(&repo, &content_files)).await {
+                Ok(Ok(buffer)) => HttpResponse::Ok().body(buffer),
+                Ok(Err(err)) => {
+                    log::error!("Could not tar content files: {}", err);
+                    HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
                 }
-
-                let version_path = repo.path.join(content_file);
-                if version_path.exists() {
-                    tar.append_path_with_name(version_path, content_file)
-                        .unwrap();
-                } else {
-                    log::error!(
-                        "Could not find content: {:?} -> {:?}",
-                        content_file,
-                        version_path
-                    );
+                Err(err) => {
+                    log::error!("Could not tar content files: {}", err);
+                    HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
                 }
             }
-
-            tar.finish().unwrap();
-            let buffer: Vec<u8> = tar.into_inner().unwrap().finish().unwrap();
-            HttpResponse::Ok().body(buffer)
         }
         Ok(None) => {
             log::debug!("Could not find repo with name {}", name);
@@ -137,20 +127,21 @@ pub async fn download_chunk(req: HttpRequest, query: web::Query<ChunkQuery>) ->
                     filepath
                 );
 
-                match util::fs::version_path_for_commit_id(&repo, &commit_id, &filepath) {
-                    Ok(version_path) => {
-                        let chunk_start: u64 = query.chunk_start.unwrap_or(0);
-                        let chunk_size: u64 = query.chunk_size.unwrap_or(AVG_CHUNK_SIZE);
-
-                        let mut f = File::open(version_path).unwrap();
-                        f.seek(std::io::SeekFrom::Start(chunk_start)).unwrap();
-                        let mut buffer = vec![0u8; chunk_size as usize];
-                        f.read_exact(&mut buffer).unwrap();
-
-                        HttpResponse::Ok().body(buffer)
+                let chunk_start: u64 = query.chunk_start.unwrap_or(0);
+                let chunk_size: u64 = query.chunk_size.unwrap_or(AVG_CHUNK_SIZE);
+                let result = web::block(move || {
+                    read_entry_chunk(&repo, &commit_id, &filepath, chunk_start, chunk_size)
+                })
+                .await;
+                match result {
+                    Ok(Ok(buffer)) => HttpResponse::Ok().body(buffer),
+                    Ok(Err(err)) => {
+                        log::error!("Error reading chunk of file {:?}", err);
+                        HttpResponse::InternalServerError()
+                            .json(StatusMessage::internal_server_error())
                     }
                     Err(err) => {
-                        log::error!("Error listing lines in file {:?}", err);
+                        log::error!("Error reading chunk of file {:?}", err);
                         HttpResponse::InternalServerError()
                             .json(StatusMessage::internal_server_error())
                     }
@@ -196,18 +187,33 @@ pub async fn download_page(req: HttpRequest, query: web::Query<PageNumQuery>) ->
         Ok(Some(repo)) => {
             log::debug!("download_entries got repo [{}]", name);
             match get_entries_for_page(&repo, commit_id, page, page_size) {
-                Ok((entries, commit)) => match compress_entries(&repo, &commit, &entries.entries) {
-                    Ok(buffer) => HttpResponse::Ok().body(buffer),
-                    Err(err) => {
-                        log::error!(
-                            "Unable to get compress {} entries Err: {}",
-                            entries.entries.len(),
-                            err
-                        );
-                        HttpResponse::InternalServerError()
-                            .json(StatusMessage::internal_server_error())
+                Ok((entries, commit)) => {
+                    let num_entries = entries.entries.len();
+                    let result =
+                        web::block(move || compress_entries(&repo, &commit, &entries.entries))
+                            .await;
+                    match result {
+                        Ok(Ok(buffer)) => HttpResponse::Ok().body(buffer),
+                        Ok(Err(err)) => {
+                            log::error!(
+                                "Unable to get compress {} entries Err: {}",
+                                num_entries,
+                                err
+                            );
+                            HttpResponse::InternalServerError()
+                                .json(StatusMessage::internal_server_error())
+                        }
+                        Err(err) => {
+                            log::error!(
+                                "Unable to get compress {} entries Err: {}",
+                                num_entries,
+                                err
+                            );
+                            HttpResponse::InternalServerError()
+                                .json(StatusMessage::internal_server_error())
+                        }
                     }
-                },
+                }
                 Err(status_message) => HttpResponse::InternalServerError().json(status_message),
             }
         }
@@ -222,12 +228,91 @@ pub async fn download_page(req: HttpRequest, query: web::Query<PageNumQuery>) ->
     }
 }
 
+/// Tars up the content files, skipping the ones that cannot be found
+fn tar_content_files(
+    repo: &LocalRepository,
+    content_files: &[String],
+) -> Result<Vec<u8>, OxenError> {
+    let enc = GzEncoder::new(Vec::new(), Compression::default());
+    let mut tar = tar::Builder::new(enc);
+
+    log::debug!("Got {} content ids", content_files.len());
+    for content_file in content_files.iter() {
+        if content_file.is_empty() {
+            // last line might be empty on split \n
+            continue;
+        }
+
+        match open_content_file(repo, content_file) {
+            Ok((size, data)) => {
+                append_data_to_tar(&mut tar, content_file, size, data)?;
+            }
+            Err(err) => {
+                log::error!("Could not find content: {:?} -> {}", content_file, err);
+            }
+        }
+    }
+
+    tar.finish()?;
+    let buffer: Vec<u8> = tar.into_inner()?.finish()?;
+    Ok(buffer)
+}
+
+/// Opens a path relative to the repo, like .oxen/versions/files/59/E029D4812AEBF0/COMMIT_ID.txt, from the version store,
+/// returning its size and a stream of its contents
+fn open_content_file(
+    repo: &LocalRepository,
+    content_file: &str,
+) -> Result<(u64, Box<dyn Read + Send>), OxenError> {
+    let hidden_dir = util::fs::oxen_hidden_dir(&repo.path);
+    let full_path = repo.path.join(content_file);
+    let version_path = util::fs::path_relative_to_dir(&full_path, &hidden_dir)?;
+    match storage::version_store::hash_and_filename_from_version_path(&version_path) {
+        Some((hash, filename)) => {
+            let version_store = repo.version_store()?;
+            let size = version_store.version_size(&hash, &filename)?;
+            Ok((size, version_store.open_version(&hash, &filename)?))
+        }
+        None => Err(OxenError::file_does_not_exist(content_file)),
+    }
+}
+
+fn read_entry_chunk(
+    repo: &LocalRepository,
+    commit_id: &str,
+    filepath: &Path,
+    chunk_start: u64,
+    chunk_size: u64,
+) -> Result<Vec<u8>, OxenError> {
+    let commit = api::local::commits::get_by_id(repo, commit_id)?
+        .ok_or_else(|| OxenError::commit_id_does_not_exist(commit_id))?;
+    let entry = api::local::entries::get_entry_for_commit(repo, &commit, filepath)?
+        .ok_or_else(|| OxenError::file_does_not_exist_in_commit(filepath, commit_id))?;
+    let version_store = repo.version_store()?;
+    version_store.get_version_chunk(&entry.hash, &entry.filename(), chunk_start, chunk_size)
+}
+
+fn append_data_to_tar<W: Write, R: Read>(
+    tar: &mut tar::Builder<W>,
+    name: &str,
+    size: u64,
+    data: R,
+) -> Result<(), OxenError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
 fn compress_entries(
     repo: &LocalRepository,
     commit: &Commit,
     entries: &[RemoteEntry],
 ) -> Result<Vec<u8>, OxenError> {
     let entry_reader = CommitDirReader::new(repo, commit)?;
+    let version_store = repo.version_store()?;
 
     let enc = GzEncoder::new(Vec::new(), Compression::default());
     let mut tar = tar::Builder::new(enc);
@@ -235,8 +320,9 @@ fn compress_entries(
     for entry in entries.iter() {
         let filename = &entry.filename;
         if let Some(entry) = entry_reader.get_entry(Path::new(filename))? {
-            let version_path = util::fs::version_path(repo, &entry);
-            tar.append_path_with_name(version_path, filename)?;
+            let size = version_store.version_size(&entry.hash, &entry.filename())?;
+            let data = version_store.open_version(&entry.hash, &entry.filename())?;
+            append_data_to_tar(&mut tar, filename, size, data)?;
         } else {
             log::error!(
                 "Could not read entry {} from commit {}",
@@ -332,12 +418,12 @@ pub async fn list_lines_in_file(req: HttpRequest, query: web::Query<PageNumQuery
                     commit_id,
                     filepath
                 );
-                match util::fs::version_path_for_commit_id(&repo, &commit_id, &filepath) {
-                    Ok(version_path) => {
+                match util::fs::local_version_for_commit_id(&repo, &commit_id, &filepath) {
+                    Ok(version) => {
                         let start = page * page_size;
                         let (lines, total_entries) =
                             liboxen::util::fs::read_lines_paginated_ret_size(
-                                &version_path,
+                                version.path(),
                                 start,
                                 page_size,
                             );
@@ -437,25 +523,39 @@ async fn p_create_entry(
     mut body: web::Payload,
     data: web::Query<CommitEntry>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    // Write entry to versions dir
-    let version_path = util::fs::version_path(repository, &data);
-
-    if let Some(parent) = version_path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)?;
-        }
-    }
-
-    let mut file = File::create(&version_path)?;
-    let mut total_bytes = 0;
+    // Stream the body to disk so large files never have to fit in memory
+    let tmp_dir = util::fs::oxen_hidden_dir(&repository.path)
+        .join("tmp")
+        .join("entries");
+    std::fs::create_dir_all(&tmp_dir)?;
+    let tmp_path = tmp_dir.join(uuid::Uuid::new_v4().to_string());
+    let mut file = std::fs::File::create(&tmp_path)?;
+    let mut num_bytes: usize = 0;
     while let Some(item) = body.next().await {
-        total_bytes += file.write(&item?)?;
+        let chunk = item?;
+        num_bytes += chunk.len();
+        file.write_all(&chunk)?;
     }
+    drop(file);
+
+    // Write entry to version store, off the workers since it may upload to a remote store
+    let repo = repository.clone();
+    let hash = data.hash.to_owned();
+    let filename = data.filename();
+    let stored = web::block(move || {
+        let stored = repo.version_store().and_then(|version_store| {
+            version_store.store_version_from_path(&hash, &filename, &tmp_path)
+        });
+        std::fs::remove_file(&tmp_path)?;
+        stored
+    })
+    .await?;
+    stored.map_err(actix_web::error::ErrorInternalServerError)?;
     log::debug!(
-        "Wrote {} bytes to for {:?} to {:?}",
-        total_bytes,
+        "Wrote {} bytes to for {:?} to version {}",
+        num_bytes,
         data.path,
-        version_path,
+        data.hash,
     );
 
     Ok(HttpResponse::Ok().json(RemoteEntryResponse {
@@ -12,7 +12,7 @@ use std::path::{Path, PathBuf};
 use liboxen::model::{DirEntry, LocalRepository};
 use liboxen::util;
 
-pub async fn get(req: HttpRequest) -> Result<NamedFile, actix_web::Error> {
+pub async fn get(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
     let app_data = req.app_data::<OxenAppData>().unwrap();
 
     let namespace: &str = req.match_info().get("namespace").unwrap();
@@ -30,19 +30,19 @@ pub async fn get(req: HttpRequest) -> Result<NamedFile, actix_web::Error> {
                     commit_id,
                     filepath
                 );
-                get_file_for_commit_id(&repo, &commit_id, &filepath)
+                get_file_for_commit_id(&req, &repo, &commit_id, &filepath)
             } else {
                 log::debug!("file::get could not find resource from uri {:?}", resource);
-                Ok(NamedFile::open("")?)
+                Ok(HttpResponse::NotFound().json(StatusMessage::resource_not_found()))
             }
         }
         Ok(None) => {
             log::debug!("file::get could not find repo with name {}", name);
-            Ok(NamedFile::open("")?)
+            Ok(HttpResponse::NotFound().json(StatusMessage::resource_not_found()))
         }
         Err(err) => {
             log::error!("unable to get file {:?}. Err: {}", resource, err);
-            Ok(NamedFile::open("")?)
+            Ok(HttpResponse::InternalServerError().json(StatusMessage::internal_server_error()))
         }
     }
 }
@@ -80,22 +80,25 @@ pub async fn meta_data_legacy(req: HttpRequest) -> HttpResponse {
             if let Ok(Some((commit_id, _, filepath))) =
                 util::resource::parse_resource(&repo, &resource)
             {
-                match util::fs::version_path_for_commit_id(&repo, &commit_id, &filepath) {
-                    Ok(version_path) => {
+                match util::fs::local_version_for_commit_id(&repo, &commit_id, &filepath) {
+                    Ok(version) => {
+                        let version_path = version.path();
                         log::debug!(
                             "file::meta_data looking for {:?} -> {:?}",
                             filepath,
                             version_path
                         );
 
-                        let meta = std::fs::metadata(&version_path).unwrap();
+                        let size = std::fs::metadata(version_path)
+                            .map(|meta| meta.len())
+                            .unwrap_or(0);
 
                         let meta = FileMetaDataResponse {
                             status: String::from(STATUS_SUCCESS),
                             status_message: String::from(MSG_RESOURCE_FOUND),
                             meta: FileMetaData {
-                                size: meta.len(),
-                                data_type: util::fs::file_datatype(&version_path),
+                                size,
+                                data_type: util::fs::file_datatype(version_path),
                                 resource: ResourceVersion {
                                     path: String::from(filepath.to_str().unwrap()),
                                     version: commit_id,
@@ -150,8 +153,9 @@ pub async fn meta_data(req: HttpRequest) -> HttpResponse {
             if let Ok(Some((commit_id, branch_or_commit_id, filepath))) =
                 util::resource::parse_resource(&repo, &resource)
             {
-                match util::fs::version_path_for_commit_id(&repo, &commit_id, &filepath) {
-                    Ok(version_path) => {
+                match util::fs::local_version_for_commit_id(&repo, &commit_id, &filepath) {
+                    Ok(version) => {
+                        let version_path = version.path();
                         log::debug!(
                             "file::meta_data looking for {:?} -> {:?}",
                             filepath,
@@ -159,7 +163,9 @@ pub async fn meta_data(req: HttpRequest) -> HttpResponse {
                         );
 
                         // Unwraps should be find since above call already checks for existence
-                        let meta = std::fs::metadata(&version_path).unwrap();
+                        let size = std::fs::metadata(version_path)
+                            .map(|meta| meta.len())
+                            .unwrap_or(0);
                         let latest_commit = api::local::commits::get_by_id(&repo, &commit_id)
                             .unwrap()
                             .unwrap();
@@ -174,9 +180,9 @@ pub async fn meta_data(req: HttpRequest) -> HttpResponse {
                             entry: DirEntry {
                                 filename: String::from(filepath.to_str().unwrap()),
                                 is_dir: false,
-                                size: meta.len(),
+                                size,
                                 latest_commit: Some(latest_commit),
-                                datatype: util::fs::file_datatype(&version_path),
+                                datatype: util::fs::file_datatype(version_path),
                                 resource,
                             },
                         };
@@ -212,23 +218,25 @@ pub async fn meta_data(req: HttpRequest) -> HttpResponse {
 }
 
 fn get_file_for_commit_id(
+    req: &HttpRequest,
     repo: &LocalRepository,
     commit_id: &str,
     filepath: &Path,
-) -> Result<NamedFile, actix_web::Error> {
-    match util::fs::version_path_for_commit_id(repo, commit_id, filepath) {
-        Ok(version_path) => {
+) -> Result<HttpResponse, actix_web::Error> {
+    match util::fs::local_version_for_commit_id(repo, commit_id, filepath) {
+        Ok(version) => {
             log::debug!(
                 "get_file_for_commit_id looking for {:?} -> {:?}",
                 filepath,
-                version_path
+                version.path()
             );
-            Ok(NamedFile::open(version_path)?)
+
+            // The open file keeps serving after a downloaded version is cleaned up
+            Ok(NamedFile::open(version.path())?.into_response(req))
         }
         Err(err) => {
             log::error!("get_file_for_commit_id get entry err: {:?}", err);
-            // gives a 404
-            Ok(NamedFile::open("")?)
+            Ok(HttpResponse::NotFound().json(StatusMessage::resource_not_found()))
         }
     }
 }
@@ -0,0 +1,106 @@
+use crate::app_data::OxenAppData;
+use crate::job_queue::JobQueue;
+use crate::view::ListJobsResponse;
+
+use liboxen::api;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use liboxen::view::http::{MSG_RESOURCE_FOUND, STATUS_SUCCESS};
+use liboxen::view::StatusMessage;
+
+/// Lists the queued, running and failed commit processing jobs for a repository
+pub async fn index(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    let queue = match req.app_data::<web::Data<JobQueue>>() {
+        Some(queue) => queue,
+        None => {
+            log::error!("jobs::index job queue is not running");
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    };
+
+    match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
+        Ok(Some(_)) => match queue.list_for_repo(namespace, name) {
+            Ok(jobs) => HttpResponse::Ok().json(ListJobsResponse {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_FOUND),
+                jobs,
+            }),
+            Err(err) => {
+                log::error!("jobs::index could not list jobs: {}", err);
+                HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+            }
+        },
+        Ok(None) => {
+            log::debug!("jobs::index could not find repo with name {}", name);
+            HttpResponse::NotFound().json(StatusMessage::resource_not_found())
+        }
+        Err(err) => {
+            log::error!("jobs::index Err: {}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http;
+    use actix_web::web;
+
+    use liboxen::command;
+    use liboxen::error::OxenError;
+
+    use crate::app_data::OxenAppData;
+    use crate::controllers;
+    use crate::job_queue::{JobQueue, JobStatus, JobTask};
+    use crate::test;
+    use crate::view::ListJobsResponse;
+
+    #[actix_web::test]
+    async fn test_controllers_jobs_index() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Jobs";
+        let repo = test::create_local_repo(&sync_dir, namespace, name)?;
+        let commit = command::head_commit(&repo)?;
+
+        let queue = web::Data::new(JobQueue::new(&sync_dir)?);
+        queue.enqueue_commit(namespace, name, &commit.id)?;
+        queue.enqueue_commit("other", "repo", &commit.id)?;
+
+        let uri = format!("/oxen/{namespace}/{name}/jobs");
+        let req = actix_web::test::TestRequest::with_uri(&uri)
+            .app_data(OxenAppData {
+                path: sync_dir.to_path_buf(),
+            })
+            .app_data(queue.clone())
+            .param("namespace", namespace)
+            .param("repo_name", name)
+            .to_http_request();
+
+        let resp = controllers::jobs::index(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let list: ListJobsResponse = serde_json::from_str(text)?;
+        assert_eq!(list.jobs.len(), 1);
+        assert_eq!(
+            list.jobs[0].task,
+            JobTask::ProcessCommit {
+                commit_id: commit.id.clone()
+            }
+        );
+        assert_eq!(list.jobs[0].status, JobStatus::Queued);
+
+        // cleanup
+        drop(queue);
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+use crate::app_data::OxenAppData;
+use crate::auth::access_keys::JWTClaim;
+use crate::auth::permissions::Role;
+use crate::auth::validator;
+use crate::controllers::branches;
+use crate::job_queue::JobQueue;
+
+use liboxen::api;
+use liboxen::error::OxenError;
+use liboxen::model::{LocalRepository, MergeRequestStatus};
+use liboxen::view::http::{
+    MSG_RESOURCE_CREATED, MSG_RESOURCE_FOUND, MSG_RESOURCE_UPDATED, STATUS_SUCCESS,
+};
+use liboxen::view::{
+    ListMergeRequestsResponse, MergeRequestNew, MergeRequestResponse, StatusMessage,
+};
+
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use std::path::Path;
+
+/// Merge requests opened into the repository from its forks
+pub async fn index(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
+        Ok(Some(repository)) => match api::local::forks::list_merge_requests(&repository) {
+            Ok(merge_requests) => HttpResponse::Ok().json(ListMergeRequestsResponse {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_FOUND),
+                merge_requests,
+            }),
+            Err(err) => {
+                log::error!("Unable to list merge requests. Err: {}", err);
+                HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+            }
+        },
+        Ok(None) => {
+            log::debug!("404 merge_requests::index could not get repo {}", name);
+            HttpResponse::NotFound().json(StatusMessage::resource_not_found())
+        }
+        Err(err) => {
+            log::error!(
+                "Err merge_requests::index could not get repo {} {:?}",
+                name,
+                err
+            );
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Opens a merge from a branch of a fork into this repository. Anyone who can read this repository
+/// and the fork can open one, it is only a record until a user who can write to this repository accepts it.
+pub async fn create(req: HttpRequest, body: String) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+
+    let data: MergeRequestNew = match serde_json::from_str(&body) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("Err merge_requests::create parse error: {:?}", err);
+            return HttpResponse::BadRequest().json(StatusMessage::error("Invalid body."));
+        }
+    };
+
+    let upstream = match get_repo(&app_data.path, namespace, name) {
+        Ok(repo) => repo,
+        Err(response) => return response,
+    };
+
+    // Do not let users open merge requests with the data of forks they cannot read
+    match validator::has_role(
+        &req,
+        &app_data.path,
+        &data.source_namespace,
+        &data.source_name,
+        Role::Read,
+    ) {
+        Ok(true) => {}
+        Ok(false) => {
+            let msg = format!("cannot read {}/{}", data.source_namespace, data.source_name);
+            return HttpResponse::Forbidden().json(StatusMessage::error(&msg));
+        }
+        Err(err) => {
+            log::error!("Err merge_requests::create could not check access: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    }
+
+    match api::local::forks::open_merge_request(
+        &app_data.path,
+        &upstream,
+        &data.source_namespace,
+        &data.source_name,
+        &data.branch,
+        &data.target_branch,
+    ) {
+        Ok(merge_request) => HttpResponse::Ok().json(MergeRequestResponse {
+            status: String::from(STATUS_SUCCESS),
+            status_message: String::from(MSG_RESOURCE_CREATED),
+            merge_request,
+        }),
+        // Not a fork, one of the branches does not exist or the branch is taken
+        Err(OxenError::Basic(msg)) => {
+            log::debug!("Could not open merge request: {}", msg);
+            HttpResponse::BadRequest().json(StatusMessage::error(&msg))
+        }
+        Err(err) => {
+            log::error!("Err merge_requests::create {:?}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Copies the commits of the merge request into this repository, on the branch named in the
+/// merge request, if the branch protection rules and pre-receive hooks allow it
+pub async fn accept(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    let merge_request_id: &str = req.match_info().get("merge_request_id").unwrap();
+
+    let upstream = match get_repo(&app_data.path, namespace, name) {
+        Ok(repo) => repo,
+        Err(response) => return response,
+    };
+    let merge_request = match api::local::forks::get_merge_request(&upstream, merge_request_id) {
+        Ok(Some(merge_request)) => merge_request,
+        Ok(None) => return HttpResponse::NotFound().json(StatusMessage::resource_not_found()),
+        Err(err) => {
+            log::error!(
+                "Err merge_requests::accept could not get merge request: {}",
+                err
+            );
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    };
+    if merge_request.status != MergeRequestStatus::Open {
+        let msg = format!("Merge request {merge_request_id} is not open");
+        return HttpResponse::BadRequest().json(StatusMessage::error(&msg));
+    }
+    if let Ok(Some(_)) = api::local::branches::get_by_name(&upstream, &merge_request.branch) {
+        let msg = format!("Branch '{}' already exists", merge_request.branch);
+        return HttpResponse::Conflict().json(StatusMessage::error(&msg));
+    }
+
+    if let Err(err) =
+        api::local::forks::import_merge_request(&app_data.path, &upstream, &merge_request)
+    {
+        log::error!(
+            "Err merge_requests::accept could not import commits: {}",
+            err
+        );
+        return HttpResponse::InternalServerError().json(StatusMessage::internal_server_error());
+    }
+    // Processed like a pushed commit, so a branch that requires valid commits can accept it on a later try
+    if let Some(queue) = req.app_data::<web::Data<JobQueue>>() {
+        if let Err(err) = queue.enqueue_commit(namespace, name, &merge_request.commit_id) {
+            log::error!(
+                "Could not queue commit {}: {}",
+                merge_request.commit_id,
+                err
+            );
+        }
+    }
+
+    // The same checks as pushing the branch
+    let accepter = req
+        .extensions()
+        .get::<JWTClaim>()
+        .map(|claim| claim.email().to_owned());
+    match branches::check_update(
+        &upstream,
+        &merge_request.branch,
+        &merge_request.commit_id,
+        accepter.as_deref(),
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(reason)) => {
+            log::info!("Rejected merge request {}: {}", merge_request_id, reason);
+            return HttpResponse::Forbidden().json(StatusMessage::error(&reason));
+        }
+        Err(err) => {
+            log::error!("Err merge_requests::accept could not check branch: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    }
+
+    match api::local::forks::accept_merge_request(&upstream, merge_request_id) {
+        Ok(merge_request) => HttpResponse::Ok().json(MergeRequestResponse {
+            status: String::from(STATUS_SUCCESS),
+            status_message: String::from(MSG_RESOURCE_UPDATED),
+            merge_request,
+        }),
+        Err(OxenError::Basic(msg)) => {
+            log::debug!("Could not accept merge request: {}", msg);
+            HttpResponse::BadRequest().json(StatusMessage::error(&msg))
+        }
+        Err(err) => {
+            log::error!("Err merge_requests::accept {:?}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+fn get_repo(sync_dir: &Path, namespace: &str, name: &str) -> Result<LocalRepository, HttpResponse> {
+    match api::local::repositories::get_by_namespace_and_name(sync_dir, namespace, name) {
+        Ok(Some(repo)) => Ok(repo),
+        Ok(None) => {
+            log::debug!("404 merge_requests could not get repo {}", name);
+            Err(HttpResponse::NotFound().json(StatusMessage::resource_not_found()))
+        }
+        Err(err) => {
+            log::error!("Err merge_requests could not get repo {} {:?}", name, err);
+            Err(HttpResponse::InternalServerError().json(StatusMessage::internal_server_error()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http;
+
+    use liboxen::api;
+    use liboxen::command;
+    use liboxen::error::OxenError;
+    use liboxen::model::MergeRequestStatus;
+    use liboxen::util;
+    use liboxen::view::{ListMergeRequestsResponse, MergeRequestNew, MergeRequestResponse};
+
+    use crate::branch_protection::{BranchProtection, BranchProtectionConfig};
+    use crate::controllers;
+    use crate::test;
+
+    fn new_merge_request(source_namespace: &str) -> Result<String, OxenError> {
+        Ok(serde_json::to_string(&MergeRequestNew {
+            source_namespace: String::from(source_namespace),
+            source_name: String::from("data"),
+            branch: String::from("main"),
+            target_branch: String::from("main"),
+        })?)
+    }
+
+    #[actix_web::test]
+    async fn test_controllers_merge_requests_create_list_and_accept() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let upstream = test::create_local_repo(&sync_dir, "ox", "data")?;
+        let file = upstream.path.join("hello.txt");
+        util::fs::write_to_path(&file, "hello")?;
+        command::add(&upstream, &file)?;
+        command::commit(&upstream, "Adding hello")?;
+
+        let fork = api::local::forks::fork(&sync_dir, "ox", "data", "bessie", "data", None)?;
+        let file = fork.path.join("world.txt");
+        util::fs::write_to_path(&file, "world")?;
+        command::add(&fork, &file)?;
+        let commit = command::commit(&fork, "Adding world")?.unwrap();
+
+        let uri = "/api/repos/ox/data/merge_requests";
+        let req = test::repo_request(&sync_dir, uri, "ox", "data");
+        let resp = controllers::merge_requests::create(req, new_merge_request("bessie")?).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let response: MergeRequestResponse = serde_json::from_str(std::str::from_utf8(&body)?)?;
+        let merge_request = response.merge_request;
+        assert_eq!(merge_request.branch, "bessie-main");
+        assert_eq!(merge_request.status, MergeRequestStatus::Open);
+        assert!(api::local::commits::get_by_id(&upstream, &commit.id)?.is_none());
+
+        let req = test::repo_request(&sync_dir, uri, "ox", "data");
+        let resp = controllers::merge_requests::index(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let list: ListMergeRequestsResponse = serde_json::from_str(std::str::from_utf8(&body)?)?;
+        assert_eq!(list.merge_requests.len(), 1);
+        assert_eq!(list.merge_requests[0].id, merge_request.id);
+
+        // The branch protection rules of the upstream apply to the new branch
+        let mut config = BranchProtectionConfig::default();
+        config.protect(BranchProtection {
+            branch: String::from("bessie-*"),
+            allowed_pushers: vec![String::from("ox@oxen.ai")],
+            ..BranchProtection::default()
+        });
+        config.save(&upstream)?;
+        let uri = format!(
+            "/api/repos/ox/data/merge_requests/{}/accept",
+            merge_request.id
+        );
+        let req = test::repo_request_with_param(
+            &sync_dir,
+            &uri,
+            "ox",
+            "data",
+            "merge_request_id",
+            merge_request.id.clone(),
+        );
+        let resp = controllers::merge_requests::accept(req).await;
+        assert_eq!(resp.status(), http::StatusCode::FORBIDDEN);
+        assert!(api::local::branches::get_by_name(&upstream, "bessie-main")?.is_none());
+
+        BranchProtectionConfig::default().save(&upstream)?;
+        let req = test::repo_request_with_param(
+            &sync_dir,
+            &uri,
+            "ox",
+            "data",
+            "merge_request_id",
+            merge_request.id.clone(),
+        );
+        let resp = controllers::merge_requests::accept(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let branch = api::local::branches::get_by_name(&upstream, "bessie-main")?.unwrap();
+        assert_eq!(branch.commit_id, commit.id);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_controllers_merge_requests_create_not_a_fork() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        test::create_local_repo(&sync_dir, "ox", "data")?;
+        test::create_local_repo(&sync_dir, "bessie", "data")?;
+
+        let uri = "/api/repos/ox/data/merge_requests";
+        let req = test::repo_request(&sync_dir, uri, "ox", "data");
+        let resp = controllers::merge_requests::create(req, new_merge_request("bessie")?).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::job_queue::JobQueue;
+use crate::metrics::Metrics;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use liboxen::view::StatusMessage;
+
+/// Serves the request, upload and job queue metrics in the Prometheus text format
+pub async fn index(req: HttpRequest) -> HttpResponse {
+    let queue = req.app_data::<web::Data<JobQueue>>();
+    match req.app_data::<web::Data<Metrics>>() {
+        Some(metrics) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(metrics.render(queue.map(|queue| queue.as_ref()))),
+        None => {
+            log::error!("metrics::index metrics are not being recorded");
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http;
+    use actix_web::web;
+
+    use liboxen::error::OxenError;
+
+    use crate::app_data::OxenAppData;
+    use crate::controllers;
+    use crate::metrics::Metrics;
+    use crate::test;
+
+    #[actix_web::test]
+    async fn test_controllers_metrics_index() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let req = actix_web::test::TestRequest::with_uri("/metrics")
+            .app_data(OxenAppData {
+                path: sync_dir.to_path_buf(),
+            })
+            .app_data(web::Data::new(Metrics::new()))
+            .to_http_request();
+
+        let resp = controllers::metrics::index(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        assert!(text.contains("# TYPE oxen_http_requests_total counter"));
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
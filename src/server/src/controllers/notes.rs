@@ -0,0 +1,231 @@
+use crate::app_data::OxenAppData;
+use crate::auth::access_keys::JWTClaim;
+
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+
+use liboxen::api;
+use liboxen::error::OxenError;
+use liboxen::index::{notes, CommitReader};
+use liboxen::model::{LocalRepository, Note};
+use liboxen::view::{ListNotesResponse, NotesUpdate, StatusMessage};
+
+/// Lists every note including the removed ones, so clients can sync the removals
+pub async fn index(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
+        Ok(Some(repository)) => match notes::list_all(&repository) {
+            Ok(notes) => HttpResponse::Ok().json(ListNotesResponse::success(notes)),
+            Err(err) => {
+                log::error!("Unable to list notes. Err: {}", err);
+                HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+            }
+        },
+        Ok(None) => {
+            log::debug!("404 controllers::notes::index could not get repo {}", name);
+            HttpResponse::NotFound().json(StatusMessage::resource_not_found())
+        }
+        Err(err) => {
+            log::error!(
+                "Err controllers::notes::index could not get repo {} {:?}",
+                name,
+                err
+            );
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Merges the pushed notes, the newest note for each commit wins. The notes that change are
+/// stamped with the user from the token and the time they were received. Responds with all the
+/// notes so the client can merge the ones it is missing.
+pub async fn update(req: HttpRequest, body: String) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+
+    let data: NotesUpdate = match serde_json::from_str(&body) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("Unable to parse notes. Err: {}\n{}", err, body);
+            return HttpResponse::BadRequest().json(StatusMessage::error(&err.to_string()));
+        }
+    };
+
+    let pusher = req
+        .extensions()
+        .get::<JWTClaim>()
+        .map(|claim| (claim.name().to_owned(), claim.email().to_owned()));
+
+    match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
+        Ok(Some(repository)) => {
+            match unknown_commit_id(&repository, &data.notes) {
+                Ok(None) => {}
+                Ok(Some(commit_id)) => {
+                    log::debug!(
+                        "400 controllers::notes::update unknown commit {}",
+                        commit_id
+                    );
+                    return HttpResponse::BadRequest().json(StatusMessage::error(&format!(
+                        "Commit {commit_id} does not exist"
+                    )));
+                }
+                Err(err) => {
+                    log::error!("Unable to read commits. Err: {}", err);
+                    return HttpResponse::InternalServerError()
+                        .json(StatusMessage::internal_server_error());
+                }
+            }
+
+            let pusher = pusher
+                .as_ref()
+                .map(|(name, email)| (name.as_str(), email.as_str()));
+            match notes::merge_pushed(&repository, &data.notes, pusher)
+                .and_then(|_| notes::list_all(&repository))
+            {
+                Ok(notes) => HttpResponse::Ok().json(ListNotesResponse::success(notes)),
+                Err(err) => {
+                    log::error!("Unable to merge notes. Err: {}", err);
+                    HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+                }
+            }
+        }
+        Ok(None) => {
+            log::debug!("404 controllers::notes::update could not get repo {}", name);
+            HttpResponse::NotFound().json(StatusMessage::resource_not_found())
+        }
+        Err(err) => {
+            log::error!(
+                "Err controllers::notes::update could not get repo {} {:?}",
+                name,
+                err
+            );
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// The first commit id a note is for that the repository does not have
+fn unknown_commit_id(
+    repository: &LocalRepository,
+    notes: &[Note],
+) -> Result<Option<String>, OxenError> {
+    let commit_reader = CommitReader::new(repository)?;
+    Ok(notes
+        .iter()
+        .find(|note| !commit_reader.commit_id_exists(&note.commit_id))
+        .map(|note| note.commit_id.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use actix_web::body::to_bytes;
+    use actix_web::{http, HttpMessage};
+
+    use liboxen::command;
+    use liboxen::error::OxenError;
+    use liboxen::model::Note;
+    use liboxen::view::http::STATUS_SUCCESS;
+    use liboxen::view::{ListNotesResponse, NotesUpdate};
+
+    use time::{Duration, OffsetDateTime};
+
+    use crate::auth::access_keys::JWTClaim;
+    use crate::controllers;
+    use crate::test;
+
+    #[actix_web::test]
+    async fn test_controllers_notes_update_and_index() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Notes";
+        let repo = test::create_local_repo(&sync_dir, namespace, name)?;
+        let commit = command::head_commit(&repo)?;
+
+        let data = NotesUpdate {
+            notes: vec![Note {
+                commit_id: commit.id.to_owned(),
+                message: String::from("Reviewed"),
+                author: String::from("Ox"),
+                email: String::from("ox@oxen.ai"),
+                timestamp: OffsetDateTime::now_utc(),
+                deleted: false,
+            }],
+        };
+        let uri = format!("/oxen/{namespace}/{name}/notes");
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let resp = controllers::notes::update(req, serde_json::to_string(&data)?).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let resp = controllers::notes::index(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let list: ListNotesResponse = serde_json::from_str(text)?;
+        assert_eq!(list.status, STATUS_SUCCESS);
+        assert_eq!(list.notes.len(), 1);
+        assert_eq!(list.notes[0].commit_id, commit.id);
+        assert_eq!(list.notes[0].message, "Reviewed");
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_controllers_notes_update_stamps_user_and_rejects_unknown_commits(
+    ) -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Notes-Stamped";
+        let repo = test::create_local_repo(&sync_dir, namespace, name)?;
+        let commit = command::head_commit(&repo)?;
+
+        let note = Note {
+            commit_id: commit.id.to_owned(),
+            message: String::from("Reviewed"),
+            author: String::from("Someone"),
+            email: String::from("someone@else.com"),
+            timestamp: OffsetDateTime::now_utc() + Duration::days(365),
+            deleted: false,
+        };
+        let claim: JWTClaim =
+            serde_json::from_str(r#"{"id": "1", "name": "Ox", "email": "ox@oxen.ai"}"#)?;
+        let uri = format!("/oxen/{namespace}/{name}/notes");
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        req.extensions_mut().insert(claim);
+        let data = NotesUpdate {
+            notes: vec![note.clone()],
+        };
+        let resp = controllers::notes::update(req, serde_json::to_string(&data)?).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let list: ListNotesResponse = serde_json::from_str(text)?;
+        assert_eq!(list.notes.len(), 1);
+        assert_eq!(list.notes[0].author, "Ox");
+        assert_eq!(list.notes[0].email, "ox@oxen.ai");
+        assert!(list.notes[0].timestamp < note.timestamp);
+
+        let data = NotesUpdate {
+            notes: vec![Note {
+                commit_id: String::from("not-a-commit"),
+                ..note
+            }],
+        };
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let resp = controllers::notes::update(req, serde_json::to_string(&data)?).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
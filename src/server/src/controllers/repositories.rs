@@ -1,6 +1,14 @@
 use crate::app_data::OxenAppData;
+use crate::auth::access_keys::JWTClaim;
+use crate::auth::permissions::{PermissionsManager, Role};
+use crate::auth::validator::Identity;
+use crate::limits;
+use crate::webhooks;
+use crate::webhooks::WebhookEvent;
 
 use liboxen::api;
+use liboxen::error::OxenError;
+use liboxen::storage::StorageConfig;
 use liboxen::util;
 use liboxen::view::http::{
     MSG_RESOURCE_CREATED, MSG_RESOURCE_DELETED, MSG_RESOURCE_FOUND, STATUS_SUCCESS,
@@ -8,12 +16,14 @@ use liboxen::view::http::{
 use liboxen::view::repository::DataTypeView;
 use liboxen::view::repository::RepositoryStatsResponse;
 use liboxen::view::repository::RepositoryStatsView;
-use liboxen::view::{ListRepositoryResponse, RepositoryResponse, RepositoryView, StatusMessage};
+use liboxen::view::{
+    ListRepositoryResponse, RepositoryFork, RepositoryResponse, RepositoryView, StatusMessage,
+};
 
-use liboxen::model::{LocalRepository, RepositoryNew};
+use liboxen::model::{ForkOrigin, LocalRepository, RepositoryNew};
 
 use actix_files::NamedFile;
-use actix_web::{HttpRequest, HttpResponse};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
 use std::path::{Path, PathBuf};
 
 pub async fn index(req: HttpRequest) -> HttpResponse {
@@ -23,12 +33,33 @@ pub async fn index(req: HttpRequest) -> HttpResponse {
     if let Some(namespace) = namespace {
         let namespace_path = &app_data.path.join(namespace);
 
+        // Only list the repos the user can read when auth is enabled
+        let identity = req.extensions().get::<Identity>().cloned();
+        let permissions = match identity {
+            Some(_) => match PermissionsManager::new_read_only(&app_data.path) {
+                Ok(permissions) => Some(permissions),
+                Err(err) => {
+                    log::error!("Could not read permissions: {}", err);
+                    return HttpResponse::InternalServerError()
+                        .json(StatusMessage::internal_server_error());
+                }
+            },
+            None => None,
+        };
+
         let repos: Vec<RepositoryView> =
             api::local::repositories::list_repos_in_namespace(namespace_path)
                 .iter()
+                .filter(|repo| match (&permissions, &identity) {
+                    (Some(permissions), Some(identity)) => permissions
+                        .is_authorized(namespace, &repo.dirname(), identity.email(), Role::Read)
+                        .unwrap_or(false),
+                    _ => true,
+                })
                 .map(|repo| RepositoryView {
                     name: repo.dirname(),
                     namespace: namespace.to_string(),
+                    fork_of: api::local::forks::get_origin(repo).unwrap_or(None),
                 })
                 .collect();
         let view = ListRepositoryResponse {
@@ -50,14 +81,21 @@ pub async fn show(req: HttpRequest) -> HttpResponse {
     let name: Option<&str> = req.match_info().get("repo_name");
     if let (Some(name), Some(namespace)) = (name, namespace) {
         match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
-            Ok(Some(_)) => HttpResponse::Ok().json(RepositoryResponse {
-                status: String::from(STATUS_SUCCESS),
-                status_message: String::from(MSG_RESOURCE_FOUND),
-                repository: RepositoryView {
-                    namespace: String::from(namespace),
-                    name: String::from(name),
-                },
-            }),
+            Ok(Some(repo)) => match api::local::forks::get_origin(&repo) {
+                Ok(fork_of) => HttpResponse::Ok().json(RepositoryResponse {
+                    status: String::from(STATUS_SUCCESS),
+                    status_message: String::from(MSG_RESOURCE_FOUND),
+                    repository: RepositoryView {
+                        namespace: String::from(namespace),
+                        name: String::from(name),
+                        fork_of,
+                    },
+                }),
+                Err(err) => {
+                    log::error!("Could not read fork origin of {}: {}", name, err);
+                    HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+                }
+            },
 
             Ok(None) => {
                 log::debug!("404 Could not find repo: {}", name);
@@ -121,28 +159,216 @@ pub async fn create(req: HttpRequest, body: String) -> HttpResponse {
     // println!("controllers::repositories::create body:\n{}", body);
     let data: Result<RepositoryNew, serde_json::Error> = serde_json::from_str(&body);
     match data {
-        Ok(data) => match api::local::repositories::create_empty(&app_data.path, &data) {
-            Ok(_) => HttpResponse::Ok().json(RepositoryResponse {
+        Ok(data) => {
+            let claim = req.extensions().get::<JWTClaim>().cloned();
+            if let Some(claim) = &claim {
+                match can_create_in_namespace(&app_data.path, &data.namespace, claim) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        let msg = format!(
+                            "{} cannot create repositories in namespace {}",
+                            claim.email(),
+                            data.namespace
+                        );
+                        return HttpResponse::Forbidden().json(StatusMessage::error(&msg));
+                    }
+                    Err(err) => {
+                        log::error!("Could not read permissions: {}", err);
+                        return HttpResponse::InternalServerError()
+                            .json(StatusMessage::internal_server_error());
+                    }
+                }
+            }
+
+            p_create(&req, &app_data.path, &data, claim.as_ref())
+        }
+        Err(err) => {
+            log::error!(
+                "Err api::local::repositories::create parse error: {:?}",
+                err
+            );
+            HttpResponse::BadRequest().json(StatusMessage::error("Invalid body."))
+        }
+    }
+}
+
+pub async fn fork(req: HttpRequest, body: String) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+
+    let namespace: Option<&str> = req.match_info().get("namespace");
+    let name: Option<&str> = req.match_info().get("repo_name");
+    if let (Some(name), Some(namespace)) = (name, namespace) {
+        p_fork(&req, &app_data.path, namespace, name, &body)
+    } else {
+        let msg = "Could not find `name` or `namespace` param...";
+        HttpResponse::BadRequest().json(StatusMessage::error(msg))
+    }
+}
+
+fn p_fork(
+    req: &HttpRequest,
+    sync_dir: &Path,
+    namespace: &str,
+    name: &str,
+    body: &str,
+) -> HttpResponse {
+    let data: RepositoryFork = match serde_json::from_str(body) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("Err controllers::repositories::fork parse error: {:?}", err);
+            return HttpResponse::BadRequest().json(StatusMessage::error("Invalid body."));
+        }
+    };
+
+    match api::local::repositories::get_by_namespace_and_name(sync_dir, namespace, name) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            log::debug!("404 Could not find repo: {}", name);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+        Err(err) => {
+            log::error!("Err finding repo: {} => {:?}", name, err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    }
+
+    let claim = req.extensions().get::<JWTClaim>().cloned();
+    if let Some(claim) = &claim {
+        match can_create_in_namespace(sync_dir, &data.namespace, claim) {
+            Ok(true) => {}
+            Ok(false) => {
+                let msg = format!(
+                    "{} cannot create repositories in namespace {}",
+                    claim.email(),
+                    data.namespace
+                );
+                return HttpResponse::Forbidden().json(StatusMessage::error(&msg));
+            }
+            Err(err) => {
+                log::error!("Could not read permissions: {}", err);
+                return HttpResponse::InternalServerError()
+                    .json(StatusMessage::internal_server_error());
+            }
+        }
+    }
+
+    if sync_dir.join(&data.namespace).join(&data.name).exists() {
+        let msg = format!("Repository {}/{} already exists", data.namespace, data.name);
+        return HttpResponse::Conflict().json(StatusMessage::error(&msg));
+    }
+
+    // The fork gets its own prefix in the server's version store, like any new repository
+    let storage = storage_for_repo(req, &data.namespace, &data.name);
+    match api::local::forks::fork(
+        sync_dir,
+        namespace,
+        name,
+        &data.namespace,
+        &data.name,
+        storage,
+    ) {
+        Ok(_) => {
+            if let Some(claim) = &claim {
+                let result = PermissionsManager::new(sync_dir).and_then(|permissions| {
+                    permissions.grant(&data.namespace, &data.name, claim.email(), Role::Admin)
+                });
+                if let Err(err) = result {
+                    log::error!("Could not grant creator access to fork: {}", err);
+                }
+            }
+
+            let view = RepositoryResponse {
                 status: String::from(STATUS_SUCCESS),
                 status_message: String::from(MSG_RESOURCE_CREATED),
                 repository: RepositoryView {
                     namespace: data.namespace.clone(),
                     name: data.name.clone(),
+                    fork_of: Some(ForkOrigin {
+                        namespace: String::from(namespace),
+                        name: String::from(name),
+                    }),
                 },
-            }),
+            };
+            webhooks::notify(
+                req,
+                &data.namespace,
+                &data.name,
+                WebhookEvent::RepoCreated,
+                &view,
+            );
+            HttpResponse::Ok().json(view)
+        }
+        Err(err) => {
+            log::error!("Err api::local::forks::fork: {:?}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
 
-            Err(err) => {
-                println!("Err api::local::repositories::create: {err:?}");
-                log::error!("Err api::local::repositories::create: {:?}", err);
-                HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+/// New repositories keep their versions wherever the server is configured to
+fn storage_for_repo(req: &HttpRequest, namespace: &str, name: &str) -> Option<StorageConfig> {
+    req.app_data::<web::Data<StorageConfig>>()
+        .filter(|storage| !storage.is_local())
+        .map(|storage| storage.for_repo(namespace, name))
+}
+
+/// Only the owners of a namespace can create repositories in it, once it has owners
+fn can_create_in_namespace(
+    sync_dir: &Path,
+    namespace: &str,
+    claim: &JWTClaim,
+) -> Result<bool, OxenError> {
+    let permissions = PermissionsManager::new_read_only(sync_dir)?;
+    match permissions.get_namespace(namespace)? {
+        Some(ns_perms) if !ns_perms.owners.is_empty() => {
+            Ok(ns_perms.owners.iter().any(|owner| owner == claim.email()))
+        }
+        _ => Ok(true),
+    }
+}
+
+fn p_create(
+    req: &HttpRequest,
+    sync_dir: &Path,
+    data: &RepositoryNew,
+    claim: Option<&JWTClaim>,
+) -> HttpResponse {
+    let storage = storage_for_repo(req, &data.namespace, &data.name);
+    match api::local::repositories::create_empty_with_storage(sync_dir, data, storage) {
+        Ok(_) => {
+            // The creator administers the repository, which is private until made public
+            if let Some(claim) = claim {
+                let result = PermissionsManager::new(sync_dir).and_then(|permissions| {
+                    permissions.grant(&data.namespace, &data.name, claim.email(), Role::Admin)
+                });
+                if let Err(err) = result {
+                    log::error!("Could not grant creator access to repository: {}", err);
+                }
             }
-        },
-        Err(err) => {
-            log::error!(
-                "Err api::local::repositories::create parse error: {:?}",
-                err
+
+            let view = RepositoryResponse {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_CREATED),
+                repository: RepositoryView {
+                    namespace: data.namespace.clone(),
+                    name: data.name.clone(),
+                    fork_of: None,
+                },
+            };
+            webhooks::notify(
+                req,
+                &data.namespace,
+                &data.name,
+                WebhookEvent::RepoCreated,
+                &view,
             );
-            HttpResponse::BadRequest().json(StatusMessage::error("Invalid body."))
+            HttpResponse::Ok().json(view)
+        }
+        Err(err) => {
+            println!("Err api::local::repositories::create: {err:?}");
+            log::error!("Err api::local::repositories::create: {:?}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
         }
     }
 }
@@ -155,10 +381,20 @@ pub async fn delete(req: HttpRequest) -> HttpResponse {
     if let (Some(name), Some(namespace)) = (name, namespace) {
         match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
             Ok(Some(repository)) => match api::local::repositories::delete(repository) {
-                Ok(_) => HttpResponse::Ok().json(StatusMessage {
-                    status: String::from(STATUS_SUCCESS),
-                    status_message: String::from(MSG_RESOURCE_DELETED),
-                }),
+                Ok(_) => {
+                    // Do not leave the access behind for a new repository with the same name
+                    if let Err(err) = PermissionsManager::new(&app_data.path)
+                        .and_then(|permissions| permissions.delete_repo(namespace, name))
+                    {
+                        log::error!("Could not delete repository permissions: {}", err);
+                    }
+                    limits::refresh_namespace_usage(&app_data.path, namespace);
+
+                    HttpResponse::Ok().json(StatusMessage {
+                        status: String::from(STATUS_SUCCESS),
+                        status_message: String::from(MSG_RESOURCE_DELETED),
+                    })
+                }
                 Err(err) => {
                     log::error!("Error deleting repository: {}", err);
                     HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
@@ -242,14 +478,14 @@ fn p_get_file_for_commit_id(
     commit_id: &str,
     filepath: &Path,
 ) -> Result<NamedFile, actix_web::Error> {
-    match util::fs::version_path_for_commit_id(repo, commit_id, filepath) {
-        Ok(version_path) => {
+    match util::fs::local_version_for_commit_id(repo, commit_id, filepath) {
+        Ok(version) => {
             log::debug!(
                 "p_get_file_for_commit_id looking for {:?} -> {:?}",
                 filepath,
-                version_path
+                version.path()
             );
-            Ok(NamedFile::open(version_path)?)
+            Ok(NamedFile::open(version.path())?)
         }
         Err(err) => {
             log::error!("p_get_file_for_commit_id get entry err: {:?}", err);
@@ -271,7 +507,8 @@ mod tests {
     use liboxen::model::{Commit, RepositoryNew};
 
     use liboxen::view::http::STATUS_SUCCESS;
-    use liboxen::view::{ListRepositoryResponse, RepositoryResponse};
+    use liboxen::view::{ListRepositoryResponse, RepositoryFork, RepositoryResponse};
+    use std::collections::HashMap;
     use time::OffsetDateTime;
 
     use crate::controllers;
@@ -362,6 +599,8 @@ mod tests {
                 author: String::from("Ox"),
                 email: String::from("ox@oxen.ai"),
                 timestamp,
+                metadata: HashMap::new(),
+                signature: None,
             }),
         };
         let data = serde_json::to_string(&repo_new)?;
@@ -382,4 +621,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[actix_web::test]
+    async fn test_controllers_repositories_fork() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        test::create_local_repo(&sync_dir, "ox", "data")?;
+
+        let data = serde_json::to_string(&RepositoryFork {
+            namespace: String::from("bessie"),
+            name: String::from("data"),
+        })?;
+        let uri = "/api/repos/ox/data/fork";
+        let req = test::repo_request(&sync_dir, uri, "ox", "data");
+        let resp = controllers::repositories::fork(req, data.clone()).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let repo_response: RepositoryResponse = serde_json::from_str(text)?;
+        assert_eq!(repo_response.repository.namespace, "bessie");
+        let fork_of = repo_response.repository.fork_of.unwrap();
+        assert_eq!(fork_of.namespace, "ox");
+
+        // Shows where the fork came from
+        let uri = "/api/repos/bessie/data";
+        let req = test::repo_request(&sync_dir, uri, "bessie", "data");
+        let resp = controllers::repositories::show(req).await;
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let repo_response: RepositoryResponse = serde_json::from_str(text)?;
+        assert_eq!(repo_response.repository.fork_of.unwrap().name, "data");
+
+        // Cannot fork over an existing repository
+        let uri = "/api/repos/ox/data/fork";
+        let req = test::repo_request(&sync_dir, uri, "ox", "data");
+        let resp = controllers::repositories::fork(req, data).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,196 @@
+use crate::app_data::OxenAppData;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use liboxen::index::searcher;
+use liboxen::view::http::{MSG_RESOURCE_FOUND, STATUS_SUCCESS};
+use liboxen::view::{PaginatedSearchMatches, StatusMessage};
+use liboxen::{api, command, constants};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub glob: Option<String>,
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+/// A page of the lines and cells that match the pattern in the files of the commit
+pub async fn index(req: HttpRequest, query: web::Query<SearchQuery>) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    let commit_or_branch: &str = req.match_info().get("commit_or_branch").unwrap();
+
+    log::debug!(
+        "search::index repo name [{}] commit [{}] pattern [{}]",
+        name,
+        commit_or_branch,
+        query.pattern
+    );
+    let repo = match api::local::repositories::get_by_namespace_and_name(
+        &app_data.path,
+        namespace,
+        name,
+    ) {
+        Ok(Some(repo)) => repo,
+        Ok(None) => {
+            log::debug!("search::index could not find repo with name {}", name);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+        Err(err) => {
+            log::error!("search::index Err: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    };
+
+    let commit = match api::local::commits::get_by_id_or_branch(&repo, commit_or_branch) {
+        Ok(Some(commit)) => commit,
+        _ => {
+            log::debug!("search::index could not find commit {}", commit_or_branch);
+            return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+        }
+    };
+
+    let page_size = query.page_size.unwrap_or(constants::DEFAULT_PAGE_SIZE);
+    let page = query.page.unwrap_or(constants::DEFAULT_PAGE_NUM).max(1);
+    if page_size == 0 {
+        return HttpResponse::BadRequest()
+            .json(StatusMessage::error("page_size must be greater than 0"));
+    }
+
+    if let Err(err) = searcher::check_query(&repo, &query.pattern, query.glob.as_deref()) {
+        return HttpResponse::BadRequest().json(StatusMessage::error(&err.to_string()));
+    }
+
+    // Only search as far as the requested page, and one match past it to know if there are more
+    let max_matches = page
+        .saturating_mul(page_size)
+        .saturating_add(1)
+        .min(constants::MAX_SEARCH_MATCHES);
+
+    // Searching reads every file of the commit, keep it off of the workers that serve requests
+    let pattern = query.pattern.clone();
+    let glob = query.glob.clone();
+    let search_commit = commit.clone();
+    let result = web::block(move || {
+        command::search(
+            &repo,
+            &search_commit,
+            &pattern,
+            glob.as_deref(),
+            Some(max_matches),
+        )
+    })
+    .await;
+    match result {
+        Ok(Ok(matches)) => {
+            let truncated = matches.len() >= max_matches;
+            // Leave out the match past this page that was only searched for to set `truncated`
+            let total_entries = matches.len().min(page.saturating_mul(page_size));
+            let total_pages = (total_entries + page_size - 1) / page_size;
+            let matches = matches
+                .into_iter()
+                .skip((page - 1) * page_size)
+                .take(page_size)
+                .collect();
+
+            HttpResponse::Ok().json(PaginatedSearchMatches {
+                status: String::from(STATUS_SUCCESS),
+                status_message: String::from(MSG_RESOURCE_FOUND),
+                commit_id: commit.id,
+                matches,
+                page_size,
+                page_number: page,
+                total_pages,
+                total_entries,
+                truncated,
+            })
+        }
+        Ok(Err(err)) => {
+            log::error!("search::index could not search {}: {}", commit.id, err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+        Err(err) => {
+            log::error!("search::index blocking error {}: {}", commit.id, err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http, web, App};
+
+    use liboxen::command;
+    use liboxen::error::OxenError;
+    use liboxen::view::PaginatedSearchMatches;
+
+    use crate::app_data::OxenAppData;
+    use crate::controllers;
+    use crate::test;
+
+    #[actix_web::test]
+    async fn test_controllers_search_index() -> Result<(), OxenError> {
+        test::init_test_env();
+        let sync_dir = test::get_sync_dir()?;
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Search";
+        let repo = test::create_local_repo(&sync_dir, namespace, name)?;
+        liboxen::test::populate_dir_with_training_data(&repo.path)?;
+        command::add(&repo, &repo.path)?;
+        command::commit(&repo, "Adding training data")?;
+
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(OxenAppData {
+                    path: sync_dir.clone(),
+                })
+                .route(
+                    "/oxen/{namespace}/{repo_name}/search/{commit_or_branch:.*}",
+                    web::get().to(controllers::search::index),
+                ),
+        )
+        .await;
+
+        let uri = format!("/oxen/{namespace}/{name}/search/main?pattern=cat&page_size=3");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let bytes = actix_http::body::to_bytes(resp.into_body()).await.unwrap();
+        let page: PaginatedSearchMatches = serde_json::from_str(std::str::from_utf8(&bytes)?)?;
+        assert_eq!(page.matches.len(), 3);
+        assert_eq!(page.page_number, 1);
+        // Stops one match past the first page, which is not counted in the totals
+        assert_eq!(page.total_entries, 3);
+        assert_eq!(page.total_pages, 1);
+        assert!(page.truncated);
+
+        let uri = format!("/oxen/{namespace}/{name}/search/main?pattern=cat&page_size=0");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("/oxen/{namespace}/{name}/search/main?pattern=(");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("/oxen/{namespace}/{name}/search/main?pattern=cat&glob=a%5B");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let uri = format!("/oxen/{namespace}/{name}/search/not-a-branch?pattern=cat");
+        let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::app_data::OxenAppData;
+use crate::view::{ListWebhooksResponse, NewWebhook, WebhookResponse, WebhookView};
+use crate::webhooks::{Webhook, WebhookConfig};
+
+use liboxen::api;
+use liboxen::view::http::{
+    MSG_RESOURCE_CREATED, MSG_RESOURCE_DELETED, MSG_RESOURCE_FOUND, STATUS_SUCCESS,
+};
+use liboxen::view::StatusMessage;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+
+/// Lists the webhooks that get the events of a repository, without their secrets
+pub async fn index(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+
+    if let Some(response) = repo_not_found(&req, namespace, name) {
+        return response;
+    }
+
+    match WebhookConfig::load(&app_data.path) {
+        Ok(config) => HttpResponse::Ok().json(ListWebhooksResponse {
+            status: String::from(STATUS_SUCCESS),
+            status_message: String::from(MSG_RESOURCE_FOUND),
+            webhooks: config
+                .list_for(namespace, Some(name))
+                .into_iter()
+                .map(WebhookView::from)
+                .collect(),
+        }),
+        Err(err) => {
+            log::error!("webhooks::index could not load webhooks: {}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Registers a webhook for the repository, the response has the secret its requests are signed with
+pub async fn create(req: HttpRequest, body: web::Json<NewWebhook>) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+
+    if let Some(response) = repo_not_found(&req, namespace, name) {
+        return response;
+    }
+
+    let new_webhook = body.into_inner();
+    if let Err(err) = Webhook::validate_url(&new_webhook.url) {
+        return HttpResponse::BadRequest().json(StatusMessage::error(&err.to_string()));
+    }
+
+    let webhook = Webhook::new(namespace, Some(name), &new_webhook.url, new_webhook.events);
+    match WebhookConfig::add(&app_data.path, &webhook) {
+        Ok(_) => HttpResponse::Ok().json(WebhookResponse {
+            status: String::from(STATUS_SUCCESS),
+            status_message: String::from(MSG_RESOURCE_CREATED),
+            webhook: WebhookView::from(&webhook),
+            secret: webhook.secret.clone(),
+        }),
+        Err(err) => {
+            log::error!("webhooks::create could not save webhook: {}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+/// Removes a webhook of the repository
+pub async fn delete(req: HttpRequest) -> HttpResponse {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    let namespace: &str = req.match_info().get("namespace").unwrap();
+    let name: &str = req.match_info().get("repo_name").unwrap();
+    let webhook_id: &str = req.match_info().get("webhook_id").unwrap();
+
+    if let Some(response) = repo_not_found(&req, namespace, name) {
+        return response;
+    }
+
+    // Admins of a repository cannot remove the webhooks of other repositories, or of the whole namespace
+    let belongs_to_repo = match WebhookConfig::load(&app_data.path) {
+        Ok(config) => config
+            .list_for(namespace, Some(name))
+            .iter()
+            .any(|webhook| webhook.id == webhook_id),
+        Err(err) => {
+            log::error!("webhooks::delete could not load webhooks: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(StatusMessage::internal_server_error());
+        }
+    };
+    if !belongs_to_repo {
+        return HttpResponse::NotFound().json(StatusMessage::resource_not_found());
+    }
+
+    match WebhookConfig::delete(&app_data.path, webhook_id) {
+        Ok(true) => HttpResponse::Ok().json(StatusMessage::success(MSG_RESOURCE_DELETED)),
+        Ok(false) => HttpResponse::NotFound().json(StatusMessage::resource_not_found()),
+        Err(err) => {
+            log::error!("webhooks::delete could not remove webhook: {}", err);
+            HttpResponse::InternalServerError().json(StatusMessage::internal_server_error())
+        }
+    }
+}
+
+fn repo_not_found(req: &HttpRequest, namespace: &str, name: &str) -> Option<HttpResponse> {
+    let app_data = req.app_data::<OxenAppData>().unwrap();
+    match api::local::repositories::get_by_namespace_and_name(&app_data.path, namespace, name) {
+        Ok(Some(_)) => None,
+        Ok(None) => {
+            log::debug!("webhooks could not find repo {}/{}", namespace, name);
+            Some(HttpResponse::NotFound().json(StatusMessage::resource_not_found()))
+        }
+        Err(err) => {
+            log::error!(
+                "webhooks could not get repo {}/{}: {}",
+                namespace,
+                name,
+                err
+            );
+            Some(HttpResponse::InternalServerError().json(StatusMessage::internal_server_error()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::{http, web};
+
+    use liboxen::error::OxenError;
+
+    use crate::controllers;
+    use crate::test;
+    use crate::view::{ListWebhooksResponse, NewWebhook, WebhookResponse};
+    use crate::webhooks::{Webhook, WebhookConfig, WebhookEvent};
+
+    #[actix_web::test]
+    async fn test_controllers_webhooks_create_list_delete() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Webhooks";
+        test::create_local_repo(&sync_dir, namespace, name)?;
+        // A webhook for the whole namespace is not the repository's to list or remove
+        let namespace_webhook = Webhook::new(namespace, None, "http://localhost/all", vec![]);
+        WebhookConfig::add(&sync_dir, &namespace_webhook)?;
+
+        let uri = format!("/api/repos/{namespace}/{name}/webhooks");
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let body = NewWebhook {
+            url: String::from("http://localhost/hook"),
+            events: vec![WebhookEvent::BranchUpdated],
+        };
+        let resp = controllers::webhooks::create(req, web::Json(body)).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let created: WebhookResponse = serde_json::from_str(std::str::from_utf8(&body).unwrap())?;
+        assert!(!created.secret.is_empty());
+
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let body = NewWebhook {
+            url: String::from("ftp://localhost/hook"),
+            events: vec![],
+        };
+        let resp = controllers::webhooks::create(req, web::Json(body)).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::repo_request(&sync_dir, &uri, namespace, name);
+        let resp = controllers::webhooks::index(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+        let list: ListWebhooksResponse = serde_json::from_str(text)?;
+        assert_eq!(list.webhooks.len(), 1);
+        assert_eq!(list.webhooks[0].id, created.webhook.id);
+        assert!(!text.contains(&created.secret));
+
+        let req = test::repo_request_with_param(
+            &sync_dir,
+            &uri,
+            namespace,
+            name,
+            "webhook_id",
+            namespace_webhook.id.clone(),
+        );
+        let resp = controllers::webhooks::delete(req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::repo_request_with_param(
+            &sync_dir,
+            &uri,
+            namespace,
+            name,
+            "webhook_id",
+            created.webhook.id.clone(),
+        );
+        let resp = controllers::webhooks::delete(req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(WebhookConfig::load(&sync_dir)?.webhooks.len(), 1);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,596 @@
+use crate::limits;
+use crate::view::CommitProcessedView;
+use crate::webhooks;
+use crate::webhooks::{WebhookEvent, WebhookPayload};
+
+use liboxen::api;
+use liboxen::compute::cachers::content_validator;
+use liboxen::compute::commit_cacher;
+use liboxen::compute::commit_cacher::CacherStatusType;
+use liboxen::error::OxenError;
+use liboxen::model::{Commit, LocalRepository};
+use liboxen::util;
+use liboxen::view::http::{MSG_FAILED_PROCESS, MSG_RESOURCE_FOUND, STATUS_ERROR, STATUS_SUCCESS};
+use liboxen::view::IsValidStatusMessage;
+
+use rocksdb::{DBWithThreadMode, IteratorMode, LogLevel, MultiThreaded, Options};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+pub const JOBS_DIR: &str = "jobs";
+
+/// How many times a job is tried before it is marked as failed
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Workers check for jobs that became ready to retry this often, even if nothing was enqueued
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Failed,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Failed => "failed",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// What a job does when it runs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobTask {
+    /// Run the commit cachers on a pushed commit
+    ProcessCommit { commit_id: String },
+    /// Send an event to a webhook
+    DeliverWebhook {
+        webhook_id: String,
+        payload: WebhookPayload,
+    },
+}
+
+impl JobTask {
+    /// Name of the kind of task, for the metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JobTask::ProcessCommit { .. } => "process_commit",
+            JobTask::DeliverWebhook { .. } => "deliver_webhook",
+        }
+    }
+}
+
+/// Background work for a repository, jobs are removed from the queue once they succeed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Job {
+    pub id: String,
+    pub namespace: String,
+    pub repo_name: String,
+    pub task: JobTask,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// Queued jobs are not run before this time, to back off between retries
+    pub run_after: i64,
+}
+
+impl Job {
+    pub fn new(namespace: &str, repo_name: &str, task: JobTask) -> Job {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        // Commits are processed once, so pushing the same commit again does not queue it twice
+        let id = match &task {
+            JobTask::ProcessCommit { commit_id } => format!("{namespace}/{repo_name}/{commit_id}"),
+            JobTask::DeliverWebhook { payload, .. } => {
+                format!("{namespace}/{repo_name}/webhooks/{}", payload.id)
+            }
+        };
+        Job {
+            id,
+            namespace: namespace.to_owned(),
+            repo_name: repo_name.to_owned(),
+            task,
+            status: JobStatus::Queued,
+            attempts: 0,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            run_after: now,
+        }
+    }
+
+    pub fn process_commit(namespace: &str, repo_name: &str, commit_id: &str) -> Job {
+        let task = JobTask::ProcessCommit {
+            commit_id: commit_id.to_owned(),
+        };
+        Job::new(namespace, repo_name, task)
+    }
+}
+
+/// # JobQueue
+/// Persistent queue of commits to process and webhooks to deliver, stored in `SYNC_DIR/.oxen/jobs` so that jobs
+/// that were queued or running when the server stopped are picked up again on startup.
+/// A fixed number of worker threads run the jobs, retrying failures with exponential backoff.
+pub struct JobQueue {
+    db: DBWithThreadMode<MultiThreaded>,
+    sync_dir: PathBuf,
+    // The queued jobs ordered by when they can run, held while claiming a job so two workers never run the same one
+    pending: Mutex<BTreeSet<PendingJob>>,
+    // Number of jobs of each kind the workers are running
+    running: Mutex<BTreeMap<&'static str, usize>>,
+    wakeup: Condvar,
+}
+
+/// Index entry for a queued job, so claiming the next job does not have to scan the whole db
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct PendingJob {
+    run_after: i64,
+    created_at: i64,
+    id: String,
+    kind: &'static str,
+}
+
+impl From<&Job> for PendingJob {
+    fn from(job: &Job) -> PendingJob {
+        PendingJob {
+            run_after: job.run_after,
+            created_at: job.created_at,
+            id: job.id.to_owned(),
+            kind: job.task.kind(),
+        }
+    }
+}
+
+impl JobQueue {
+    pub fn new(sync_dir: &Path) -> Result<JobQueue, OxenError> {
+        let hidden_dir = util::fs::oxen_hidden_dir(sync_dir);
+        if !hidden_dir.exists() {
+            std::fs::create_dir_all(&hidden_dir)?;
+        }
+
+        let mut opts = Options::default();
+        opts.set_log_level(LogLevel::Fatal);
+        opts.create_if_missing(true);
+        let db = DBWithThreadMode::open(&opts, hidden_dir.join(JOBS_DIR))?;
+        let queue = JobQueue {
+            db,
+            sync_dir: sync_dir.to_path_buf(),
+            pending: Mutex::new(BTreeSet::new()),
+            running: Mutex::new(BTreeMap::new()),
+            wakeup: Condvar::new(),
+        };
+
+        // Only scan the db once on startup to build the index
+        let pending: BTreeSet<PendingJob> = queue
+            .list()?
+            .iter()
+            .filter(|job| job.status == JobStatus::Queued)
+            .map(PendingJob::from)
+            .collect();
+        *queue.pending.lock().unwrap() = pending;
+        Ok(queue)
+    }
+
+    pub fn sync_dir(&self) -> &Path {
+        &self.sync_dir
+    }
+
+    /// Queues the commit to be processed
+    pub fn enqueue_commit(
+        &self,
+        namespace: &str,
+        repo_name: &str,
+        commit_id: &str,
+    ) -> Result<Job, OxenError> {
+        self.enqueue(Job::process_commit(namespace, repo_name, commit_id))
+    }
+
+    /// Queues the job, if a job with the same id is already queued or running this is a no-op
+    pub fn enqueue(&self, job: Job) -> Result<Job, OxenError> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(existing) = self.get(&job.id)? {
+            if existing.status != JobStatus::Failed {
+                return Ok(existing);
+            }
+        }
+        self.put(&job)?;
+        pending.insert(PendingJob::from(&job));
+        self.wakeup.notify_one();
+        Ok(job)
+    }
+
+    /// Puts the jobs that were running when the server stopped back in the queue
+    pub fn recover(&self) -> Result<usize, OxenError> {
+        let mut pending = self.pending.lock().unwrap();
+        let mut num_recovered = 0;
+        for mut job in self.list()? {
+            if job.status == JobStatus::Running {
+                job.status = JobStatus::Queued;
+                job.updated_at = OffsetDateTime::now_utc().unix_timestamp();
+                self.put(&job)?;
+                pending.insert(PendingJob::from(&job));
+                num_recovered += 1;
+            }
+        }
+        Ok(num_recovered)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Job>, OxenError> {
+        match self.db.get(id.as_bytes())? {
+            Some(value) => {
+                let value = str::from_utf8(&value)?;
+                Ok(Some(serde_json::from_str(value)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<Job>, OxenError> {
+        let mut jobs: Vec<Job> = vec![];
+        for (_key, value) in self.db.iterator(IteratorMode::Start) {
+            let value = str::from_utf8(&value)?;
+            jobs.push(serde_json::from_str(value)?);
+        }
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    pub fn list_for_repo(&self, namespace: &str, repo_name: &str) -> Result<Vec<Job>, OxenError> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|job| job.namespace == namespace && job.repo_name == repo_name)
+            .collect())
+    }
+
+    /// Starts the worker threads, at most `num_workers` jobs run at the same time
+    pub fn start_workers(queue: Arc<JobQueue>, num_workers: usize) {
+        for i in 0..num_workers {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                log::debug!("Started job worker {}", i);
+                loop {
+                    match queue.run_next() {
+                        Ok(true) => {}
+                        Ok(false) => queue.wait_for_job(),
+                        Err(err) => {
+                            log::error!("Job worker {} could not run job: {}", i, err);
+                            queue.wait_for_job();
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    fn wait_for_job(&self) {
+        let guard = self.pending.lock().unwrap();
+        let _ = self.wakeup.wait_timeout(guard, POLL_INTERVAL).unwrap();
+    }
+
+    /// Runs the job that has been ready to run the longest, returns false if there was none
+    pub fn run_next(&self) -> Result<bool, OxenError> {
+        let job = match self.claim_next()? {
+            Some(job) => job,
+            None => return Ok(false),
+        };
+
+        log::debug!("Running job {} attempt {}", job.id, job.attempts + 1);
+        let kind = job.task.kind();
+        *self.running.lock().unwrap().entry(kind).or_default() += 1;
+        let result = self.process(&job);
+        if let Some(num_running) = self.running.lock().unwrap().get_mut(kind) {
+            *num_running -= 1;
+        }
+        match result {
+            Ok(_) => {
+                log::debug!("Finished job {}", job.id);
+                self.db.delete(job.id.as_bytes())?;
+            }
+            Err(err) => {
+                log::error!("Job {} failed: {}", job.id, err);
+                self.fail(job, &err)?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn claim_next(&self) -> Result<Option<Job>, OxenError> {
+        let mut pending = self.pending.lock().unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        loop {
+            let next = match pending.iter().next() {
+                Some(next) if next.run_after <= now => next.clone(),
+                _ => return Ok(None),
+            };
+            pending.remove(&next);
+
+            // Skip index entries for jobs that are gone or were queued again with a new run_after
+            match self.get(&next.id)? {
+                Some(mut job)
+                    if job.status == JobStatus::Queued && job.run_after == next.run_after =>
+                {
+                    job.status = JobStatus::Running;
+                    job.updated_at = now;
+                    self.put(&job)?;
+                    return Ok(Some(job));
+                }
+                _ => {
+                    log::debug!("claim_next skipping stale job {}", next.id);
+                }
+            }
+        }
+    }
+
+    /// Number of jobs waiting to run, including ones backing off before a retry
+    pub fn num_pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Number of queued and running jobs by the kind of task, without reading the db
+    pub fn depths(&self) -> BTreeMap<(&'static str, JobStatus), usize> {
+        let mut depths: BTreeMap<(&'static str, JobStatus), usize> = BTreeMap::new();
+        for job in self.pending.lock().unwrap().iter() {
+            *depths.entry((job.kind, JobStatus::Queued)).or_default() += 1;
+        }
+        for (kind, num_running) in self.running.lock().unwrap().iter() {
+            *depths.entry((kind, JobStatus::Running)).or_default() += num_running;
+        }
+        depths
+    }
+
+    fn process(&self, job: &Job) -> Result<(), OxenError> {
+        match &job.task {
+            JobTask::ProcessCommit { commit_id } => self.process_commit(job, commit_id),
+            JobTask::DeliverWebhook {
+                webhook_id,
+                payload,
+            } => webhooks::deliver(&self.sync_dir, webhook_id, payload),
+        }
+    }
+
+    fn process_commit(&self, job: &Job, commit_id: &str) -> Result<(), OxenError> {
+        let repo = api::local::repositories::get_by_namespace_and_name(
+            &self.sync_dir,
+            &job.namespace,
+            &job.repo_name,
+        )?
+        .ok_or_else(|| {
+            OxenError::basic_str(format!(
+                "Repository not found: {}/{}",
+                job.namespace, job.repo_name
+            ))
+        })?;
+        let commit = api::local::commits::get_by_id(&repo, commit_id)?
+            .ok_or_else(|| OxenError::basic_str(format!("Commit not found: {commit_id}")))?;
+        commit_cacher::run_all(&repo, &commit)?;
+        // The namespace holds more, or less, data with the commit
+        limits::refresh_namespace_usage(&self.sync_dir, &job.namespace);
+
+        let status = JobQueue::processed_status(&repo, &commit)?;
+        let event = if status.is_valid {
+            WebhookEvent::CommitProcessingSucceeded
+        } else {
+            WebhookEvent::CommitProcessingFailed
+        };
+        self.notify_commit_processed(job, event, commit, status);
+        Ok(())
+    }
+
+    /// The same status `is_synced` reports once the commit is done processing
+    fn processed_status(
+        repo: &LocalRepository,
+        commit: &Commit,
+    ) -> Result<IsValidStatusMessage, OxenError> {
+        let status = match commit_cacher::get_status(repo, commit)? {
+            Some(CacherStatusType::Success) if content_validator::is_valid(repo, commit)? => {
+                IsValidStatusMessage {
+                    status: String::from(STATUS_SUCCESS),
+                    status_message: String::from(MSG_RESOURCE_FOUND),
+                    status_description: String::from(""),
+                    is_processing: false,
+                    is_valid: true,
+                }
+            }
+            _ => {
+                let errors: Vec<String> = commit_cacher::get_failures(repo, commit)?
+                    .into_iter()
+                    .map(|failure| failure.status_message)
+                    .collect();
+                IsValidStatusMessage {
+                    status: String::from(STATUS_ERROR),
+                    status_message: String::from(MSG_FAILED_PROCESS),
+                    status_description: format!("Err: {}", errors.join(", ")),
+                    is_processing: false,
+                    is_valid: false,
+                }
+            }
+        };
+        Ok(status)
+    }
+
+    fn notify_commit_processed(
+        &self,
+        job: &Job,
+        event: WebhookEvent,
+        commit: Commit,
+        status: IsValidStatusMessage,
+    ) {
+        let data = CommitProcessedView { commit, status };
+        if let Err(err) =
+            webhooks::queue_deliveries(self, &job.namespace, &job.repo_name, event, &data)
+        {
+            log::error!("Could not queue webhooks for job {}: {}", job.id, err);
+        }
+    }
+
+    fn fail(&self, mut job: Job, err: &OxenError) -> Result<(), OxenError> {
+        {
+            let mut pending = self.pending.lock().unwrap();
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            job.attempts += 1;
+            job.error = Some(err.to_string());
+            job.updated_at = now;
+            if job.attempts >= MAX_ATTEMPTS {
+                job.status = JobStatus::Failed;
+            } else {
+                job.status = JobStatus::Queued;
+                job.run_after = now + JobQueue::backoff_secs(job.attempts);
+                pending.insert(PendingJob::from(&job));
+            }
+            self.put(&job)?;
+        }
+
+        // Let webhooks know the commit is not going to be processed
+        if let (JobStatus::Failed, JobTask::ProcessCommit { commit_id }) = (&job.status, &job.task)
+        {
+            let commit = api::local::repositories::get_by_namespace_and_name(
+                &self.sync_dir,
+                &job.namespace,
+                &job.repo_name,
+            )?
+            .and_then(|repo| {
+                api::local::commits::get_by_id(&repo, commit_id)
+                    .ok()
+                    .flatten()
+            });
+            if let Some(commit) = commit {
+                let status = IsValidStatusMessage {
+                    status: String::from(STATUS_ERROR),
+                    status_message: String::from(MSG_FAILED_PROCESS),
+                    status_description: format!("Err: {err}"),
+                    is_processing: false,
+                    is_valid: false,
+                };
+                self.notify_commit_processed(
+                    &job,
+                    WebhookEvent::CommitProcessingFailed,
+                    commit,
+                    status,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 2, 4, 8... seconds between attempts, up to five minutes
+    fn backoff_secs(attempts: u32) -> i64 {
+        2_i64.saturating_pow(attempts).min(MAX_BACKOFF_SECS)
+    }
+
+    fn put(&self, job: &Job) -> Result<(), OxenError> {
+        let value = serde_json::to_string(job)?;
+        self.db.put(job.id.as_bytes(), value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::job_queue::{JobQueue, JobStatus};
+    use crate::test;
+
+    use liboxen::command;
+    use liboxen::compute::commit_cacher;
+    use liboxen::compute::commit_cacher::CacherStatusType;
+    use liboxen::error::OxenError;
+
+    #[test]
+    fn test_job_queue_processes_commit() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let repo = test::create_local_repo(sync_dir, "ox", "jobs")?;
+            let commit = command::head_commit(&repo)?;
+
+            let queue = JobQueue::new(sync_dir)?;
+            queue.enqueue_commit("ox", "jobs", &commit.id)?;
+            // Enqueuing the same commit twice does not run it twice
+            queue.enqueue_commit("ox", "jobs", &commit.id)?;
+            assert_eq!(queue.list_for_repo("ox", "jobs")?.len(), 1);
+
+            assert_eq!(queue.num_pending(), 1);
+            assert_eq!(
+                queue.depths().get(&("process_commit", JobStatus::Queued)),
+                Some(&1)
+            );
+
+            assert!(queue.run_next()?);
+            assert!(!queue.run_next()?);
+            assert!(queue.list()?.is_empty());
+            assert_eq!(queue.num_pending(), 0);
+            assert_eq!(
+                commit_cacher::get_status(&repo, &commit)?,
+                Some(CacherStatusType::Success)
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_job_queue_retries_with_backoff() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            test::create_local_repo(sync_dir, "ox", "jobs")?;
+
+            let queue = JobQueue::new(sync_dir)?;
+            let job = queue.enqueue_commit("ox", "jobs", "does-not-exist")?;
+            assert!(queue.run_next()?);
+
+            let job = queue.get(&job.id)?.unwrap();
+            assert_eq!(job.status, JobStatus::Queued);
+            assert_eq!(job.attempts, 1);
+            assert!(job.error.is_some());
+            // Not ready to run again until the backoff is over
+            assert!(!queue.run_next()?);
+            assert_eq!(queue.num_pending(), 1);
+
+            // A queue opened on startup picks the job up from the db
+            drop(queue);
+            let queue = JobQueue::new(sync_dir)?;
+            assert_eq!(queue.num_pending(), 1);
+            assert!(!queue.run_next()?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_job_queue_recovers_running_jobs() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let repo = test::create_local_repo(sync_dir, "ox", "jobs")?;
+            let commit = command::head_commit(&repo)?;
+
+            let job = {
+                let queue = JobQueue::new(sync_dir)?;
+                let job = queue.enqueue_commit("ox", "jobs", &commit.id)?;
+                // Server stops while the job is running
+                queue.claim_next()?;
+                job
+            };
+
+            let queue = JobQueue::new(sync_dir)?;
+            assert_eq!(queue.get(&job.id)?.unwrap().status, JobStatus::Running);
+            assert_eq!(queue.recover()?, 1);
+            assert_eq!(queue.get(&job.id)?.unwrap().status, JobStatus::Queued);
+            assert!(queue.run_next()?);
+            assert!(queue.list()?.is_empty());
+
+            Ok(())
+        })
+    }
+}
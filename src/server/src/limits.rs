@@ -0,0 +1,422 @@
+//! Per-namespace storage quotas and per-user request rate limits. Requests over either are
+//! rejected with a `LimitExceededResponse` so clients can tell why.
+
+use crate::auth::validator::Identity;
+
+use liboxen::api;
+use liboxen::error::OxenError;
+use liboxen::view::http::{MSG_QUOTA_EXCEEDED, MSG_RATE_LIMITED};
+use liboxen::view::LimitExceededResponse;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse};
+use bytesize::ByteSize;
+use futures_util::future::LocalBoxFuture;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A bucket that has not been used for this long is full again, so it is dropped
+const IDLE_BUCKET: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // Usage of each namespace dir, so uploads do not have to go through every repository in it
+    static ref NAMESPACE_USAGE: Mutex<HashMap<PathBuf, u64>> = Mutex::new(HashMap::new());
+}
+
+/// How much data the repositories in a namespace can hold, in the `[quotas]` section of the server config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct QuotaConfig {
+    /// Applies to namespaces that are not listed, unlimited if not set
+    pub default_mb: Option<u64>,
+    #[serde(default)]
+    pub namespaces: HashMap<String, u64>,
+}
+
+impl QuotaConfig {
+    pub fn limit_bytes_for(&self, namespace: &str) -> Option<u64> {
+        self.namespaces
+            .get(namespace)
+            .copied()
+            .or(self.default_mb)
+            .map(|mb| mb * 1024 * 1024)
+    }
+}
+
+/// Requests each user, or each address for anonymous requests, can make, in the `[rate_limit]`
+/// section of the server config
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+}
+
+/// Total size of the data in every repository in the namespace, only computed the first time
+pub fn namespace_usage(sync_dir: &Path, namespace: &str) -> u64 {
+    let cached = NAMESPACE_USAGE
+        .lock()
+        .unwrap()
+        .get(&sync_dir.join(namespace))
+        .copied();
+    match cached {
+        Some(used) => used,
+        None => refresh_namespace_usage(sync_dir, namespace),
+    }
+}
+
+/// Computes the usage of the namespace again, once a commit or a deleted repository changed it
+pub fn refresh_namespace_usage(sync_dir: &Path, namespace: &str) -> u64 {
+    let namespace_dir = sync_dir.join(namespace);
+    let used = api::local::repositories::list_repos_in_namespace(&namespace_dir)
+        .iter()
+        .map(|repo| api::local::repositories::get_repo_stats(repo).data_size)
+        .sum();
+    NAMESPACE_USAGE.lock().unwrap().insert(namespace_dir, used);
+    used
+}
+
+/// Returns the response to reject the upload with if it would put the namespace over its quota
+pub fn check_quota(
+    sync_dir: &Path,
+    quotas: &QuotaConfig,
+    namespace: &str,
+    upload_size: u64,
+) -> Option<LimitExceededResponse> {
+    let limit = quotas.limit_bytes_for(namespace)?;
+    let used = namespace_usage(sync_dir, namespace);
+    if used + upload_size <= limit {
+        return None;
+    }
+
+    let desc = format!(
+        "namespace {} is using {} of its {} quota, cannot upload {} more",
+        namespace,
+        ByteSize::b(used),
+        ByteSize::b(limit),
+        ByteSize::b(upload_size)
+    );
+    let mut response = LimitExceededResponse::new(MSG_QUOTA_EXCEEDED, &desc);
+    response.limit = Some(limit);
+    response.used = Some(used);
+    Some(response)
+}
+
+/// Checks the quota for an upload request, the quotas are not set in the controller tests
+pub fn check_upload_quota(
+    req: &HttpRequest,
+    sync_dir: &Path,
+    namespace: &str,
+) -> Option<HttpResponse> {
+    let quotas = req.app_data::<web::Data<QuotaConfig>>()?;
+    let upload_size = req
+        .headers()
+        .get("content-length")
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse::<u64>().ok())
+        .unwrap_or(0);
+    let response = check_quota(sync_dir, quotas, namespace, upload_size)?;
+    log::info!("Rejected upload: {}", response.status_description);
+    Some(HttpResponse::PayloadTooLarge().json(response))
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    buckets: HashMap<String, Bucket>,
+    evicted_at: Instant,
+}
+
+/// Token bucket for each key, that refills `requests_per_minute` tokens a minute
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> RateLimiter {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                evicted_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a request from the bucket for the key, or returns how many seconds until one is available
+    pub fn acquire(&self, key: &str, now: Instant) -> Result<(), u64> {
+        let capacity = f64::from(self.config.requests_per_minute);
+        let mut buckets = self.buckets.lock().unwrap();
+        if now.saturating_duration_since(buckets.evicted_at) >= IDLE_BUCKET {
+            buckets
+                .buckets
+                .retain(|_, bucket| now.saturating_duration_since(bucket.updated_at) < IDLE_BUCKET);
+            buckets.evicted_at = now;
+        }
+
+        let bucket = buckets.buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now
+            .saturating_duration_since(bucket.updated_at)
+            .as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * capacity / 60.0).min(capacity);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if capacity <= 0.0 {
+            Err(60)
+        } else {
+            Err(((1.0 - bucket.tokens) * 60.0 / capacity).ceil() as u64)
+        }
+    }
+}
+
+/// Which bucket a request takes from, the user once `Authenticate` validated their token, or the
+/// address the request came from for anonymous requests and when auth is not enabled
+fn rate_limit_key(req: &ServiceRequest) -> String {
+    if let Some(Identity::User(claim)) = req.extensions().get::<Identity>() {
+        return format!("user:{}", claim.email());
+    }
+    match req.peer_addr() {
+        Some(addr) => format!("ip:{}", addr.ip()),
+        None => String::from("ip:unknown"),
+    }
+}
+
+/// Middleware that rate limits requests by `rate_limit_key`, it has to be wrapped inside of
+/// `Authenticate` so that the identity is known
+pub struct RateLimit {
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Option<Arc<RateLimiter>>) -> RateLimit {
+        RateLimit { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service,
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(limiter) = &self.limiter {
+            if let Err(retry_after) = limiter.acquire(&rate_limit_key(&req), Instant::now()) {
+                let desc = format!(
+                    "over {} requests per minute, retry in {}s",
+                    limiter.config.requests_per_minute, retry_after
+                );
+                log::info!("Rate limited {} {}: {}", req.method(), req.path(), desc);
+                let mut body = LimitExceededResponse::new(MSG_RATE_LIMITED, &desc);
+                body.limit = Some(u64::from(limiter.config.requests_per_minute));
+                body.retry_after_secs = Some(retry_after);
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header(("Retry-After", retry_after.to_string()))
+                    .json(body);
+                return Box::pin(
+                    async move { Ok(req.into_response(response).map_into_right_body()) },
+                );
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+/// Checks the limits from the server config before the server starts
+pub fn validate(
+    quotas: &QuotaConfig,
+    rate_limit: &Option<RateLimitConfig>,
+) -> Result<(), OxenError> {
+    if let Some(rate_limit) = rate_limit {
+        if rate_limit.requests_per_minute == 0 {
+            return Err(OxenError::basic_str(
+                "rate_limit.requests_per_minute must be greater than 0",
+            ));
+        }
+    }
+    if quotas
+        .namespaces
+        .keys()
+        .any(|namespace| namespace.is_empty())
+    {
+        return Err(OxenError::basic_str(
+            "quotas.namespaces cannot have an empty namespace",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::limits::{self, QuotaConfig, RateLimit, RateLimitConfig, RateLimiter};
+    use crate::test;
+
+    use actix_web::{http, web, App, HttpResponse};
+    use liboxen::command;
+    use liboxen::error::OxenError;
+    use liboxen::util;
+    use liboxen::view::http::MSG_QUOTA_EXCEEDED;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_limits_quota_for_namespace() {
+        let quotas = QuotaConfig {
+            default_mb: Some(1),
+            namespaces: HashMap::from([(String::from("big"), 10)]),
+        };
+        assert_eq!(quotas.limit_bytes_for("big"), Some(10 * 1024 * 1024));
+        assert_eq!(quotas.limit_bytes_for("other"), Some(1024 * 1024));
+        assert_eq!(QuotaConfig::default().limit_bytes_for("other"), None);
+    }
+
+    #[test]
+    fn test_limits_check_quota() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            test::create_local_repo(sync_dir, "ox", "data")?;
+            let quotas = QuotaConfig {
+                default_mb: Some(1),
+                namespaces: HashMap::new(),
+            };
+
+            assert!(limits::check_quota(sync_dir, &quotas, "ox", 1024).is_none());
+
+            let response = limits::check_quota(sync_dir, &quotas, "ox", 2 * 1024 * 1024).unwrap();
+            assert_eq!(response.status_message, MSG_QUOTA_EXCEEDED);
+            assert_eq!(response.limit, Some(1024 * 1024));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_limits_rate_limiter() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 2,
+        });
+        let now = Instant::now();
+        assert!(limiter.acquire("token", now).is_ok());
+        assert!(limiter.acquire("token", now).is_ok());
+        // Refills one request every 30 seconds
+        assert_eq!(limiter.acquire("token", now), Err(30));
+        // Other tokens have their own bucket
+        assert!(limiter.acquire("other", now).is_ok());
+        assert!(limiter
+            .acquire("token", now + Duration::from_secs(30))
+            .is_ok());
+
+        // Buckets that have not been used for a minute are full again, so they are dropped
+        assert!(limiter
+            .acquire("token", now + Duration::from_secs(120))
+            .is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().buckets.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_limits_rate_limit_by_identity_or_address() {
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 1,
+        }));
+        let app = actix_web::test::init_service(
+            App::new()
+                .route("/api/version", web::get().to(HttpResponse::Ok))
+                .wrap(RateLimit::new(Some(limiter))),
+        )
+        .await;
+
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/version")
+            .peer_addr(peer)
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        // Anonymous requests from the same address share a bucket, whatever token they send
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/version")
+            .peer_addr(peer)
+            .insert_header(("authorization", "Bearer made-up"))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/version")
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+
+    #[test]
+    fn test_limits_namespace_usage_is_cached() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            assert_eq!(limits::namespace_usage(sync_dir, "ox"), 0);
+            let repo = test::create_local_repo(sync_dir, "ox", "data")?;
+            let path = repo.path.join("data.txt");
+            util::fs::write_to_path(&path, "some data")?;
+            command::add(&repo, &path)?;
+            command::commit(&repo, "Adding data")?;
+
+            // Only computed again once a commit is processed
+            assert_eq!(limits::namespace_usage(sync_dir, "ox"), 0);
+            let used = limits::refresh_namespace_usage(sync_dir, "ox");
+            assert!(used > 0);
+            assert_eq!(limits::namespace_usage(sync_dir, "ox"), used);
+
+            Ok(())
+        })
+    }
+}
@@ -1,31 +1,91 @@
-use liboxen::config::UserConfig;
-use liboxen::model::User;
+use liboxen::api;
+use liboxen::command;
+use liboxen::config::{TrustedKey, TrustedKeysConfig, UserConfig};
+use liboxen::error::OxenError;
+use liboxen::model::{LocalRepository, User};
+use liboxen::opts::GCOpts;
+
+use crate::auth::access_keys::{AccessKeyManager, TokenOpts};
+use crate::auth::permissions::{PermissionsManager, Role};
+use crate::auth::validator::{Access, Authenticate};
+use crate::branch_protection::{BranchProtection, BranchProtectionConfig};
+use crate::job_queue::JobQueue;
+use crate::limits::{RateLimit, RateLimiter};
+use crate::metrics::{Metrics, RequestTracing};
+use crate::server_config::{AuthMode, ServerConfig, TlsConfig};
+use crate::upload_limit::UploadLimit;
+use crate::webhooks::{Webhook, WebhookConfig, WebhookEvent};
 
 pub mod app_data;
 pub mod auth;
+pub mod branch_protection;
 pub mod controllers;
+pub mod job_queue;
+pub mod limits;
+pub mod metrics;
 pub mod routes;
+pub mod server_config;
 pub mod test;
+pub mod upload_limit;
 pub mod view;
+pub mod webhooks;
 
 extern crate dotenv;
 extern crate log;
 
 // use actix_http::KeepAlive;
 // use std::time;
+use actix_cors::Cors;
+use actix_web::http::Method;
 use actix_web::middleware::{Condition, Logger};
 use actix_web::{web, App, HttpServer};
-use actix_web_httpauth::middleware::HttpAuthentication;
+use bytesize::ByteSize;
 use clap::{Arg, Command};
 use env_logger::Env;
-use std::path::Path;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const ADD_USER_USAGE: &str =
     "Usage: `oxen-server add-user -e <email> -n <name> -o user_config.toml`";
 
-const START_SERVER_USAGE: &str = "Usage: `oxen-server start -h 0.0.0.0 -p 3000`";
+const START_SERVER_USAGE: &str =
+    "Usage: `oxen-server start -i 0.0.0.0 -p 3000` or `oxen-server --config server.toml start`";
+
+const GC_USAGE: &str = "Usage: `oxen-server gc --dry-run`";
+
+const REVOKE_TOKEN_USAGE: &str = "Usage: `oxen-server revoke-token <TOKEN_OR_ID>`";
+
+const GRANT_USAGE: &str =
+    "Usage: `oxen-server grant -n <namespace> [-r <repo> --role <read|write|admin>] -e <email>`";
+
+const REVOKE_USAGE: &str = "Usage: `oxen-server revoke -n <namespace> [-r <repo>] -e <email>`";
+
+const SET_VISIBILITY_USAGE: &str =
+    "Usage: `oxen-server set-visibility -n <namespace> -r <repo> <public|private>`";
+
+const PROTECT_BRANCH_USAGE: &str =
+    "Usage: `oxen-server protect-branch -n <namespace> -r <repo> -b <branch> [--no-delete] [--no-force-update] [--require-valid] [--require-signed] [--allowed-pusher <email>]`";
+
+const UNPROTECT_BRANCH_USAGE: &str =
+    "Usage: `oxen-server unprotect-branch -n <namespace> -r <repo> -b <branch>`";
+
+const TRUST_KEY_USAGE: &str =
+    "Usage: `oxen-server trust-key -n <namespace> -r <repo> -e <email> --public-key <key> [--branch <branch>]`";
+
+const UNTRUST_KEY_USAGE: &str =
+    "Usage: `oxen-server untrust-key -n <namespace> -r <repo> -e <email> --public-key <key>`";
+
+const ADD_WEBHOOK_USAGE: &str =
+    "Usage: `oxen-server add-webhook -n <namespace> [-r <repo>] --url <url> [--event <event>]`";
+
+const LIST_WEBHOOKS_USAGE: &str = "Usage: `oxen-server list-webhooks -n <namespace> [-r <repo>]`";
+
+const REMOVE_WEBHOOK_USAGE: &str = "Usage: `oxen-server remove-webhook <ID>`";
 
 const INVALID_PORT_MSG: &str = "Port must a valid number between 0-65535";
 
@@ -33,11 +93,6 @@ const INVALID_PORT_MSG: &str = "Port must a valid number between 0-65535";
 async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(Env::default().default_filter_or("info,debug"));
 
-    let sync_dir = match std::env::var("SYNC_DIR") {
-        Ok(dir) => dir,
-        Err(_) => String::from("/tmp/oxen_sync"),
-    };
-
     let command = Command::new("oxen-server")
         .version(VERSION)
         .about("Oxen Server")
@@ -45,35 +100,15 @@ async fn main() -> std::io::Result<()> {
         .arg_required_else_help(true)
         .allow_external_subcommands(true)
         .allow_invalid_utf8_for_external_subcommands(true)
-        .subcommand(
-            Command::new("start")
-                .about(START_SERVER_USAGE)
-                .arg(
-                    Arg::new("ip")
-                        .long("ip")
-                        .short('i')
-                        .default_value("0.0.0.0")
-                        .default_missing_value("always")
-                        .help("What host to bind the server to")
-                        .takes_value(true),
-                )
-                .arg(
-                    Arg::new("port")
-                        .long("port")
-                        .short('p')
-                        .default_value("3000")
-                        .default_missing_value("always")
-                        .help("What port to bind the server to")
-                        .takes_value(true),
-                )
-                .arg(
-                    Arg::new("auth")
-                        .long("auth")
-                        .short('a')
-                        .help("Start the server with token-based authentication enforced")
-                        .takes_value(false),
-                ),
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .short('c')
+                .help("Path to the server config TOML file, flags take precedence over it")
+                .global(true)
+                .takes_value(true),
         )
+        .subcommand(start_command())
         .subcommand(
             Command::new("add-user")
                 .about(ADD_USER_USAGE)
@@ -101,50 +136,308 @@ async fn main() -> std::io::Result<()> {
                         .default_missing_value("always")
                         .help("Where to write the output config file to give to the user")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::new("expires-in-days")
+                        .long("expires-in-days")
+                        .help("Number of days until the token expires, never expires if not set")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("read-only")
+                        .long("read-only")
+                        .help("The token can only read, not push or modify repositories")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("repo")
+                        .long("repo")
+                        .short('r')
+                        .help("Restrict the token to a single `namespace/name` repository")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("admin")
+                        .long("admin")
+                        .help("The token can reach the routes for the whole server, like /metrics")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(Command::new("list-users").about("List every access token that was issued"))
+        .subcommand(
+            Command::new("revoke-token")
+                .about(REVOKE_TOKEN_USAGE)
+                .arg(
+                    Arg::new("token")
+                        .help("The token, or the id of the token from `oxen-server list-users`")
+                        .required(true),
+                ),
+        )
+        .subcommand(Command::new("rotate-secret").about(
+            "Generate a new secret key, which invalidates every access token that was issued",
+        ))
+        .subcommand(
+            Command::new("gc").about(GC_USAGE).arg(
+                Arg::new("dry-run")
+                    .long("dry-run")
+                    .short('n')
+                    .help(
+                        "Report the unreachable objects in every repository without deleting them",
+                    )
+                    .takes_value(false),
+            ),
+        )
+        .subcommand(
+            Command::new("grant")
+                .about(GRANT_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().help(
+                    "Repository to grant the role on, if omitted the user becomes an owner of the namespace",
+                ))
+                .arg(email_arg())
+                .arg(
+                    Arg::new("role")
+                        .long("role")
+                        .default_value("read")
+                        .possible_values(["read", "write", "admin"])
+                        .help("Role to grant on the repository")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            Command::new("revoke")
+                .about(REVOKE_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().help(
+                    "Repository to revoke access to, if omitted the user is removed as an owner of the namespace",
+                ))
+                .arg(email_arg()),
+        )
+        .subcommand(
+            Command::new("set-visibility")
+                .about(SET_VISIBILITY_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().required(true))
+                .arg(
+                    Arg::new("visibility")
+                        .possible_values(["public", "private"])
+                        .help("Public repositories can be read by any user")
+                        .required(true),
                 ),
+        )
+        .subcommand(
+            Command::new("protect-branch")
+                .about(PROTECT_BRANCH_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().required(true))
+                .arg(branch_arg())
+                .arg(
+                    Arg::new("no-delete")
+                        .long("no-delete")
+                        .help("The branch cannot be deleted")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("no-force-update")
+                        .long("no-force-update")
+                        .help("The branch can only move to commits that contain its current commit")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("require-valid")
+                        .long("require-valid")
+                        .help("The branch can only move to commits that passed validation on the server")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("require-signed")
+                        .long("require-signed")
+                        .help("Every commit pushed to the branch must be signed by a key trusted for its author, see trust-key")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("allowed-pusher")
+                        .long("allowed-pusher")
+                        .help("Email of a user that can push to the branch, can be repeated. Anyone with write access can push if omitted")
+                        .multiple_occurrences(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            Command::new("unprotect-branch")
+                .about(UNPROTECT_BRANCH_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().required(true))
+                .arg(branch_arg()),
+        )
+        .subcommand(
+            Command::new("trust-key")
+                .about(TRUST_KEY_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().required(true))
+                .arg(email_arg().help("Email of the author whose commits the key signs"))
+                .arg(public_key_arg())
+                .arg(
+                    branch_arg()
+                        .required(false)
+                        .multiple_occurrences(true)
+                        .help("Only trust the key on this branch, or a prefix ending in `*`, can be repeated. Trusted on every branch if omitted"),
+                ),
+        )
+        .subcommand(
+            Command::new("untrust-key")
+                .about(UNTRUST_KEY_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().required(true))
+                .arg(email_arg().help("Email of the author whose commits the key signs"))
+                .arg(public_key_arg()),
+        )
+        .subcommand(
+            Command::new("add-webhook")
+                .about(ADD_WEBHOOK_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg().help(
+                    "Repository to send events for, if omitted events for every repository in the namespace are sent",
+                ))
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .help("URL the events are posted to")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("event")
+                        .long("event")
+                        .possible_values([
+                            "branch_updated",
+                            "branch_created",
+                            "branch_deleted",
+                            "commit_processing_succeeded",
+                            "commit_processing_failed",
+                            "repo_created",
+                        ])
+                        .help("Event to send, can be repeated. Every event is sent if omitted")
+                        .multiple_occurrences(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            Command::new("list-webhooks")
+                .about(LIST_WEBHOOKS_USAGE)
+                .arg(namespace_arg())
+                .arg(repo_arg()),
+        )
+        .subcommand(
+            Command::new("remove-webhook")
+                .about(REMOVE_WEBHOOK_USAGE)
+                .arg(Arg::new("id").help("Id of the webhook").required(true)),
         );
     let matches = command.get_matches();
 
+    let config = match ServerConfig::load(matches.value_of("config").map(Path::new)) {
+        Ok(config) => config,
+        Err(err) => exit_with_error(&format!("Err: {err}")),
+    };
+    let sync_dir = config.sync_dir().to_string_lossy().to_string();
+
     match matches.subcommand() {
         Some(("start", sub_matches)) => {
-            match (sub_matches.value_of("ip"), sub_matches.value_of("port")) {
-                (Some(host), Some(port)) => {
-                    let port: u16 = port.parse::<u16>().expect(INVALID_PORT_MSG);
-                    println!("🐂 v{VERSION}");
-                    println!("Running on {host}:{port}");
-                    println!("Syncing to directory: {sync_dir}");
-                    let enable_auth = sub_matches.is_present("auth");
-
-                    let data = app_data::OxenAppData::from(&sync_dir);
-                    HttpServer::new(move || {
-                        App::new()
-                            .app_data(data.clone())
-                            .route("/api/version", web::get().to(controllers::version::index))
-                            .route(
-                                "/api/namespaces",
-                                web::get().to(controllers::namespaces::index),
-                            )
-                            .route(
-                                "/api/namespaces/{namespace}",
-                                web::get().to(controllers::namespaces::show),
-                            )
-                            .wrap(Condition::new(
-                                enable_auth,
-                                HttpAuthentication::bearer(auth::validator::validate),
-                            ))
-                            .service(web::scope("/api/repos").configure(routes::config))
-                            .wrap(Logger::default())
-                            .wrap(Logger::new("%a %{User-Agent}i"))
-                    })
-                    .bind((host, port))?
-                    .run()
-                    .await
-                }
-                _ => {
-                    eprintln!("{START_SERVER_USAGE}");
-                    Ok(())
-                }
+            let config = match apply_start_flags(config, sub_matches) {
+                Ok(config) => config,
+                Err(err) => exit_with_error(&format!("{err}\n\n{START_SERVER_USAGE}")),
+            };
+            if let Err(err) = limits::validate(&config.quotas, &config.rate_limit) {
+                exit_with_error(&format!("Err: {err}"));
+            }
+            let bind_addresses = config.bind_addresses();
+            println!("🐂 v{VERSION}");
+            println!("Running on {}", bind_addresses.join(", "));
+            println!("Syncing to directory: {sync_dir}");
+            let enable_auth = config.auth == AuthMode::Token;
+
+            let queue = match JobQueue::new(Path::new(&sync_dir)) {
+                Ok(queue) => Arc::new(queue),
+                Err(err) => exit_with_error(&format!("Err could not open job queue: {err}")),
+            };
+            match queue.recover() {
+                Ok(0) => {}
+                Ok(num_recovered) => println!("Resuming {num_recovered} interrupted jobs"),
+                Err(err) => eprintln!("Err could not recover jobs: {err}"),
+            }
+            JobQueue::start_workers(queue.clone(), config.job_workers());
+
+            let data = app_data::OxenAppData::from(&sync_dir);
+            let queue = web::Data::from(queue);
+            let metrics = Arc::new(Metrics::new());
+            Metrics::start_rocksdb_sampler(metrics.clone(), PathBuf::from(&sync_dir));
+            let metrics = web::Data::from(metrics);
+            let max_upload_size = config.max_upload_size_bytes();
+            let cors_origins = config.cors_origins.clone();
+            let quotas = web::Data::new(config.quotas.clone());
+            let storage = web::Data::new(config.storage());
+            let rate_limiter = config
+                .rate_limit
+                .map(|limit| Arc::new(RateLimiter::new(limit)));
+            let mut server = HttpServer::new(move || {
+                App::new()
+                    .app_data(data.clone())
+                    .app_data(queue.clone())
+                    .app_data(metrics.clone())
+                    .app_data(quotas.clone())
+                    .app_data(storage.clone())
+                    .service(routes::route(
+                        "/api/version",
+                        Method::GET,
+                        Access::Public,
+                        controllers::version::index,
+                    ))
+                    .service(routes::route(
+                        "/metrics",
+                        Method::GET,
+                        Access::Admin,
+                        controllers::metrics::index,
+                    ))
+                    .service(routes::route(
+                        "/api/namespaces",
+                        Method::GET,
+                        Access::User,
+                        controllers::namespaces::index,
+                    ))
+                    .service(routes::route(
+                        "/api/namespaces/{namespace}",
+                        Method::GET,
+                        Access::User,
+                        controllers::namespaces::show,
+                    ))
+                    .service(web::scope("/api/repos").configure(routes::config))
+                    // Inside of Authenticate so requests are limited by who is making them
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .wrap(Condition::new(enable_auth, Authenticate))
+                    .wrap(UploadLimit::new(max_upload_size))
+                    .wrap(Condition::new(
+                        !cors_origins.is_empty(),
+                        cors(&cors_origins),
+                    ))
+                    .wrap(RequestTracing)
+                    .wrap(Logger::default())
+                    .wrap(Logger::new("%a %{User-Agent}i %{X-Oxen-Request-Id}o"))
+            });
+            if let Some(workers) = config.workers {
+                server = server.workers(workers);
+            }
+            for address in bind_addresses.iter() {
+                server = match &config.tls {
+                    Some(tls) => match ssl_acceptor(tls) {
+                        Ok(acceptor) => server.bind_openssl(address, acceptor)?,
+                        Err(err) => {
+                            exit_with_error(&format!("Err could not load TLS certificate: {err}"))
+                        }
+                    },
+                    None => server.bind(address)?,
+                };
             }
+            server.run().await
         }
         Some(("add-user", sub_matches)) => {
             match (
@@ -155,12 +448,28 @@ async fn main() -> std::io::Result<()> {
                 (Some(email), Some(name), Some(output)) => {
                     let path = Path::new(&sync_dir);
                     log::debug!("Saving to sync dir: {:?}", path);
+                    let expires_in = match sub_matches.value_of("expires-in-days") {
+                        Some(days) => match days.parse::<u64>() {
+                            Ok(days) => Some(Duration::from_secs(days * 24 * 60 * 60)),
+                            Err(_) => {
+                                eprintln!("--expires-in-days must be a number");
+                                return Ok(());
+                            }
+                        },
+                        None => None,
+                    };
+                    let opts = TokenOpts {
+                        expires_in,
+                        read_only: sub_matches.is_present("read-only"),
+                        repo: sub_matches.value_of("repo").map(String::from),
+                        admin: sub_matches.is_present("admin"),
+                    };
                     if let Ok(keygen) = auth::access_keys::AccessKeyManager::new(path) {
                         let new_user = User {
                             name: name.to_string(),
                             email: email.to_string(),
                         };
-                        match keygen.create(&new_user) {
+                        match keygen.create_with_opts(&new_user, &opts) {
                             Ok((user, token)) => {
                                 let cfg = UserConfig::from_user(&user);
                                 match cfg.save(Path::new(output)) {
@@ -185,6 +494,688 @@ async fn main() -> std::io::Result<()> {
 
             Ok(())
         }
+        Some(("gc", sub_matches)) => {
+            let opts = GCOpts {
+                dry_run: sub_matches.is_present("dry-run"),
+                ..GCOpts::default()
+            };
+            let path = Path::new(&sync_dir);
+            match command::gc_all_repos(path, &opts) {
+                Ok(reports) => {
+                    let verb = if opts.dry_run {
+                        "Would remove"
+                    } else {
+                        "Removed"
+                    };
+                    let mut total_size: u64 = 0;
+                    for (repo, report) in reports.iter() {
+                        if report.is_empty() {
+                            continue;
+                        }
+                        total_size += report.num_bytes;
+                        println!(
+                            "{:?}: {} {} unreachable versions and {} unreachable commits ({})",
+                            repo.path,
+                            verb,
+                            report.unreachable_versions.len(),
+                            report.unreachable_commits.len(),
+                            ByteSize::b(report.num_bytes)
+                        );
+                    }
+                    println!("{} {} total", verb, ByteSize::b(total_size));
+                }
+                Err(err) => {
+                    eprintln!("Err: {err}")
+                }
+            }
+
+            Ok(())
+        }
+        Some(("list-users", _sub_matches)) => {
+            let path = Path::new(&sync_dir);
+            let result = AccessKeyManager::new(path).and_then(|keygen| {
+                for (_token, claim) in keygen.list_claims()? {
+                    let expires = match claim.expires_at() {
+                        Some(expires_at) if claim.is_expired() => format!("expired {expires_at}"),
+                        Some(expires_at) => format!("expires {expires_at}"),
+                        None => String::from("never expires"),
+                    };
+                    let mut scopes: Vec<String> = vec![];
+                    if claim.read_only() {
+                        scopes.push(String::from("read-only"));
+                    }
+                    if let Some(repo) = claim.repo() {
+                        scopes.push(format!("repo {repo}"));
+                    }
+                    if claim.is_admin() {
+                        scopes.push(String::from("admin"));
+                    }
+                    if keygen.is_revoked(&claim) {
+                        scopes.push(String::from("REVOKED"));
+                    }
+                    println!(
+                        "{}\t{} <{}>\t{}\t{}",
+                        claim.id(),
+                        claim.name(),
+                        claim.email(),
+                        expires,
+                        scopes.join(", ")
+                    );
+                }
+                Ok(())
+            });
+            if let Err(err) = result {
+                eprintln!("Err: {err}")
+            }
+
+            Ok(())
+        }
+        Some(("revoke-token", sub_matches)) => {
+            let token = sub_matches.value_of("token").unwrap();
+            let path = Path::new(&sync_dir);
+            match AccessKeyManager::new(path).and_then(|keygen| keygen.revoke(token)) {
+                Ok(Some(claim)) => {
+                    println!("Revoked token {} for {}", claim.id(), claim.email())
+                }
+                Ok(None) => eprintln!("Token not found: {token}"),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("rotate-secret", _sub_matches)) => {
+            let path = Path::new(&sync_dir);
+            match AccessKeyManager::new(path).and_then(|keygen| keygen.rotate_secret()) {
+                Ok(num_tokens) => {
+                    println!("Rotated secret key, {num_tokens} tokens are no longer valid. Issue new ones with `oxen-server add-user`")
+                }
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("grant", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let email = sub_matches.value_of("email").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = PermissionsManager::new(path).and_then(|permissions| {
+                match sub_matches.value_of("repo") {
+                    Some(repo) => {
+                        let role = Role::from_str(sub_matches.value_of("role").unwrap())?;
+                        permissions.grant(namespace, repo, email, role)?;
+                        println!("Granted {email} {role} access to {namespace}/{repo}");
+                    }
+                    None => {
+                        permissions.add_namespace_owner(namespace, email)?;
+                        println!("{email} is now an owner of namespace {namespace}");
+                    }
+                }
+                Ok(())
+            });
+            if let Err(err) = result {
+                eprintln!("Err: {err}")
+            }
+
+            Ok(())
+        }
+        Some(("revoke", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let email = sub_matches.value_of("email").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = PermissionsManager::new(path).and_then(|permissions| {
+                match sub_matches.value_of("repo") {
+                    Some(repo) => {
+                        permissions.revoke(namespace, repo, email)?;
+                        println!("Revoked {email} access to {namespace}/{repo}");
+                    }
+                    None => {
+                        permissions.remove_namespace_owner(namespace, email)?;
+                        println!("{email} is no longer an owner of namespace {namespace}");
+                    }
+                }
+                Ok(())
+            });
+            if let Err(err) = result {
+                eprintln!("Err: {err}")
+            }
+
+            Ok(())
+        }
+        Some(("set-visibility", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let repo = sub_matches.value_of("repo").unwrap();
+            let visibility = sub_matches.value_of("visibility").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = PermissionsManager::new(path).and_then(|permissions| {
+                permissions.set_public(namespace, repo, visibility == "public")
+            });
+            match result {
+                Ok(_) => println!("{namespace}/{repo} is now {visibility}"),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("protect-branch", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let name = sub_matches.value_of("repo").unwrap();
+            let rule = BranchProtection {
+                branch: sub_matches.value_of("branch").unwrap().to_string(),
+                no_delete: sub_matches.is_present("no-delete"),
+                no_force_update: sub_matches.is_present("no-force-update"),
+                require_valid: sub_matches.is_present("require-valid"),
+                require_signed: sub_matches.is_present("require-signed"),
+                allowed_pushers: sub_matches
+                    .values_of("allowed-pusher")
+                    .map(|emails| emails.map(String::from).collect())
+                    .unwrap_or_default(),
+            };
+            let path = Path::new(&sync_dir);
+            let result = get_repo(path, namespace, name).and_then(|repo| {
+                let mut config = BranchProtectionConfig::load(&repo)?;
+                config.protect(rule.clone());
+                config.save(&repo)
+            });
+            match result {
+                Ok(_) => println!("Protected {namespace}/{name} branch {}", rule.branch),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("unprotect-branch", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let name = sub_matches.value_of("repo").unwrap();
+            let branch = sub_matches.value_of("branch").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = get_repo(path, namespace, name).and_then(|repo| {
+                let mut config = BranchProtectionConfig::load(&repo)?;
+                let removed = config.unprotect(branch);
+                config.save(&repo)?;
+                Ok(removed)
+            });
+            match result {
+                Ok(true) => println!("Removed protection from {namespace}/{name} branch {branch}"),
+                Ok(false) => eprintln!("Branch {branch} is not protected in {namespace}/{name}"),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("trust-key", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let name = sub_matches.value_of("repo").unwrap();
+            let key = TrustedKey {
+                email: sub_matches.value_of("email").unwrap().to_string(),
+                public_key: sub_matches.value_of("public-key").unwrap().to_string(),
+                branches: sub_matches
+                    .values_of("branch")
+                    .map(|branches| branches.map(String::from).collect())
+                    .unwrap_or_default(),
+            };
+            let path = Path::new(&sync_dir);
+            let result = get_repo(path, namespace, name).and_then(|repo| {
+                let mut config = TrustedKeysConfig::get(&repo)?;
+                config.trust(key.clone());
+                config.save(&repo)
+            });
+            match result {
+                Ok(_) => println!(
+                    "Trusting key {} for the commits of {} in {namespace}/{name}",
+                    key.public_key, key.email
+                ),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("untrust-key", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let name = sub_matches.value_of("repo").unwrap();
+            let email = sub_matches.value_of("email").unwrap();
+            let public_key = sub_matches.value_of("public-key").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = get_repo(path, namespace, name).and_then(|repo| {
+                let mut config = TrustedKeysConfig::get(&repo)?;
+                let removed = config.untrust(email, public_key);
+                config.save(&repo)?;
+                Ok(removed)
+            });
+            match result {
+                Ok(true) => println!("Removed key {public_key} of {email} from {namespace}/{name}"),
+                Ok(false) => {
+                    eprintln!("Key {public_key} is not trusted for {email} in {namespace}/{name}")
+                }
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("add-webhook", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let repo = sub_matches.value_of("repo");
+            let url = sub_matches.value_of("url").unwrap();
+            let path = Path::new(&sync_dir);
+            let result = sub_matches
+                .values_of("event")
+                .map(|events| events.map(WebhookEvent::from_str).collect())
+                .unwrap_or_else(|| Ok(vec![]))
+                .and_then(|events| {
+                    let webhook = Webhook::new(namespace, repo, url, events);
+                    WebhookConfig::add(path, &webhook)?;
+                    Ok(webhook)
+                });
+            match result {
+                Ok(webhook) => {
+                    println!("Added webhook {}\n\nRequests are signed with the secret:\n\n{}\n\nin the X-Oxen-Signature header as `sha256=<hex hmac of the body>`", webhook.id, webhook.secret)
+                }
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("list-webhooks", sub_matches)) => {
+            let namespace = sub_matches.value_of("namespace").unwrap();
+            let repo = sub_matches.value_of("repo");
+            let path = Path::new(&sync_dir);
+            match WebhookConfig::load(path) {
+                Ok(config) => {
+                    for webhook in config.list_for(namespace, repo) {
+                        let events = if webhook.events.is_empty() {
+                            String::from("all events")
+                        } else {
+                            let events: Vec<String> =
+                                webhook.events.iter().map(|e| e.to_string()).collect();
+                            events.join(", ")
+                        };
+                        println!(
+                            "{}\t{}/{}\t{}\t{}",
+                            webhook.id,
+                            webhook.namespace,
+                            webhook.repo_name.as_deref().unwrap_or("*"),
+                            webhook.url,
+                            events
+                        );
+                    }
+                }
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
+        Some(("remove-webhook", sub_matches)) => {
+            let id = sub_matches.value_of("id").unwrap();
+            let path = Path::new(&sync_dir);
+            match WebhookConfig::delete(path, id) {
+                Ok(true) => println!("Removed webhook {id}"),
+                Ok(false) => eprintln!("Webhook not found: {id}"),
+                Err(err) => eprintln!("Err: {err}"),
+            }
+
+            Ok(())
+        }
         _ => unreachable!(), // If all subcommands are defined above, anything else is unreachabe!()
     }
 }
+
+/// `start` is its own command so the tests can parse its flags
+fn start_command() -> Command<'static> {
+    Command::new("start")
+        .about(START_SERVER_USAGE)
+        .arg(
+            Arg::new("ip")
+                .long("ip")
+                .short('i')
+                .default_value("0.0.0.0")
+                .default_missing_value("always")
+                .help("What host to bind the server to")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .short('p')
+                .default_value("3000")
+                .default_missing_value("always")
+                .help("What port to bind the server to")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("auth")
+                .long("auth")
+                .short('a')
+                .help("Start the server with token-based authentication enforced")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("job-workers")
+                .long("job-workers")
+                .default_value("2")
+                .help("How many pushed commits can be processed at the same time")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("workers")
+                .long("workers")
+                .help("Number of http workers, defaults to the number of cpus")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("tls-cert")
+                .long("tls-cert")
+                .requires("tls-key")
+                .help("PEM certificate chain to serve https with")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("tls-key")
+                .long("tls-key")
+                .requires("tls-cert")
+                .help("PEM private key for the certificate")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("max-upload-size-mb")
+                .long("max-upload-size-mb")
+                .help("Reject requests with a body larger than this")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("cors-origin")
+                .long("cors-origin")
+                .help("Origin browsers can make requests from, can be repeated. `*` allows any origin")
+                .multiple_occurrences(true)
+                .takes_value(true),
+        )
+}
+
+/// The server can not start, so exit with a status scripts and service managers can check
+fn exit_with_error(msg: &str) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(1)
+}
+
+/// Flags passed to `start` take precedence over the config file
+fn apply_start_flags(
+    mut config: ServerConfig,
+    sub_matches: &clap::ArgMatches,
+) -> Result<ServerConfig, OxenError> {
+    if sub_matches.occurrences_of("ip") > 0 || sub_matches.occurrences_of("port") > 0 {
+        let host = sub_matches.value_of("ip").unwrap();
+        let port = sub_matches.value_of("port").unwrap();
+        if port.parse::<u16>().is_err() {
+            return Err(OxenError::basic_str(INVALID_PORT_MSG));
+        }
+        config.bind = vec![format!("{host}:{port}")];
+    }
+    if sub_matches.is_present("auth") {
+        config.auth = AuthMode::Token;
+    }
+    if sub_matches.occurrences_of("job-workers") > 0 {
+        config.job_workers = Some(parse_flag(sub_matches, "job-workers")?);
+    }
+    if sub_matches.is_present("workers") {
+        config.workers = Some(parse_flag(sub_matches, "workers")?);
+    }
+    if sub_matches.is_present("max-upload-size-mb") {
+        config.max_upload_size_mb = Some(parse_flag(sub_matches, "max-upload-size-mb")?);
+    }
+    if let (Some(cert), Some(key)) = (
+        sub_matches.value_of("tls-cert"),
+        sub_matches.value_of("tls-key"),
+    ) {
+        config.tls = Some(TlsConfig {
+            cert: cert.into(),
+            key: key.into(),
+        });
+    }
+    if let Some(origins) = sub_matches.values_of("cors-origin") {
+        config.cors_origins = origins.map(String::from).collect();
+    }
+    Ok(config)
+}
+
+fn parse_flag<T: FromStr>(sub_matches: &clap::ArgMatches, name: &str) -> Result<T, OxenError> {
+    sub_matches
+        .value_of(name)
+        .unwrap()
+        .parse::<T>()
+        .map_err(|_| OxenError::basic_str(format!("--{name} must be a number")))
+}
+
+fn ssl_acceptor(tls: &TlsConfig) -> Result<SslAcceptorBuilder, openssl::error::ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_private_key_file(&tls.key, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(&tls.cert)?;
+    builder.check_private_key()?;
+    Ok(builder)
+}
+
+fn cors(origins: &[String]) -> Cors {
+    let cors = Cors::default()
+        .allow_any_method()
+        .allow_any_header()
+        .expose_headers(vec![liboxen::view::http::REQUEST_ID_HEADER]);
+    if origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+    origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
+
+fn namespace_arg() -> Arg<'static> {
+    Arg::new("namespace")
+        .long("namespace")
+        .short('n')
+        .help("Namespace of the repository")
+        .required(true)
+        .takes_value(true)
+}
+
+fn repo_arg() -> Arg<'static> {
+    Arg::new("repo")
+        .long("repo")
+        .short('r')
+        .help("Name of the repository")
+        .takes_value(true)
+}
+
+fn email_arg() -> Arg<'static> {
+    Arg::new("email")
+        .long("email")
+        .short('e')
+        .help("Email of the user, as used when creating their access token")
+        .required(true)
+        .takes_value(true)
+}
+
+fn branch_arg() -> Arg<'static> {
+    Arg::new("branch")
+        .long("branch")
+        .short('b')
+        .help("Name of the branch, or a prefix ending in `*` to match many branches")
+        .required(true)
+        .takes_value(true)
+}
+
+fn public_key_arg() -> Arg<'static> {
+    Arg::new("public-key")
+        .long("public-key")
+        .help("Hex encoded Ed25519 public key, as printed by `oxen config --generate-signing-key`")
+        .required(true)
+        .takes_value(true)
+}
+
+fn get_repo(sync_dir: &Path, namespace: &str, name: &str) -> Result<LocalRepository, OxenError> {
+    match api::local::repositories::get_by_namespace_and_name(sync_dir, namespace, name)? {
+        Some(repo) => Ok(repo),
+        None => Err(OxenError::basic_str(format!(
+            "Repository not found: {namespace}/{name}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server_config::{ServerConfig, TlsConfig};
+    use crate::test;
+
+    use actix_web::{http, web, App, HttpResponse};
+    use liboxen::error::OxenError;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509NameBuilder, X509};
+    use std::path::{Path, PathBuf};
+
+    fn parse_start_flags(config: ServerConfig, args: &[&str]) -> Result<ServerConfig, OxenError> {
+        let matches = super::start_command()
+            .try_get_matches_from(std::iter::once("start").chain(args.iter().copied()))
+            .map_err(|err| OxenError::basic_str(err.to_string()))?;
+        super::apply_start_flags(config, &matches)
+    }
+
+    fn write_self_signed_cert(dir: &Path) -> Result<TlsConfig, OxenError> {
+        let key = new_key();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost").unwrap();
+        let name = name.build();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let tls = TlsConfig {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+        };
+        std::fs::write(&tls.cert, cert.build().to_pem().unwrap())?;
+        std::fs::write(&tls.key, key.private_key_to_pem_pkcs8().unwrap())?;
+        Ok(tls)
+    }
+
+    fn new_key() -> PKey<Private> {
+        PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_start_flags_take_precedence_over_config_file() -> Result<(), OxenError> {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            bind = ["127.0.0.1:4000"]
+            workers = 4
+            job_workers = 3
+            max_upload_size_mb = 10
+            cors_origins = ["https://hub.example.com"]
+            "#,
+        )
+        .unwrap();
+
+        // Flags that are not given, even ones with a default, leave the file alone
+        let unchanged = parse_start_flags(config.clone(), &[])?;
+        assert_eq!(unchanged, config);
+
+        let config = parse_start_flags(
+            config,
+            &[
+                "--port",
+                "5000",
+                "--max-upload-size-mb",
+                "20",
+                "--cors-origin",
+                "https://a.example.com",
+                "--cors-origin",
+                "https://b.example.com",
+            ],
+        )?;
+        assert_eq!(config.bind_addresses(), vec![String::from("0.0.0.0:5000")]);
+        assert_eq!(config.max_upload_size_bytes(), Some(20 * 1024 * 1024));
+        assert_eq!(config.workers, Some(4));
+        assert_eq!(config.job_workers(), 3);
+        assert_eq!(config.cors_origins.len(), 2);
+
+        assert!(parse_start_flags(ServerConfig::default(), &["--port", "not-a-port"]).is_err());
+        assert!(parse_start_flags(ServerConfig::default(), &["--tls-cert", "cert.pem"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_tls_flags_load_certificate() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let tls = write_self_signed_cert(&sync_dir)?;
+
+        let cert = tls.cert.to_str().unwrap();
+        let key = tls.key.to_str().unwrap();
+        let config = parse_start_flags(
+            ServerConfig::default(),
+            &["--tls-cert", cert, "--tls-key", key],
+        )?;
+        assert_eq!(config.tls, Some(tls.clone()));
+        assert!(super::ssl_acceptor(config.tls.as_ref().unwrap()).is_ok());
+
+        // A key that does not belong to the certificate is refused before the server starts
+        let other_key = sync_dir.join("other_key.pem");
+        std::fs::write(&other_key, new_key().private_key_to_pem_pkcs8().unwrap())?;
+        let mismatched = TlsConfig {
+            cert: tls.cert.clone(),
+            key: other_key,
+        };
+        assert!(super::ssl_acceptor(&mismatched).is_err());
+        let missing = TlsConfig {
+            cert: PathBuf::from("does-not-exist.pem"),
+            key: tls.key,
+        };
+        assert!(super::ssl_acceptor(&missing).is_err());
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_cors_allows_configured_origins() {
+        let origins = vec![String::from("https://hub.example.com")];
+        let app = actix_web::test::init_service(
+            App::new()
+                .route("/api/version", web::get().to(HttpResponse::Ok))
+                .wrap(super::cors(&origins)),
+        )
+        .await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/version")
+            .insert_header(("origin", "https://hub.example.com"))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get("access-control-allow-origin").unwrap(),
+            "https://hub.example.com"
+        );
+        assert!(headers
+            .get("access-control-expose-headers")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_lowercase()
+            .contains(&liboxen::view::http::REQUEST_ID_HEADER.to_lowercase()));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/version")
+            .insert_header(("origin", "https://evil.example.com"))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert!(resp.headers().get("access-control-allow-origin").is_none());
+    }
+}
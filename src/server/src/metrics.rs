@@ -0,0 +1,578 @@
+//! Request metrics served in the Prometheus text format on `/metrics`, and the middleware
+//! that records them and tags every request with an id that shows up in the logs.
+
+use crate::app_data::OxenAppData;
+use crate::auth::validator::AuthorizedRepo;
+use crate::job_queue::{JobQueue, JobStatus};
+
+use liboxen::util;
+use liboxen::view::http::REQUEST_ID_HEADER;
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{web, Error, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::future::{ready, Ready};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bounds in seconds of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Requests to routes that are not defined are grouped under this route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Most repositories that get their own label, the rest are counted under `OTHER_REPOS`
+const MAX_REPO_LABELS: usize = 1000;
+const OTHER_REPOS: &str = "other";
+
+/// How often the open RocksDB databases are counted, instead of on every scrape
+const ROCKSDB_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The id of the request, taken from the `X-Oxen-Request-Id` header or generated if the client did not send one
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Id of a request being handled, for logging in the controllers
+    pub fn of(req: &HttpRequest) -> String {
+        req.extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if seconds <= *bound {
+                self.buckets[i] += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+// BTreeMaps so the metrics are always written in the same order
+#[derive(Debug, Default)]
+struct MetricsData {
+    // (method, route, status)
+    requests: BTreeMap<(String, String, u16), u64>,
+    // (method, route)
+    latencies: BTreeMap<(String, String), Histogram>,
+    // namespace/repo_name
+    bytes_uploaded: BTreeMap<String, u64>,
+    bytes_downloaded: BTreeMap<String, u64>,
+    chunk_upload_failures: BTreeMap<String, u64>,
+    // Repositories that have a label so far
+    repos: HashSet<String>,
+}
+
+impl MetricsData {
+    fn repo_label(&mut self, repo: &str) -> String {
+        if self.repos.contains(repo) {
+            return repo.to_string();
+        }
+        if self.repos.len() >= MAX_REPO_LABELS {
+            return String::from(OTHER_REPOS);
+        }
+        self.repos.insert(repo.to_string());
+        repo.to_string()
+    }
+}
+
+/// Counters for the requests the server has handled since it started
+#[derive(Debug, Default)]
+pub struct Metrics {
+    data: Mutex<MetricsData>,
+    rocksdb_open_handles: AtomicUsize,
+}
+
+/// What the middleware records about a single request
+pub struct RequestRecord<'a> {
+    pub method: &'a str,
+    pub route: &'a str,
+    /// Only set for existing repositories the request was authorized to access
+    pub repo: Option<String>,
+    pub status: u16,
+    pub duration: Duration,
+    pub bytes_uploaded: u64,
+    pub bytes_downloaded: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Counts the open RocksDB databases in the background, so scrapes do not read `/proc`
+    pub fn start_rocksdb_sampler(metrics: Arc<Metrics>, sync_dir: PathBuf) {
+        std::thread::spawn(move || loop {
+            metrics
+                .rocksdb_open_handles
+                .store(count_open_rocksdb_handles(&sync_dir), Ordering::Relaxed);
+            std::thread::sleep(ROCKSDB_SAMPLE_INTERVAL);
+        });
+    }
+
+    pub fn record(&self, record: &RequestRecord) {
+        let mut data = self.data.lock().unwrap();
+        let method = record.method.to_string();
+        let route = record.route.to_string();
+        *data
+            .requests
+            .entry((method.clone(), route.clone(), record.status))
+            .or_default() += 1;
+        data.latencies
+            .entry((method, route))
+            .or_default()
+            .observe(record.duration.as_secs_f64());
+
+        if let Some(repo) = &record.repo {
+            let repo = data.repo_label(repo);
+            if record.bytes_uploaded > 0 {
+                *data.bytes_uploaded.entry(repo.clone()).or_default() += record.bytes_uploaded;
+            }
+            if record.bytes_downloaded > 0 {
+                *data.bytes_downloaded.entry(repo.clone()).or_default() += record.bytes_downloaded;
+            }
+            if record.route.ends_with("/upload_chunk") && record.status >= 400 {
+                *data.chunk_upload_failures.entry(repo.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Writes the metrics in the Prometheus text format
+    pub fn render(&self, queue: Option<&JobQueue>) -> String {
+        let mut out = String::new();
+        {
+            let data = self.data.lock().unwrap();
+
+            write_header(
+                &mut out,
+                "oxen_http_requests_total",
+                "counter",
+                "HTTP requests handled by route and status",
+            );
+            for ((method, route, status), count) in data.requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "oxen_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    method,
+                    escape(route),
+                    status,
+                    count
+                );
+            }
+
+            write_header(
+                &mut out,
+                "oxen_http_request_duration_seconds",
+                "histogram",
+                "HTTP request latency by route",
+            );
+            for ((method, route), histogram) in data.latencies.iter() {
+                let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                    let _ = writeln!(
+                        out,
+                        "oxen_http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}"
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "oxen_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                    histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "oxen_http_request_duration_seconds_sum{{{labels}}} {}",
+                    histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "oxen_http_request_duration_seconds_count{{{labels}}} {}",
+                    histogram.count
+                );
+            }
+
+            write_repo_counter(
+                &mut out,
+                "oxen_bytes_uploaded_total",
+                "Bytes received in request bodies by repository",
+                &data.bytes_uploaded,
+            );
+            write_repo_counter(
+                &mut out,
+                "oxen_bytes_downloaded_total",
+                "Bytes sent in response bodies by repository",
+                &data.bytes_downloaded,
+            );
+            write_repo_counter(
+                &mut out,
+                "oxen_chunk_upload_failures_total",
+                "Chunk uploads that did not succeed by repository",
+                &data.chunk_upload_failures,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "oxen_job_queue_depth",
+            "gauge",
+            "Jobs waiting in the commit processing and webhook queue",
+        );
+        if let Some(queue) = queue {
+            let mut depths = queue.depths();
+            // Always report the queued commits so the gauge goes back to zero
+            depths
+                .entry(("process_commit", JobStatus::Queued))
+                .or_default();
+            for ((task, status), depth) in depths.iter() {
+                let _ = writeln!(
+                    out,
+                    "oxen_job_queue_depth{{task=\"{task}\",status=\"{status}\"}} {depth}"
+                );
+            }
+        }
+
+        write_header(
+            &mut out,
+            "oxen_rocksdb_open_handles",
+            "gauge",
+            "RocksDB databases the server has files open in",
+        );
+        let _ = writeln!(
+            out,
+            "oxen_rocksdb_open_handles {}",
+            self.rocksdb_open_handles.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_repo_counter(out: &mut String, name: &str, help: &str, counts: &BTreeMap<String, u64>) {
+    write_header(out, name, "counter", help);
+    for (repo, count) in counts.iter() {
+        let _ = writeln!(out, "{}{{repo=\"{}\"}} {}", name, escape(repo), count);
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// RocksDB keeps a LOCK, MANIFEST, log or table file open for as long as a database is open,
+/// so count the distinct directories in the sync dir the process has one of those open in
+fn count_open_rocksdb_handles(sync_dir: &Path) -> usize {
+    let fds = match std::fs::read_dir("/proc/self/fd") {
+        Ok(fds) => fds,
+        // Only available on linux
+        Err(_) => return 0,
+    };
+    let sync_dir = std::fs::canonicalize(sync_dir).unwrap_or_else(|_| sync_dir.to_path_buf());
+    let mut db_dirs: HashSet<PathBuf> = HashSet::new();
+    for fd in fds.flatten() {
+        let target = match std::fs::read_link(fd.path()) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        if !target.starts_with(&sync_dir) {
+            continue;
+        }
+        let is_db_file = target
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| {
+                name == "LOCK"
+                    || name.starts_with("MANIFEST-")
+                    || name.ends_with(".sst")
+                    || name.ends_with(".log")
+            })
+            .unwrap_or(false);
+        if let (true, Some(parent)) = (is_db_file, target.parent()) {
+            db_dirs.insert(parent.to_path_buf());
+        }
+    }
+    db_dirs.len()
+}
+
+fn repo_exists(sync_dir: &Path, repo: &str) -> bool {
+    util::fs::oxen_hidden_dir(sync_dir.join(repo)).exists()
+}
+
+fn request_id_for(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(String::from)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Middleware that tags each request with a request id and records it in the `Metrics`
+pub struct RequestTracing;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestTracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware { service }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let request_id = request_id_for(&req);
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| String::from(UNMATCHED_ROUTE));
+        let bytes_uploaded = req
+            .headers()
+            .get("content-length")
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse::<u64>().ok())
+            .unwrap_or(0);
+        let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+        let sync_dir = req
+            .app_data::<OxenAppData>()
+            .map(|app_data| app_data.path.clone());
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let result = fut.await;
+            let (status, bytes_downloaded, repo) = match &result {
+                Ok(res) => {
+                    let bytes = match res.response().body().size() {
+                        BodySize::Sized(size) => size,
+                        _ => 0,
+                    };
+                    let repo = res
+                        .request()
+                        .extensions()
+                        .get::<AuthorizedRepo>()
+                        .map(|repo| repo.0.clone());
+                    (res.status(), bytes, repo)
+                }
+                Err(err) => (err.as_response_error().status_code(), 0, None),
+            };
+            // The repository in the path is only a label once auth let the request through to
+            // it, and only if it exists, so made up names cannot grow the metrics
+            let repo = match (repo, &sync_dir) {
+                (Some(repo), Some(sync_dir)) if repo_exists(sync_dir, &repo) => Some(repo),
+                _ => None,
+            };
+
+            if status.is_client_error() || status.is_server_error() {
+                log::warn!("[{}] {} {} -> {}", request_id, method, path, status);
+            }
+            if let Some(metrics) = metrics {
+                metrics.record(&RequestRecord {
+                    method: &method,
+                    route: &route,
+                    repo,
+                    status: status.as_u16(),
+                    duration: start.elapsed(),
+                    bytes_uploaded,
+                    bytes_downloaded,
+                });
+            }
+
+            // Let the client know which id to look for in the logs
+            result.map(|mut res| {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(REQUEST_ID_HEADER.as_bytes()),
+                    HeaderValue::from_str(&request_id),
+                ) {
+                    res.headers_mut().insert(name, value);
+                }
+                res
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app_data::OxenAppData;
+    use crate::auth::permissions::Role;
+    use crate::auth::validator::{Access, Authorize};
+    use crate::metrics::{Metrics, RequestRecord, RequestTracing};
+    use crate::test;
+
+    use actix_web::{http, web, App, HttpResponse};
+    use liboxen::error::OxenError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_metrics_render() -> Result<(), OxenError> {
+        let metrics = Metrics::new();
+        let route = "/api/repos/{namespace}/{repo_name}/commits/{commit_id}/upload_chunk";
+        metrics.record(&RequestRecord {
+            method: "POST",
+            route,
+            repo: Some(String::from("ox/data")),
+            status: 200,
+            duration: Duration::from_millis(20),
+            bytes_uploaded: 1024,
+            bytes_downloaded: 0,
+        });
+        metrics.record(&RequestRecord {
+            method: "POST",
+            route,
+            repo: Some(String::from("ox/data")),
+            status: 500,
+            duration: Duration::from_secs(3),
+            bytes_uploaded: 1024,
+            bytes_downloaded: 0,
+        });
+
+        let text = metrics.render(None);
+        assert!(text.contains(&format!(
+            "oxen_http_requests_total{{method=\"POST\",route=\"{route}\",status=\"200\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "oxen_http_request_duration_seconds_bucket{{method=\"POST\",route=\"{route}\",le=\"0.025\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "oxen_http_request_duration_seconds_count{{method=\"POST\",route=\"{route}\"}} 2"
+        )));
+        assert!(text.contains("oxen_bytes_uploaded_total{repo=\"ox/data\"} 2048"));
+        assert!(text.contains("oxen_chunk_upload_failures_total{repo=\"ox/data\"} 1"));
+        assert!(text.contains("oxen_rocksdb_open_handles 0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_caps_repo_labels() {
+        let metrics = Metrics::new();
+        for i in 0..super::MAX_REPO_LABELS + 10 {
+            metrics.record(&RequestRecord {
+                method: "GET",
+                route: "/api/repos/{namespace}/{repo_name}",
+                repo: Some(format!("ox/repo-{i}")),
+                status: 200,
+                duration: Duration::from_millis(1),
+                bytes_uploaded: 0,
+                bytes_downloaded: 1,
+            });
+        }
+
+        let text = metrics.render(None);
+        assert!(text.contains("oxen_bytes_downloaded_total{repo=\"ox/repo-0\"} 1"));
+        assert!(text.contains("oxen_bytes_downloaded_total{repo=\"other\"} 10"));
+    }
+
+    #[actix_web::test]
+    async fn test_request_tracing_labels_only_authorized_repos() -> Result<(), OxenError> {
+        let sync_dir = test::get_sync_dir()?;
+        let namespace = "Testing-Namespace";
+        let name = "Testing-Metrics";
+        test::create_local_repo(&sync_dir, namespace, name)?;
+
+        let metrics = web::Data::new(Metrics::new());
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(OxenAppData {
+                    path: sync_dir.clone(),
+                })
+                .app_data(metrics.clone())
+                .service(
+                    web::scope("/api/repos").service(
+                        web::resource("/{namespace}/{repo_name}")
+                            .route(web::get().to(|| async { HttpResponse::Ok().body("ok") }))
+                            .wrap(Authorize::new(Access::Repo(Role::Read))),
+                    ),
+                )
+                .service(
+                    web::resource("/unauthorized/{namespace}/{repo_name}")
+                        .route(web::get().to(|| async { HttpResponse::Ok().body("ok") })),
+                )
+                .wrap(RequestTracing),
+        )
+        .await;
+
+        for uri in [
+            format!("/api/repos/{namespace}/{name}"),
+            format!("/api/repos/{namespace}/does-not-exist"),
+            format!("/unauthorized/{namespace}/{name}"),
+        ] {
+            let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+        }
+
+        let text = metrics.render(None);
+        assert!(text.contains(&format!(
+            "oxen_bytes_downloaded_total{{repo=\"{namespace}/{name}\"}}"
+        )));
+        assert!(!text.contains("does-not-exist"));
+        assert_eq!(text.matches("oxen_bytes_downloaded_total{").count(), 1);
+
+        // cleanup
+        std::fs::remove_dir_all(sync_dir)?;
+
+        Ok(())
+    }
+}
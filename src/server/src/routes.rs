@@ -1,132 +1,248 @@
-use actix_web::web;
+use actix_web::dev::HttpServiceFactory;
+use actix_web::http::Method;
+use actix_web::{guard, web, FromRequest, Handler, Responder};
 
 use super::controllers;
+use crate::auth::permissions::Role;
+use crate::auth::validator::{Access, Authorize};
 
+/// Every route declares the access it needs, see `auth::validator::Authorize`
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.route(
+    cfg.service(route(
         "",
-        web::post().to(controllers::repositories::create),
-    )
-    .route(
+        Method::POST,
+        Access::Unscoped,
+        controllers::repositories::create,
+    ))
+    .service(route(
         "/{namespace}",
-        web::get().to(controllers::repositories::index),
-    )
+        Method::GET,
+        Access::User,
+        controllers::repositories::index,
+    ))
     .service(web::resource("/{namespace}/{repo_name}")
         // we give the resource a name here so it can be used with HttpRequest.url_for
         .name("repo_root")
+        .guard(guard::Get())
+        .wrap(Authorize::new(Access::Repo(Role::Read)))
         .route(web::get().to(controllers::repositories::show))
-        .route(web::delete().to(controllers::repositories::delete))
     )
+    .service(route(
+        "/{namespace}/{repo_name}",
+        Method::DELETE,
+        Access::Repo(Role::Admin),
+        controllers::repositories::delete,
+    ))
     // ----- Commits ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/commits",
-        web::get().to(controllers::commits::index),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::index,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits",
-        web::post().to(controllers::commits::create),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::commits::create,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_id}",
-        web::get().to(controllers::commits::show),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::show,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_id}/data",
-        web::post().to(controllers::commits::upload),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::commits::upload,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_id}/complete",
-        web::post().to(controllers::commits::complete),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::commits::complete,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_id}/upload_chunk",
-        web::post().to(controllers::commits::upload_chunk),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::commits::upload_chunk,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_or_branch:.*}/history",
-        web::get().to(controllers::commits::commit_history),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::commit_history,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_or_branch:.*}/parents",
-        web::get().to(controllers::commits::parents),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::parents,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_or_branch:.*}/is_synced",
-        web::get().to(controllers::commits::is_synced),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::is_synced,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/commits/{commit_or_branch:.*}/commit_db",
-        web::get().to(controllers::commits::download_commit_db),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::commits::download_commit_db,
+    ))
     // ----- Branches ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/branches",
-        web::get().to(controllers::branches::index),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::branches::index,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/branches",
-        web::post().to(controllers::branches::create_or_get),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::branches::create_or_get,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/branches/{branch_name:.*}",
-        web::get().to(controllers::branches::show),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::branches::show,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/branches/{branch_name:.*}",
-        web::delete().to(controllers::branches::delete),
-    )
-    .route(
+        Method::DELETE,
+        Access::Repo(Role::Write),
+        controllers::branches::delete,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/branches/{branch_name:.*}",
-        web::put().to(controllers::branches::update),
-    )
+        Method::PUT,
+        Access::Repo(Role::Write),
+        controllers::branches::update,
+    ))
+    // ----- Notes ----- //
+    .service(route(
+        "/{namespace}/{repo_name}/notes",
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::notes::index,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/notes",
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::notes::update,
+    ))
+    // ----- Forks ----- //
+    .service(route(
+        "/{namespace}/{repo_name}/fork",
+        Method::POST,
+        Access::Repo(Role::Read),
+        controllers::repositories::fork,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/merge_requests",
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::merge_requests::index,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/merge_requests",
+        Method::POST,
+        Access::Repo(Role::Read),
+        controllers::merge_requests::create,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/merge_requests/{merge_request_id}/accept",
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::merge_requests::accept,
+    ))
     // ----- Stats ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/stats",
-        web::get().to(controllers::repositories::stats),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::repositories::stats,
+    ))
     // ----- Dir ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/dir/{resource:.*}",
-        web::get().to(controllers::dir::get),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::dir::get,
+    ))
     // ----- File (returns raw file data) ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/file/{resource:.*}",
-        web::get().to(controllers::file::get),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::file::get,
+    ))
     // ----- Entry (returns meta data for a file or a dir) ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/entry/{resource:.*}",
-        web::get().to(controllers::file::meta_data),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::file::meta_data,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/meta/{resource:.*}",
-        web::get().to(controllers::file::meta_data_legacy),
-    )
-    .route(
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::file::meta_data_legacy,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/chunk/{resource:.*}", // Download a chunk of a larger versioned file
-        web::get().to(controllers::entries::download_chunk),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::entries::download_chunk,
+    ))
     // ----- Versions ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/versions", // Download tar.gz set of version files
-        web::get().to(controllers::entries::download_data_from_version_paths),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::entries::download_data_from_version_paths,
+    ))
     // POST to versions will be depreciated...
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/versions", // Download tar.gz set of version files
-        web::post().to(controllers::entries::download_data_from_version_paths),
-    )
+        Method::POST,
+        Access::Repo(Role::Read),
+        controllers::entries::download_data_from_version_paths,
+    ))
     // ----- Schemas ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/schemas/{resource:.*}",
-        web::get().to(controllers::schemas::get),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::schemas::get,
+    ))
     // ----- DataFrame ----- //
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/df/{resource:.*}",
-        web::get().to(controllers::df::get),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::df::get,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/blame/{resource:.*}",
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::df::blame,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/search/{commit_or_branch:.*}",
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::search::index,
+    ))
 
     // .route(
     //     "/{namespace}/{repo_name}/commits/{commit_id}/entries",
@@ -146,14 +262,44 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     //     web::get().to(controllers::entries::download_page),
     // )
 
-    .route(
+    .service(route(
         "/{namespace}/{repo_name}/entries",
-        web::post().to(controllers::entries::create),
-    )
-    .route(
+        Method::POST,
+        Access::Repo(Role::Write),
+        controllers::entries::create,
+    ))
+    .service(route(
         "/{namespace}/{repo_name}/lines/{resource:.*}",
-        web::get().to(controllers::entries::list_lines_in_file),
-    )
+        Method::GET,
+        Access::Repo(Role::Read),
+        controllers::entries::list_lines_in_file,
+    ))
+    // ----- Webhooks ----- //
+    .service(route(
+        "/{namespace}/{repo_name}/webhooks",
+        Method::GET,
+        Access::Repo(Role::Admin),
+        controllers::webhooks::index,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/webhooks",
+        Method::POST,
+        Access::Repo(Role::Admin),
+        controllers::webhooks::create,
+    ))
+    .service(route(
+        "/{namespace}/{repo_name}/webhooks/{webhook_id}",
+        Method::DELETE,
+        Access::Repo(Role::Admin),
+        controllers::webhooks::delete,
+    ))
+    // ----- Jobs ----- //
+    .service(route(
+        "/{namespace}/{repo_name}/jobs",
+        Method::GET,
+        Access::Repo(Role::Admin),
+        controllers::jobs::index,
+    ))
     // .route(
     //     "/{namespace}/{repo_name}/branches/{branch_name}/entries/{filename:.*}",
     //     web::get().to(controllers::repositories::get_file_for_branch),
@@ -168,3 +314,22 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     // )
     ;
 }
+
+/// Registers the handler for requests with the method on the path,
+/// that are only let through if they have the access the route needs
+pub fn route<F, Args>(
+    path: &str,
+    method: Method,
+    access: Access,
+    handler: F,
+) -> impl HttpServiceFactory
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    web::resource(path)
+        .guard(guard::Method(method.clone()))
+        .wrap(Authorize::new(access))
+        .route(web::method(method).to(handler))
+}
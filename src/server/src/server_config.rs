@@ -0,0 +1,205 @@
+//! Settings for `oxen-server start`, read from a TOML file passed with `--config`. Flags
+//! given on the command line take precedence over the file.
+
+use crate::limits::{QuotaConfig, RateLimitConfig};
+
+use liboxen::error::OxenError;
+use liboxen::storage::StorageConfig;
+use liboxen::util;
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SYNC_DIR: &str = "/tmp/oxen_sync";
+pub const DEFAULT_HOST: &str = "0.0.0.0";
+pub const DEFAULT_PORT: u16 = 3000;
+pub const DEFAULT_JOB_WORKERS: usize = 2;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    #[default]
+    None,
+    Token,
+}
+
+/// Certificate chain and private key in PEM format, served directly instead of behind a proxy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// # ServerConfig
+/// For example
+/// ```toml
+/// sync_dir = "/var/oxen/data"
+/// bind = ["0.0.0.0:3000"]
+/// auth = "token"
+/// workers = 8
+/// job_workers = 2
+/// max_upload_size_mb = 2048
+/// cors_origins = ["https://hub.example.com"]
+///
+/// [tls]
+/// cert = "/etc/oxen/cert.pem"
+/// key = "/etc/oxen/key.pem"
+///
+/// [quotas]
+/// default_mb = 10240
+/// namespaces = { ox = 102400 }
+///
+/// [rate_limit]
+/// requests_per_minute = 600
+///
+/// [storage]
+/// type = "s3"
+///
+/// [storage.s3]
+/// bucket = "oxen-versions"
+/// endpoint = "https://s3.us-east-1.amazonaws.com"
+/// region = "us-east-1"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ServerConfig {
+    pub sync_dir: Option<PathBuf>,
+    /// `host:port` addresses to listen on
+    #[serde(default)]
+    pub bind: Vec<String>,
+    #[serde(default)]
+    pub auth: AuthMode,
+    pub tls: Option<TlsConfig>,
+    /// Number of http workers, defaults to the number of cpus
+    pub workers: Option<usize>,
+    pub job_workers: Option<usize>,
+    /// Requests with a larger body are rejected with a 413
+    pub max_upload_size_mb: Option<u64>,
+    /// Origins browsers can make requests from, `*` allows any
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Where new repositories keep their version files, each under its own `namespace/name` prefix.
+    /// Defaults to the .oxen/versions dir of each repository.
+    pub storage: Option<StorageConfig>,
+}
+
+impl ServerConfig {
+    pub fn from_file(path: &Path) -> Result<ServerConfig, OxenError> {
+        let contents = util::fs::read_from_path(path)?;
+        toml::from_str(&contents).map_err(|err| {
+            OxenError::basic_str(format!("Could not parse server config {path:?}: {err}"))
+        })
+    }
+
+    /// The config file if one was given, otherwise the defaults
+    pub fn load(path: Option<&Path>) -> Result<ServerConfig, OxenError> {
+        match path {
+            Some(path) => ServerConfig::from_file(path),
+            None => Ok(ServerConfig::default()),
+        }
+    }
+
+    /// The `SYNC_DIR` env var takes precedence over the config file
+    pub fn sync_dir(&self) -> PathBuf {
+        match std::env::var("SYNC_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => self
+                .sync_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_SYNC_DIR)),
+        }
+    }
+
+    pub fn bind_addresses(&self) -> Vec<String> {
+        if self.bind.is_empty() {
+            vec![format!("{DEFAULT_HOST}:{DEFAULT_PORT}")]
+        } else {
+            self.bind.clone()
+        }
+    }
+
+    pub fn job_workers(&self) -> usize {
+        self.job_workers.unwrap_or(DEFAULT_JOB_WORKERS)
+    }
+
+    pub fn max_upload_size_bytes(&self) -> Option<u64> {
+        self.max_upload_size_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    pub fn storage(&self) -> StorageConfig {
+        self.storage.clone().unwrap_or_else(StorageConfig::local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server_config::{AuthMode, ServerConfig, DEFAULT_JOB_WORKERS};
+
+    use liboxen::error::OxenError;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_server_config_parse() -> Result<(), OxenError> {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            sync_dir = "/var/oxen/data"
+            bind = ["0.0.0.0:3000", "[::]:3000"]
+            auth = "token"
+            max_upload_size_mb = 10
+            cors_origins = ["https://hub.example.com"]
+
+            [tls]
+            cert = "/etc/oxen/cert.pem"
+            key = "/etc/oxen/key.pem"
+
+            [quotas]
+            default_mb = 1
+            namespaces = { ox = 2 }
+
+            [rate_limit]
+            requests_per_minute = 600
+
+            [storage]
+            type = "s3"
+
+            [storage.s3]
+            bucket = "oxen-versions"
+            endpoint = "http://localhost:9000"
+            region = "us-east-1"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.sync_dir, Some(PathBuf::from("/var/oxen/data")));
+        assert_eq!(config.bind_addresses().len(), 2);
+        assert_eq!(config.auth, AuthMode::Token);
+        assert_eq!(config.max_upload_size_bytes(), Some(10 * 1024 * 1024));
+        assert_eq!(config.job_workers(), DEFAULT_JOB_WORKERS);
+        assert_eq!(config.quotas.limit_bytes_for("ox"), Some(2 * 1024 * 1024));
+        assert_eq!(config.rate_limit.unwrap().requests_per_minute, 600);
+        assert_eq!(config.tls.unwrap().key, PathBuf::from("/etc/oxen/key.pem"));
+
+        let storage = config.storage().for_repo("ox", "CatsVsDogs");
+        assert!(!storage.is_local());
+        assert_eq!(
+            storage.s3.unwrap().prefix,
+            Some(String::from("ox/CatsVsDogs"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_config_defaults() -> Result<(), OxenError> {
+        let config: ServerConfig = toml::from_str("").unwrap();
+        assert_eq!(config.auth, AuthMode::None);
+        assert_eq!(config.bind_addresses(), vec![String::from("0.0.0.0:3000")]);
+        assert!(config.tls.is_none());
+        assert!(config.max_upload_size_bytes().is_none());
+        assert!(config.storage().is_local());
+
+        Ok(())
+    }
+}
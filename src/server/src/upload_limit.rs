@@ -0,0 +1,161 @@
+//! Middleware that rejects requests whose body is larger than the configured max upload size
+
+use liboxen::view::http::MSG_UPLOAD_TOO_LARGE;
+use liboxen::view::LimitExceededResponse;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::PayloadError;
+use actix_web::web::Bytes;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use bytesize::ByteSize;
+use futures_util::future::LocalBoxFuture;
+use futures_util::{Stream, StreamExt};
+use std::future::{ready, Ready};
+use std::pin::Pin;
+
+/// Checks the size of the body of each request against `max_bytes`, any size is allowed if it is None.
+/// Requests that declare a larger `Content-Length` are rejected right away, and reading more than
+/// `max_bytes` of a body fails with a `PayloadError::Overflow`, so chunked bodies are limited too.
+pub struct UploadLimit {
+    max_bytes: Option<u64>,
+}
+
+impl UploadLimit {
+    pub fn new(max_bytes: Option<u64>) -> UploadLimit {
+        UploadLimit { max_bytes }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for UploadLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = UploadLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(UploadLimitMiddleware {
+            service,
+            max_bytes: self.max_bytes,
+        }))
+    }
+}
+
+pub struct UploadLimitMiddleware<S> {
+    service: S,
+    max_bytes: Option<u64>,
+}
+
+impl<S, B> Service<ServiceRequest> for UploadLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let content_length = req
+            .headers()
+            .get("content-length")
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse::<u64>().ok());
+
+        if let (Some(content_length), Some(max_bytes)) = (content_length, self.max_bytes) {
+            if content_length > max_bytes {
+                let msg = format!(
+                    "Upload of {} is larger than the max upload size of {}",
+                    ByteSize::b(content_length),
+                    ByteSize::b(max_bytes)
+                );
+                log::info!("Rejected {} {}: {}", req.method(), req.path(), msg);
+                let mut body = LimitExceededResponse::new(MSG_UPLOAD_TOO_LARGE, &msg);
+                body.limit = Some(max_bytes);
+                let response = HttpResponse::PayloadTooLarge().json(body);
+                return Box::pin(
+                    async move { Ok(req.into_response(response).map_into_right_body()) },
+                );
+            }
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            let payload = limit_payload(req.take_payload(), max_bytes);
+            req.set_payload(payload);
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+/// Fails the stream once more than `max_bytes` have been read from it
+fn limit_payload(payload: Payload, max_bytes: u64) -> Payload {
+    let mut num_read: u64 = 0;
+    let limited: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+        Box::pin(payload.map(move |chunk| {
+            let chunk = chunk?;
+            num_read += chunk.len() as u64;
+            if num_read > max_bytes {
+                log::info!(
+                    "Rejected upload larger than the max upload size of {}",
+                    ByteSize::b(max_bytes)
+                );
+                return Err(PayloadError::Overflow);
+            }
+            Ok(chunk)
+        }));
+    Payload::from(limited)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::upload_limit::UploadLimit;
+
+    use actix_web::{http, test, web, App, HttpResponse};
+
+    async fn echo(body: web::Bytes) -> HttpResponse {
+        HttpResponse::Ok().body(body)
+    }
+
+    #[actix_web::test]
+    async fn test_upload_limit_checks_content_length_and_streamed_body() {
+        let app = test::init_service(
+            App::new()
+                .route("/upload", web::post().to(echo))
+                .wrap(UploadLimit::new(Some(8))),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/upload")
+            .insert_header(("content-length", "1024"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Without a content length the body is only checked as it is read
+        let req = test::TestRequest::post()
+            .uri("/upload")
+            .set_payload("more than eight bytes")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = test::TestRequest::post()
+            .uri("/upload")
+            .set_payload("small")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+}
@@ -1,3 +1,8 @@
+use crate::job_queue::Job;
+use crate::webhooks::{Webhook, WebhookEvent};
+
+use liboxen::model::Commit;
+use liboxen::view::IsValidStatusMessage;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -10,3 +15,65 @@ pub struct PaginatedLinesResponse {
     pub total_pages: usize,
     pub total_entries: usize,
 }
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListJobsResponse {
+    pub status: String,
+    pub status_message: String,
+    pub jobs: Vec<Job>,
+}
+
+/// Sent to webhooks once a pushed commit is done processing
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CommitProcessedView {
+    pub commit: Commit,
+    pub status: IsValidStatusMessage,
+}
+
+/// A webhook without its secret, which is only shown once when it is created
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebhookView {
+    pub id: String,
+    pub namespace: String,
+    pub repo_name: Option<String>,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: i64,
+}
+
+impl From<&Webhook> for WebhookView {
+    fn from(webhook: &Webhook) -> WebhookView {
+        WebhookView {
+            id: webhook.id.to_owned(),
+            namespace: webhook.namespace.to_owned(),
+            repo_name: webhook.repo_name.to_owned(),
+            url: webhook.url.to_owned(),
+            events: webhook.events.to_owned(),
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Subscribes to every event if empty
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListWebhooksResponse {
+    pub status: String,
+    pub status_message: String,
+    pub webhooks: Vec<WebhookView>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebhookResponse {
+    pub status: String,
+    pub status_message: String,
+    pub webhook: WebhookView,
+    /// Requests to the webhook are signed with this secret
+    pub secret: String,
+}
@@ -0,0 +1,513 @@
+//! Webhooks that get notified about changes to the repositories in a namespace. Events are
+//! queued as jobs so that failed deliveries are retried, and each request is signed with
+//! the webhook secret in the `X-Oxen-Signature` header as `sha256=<hex hmac of the body>`.
+
+use crate::job_queue::{Job, JobQueue, JobTask};
+
+use liboxen::error::OxenError;
+use liboxen::util;
+
+use actix_web::{web, HttpRequest};
+use lazy_static::lazy_static;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+pub const WEBHOOKS_FILE: &str = "webhooks.json";
+
+pub const SIGNATURE_HEADER: &str = "X-Oxen-Signature";
+pub const EVENT_HEADER: &str = "X-Oxen-Event";
+pub const DELIVERY_HEADER: &str = "X-Oxen-Delivery";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // Shared by every delivery so connections to the receivers are reused
+    static ref WEBHOOK_CLIENT: reqwest::blocking::Client = reqwest::blocking::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .expect("Could not build webhook client");
+    // Held while changing webhooks.json so concurrent requests do not overwrite each other
+    static ref CONFIG_LOCK: Mutex<()> = Mutex::new(());
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    BranchUpdated,
+    BranchCreated,
+    BranchDeleted,
+    CommitProcessingSucceeded,
+    CommitProcessingFailed,
+    RepoCreated,
+}
+
+impl WebhookEvent {
+    pub fn all() -> Vec<WebhookEvent> {
+        vec![
+            WebhookEvent::BranchUpdated,
+            WebhookEvent::BranchCreated,
+            WebhookEvent::BranchDeleted,
+            WebhookEvent::CommitProcessingSucceeded,
+            WebhookEvent::CommitProcessingFailed,
+            WebhookEvent::RepoCreated,
+        ]
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = OxenError;
+
+    fn from_str(event: &str) -> Result<WebhookEvent, OxenError> {
+        WebhookEvent::all()
+            .into_iter()
+            .find(|e| e.to_string() == event)
+            .ok_or_else(|| {
+                let events: Vec<String> =
+                    WebhookEvent::all().iter().map(|e| e.to_string()).collect();
+                OxenError::basic_str(format!(
+                    "Invalid event `{event}`, must be one of {}",
+                    events.join(", ")
+                ))
+            })
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let event = match self {
+            WebhookEvent::BranchUpdated => "branch_updated",
+            WebhookEvent::BranchCreated => "branch_created",
+            WebhookEvent::BranchDeleted => "branch_deleted",
+            WebhookEvent::CommitProcessingSucceeded => "commit_processing_succeeded",
+            WebhookEvent::CommitProcessingFailed => "commit_processing_failed",
+            WebhookEvent::RepoCreated => "repo_created",
+        };
+        write!(f, "{event}")
+    }
+}
+
+/// Gets the events for every repository in the namespace, or for a single repository if `repo_name` is set
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Webhook {
+    pub id: String,
+    pub namespace: String,
+    pub repo_name: Option<String>,
+    pub url: String,
+    pub secret: String,
+    /// Subscribed to every event if empty
+    pub events: Vec<WebhookEvent>,
+    pub created_at: i64,
+}
+
+impl Webhook {
+    pub fn new(
+        namespace: &str,
+        repo_name: Option<&str>,
+        url: &str,
+        events: Vec<WebhookEvent>,
+    ) -> Webhook {
+        Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            namespace: namespace.to_owned(),
+            repo_name: repo_name.map(String::from),
+            url: url.to_owned(),
+            secret: hex::encode(rand::random::<[u8; 32]>()),
+            events,
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+        }
+    }
+
+    /// Only http and https urls can receive events
+    pub fn validate_url(url: &str) -> Result<(), OxenError> {
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => Ok(()),
+            _ => Err(OxenError::basic_str(format!(
+                "Invalid webhook url `{url}`, must be an http or https url"
+            ))),
+        }
+    }
+
+    pub fn wants(&self, namespace: &str, repo_name: &str, event: WebhookEvent) -> bool {
+        self.namespace == namespace
+            && self
+                .repo_name
+                .as_ref()
+                .map(|name| name == repo_name)
+                .unwrap_or(true)
+            && (self.events.is_empty() || self.events.contains(&event))
+    }
+}
+
+/// What gets posted to a webhook, `data` is the view of the branch, commit or repository
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayload {
+    pub id: String,
+    pub event: WebhookEvent,
+    pub namespace: String,
+    pub repo_name: String,
+    pub timestamp: i64,
+    pub data: serde_json::Value,
+}
+
+/// All the webhooks on the server, saved in `SYNC_DIR/.oxen/webhooks.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub webhooks: Vec<Webhook>,
+}
+
+impl WebhookConfig {
+    pub fn path(sync_dir: &Path) -> PathBuf {
+        util::fs::oxen_hidden_dir(sync_dir).join(WEBHOOKS_FILE)
+    }
+
+    pub fn load(sync_dir: &Path) -> Result<WebhookConfig, OxenError> {
+        let path = WebhookConfig::path(sync_dir);
+        if !path.exists() {
+            return Ok(WebhookConfig::default());
+        }
+        let contents = util::fs::read_from_path(&path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, sync_dir: &Path) -> Result<(), OxenError> {
+        let hidden_dir = util::fs::oxen_hidden_dir(sync_dir);
+        if !hidden_dir.exists() {
+            std::fs::create_dir_all(&hidden_dir)?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        util::fs::write_to_path(&WebhookConfig::path(sync_dir), &contents)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Webhook> {
+        self.webhooks.iter().find(|webhook| webhook.id == id)
+    }
+
+    /// Removes the webhook, returns false if there was none with the id
+    pub fn remove(&mut self, id: &str) -> bool {
+        let num_webhooks = self.webhooks.len();
+        self.webhooks.retain(|webhook| webhook.id != id);
+        num_webhooks != self.webhooks.len()
+    }
+
+    /// Saves a new webhook
+    pub fn add(sync_dir: &Path, webhook: &Webhook) -> Result<(), OxenError> {
+        Webhook::validate_url(&webhook.url)?;
+        let _guard = CONFIG_LOCK.lock().unwrap();
+        let mut config = WebhookConfig::load(sync_dir)?;
+        config.webhooks.push(webhook.clone());
+        config.save(sync_dir)
+    }
+
+    /// Deletes the webhook, returns false if there was none with the id
+    pub fn delete(sync_dir: &Path, id: &str) -> Result<bool, OxenError> {
+        let _guard = CONFIG_LOCK.lock().unwrap();
+        let mut config = WebhookConfig::load(sync_dir)?;
+        let removed = config.remove(id);
+        if removed {
+            config.save(sync_dir)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn list_for(&self, namespace: &str, repo_name: Option<&str>) -> Vec<&Webhook> {
+        self.webhooks
+            .iter()
+            .filter(|webhook| {
+                webhook.namespace == namespace
+                    && (repo_name.is_none() || webhook.repo_name.as_deref() == repo_name)
+            })
+            .collect()
+    }
+}
+
+/// Queues the event for every webhook that wants it, when handling a request
+pub fn notify<T: Serialize>(
+    req: &HttpRequest,
+    namespace: &str,
+    repo_name: &str,
+    event: WebhookEvent,
+    data: &T,
+) {
+    // The queue is not running in the controller tests
+    if let Some(queue) = req.app_data::<web::Data<JobQueue>>() {
+        if let Err(err) = queue_deliveries(queue, namespace, repo_name, event, data) {
+            log::error!(
+                "Could not queue {} webhooks for {}/{}: {}",
+                event,
+                namespace,
+                repo_name,
+                err
+            );
+        }
+    }
+}
+
+/// Queues a delivery job for every webhook that wants the event
+pub fn queue_deliveries<T: Serialize>(
+    queue: &JobQueue,
+    namespace: &str,
+    repo_name: &str,
+    event: WebhookEvent,
+    data: &T,
+) -> Result<usize, OxenError> {
+    let config = WebhookConfig::load(queue.sync_dir())?;
+    let webhooks: Vec<&Webhook> = config
+        .webhooks
+        .iter()
+        .filter(|webhook| webhook.wants(namespace, repo_name, event))
+        .collect();
+    if webhooks.is_empty() {
+        return Ok(0);
+    }
+
+    let data = serde_json::to_value(data)?;
+    for webhook in webhooks.iter() {
+        let payload = WebhookPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            event,
+            namespace: namespace.to_owned(),
+            repo_name: repo_name.to_owned(),
+            timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            data: data.clone(),
+        };
+        log::debug!(
+            "Queueing {} delivery {} to {}",
+            event,
+            payload.id,
+            webhook.url
+        );
+        let task = JobTask::DeliverWebhook {
+            webhook_id: webhook.id.clone(),
+            payload,
+        };
+        queue.enqueue(Job::new(namespace, repo_name, task))?;
+    }
+    Ok(webhooks.len())
+}
+
+/// Posts the payload to the webhook, fails on a non success status so the job is retried
+pub fn deliver(
+    sync_dir: &Path,
+    webhook_id: &str,
+    payload: &WebhookPayload,
+) -> Result<(), OxenError> {
+    let config = WebhookConfig::load(sync_dir)?;
+    let webhook = match config.get(webhook_id) {
+        Some(webhook) => webhook,
+        None => {
+            log::debug!(
+                "Webhook {} was removed, dropping {}",
+                webhook_id,
+                payload.id
+            );
+            return Ok(());
+        }
+    };
+
+    let body = serde_json::to_string(payload)?;
+    let signature = sign(&webhook.secret, &body)?;
+    let response = WEBHOOK_CLIENT
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(SIGNATURE_HEADER, signature)
+        .header(EVENT_HEADER, payload.event.to_string())
+        .header(DELIVERY_HEADER, &payload.id)
+        .body(body)
+        .send()
+        .map_err(|err| {
+            OxenError::basic_str(format!(
+                "Could not deliver webhook to {}: {err}",
+                webhook.url
+            ))
+        })?;
+
+    if response.status().is_success() {
+        log::debug!("Delivered {} to {}", payload.id, webhook.url);
+        Ok(())
+    } else {
+        Err(OxenError::basic_str(format!(
+            "Webhook {} responded with {}",
+            webhook.url,
+            response.status()
+        )))
+    }
+}
+
+/// `sha256=<hex>` HMAC of the body, receivers compute the same with their secret to verify it
+pub fn sign(secret: &str, body: &str) -> Result<String, OxenError> {
+    let sign_err = |err: openssl::error::ErrorStack| {
+        OxenError::basic_str(format!("Could not sign webhook payload: {err}"))
+    };
+    let key = PKey::hmac(secret.as_bytes()).map_err(sign_err)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(sign_err)?;
+    signer.update(body.as_bytes()).map_err(sign_err)?;
+    let hmac = signer.sign_to_vec().map_err(sign_err)?;
+    Ok(format!("sha256={}", hex::encode(hmac)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::job_queue::JobQueue;
+    use crate::test;
+    use crate::webhooks;
+    use crate::webhooks::{Webhook, WebhookConfig, WebhookEvent, WebhookPayload};
+
+    use liboxen::error::OxenError;
+    use liboxen::model::Branch;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Accepts a single request, responds with the status, and sends back the raw request
+    fn stand_in_receiver(status: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request: Vec<u8> = vec![];
+            let mut buffer = [0; 4096];
+            // Read until we have the headers and the full body
+            loop {
+                let n = stream.read(&mut buffer).unwrap();
+                if n == 0 {
+                    // The client closed the connection
+                    break;
+                }
+                request.extend_from_slice(&buffer[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|len| len.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+            }
+            let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n");
+            stream.write_all(response.as_bytes()).unwrap();
+            sender
+                .send(String::from_utf8_lossy(&request).to_string())
+                .unwrap();
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn test_webhook_sign() -> Result<(), OxenError> {
+        // RFC 4231 test case 2
+        assert_eq!(
+            webhooks::sign("Jefe", "what do ya want for nothing?")?,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_webhook_wants() {
+        let webhook = Webhook::new("ox", Some("data"), "http://localhost", vec![]);
+        assert!(webhook.wants("ox", "data", WebhookEvent::BranchCreated));
+        assert!(!webhook.wants("ox", "other", WebhookEvent::BranchCreated));
+
+        let webhook = Webhook::new(
+            "ox",
+            None,
+            "http://localhost",
+            vec![WebhookEvent::RepoCreated],
+        );
+        assert!(webhook.wants("ox", "other", WebhookEvent::RepoCreated));
+        assert!(!webhook.wants("ox", "other", WebhookEvent::BranchCreated));
+        assert!(!webhook.wants("cats", "other", WebhookEvent::RepoCreated));
+    }
+
+    #[test]
+    fn test_webhook_delivers_signed_payload() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let (url, receiver) = stand_in_receiver("200 OK");
+            let webhook = Webhook::new("ox", Some("data"), &url, vec![]);
+            let config = WebhookConfig {
+                webhooks: vec![webhook.clone()],
+            };
+            config.save(sync_dir)?;
+
+            let queue = JobQueue::new(sync_dir)?;
+            let branch = Branch {
+                name: String::from("main"),
+                commit_id: String::from("1234"),
+                is_head: false,
+            };
+            let num_queued = webhooks::queue_deliveries(
+                &queue,
+                "ox",
+                "data",
+                WebhookEvent::BranchUpdated,
+                &branch,
+            )?;
+            assert_eq!(num_queued, 1);
+            // Not subscribed to other repositories
+            let num_queued = webhooks::queue_deliveries(
+                &queue,
+                "ox",
+                "other",
+                WebhookEvent::BranchUpdated,
+                &branch,
+            )?;
+            assert_eq!(num_queued, 0);
+
+            assert!(queue.run_next()?);
+            assert!(queue.list()?.is_empty());
+
+            let request = receiver.recv().unwrap();
+            let (headers, body) = request.split_once("\r\n\r\n").unwrap();
+            let payload: WebhookPayload = serde_json::from_str(body)?;
+            assert_eq!(payload.event, WebhookEvent::BranchUpdated);
+            assert_eq!(payload.data["commit_id"], "1234");
+
+            let signature = webhooks::sign(&webhook.secret, body)?;
+            let headers = headers.to_lowercase();
+            assert!(headers.contains(&format!("x-oxen-signature: {signature}")));
+            assert!(headers.contains("x-oxen-event: branch_updated"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_webhook_failed_delivery_is_retried() -> Result<(), OxenError> {
+        test::run_empty_sync_dir_test(|sync_dir| {
+            let (url, receiver) = stand_in_receiver("500 Internal Server Error");
+            let config = WebhookConfig {
+                webhooks: vec![Webhook::new("ox", None, &url, vec![])],
+            };
+            config.save(sync_dir)?;
+
+            let queue = JobQueue::new(sync_dir)?;
+            webhooks::queue_deliveries(&queue, "ox", "data", WebhookEvent::RepoCreated, &())?;
+            assert!(queue.run_next()?);
+            receiver.recv().unwrap();
+
+            let jobs = queue.list()?;
+            assert_eq!(jobs.len(), 1);
+            assert_eq!(jobs[0].attempts, 1);
+            assert!(jobs[0].error.as_ref().unwrap().contains("500"));
+
+            Ok(())
+        })
+    }
+}
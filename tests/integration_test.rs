@@ -4,13 +4,16 @@ use liboxen::constants;
 use liboxen::df::tabular;
 use liboxen::df::DFOpts;
 use liboxen::error::OxenError;
-use liboxen::index::CommitDirReader;
+use liboxen::index::{CommitDirReader, StatCache};
 use liboxen::model::StagedEntryStatus;
 use liboxen::opts::RestoreOpts;
 use liboxen::test;
 use liboxen::util;
 
+use filetime::FileTime;
 use futures::future;
+use polars::prelude::IntoLazy;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -198,6 +201,65 @@ fn test_command_add_file() -> Result<(), OxenError> {
     })
 }
 
+#[test]
+fn test_command_add_respects_oxenignore() -> Result<(), OxenError> {
+    test::run_empty_local_repo_test(|repo| {
+        util::fs::write_to_path(&repo.path.join(".oxenignore"), "*.log\n__pycache__/\n")?;
+        util::fs::write_to_path(&repo.path.join("train.csv"), "a,b\n1,2")?;
+        util::fs::write_to_path(&repo.path.join("train.log"), "epoch 1")?;
+        let cache_dir = repo.path.join("src").join("__pycache__");
+        std::fs::create_dir_all(&cache_dir)?;
+        util::fs::write_to_path(&cache_dir.join("model.pyc"), "bytes")?;
+        util::fs::write_to_path(&repo.path.join("src").join("model.py"), "import oxen")?;
+
+        command::add(&repo, &repo.path)?;
+        let repo_status = command::status(&repo)?;
+        let mut added: Vec<PathBuf> = repo_status.added_files.keys().cloned().collect();
+        added.sort();
+        assert_eq!(
+            added,
+            vec![PathBuf::from("src/model.py"), PathBuf::from("train.csv")]
+        );
+        // Hidden files are not added with their dir
+        assert_eq!(
+            repo_status.untracked_files,
+            vec![PathBuf::from(".oxenignore")]
+        );
+        assert_eq!(repo_status.untracked_dirs.len(), 0);
+
+        let ignored = command::check_ignore(&repo, "src/__pycache__/model.pyc")?.unwrap();
+        assert_eq!(ignored.pattern, "__pycache__/");
+        assert!(!ignored.negated);
+        assert!(command::check_ignore(&repo, "train.csv")?.is_none());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_status_shows_modified_ignored_tracked_file() -> Result<(), OxenError> {
+    test::run_empty_local_repo_test(|repo| {
+        let log_file = repo.path.join("train.log");
+        util::fs::write_to_path(&log_file, "epoch 1")?;
+        command::add(&repo, &log_file)?;
+        command::commit(&repo, "Adding log")?;
+
+        // Ignoring a file that is already tracked does not stop tracking it
+        util::fs::write_to_path(&repo.path.join(".oxenignore"), "*.log\n")?;
+        util::fs::write_to_path(&log_file, "epoch 2")?;
+        let repo_status = command::status(&repo)?;
+        assert_eq!(repo_status.modified_files, vec![PathBuf::from("train.log")]);
+
+        command::add(&repo, &log_file)?;
+        let repo_status = command::status(&repo)?;
+        assert!(repo_status
+            .added_files
+            .contains_key(&PathBuf::from("train.log")));
+
+        Ok(())
+    })
+}
+
 #[test]
 fn test_command_commit_file() -> Result<(), OxenError> {
     test::run_empty_local_repo_test(|repo| {
@@ -871,6 +933,307 @@ fn test_command_remove_dir_then_revert() -> Result<(), OxenError> {
     })
 }
 
+#[test]
+fn test_command_mv_file_stages_rename() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let head = command::head_commit(&repo)?;
+        let og_entry = CommitDirReader::new(&repo, &head)?
+            .get_entry(Path::new("labels.txt"))?
+            .unwrap();
+        command::mv(&repo, "labels.txt", "annotations/labels.txt")?;
+        assert!(!repo.path.join("labels.txt").exists());
+        assert!(repo.path.join("annotations").join("labels.txt").exists());
+
+        let status = command::status(&repo)?;
+        assert_eq!(
+            status.moved_files,
+            vec![(
+                PathBuf::from("labels.txt"),
+                PathBuf::from("annotations/labels.txt")
+            )]
+        );
+        assert!(status.removed_files.is_empty());
+        assert!(status.modified_files.is_empty());
+
+        let commit = command::commit(&repo, "Moving labels")?.unwrap();
+        let reader = CommitDirReader::new(&repo, &commit)?;
+        assert!(!reader.has_file(Path::new("labels.txt")));
+        let entry = reader
+            .get_entry(Path::new("annotations/labels.txt"))?
+            .unwrap();
+        // Still points at the version of the commit it was added in
+        assert_eq!(entry.hash, og_entry.hash);
+        assert_eq!(entry.commit_id, og_entry.commit_id);
+        assert_ne!(entry.commit_id, commit.id);
+        assert!(util::fs::version_path(&repo, &entry).exists());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_mv_file_moves_stat_cache_entry() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let src = Path::new("labels.txt");
+        let dst = Path::new("annotations/labels.txt");
+        // Set the mtime far enough in the past that the hash is cached
+        filetime::set_file_mtime(repo.path.join(src), FileTime::from_unix_time(1_000_000, 0))?;
+        let hash = StatCache::new(&repo)?.get_hash(src)?;
+        let cached = StatCache::new_read_only(&repo)?.get_entry(src)?;
+        assert!(cached.is_some());
+
+        command::mv(&repo, src, dst)?;
+
+        let stat_cache = StatCache::new_read_only(&repo)?;
+        assert!(stat_cache.get_entry(src)?.is_none());
+        assert_eq!(stat_cache.get_entry(dst)?, cached);
+        let staged = command::status(&repo)?;
+        assert_eq!(staged.added_files.get(dst).unwrap().hash, hash);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_mv_modified_file_stages_changes() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let head = command::head_commit(&repo)?;
+        let og_entry = CommitDirReader::new(&repo, &head)?
+            .get_entry(Path::new("labels.txt"))?
+            .unwrap();
+        util::fs::write_to_path(&repo.path.join("labels.txt"), "dog\ncat\nfish")?;
+
+        command::mv(&repo, "labels.txt", "annotations/labels.txt")?;
+        let commit = command::commit(&repo, "Moving changed labels")?.unwrap();
+
+        let reader = CommitDirReader::new(&repo, &commit)?;
+        let entry = reader
+            .get_entry(Path::new("annotations/labels.txt"))?
+            .unwrap();
+        assert_ne!(entry.hash, og_entry.hash);
+        let version = util::fs::local_version(&repo, &entry)?;
+        assert_eq!(util::fs::read_from_path(version.path())?, "dog\ncat\nfish");
+
+        let status = command::status(&repo)?;
+        assert!(status.modified_files.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_mv_dir() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let og_num_files = util::fs::rcount_files_in_dir(&repo.path.join("train"));
+        command::mv(&repo, "train", "images")?;
+
+        let status = command::status(&repo)?;
+        assert_eq!(status.moved_files.len(), og_num_files);
+        assert!(status.moved_files.contains(&(
+            PathBuf::from("train/dog_1.jpg"),
+            PathBuf::from("images/dog_1.jpg")
+        )));
+
+        let commit = command::commit(&repo, "Renaming train to images")?.unwrap();
+        let reader = CommitDirReader::new(&repo, &commit)?;
+        assert_eq!(
+            reader.list_files_from_dir(Path::new("images")).len(),
+            og_num_files
+        );
+        assert!(reader.list_files_from_dir(Path::new("train")).is_empty());
+
+        let status = command::status(&repo)?;
+        assert!(!status.has_added_entries());
+        assert!(status.modified_files.is_empty());
+        assert!(status.removed_files.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_mv_uncommitted_file_fails() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let path = repo.path.join("new_file.txt");
+        util::fs::write_to_path(&path, "new file")?;
+
+        let result = command::mv(&repo, "new_file.txt", "renamed.txt");
+        assert!(result.is_err());
+        assert!(path.exists());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_log_path_follow() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let path = repo.path.join("labels.txt");
+        util::fs::write_to_path(&path, "dog\ncat\nfish")?;
+        command::add(&repo, &path)?;
+        let modified = command::commit(&repo, "Adding fish")?.unwrap();
+
+        command::mv(&repo, "labels.txt", "classes.txt")?;
+        let moved = command::commit(&repo, "Renaming labels")?.unwrap();
+
+        let history = command::log_path(&repo, "classes.txt", false)?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, moved.id);
+
+        let history = command::log_path(&repo, "classes.txt", true)?;
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].id, moved.id);
+        assert_eq!(history[1].id, modified.id);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_cherry_pick_commit_from_branch() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let orig_branch = command::current_branch(&repo)?.unwrap();
+        let labels_path = repo.path.join("labels.txt");
+        let new_path = repo.path.join("new.txt");
+
+        command::create_checkout_branch(&repo, "feature/labels")?;
+        util::fs::write_to_path(&labels_path, "dog\ncat\nfish")?;
+        command::add(&repo, &labels_path)?;
+        let metadata = HashMap::from([(String::from("run"), String::from("42"))]);
+        let picked = command::commit_with_metadata(&repo, "Adding fish", &metadata)?.unwrap();
+        util::fs::write_to_path(&new_path, "new file")?;
+        command::add(&repo, &new_path)?;
+        command::commit(&repo, "Adding new file")?;
+
+        command::checkout(&repo, &orig_branch.name)?;
+        let head = command::head_commit(&repo)?;
+        let commit = command::cherry_pick(&repo, &picked.id)?.unwrap();
+
+        assert_eq!(commit.parent_ids, vec![head.id]);
+        assert_eq!(commit.author, picked.author);
+        assert_eq!(commit.email, picked.email);
+        assert_eq!(commit.metadata, metadata);
+        assert_eq!(command::head_commit(&repo)?.id, commit.id);
+        assert_eq!(util::fs::read_from_path(&labels_path)?, "dog\ncat\nfish");
+        assert!(!new_path.exists());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_revert_tabular_rows() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let path = repo
+            .path
+            .join("annotations")
+            .join("train")
+            .join("bounding_box.csv");
+        let og_df = tabular::read_df(&path, DFOpts::empty())?;
+        let og_height = og_df.height();
+
+        let row = vec!["train/new_1.jpg", "dog", "1.0", "2.0", "3", "4"];
+        let df = tabular::add_row(og_df.lazy(), row.into_iter().map(String::from).collect())?;
+        tabular::write_df(&mut df.collect().unwrap(), &path)?;
+        command::add(&repo, &path)?;
+        let reverted = command::commit(&repo, "Adding new_1")?.unwrap();
+
+        let df = tabular::read_df(&path, DFOpts::empty())?;
+        let row = vec!["train/new_2.jpg", "cat", "5.0", "6.0", "7", "8"];
+        let df = tabular::add_row(df.lazy(), row.into_iter().map(String::from).collect())?;
+        tabular::write_df(&mut df.collect().unwrap(), &path)?;
+        command::add(&repo, &path)?;
+        command::commit(&repo, "Adding new_2")?;
+
+        // Both commits changed the file, so the revert only takes out the row
+        let commit = command::revert(&repo, &reverted.id)?;
+        assert!(commit.is_some());
+        assert!(command::status(&repo)?.merge_conflicts.is_empty());
+
+        let df = tabular::read_df(&path, DFOpts::empty())?;
+        assert_eq!(df.height(), og_height + 1);
+        let files: Vec<Option<&str>> = df
+            .column("file")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert!(files.contains(&Some("train/new_2.jpg")));
+        assert!(!files.contains(&Some("train/new_1.jpg")));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_revert_conflict() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let labels_path = repo.path.join("labels.txt");
+        util::fs::write_to_path(&labels_path, "dog\ncat\nfish")?;
+        command::add(&repo, &labels_path)?;
+        let reverted = command::commit(&repo, "Adding fish")?.unwrap();
+
+        util::fs::write_to_path(&labels_path, "dog\ncat\nfish\nbird")?;
+        command::add(&repo, &labels_path)?;
+        let head = command::commit(&repo, "Adding bird")?.unwrap();
+
+        let commit = command::revert(&repo, &reverted.id)?;
+        assert!(commit.is_none());
+        assert_eq!(command::head_commit(&repo)?.id, head.id);
+        let status = command::status(&repo)?;
+        assert_eq!(status.merge_conflicts.len(), 1);
+        assert_eq!(
+            util::fs::read_from_path(&labels_path)?,
+            "dog\ncat\nfish\nbird"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn test_command_cherry_pick_removed_file_conflict() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed(|repo| {
+        let orig_branch = command::current_branch(&repo)?.unwrap();
+        let labels_path = repo.path.join("labels.txt");
+        let new_path = repo.path.join("new.txt");
+
+        command::create_checkout_branch(&repo, "feature/labels")?;
+        util::fs::write_to_path(&labels_path, "dog\ncat\nfish")?;
+        command::add(&repo, &labels_path)?;
+        util::fs::write_to_path(&new_path, "new file")?;
+        command::add(&repo, &new_path)?;
+        let picked = command::commit(&repo, "Adding fish and a new file")?.unwrap();
+
+        command::checkout(&repo, &orig_branch.name)?;
+        std::fs::remove_file(&labels_path)?;
+        command::add(&repo, &labels_path)?;
+        let head = command::commit(&repo, "Removing labels")?.unwrap();
+
+        // The picked commit modified a file HEAD removed
+        let commit = command::cherry_pick(&repo, &picked.id)?;
+        assert!(commit.is_none());
+        assert_eq!(command::head_commit(&repo)?.id, head.id);
+        assert!(!labels_path.exists());
+
+        let status = command::status(&repo)?;
+        assert_eq!(status.merge_conflicts.len(), 1);
+        assert_eq!(status.merge_conflicts[0].path(), Path::new("labels.txt"));
+        assert_eq!(status.merge_conflicts[0].description(), "deleted by us");
+        assert!(status.merge_conflicts[0].head_entry().is_none());
+        // What applied cleanly is staged
+        assert!(status.added_files.contains_key(Path::new("new.txt")));
+
+        // Keeping it removed resolves the conflict
+        command::add(&repo, &labels_path)?;
+        let status = command::status(&repo)?;
+        assert!(status.merge_conflicts.is_empty());
+
+        Ok(())
+    })
+}
+
 #[tokio::test]
 async fn test_command_push_one_commit() -> Result<(), OxenError> {
     test::run_training_data_repo_test_no_commits_async(|repo| async {
@@ -1465,6 +1828,37 @@ async fn test_clone_full() -> Result<(), OxenError> {
     .await
 }
 
+#[tokio::test]
+async fn test_clone_moved_file() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_fully_committed_async(|mut repo| async move {
+        let remote = test::repo_remote_url_from(&repo.dirname());
+        command::add_remote(&mut repo, constants::DEFAULT_REMOTE_NAME, &remote)?;
+        let remote_repo = test::create_remote_repo(&repo).await?;
+        command::push(&repo).await?;
+
+        // Push the rename after the original file was already synced
+        let og_contents = util::fs::read_from_path(&repo.path.join("labels.txt"))?;
+        command::mv(&repo, "labels.txt", "annotations/labels.txt")?;
+        command::commit(&repo, "Moving labels")?.unwrap();
+        command::push(&repo).await?;
+
+        test::run_empty_dir_test_async(|new_repo_dir| async move {
+            let shallow = false;
+            let cloned_repo =
+                command::clone(&remote_repo.remote.url, &new_repo_dir, shallow).await?;
+            let moved_path = cloned_repo.path.join("annotations").join("labels.txt");
+            assert!(!cloned_repo.path.join("labels.txt").exists());
+            assert_eq!(util::fs::read_from_path(&moved_path)?, og_contents);
+
+            api::remote::repositories::delete(&remote_repo).await?;
+
+            Ok(new_repo_dir)
+        })
+        .await
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_pull_data_frame() -> Result<(), OxenError> {
     test::run_training_data_repo_test_no_commits_async(|mut repo| async move {
@@ -2096,7 +2490,7 @@ fn test_can_add_merge_conflict() -> Result<(), OxenError> {
         assert_eq!(status.merge_conflicts.len(), 1);
 
         // Assume that we fixed the conflict and added the file
-        let path = status.merge_conflicts[0].head_entry.path.clone();
+        let path = status.merge_conflicts[0].path().to_path_buf();
         let fullpath = repo.path.join(path);
         command::add(&repo, fullpath)?;
 
@@ -2144,7 +2538,7 @@ fn test_commit_after_merge_conflict() -> Result<(), OxenError> {
         assert_eq!(status.merge_conflicts.len(), 1);
 
         // Assume that we fixed the conflict and added the file
-        let path = status.merge_conflicts[0].head_entry.path.clone();
+        let path = status.merge_conflicts[0].path().to_path_buf();
         let fullpath = repo.path.join(path);
         command::add(&repo, fullpath)?;
 
@@ -2921,3 +3315,43 @@ fn test_status_rm_directory_file() -> Result<(), OxenError> {
         Ok(())
     })
 }
+
+#[tokio::test]
+async fn test_command_fsck_repair_from_remote() -> Result<(), OxenError> {
+    test::run_training_data_repo_test_no_commits_async(|repo| async {
+        let mut repo = repo;
+
+        let train_dir = repo.path.join("train");
+        command::add(&repo, &train_dir)?;
+        let commit = command::commit(&repo, "Adding training data")?.unwrap();
+
+        let remote = test::repo_remote_url_from(&repo.dirname());
+        command::add_remote(&mut repo, constants::DEFAULT_REMOTE_NAME, &remote)?;
+        let remote_repo = test::create_remote_repo(&repo).await?;
+        command::push(&repo).await?;
+
+        // Corrupt one version file and remove another
+        let reader = CommitDirReader::new(&repo, &commit)?;
+        let entries = reader.list_entries()?;
+        let corrupt = &entries[0];
+        let missing = entries.iter().find(|e| e.hash != corrupt.hash).unwrap();
+        util::fs::write_to_path(&util::fs::version_path(&repo, corrupt), "not the data")?;
+        std::fs::remove_file(util::fs::version_path(&repo, missing))?;
+
+        let report = command::fsck(&repo, Some(&commit.id))?;
+        assert_eq!(report.corrupt_versions.len(), 1);
+        assert_eq!(report.missing_versions.len(), 1);
+
+        let num_repaired =
+            command::fsck_repair(&repo, &report, constants::DEFAULT_REMOTE_NAME).await?;
+        assert_eq!(num_repaired, 2);
+
+        let report = command::fsck(&repo, Some(&commit.id))?;
+        assert!(report.is_ok());
+
+        api::remote::repositories::delete(&remote_repo).await?;
+
+        future::ok::<(), OxenError>(()).await
+    })
+    .await
+}